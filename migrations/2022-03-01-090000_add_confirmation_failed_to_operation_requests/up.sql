@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE operation_requests ADD COLUMN IF NOT EXISTS confirmation_failed BOOLEAN NOT NULL DEFAULT 'f'
\ No newline at end of file
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS allowed_target_addresses (
+    id              uuid NOT NULL DEFAULT uuid_generate_v4() PRIMARY KEY,
+    created_at      TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    contract_id     uuid NOT NULL,
+    address         VARCHAR NOT NULL,
+
+    UNIQUE(contract_id, address),
+    FOREIGN KEY(contract_id) REFERENCES contracts(id) ON DELETE CASCADE
+);
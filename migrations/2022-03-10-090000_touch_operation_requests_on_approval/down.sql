@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER IF EXISTS touch_operation_request_on_approval ON operation_approvals;
+DROP FUNCTION IF EXISTS touch_operation_request_on_approval();
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE OR REPLACE FUNCTION touch_operation_request_on_approval() RETURNS trigger AS $$
+BEGIN
+    UPDATE operation_requests SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.operation_request_id;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER touch_operation_request_on_approval AFTER INSERT ON operation_approvals
+    FOR EACH ROW EXECUTE PROCEDURE touch_operation_request_on_approval();
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE contracts DROP COLUMN IF EXISTS mint_entrypoint;
+ALTER TABLE contracts DROP COLUMN IF EXISTS burn_entrypoint;
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE contracts ADD COLUMN IF NOT EXISTS mint_entrypoint VARCHAR NOT NULL DEFAULT 'mint';
+ALTER TABLE contracts ADD COLUMN IF NOT EXISTS burn_entrypoint VARCHAR NOT NULL DEFAULT 'burn';
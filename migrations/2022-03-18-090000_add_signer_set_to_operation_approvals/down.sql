@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE operation_approvals DROP COLUMN IF EXISTS signer_set
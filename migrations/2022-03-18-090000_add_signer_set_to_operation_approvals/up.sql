@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE operation_approvals ADD COLUMN IF NOT EXISTS signer_set text[]
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE contracts DROP COLUMN IF EXISTS node_url;
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE contracts ADD COLUMN IF NOT EXISTS node_url VARCHAR;
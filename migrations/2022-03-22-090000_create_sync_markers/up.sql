@@ -0,0 +1,6 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS sync_markers (
+    name            VARCHAR NOT NULL PRIMARY KEY,
+    config_hash     VARCHAR NOT NULL,
+    synced_at       TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
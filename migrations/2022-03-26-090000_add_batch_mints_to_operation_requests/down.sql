@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE operation_requests DROP COLUMN IF EXISTS batch_amounts;
+ALTER TABLE operation_requests DROP COLUMN IF EXISTS batch_target_addresses;
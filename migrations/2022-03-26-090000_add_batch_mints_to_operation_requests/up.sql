@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE operation_requests ADD COLUMN IF NOT EXISTS batch_target_addresses text[];
+ALTER TABLE operation_requests ADD COLUMN IF NOT EXISTS batch_amounts numeric[];
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE capabilities ADD COLUMN IF NOT EXISTS enabled BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE capabilities ADD COLUMN IF NOT EXISTS added_at_runtime BOOLEAN NOT NULL DEFAULT FALSE;
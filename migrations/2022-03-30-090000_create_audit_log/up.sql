@@ -0,0 +1,13 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS audit_log (
+    id              uuid NOT NULL DEFAULT uuid_generate_v4() PRIMARY KEY,
+    created_at      TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    event           SMALLINT NOT NULL,
+    actor           VARCHAR,
+    contract_id     uuid,
+    resource_id     uuid,
+    detail          TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_created_at_idx ON audit_log (created_at);
+CREATE INDEX IF NOT EXISTS audit_log_contract_id_idx ON audit_log (contract_id);
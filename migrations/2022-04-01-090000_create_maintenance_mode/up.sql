@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS maintenance_mode (
+    id              SMALLINT NOT NULL DEFAULT 1 PRIMARY KEY CHECK (id = 1),
+    enabled         BOOLEAN NOT NULL,
+    message         TEXT,
+    updated_by      VARCHAR,
+    updated_at      TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
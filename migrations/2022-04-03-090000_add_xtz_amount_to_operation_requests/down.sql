@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE operation_requests DROP COLUMN IF EXISTS xtz_amount;
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+-- The trailing slashes removed from the urls are not restored, they are not needed.
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+UPDATE node_endpoints SET url = rtrim(url, '/')
+WHERE url LIKE '%/' AND rtrim(url, '/') NOT IN (SELECT url FROM node_endpoints);
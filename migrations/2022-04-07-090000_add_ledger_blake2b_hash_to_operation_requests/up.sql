@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE operation_requests ADD COLUMN IF NOT EXISTS ledger_blake2b_hash varchar;
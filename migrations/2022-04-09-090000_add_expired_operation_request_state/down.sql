@@ -0,0 +1,21 @@
+-- This file should undo anything in `up.sql`
+-- Expired operation requests are re-opened after the highest nonce of their contract, as their
+-- nonce may have been reused. Their approvals were signed for the old nonce and are removed.
+DELETE FROM operation_approvals
+    WHERE operation_request_id IN (SELECT id FROM operation_requests WHERE state = 3);
+UPDATE operation_requests
+    SET state = 0, nonce = renumbered.nonce
+    FROM (
+        SELECT expired.id,
+            highest.nonce + ROW_NUMBER() OVER (
+                PARTITION BY expired.contract_id ORDER BY expired.nonce, expired.created_at
+            ) AS nonce
+        FROM operation_requests AS expired
+        JOIN (
+            SELECT contract_id, MAX(nonce) AS nonce FROM operation_requests GROUP BY contract_id
+        ) AS highest ON highest.contract_id = expired.contract_id
+        WHERE expired.state = 3
+    ) AS renumbered
+    WHERE operation_requests.id = renumbered.id;
+DROP INDEX IF EXISTS operation_requests_contract_id_nonce_key;
+ALTER TABLE operation_requests ADD CONSTRAINT operation_requests_contract_id_nonce_key UNIQUE (contract_id, nonce);
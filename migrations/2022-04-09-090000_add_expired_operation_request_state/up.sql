@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- Operation request states: 0 = open, 1 = approved, 2 = injected, 3 = expired.
+-- Expired operation requests give up their nonce, so it can be reused by the next operation request.
+ALTER TABLE operation_requests DROP CONSTRAINT IF EXISTS operation_requests_contract_id_nonce_key;
+CREATE UNIQUE INDEX IF NOT EXISTS operation_requests_contract_id_nonce_key
+    ON operation_requests (contract_id, nonce) WHERE state <> 3;
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS api_keys (
+    id              uuid NOT NULL DEFAULT uuid_generate_v4() PRIMARY KEY,
+    created_at      TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    contract_id     uuid NOT NULL,
+    name            VARCHAR NOT NULL,
+    address         VARCHAR NOT NULL,
+    token_hash      VARCHAR NOT NULL UNIQUE,
+    actions         SMALLINT[] NOT NULL,
+    created_by      VARCHAR NOT NULL,
+    revoked_at      TIMESTAMP,
+
+    FOREIGN KEY(contract_id) REFERENCES contracts(id) ON DELETE CASCADE
+);
@@ -0,0 +1,104 @@
+use std::str::FromStr;
+
+use num_bigint::BigInt;
+
+use super::models::error::APIError;
+
+/// Converts an amount given in display units, e.g. `"1.5"`, to the base units of a token with
+/// `decimals` decimals. The amount may have a sign and a single decimal point with digits on
+/// both sides. Amounts with more fractional digits than `decimals` are rejected instead of
+/// rounded, unless the excess digits are zeros, so the converted amount is always exact.
+pub fn to_base_units(display: &str, decimals: u32) -> Result<BigInt, APIError> {
+    let invalid = || APIError::InvalidValue {
+        description: format!("amount {:?} is not a number", display),
+    };
+    let is_digits = |digits: &str| !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit());
+
+    let (negative, unsigned) = match display.as_bytes().first() {
+        Some(b'-') => (true, &display[1..]),
+        Some(b'+') => (false, &display[1..]),
+        _ => (false, display),
+    };
+    let (integer, fraction) = match unsigned.split_once('.') {
+        Some((integer, fraction)) if is_digits(fraction) => (integer, fraction),
+        Some(_) => return Err(invalid()),
+        None => (unsigned, ""),
+    };
+    if !is_digits(integer) {
+        return Err(invalid());
+    }
+
+    let fraction = fraction.trim_end_matches('0');
+    let decimals = decimals as usize;
+    if fraction.len() > decimals {
+        return Err(APIError::InvalidValue {
+            description: format!(
+                "amount {} has more than {} decimal places",
+                display, decimals
+            ),
+        });
+    }
+
+    let digits = format!(
+        "{}{}{}",
+        integer,
+        fraction,
+        "0".repeat(decimals - fraction.len())
+    );
+    let value = BigInt::from_str(&digits).map_err(|_error| invalid())?;
+
+    Ok(if negative { -value } else { value })
+}
+
+#[cfg(test)]
+mod test {
+    use num_bigint::BigInt;
+
+    use super::to_base_units;
+    use crate::api::models::error::APIError;
+
+    #[test]
+    fn test_to_base_units() -> Result<(), APIError> {
+        assert_eq!(to_base_units("1000", 0)?, BigInt::from(1000));
+        assert_eq!(to_base_units("+1000", 0)?, BigInt::from(1000));
+        assert_eq!(to_base_units("1.5", 6)?, BigInt::from(1_500_000));
+        assert_eq!(to_base_units("0.000001", 6)?, BigInt::from(1));
+        assert_eq!(to_base_units("1.50", 1)?, BigInt::from(15));
+        assert_eq!(to_base_units("100.0", 0)?, BigInt::from(100));
+        assert_eq!(
+            to_base_units("123456789012345678901234567890", 8)?.to_string(),
+            "12345678901234567890123456789000000000"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_base_units_negative() -> Result<(), APIError> {
+        assert_eq!(to_base_units("-1.5", 2)?, BigInt::from(-150));
+        assert_eq!(to_base_units("-0", 0)?, BigInt::from(0));
+        assert!(to_base_units("--1", 0).is_err());
+        assert!(to_base_units("-", 0).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_base_units_rejects_excess_precision() {
+        assert!(matches!(
+            to_base_units("1.5", 0),
+            Err(APIError::InvalidValue { .. })
+        ));
+        assert!(to_base_units("0.0000001", 6).is_err());
+        assert!(to_base_units("1.2345", 3).is_err());
+    }
+
+    #[test]
+    fn test_to_base_units_rejects_non_numeric() {
+        for display in &[
+            "", "abc", "1.2.3", "1.", ".5", "1,5", "1e6", " 1", "1_000", "0x10", "1.-5",
+        ] {
+            assert!(to_base_units(display, 6).is_err(), "{:?}", display);
+        }
+    }
+}
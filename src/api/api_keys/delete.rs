@@ -0,0 +1,58 @@
+use std::convert::TryFrom;
+
+use actix_session::Session;
+use actix_web::{web, web::Path, HttpResponse};
+use diesel::Connection;
+use log::info;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    api::models::{api_key::ApiKey, error::APIError, user::UserKind},
+    audit,
+    auth::get_current_user,
+    db::models::api_key::ApiKey as DBApiKey,
+    settings, DbPool,
+};
+
+#[derive(Deserialize)]
+pub struct PathInfo {
+    id: Uuid,
+}
+
+/// Revokes an API key. Requests with its token are rejected from then on.
+pub async fn api_key(
+    pool: web::Data<DbPool>,
+    path: Path<PathInfo>,
+    server_settings: web::Data<settings::Server>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+
+    let id = path.id;
+    let conn = pool.get()?;
+    let api_key = web::block(move || DBApiKey::get(&conn, &id)).await?;
+    current_user.require_roles(vec![UserKind::Admin], api_key.contract_id)?;
+    if api_key.revoked_at.is_some() {
+        return Ok(HttpResponse::Ok().json(ApiKey::try_from(api_key)?));
+    }
+
+    let actor = current_user.address.clone();
+    let conn = pool.get()?;
+    let api_key = web::block::<_, _, APIError>(move || {
+        conn.transaction(|| {
+            let api_key = DBApiKey::revoke(&conn, &id)?;
+            audit::record(&conn, audit::api_key_revoked(&actor, &api_key))?;
+
+            Ok(api_key)
+        })
+    })
+    .await?;
+
+    info!(
+        "API key {} ({}) of contract {} revoked by {}",
+        api_key.id, api_key.name, api_key.contract_id, current_user.address
+    );
+
+    Ok(HttpResponse::Ok().json(ApiKey::try_from(api_key)?))
+}
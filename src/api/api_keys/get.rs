@@ -0,0 +1,40 @@
+use std::convert::TryFrom;
+
+use actix_session::Session;
+use actix_web::{web, web::Query, HttpResponse};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    api::models::{api_key::ApiKey, error::APIError, user::UserKind},
+    auth::get_current_user,
+    db::models::api_key::ApiKey as DBApiKey,
+    settings, DbPool,
+};
+
+#[derive(Deserialize)]
+pub struct Info {
+    contract_id: Uuid,
+}
+
+/// The API keys of a contract, including revoked ones. Their tokens are never returned.
+pub async fn api_keys(
+    pool: web::Data<DbPool>,
+    query: Query<Info>,
+    server_settings: web::Data<settings::Server>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+    current_user.require_roles(vec![UserKind::Admin], query.contract_id)?;
+
+    let contract_id = query.contract_id;
+    let conn = pool.get()?;
+    let api_keys = web::block(move || DBApiKey::get_list(&conn, &contract_id)).await?;
+
+    let api_keys = api_keys
+        .into_iter()
+        .map(ApiKey::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(HttpResponse::Ok().json(api_keys))
+}
@@ -0,0 +1,297 @@
+use std::{
+    cell::RefCell,
+    future::{ready, Future, Ready},
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+use actix_web::{
+    dev::{Body, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{header, HeaderMap},
+    web, Error, HttpMessage,
+};
+use log::info;
+
+use crate::{
+    api::models::{error::APIError, user::UserState},
+    db::models::{api_key::ApiKey, user::User},
+    DbPool,
+};
+
+/// The API key a request was authenticated with and the active users of its contract with its
+/// address, see `auth::get_current_user_or_api_key`.
+pub struct ResolvedApiKey {
+    pub api_key: ApiKey,
+    pub users: Vec<User>,
+}
+
+type Lookup =
+    Rc<dyn Fn(String) -> Pin<Box<dyn Future<Output = Result<Option<ResolvedApiKey>, APIError>>>>>;
+
+/// Resolves the `Authorization: Bearer <token>` header of requests to the API key it belongs to.
+/// Requests with an unknown or revoked token are rejected, requests without one are passed
+/// through unchanged. Only the endpoints which accept API keys look at the resolved key, all
+/// others still require a session.
+pub struct ResolveApiKeys {
+    lookup: Lookup,
+}
+
+impl ResolveApiKeys {
+    pub fn new(pool: DbPool) -> Self {
+        ResolveApiKeys::with_lookup(move |token_hash| {
+            let pool = pool.clone();
+            async move {
+                let conn = pool.get()?;
+                Ok(web::block::<_, _, APIError>(move || {
+                    let api_key = match ApiKey::get_active(&conn, &token_hash)? {
+                        Some(api_key) => api_key,
+                        None => return Ok(None),
+                    };
+                    let users = User::get_all(
+                        &conn,
+                        None,
+                        Some(api_key.contract_id),
+                        Some(UserState::Active),
+                        Some(&api_key.address),
+                        None,
+                    )?;
+
+                    Ok(Some(ResolvedApiKey { api_key, users }))
+                })
+                .await?)
+            }
+        })
+    }
+
+    /// `lookup` finds the active API key by the hash of its token.
+    fn with_lookup<F, Fut>(lookup: F) -> Self
+    where
+        F: Fn(String) -> Fut + 'static,
+        Fut: Future<Output = Result<Option<ResolvedApiKey>, APIError>> + 'static,
+    {
+        ResolveApiKeys {
+            lookup: Rc::new(move |token_hash| Box::pin(lookup(token_hash))),
+        }
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+impl<S> Transform<S> for ResolveApiKeys
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ResolveApiKeysMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ResolveApiKeysMiddleware {
+            service: Rc::new(RefCell::new(service)),
+            lookup: self.lookup.clone(),
+        }))
+    }
+}
+
+pub struct ResolveApiKeysMiddleware<S> {
+    service: Rc<RefCell<S>>,
+    lookup: Lookup,
+}
+
+impl<S> Service for ResolveApiKeysMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let token_hash = match bearer_token(req.headers()).map(ApiKey::token_hash) {
+            None => return Box::pin(self.service.borrow_mut().call(req)),
+            Some(Ok(token_hash)) => token_hash,
+            Some(Err(error)) => return Box::pin(ready(Ok(req.error_response(error)))),
+        };
+
+        let service = self.service.clone();
+        let lookup = self.lookup.clone();
+        Box::pin(async move {
+            match lookup(token_hash).await {
+                Ok(Some(resolved)) => {
+                    info!(
+                        "Request authenticated with API key {} ({}) of contract {}",
+                        resolved.api_key.id, resolved.api_key.name, resolved.api_key.contract_id
+                    );
+                    req.extensions_mut().insert(resolved);
+                    let response = service.borrow_mut().call(req);
+                    response.await
+                }
+                Ok(None) => Ok(req.error_response(APIError::Unauthorized)),
+                Err(error) => Ok(req.error_response(error)),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::future::{ready, Ready};
+
+    use actix_session::{CookieSession, Session};
+    use actix_web::{http::StatusCode, test, web, App, HttpRequest, HttpResponse};
+    use chrono::NaiveDateTime;
+    use serde_json::Value;
+    use uuid::Uuid;
+
+    use super::{ResolveApiKeys, ResolvedApiKey};
+    use crate::{
+        api::models::{api_key::ApiKeyAction, error::APIError, user::UserKind},
+        auth::get_current_user_or_api_key,
+        db::models::{api_key::ApiKey, user::User},
+        fixtures::{self, ADDRESS},
+    };
+
+    const TOKEN: &str = "tzw_create_only";
+
+    fn user(contract_id: Uuid, kind: UserKind) -> User {
+        User {
+            display_name: "Treasury".into(),
+            ..fixtures::user(contract_id, kind)
+        }
+    }
+
+    /// A key of a user who is both gatekeeper and keyholder, which may only create operation
+    /// requests.
+    fn create_only_key(contract_id: Uuid) -> ResolvedApiKey {
+        ResolvedApiKey {
+            api_key: ApiKey {
+                id: Uuid::new_v4(),
+                created_at: NaiveDateTime::from_timestamp(0, 0),
+                contract_id,
+                name: "treasury".into(),
+                address: ADDRESS.into(),
+                token_hash: ApiKey::token_hash(TOKEN).unwrap(),
+                actions: vec![ApiKeyAction::CreateOperationRequest.into()],
+                created_by: "tz1Ts3m2dXTXB66XN7cg5ALiAvzZY6AxrFd9".into(),
+                revoked_at: None,
+            },
+            users: vec![
+                user(contract_id, UserKind::Gatekeeper),
+                user(contract_id, UserKind::Keyholder),
+            ],
+        }
+    }
+
+    /// Checks the current user like the operation request and approval endpoints do.
+    fn handler(
+        action: ApiKeyAction,
+        kind: UserKind,
+        contract_id: Uuid,
+    ) -> impl Fn(HttpRequest, Session) -> Ready<Result<HttpResponse, APIError>> + Clone {
+        move |req: HttpRequest, session: Session| {
+            ready(
+                get_current_user_or_api_key(&req, &session, 60, action).and_then(|current_user| {
+                    current_user.require_roles(vec![kind], contract_id)?;
+
+                    Ok(HttpResponse::Ok().body(current_user.address))
+                }),
+            )
+        }
+    }
+
+    async fn call(contract_id: Uuid, path: &str, token: Option<&str>) -> (StatusCode, Value) {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .wrap(ResolveApiKeys::with_lookup(move |token_hash| async move {
+                    Ok(Some(create_only_key(contract_id))
+                        .filter(|resolved| resolved.api_key.token_hash == token_hash))
+                }))
+                .route(
+                    "/operation-requests",
+                    web::post().to(handler(
+                        ApiKeyAction::CreateOperationRequest,
+                        UserKind::Gatekeeper,
+                        contract_id,
+                    )),
+                )
+                .route(
+                    "/operation-approvals",
+                    web::post().to(handler(
+                        ApiKeyAction::ApproveOperationRequest,
+                        UserKind::Keyholder,
+                        contract_id,
+                    )),
+                ),
+        )
+        .await;
+
+        let mut request = test::TestRequest::post().uri(path);
+        if let Some(token) = token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        let response = test::call_service(&mut app, request.to_request()).await;
+        let status = response.status();
+        let body = test::read_body(response).await;
+
+        (
+            status,
+            serde_json::from_slice(&body)
+                .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&body).into())),
+        )
+    }
+
+    #[actix_rt::test]
+    async fn test_create_only_key_can_create() {
+        let contract_id = Uuid::new_v4();
+        let (status, body) = call(contract_id, "/operation-requests", Some(TOKEN)).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, ADDRESS);
+    }
+
+    #[actix_rt::test]
+    async fn test_create_only_key_cannot_approve() {
+        let contract_id = Uuid::new_v4();
+        let (status, body) = call(contract_id, "/operation-approvals", Some(TOKEN)).await;
+
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["error"], "Forbidden");
+    }
+
+    #[actix_rt::test]
+    async fn test_unknown_key_rejected() {
+        let contract_id = Uuid::new_v4();
+        for token in &["tzw_unknown", ""] {
+            let (status, body) = call(contract_id, "/operation-requests", Some(token)).await;
+
+            assert_eq!(status, StatusCode::FORBIDDEN);
+            assert_eq!(body["error"], "Unauthorized");
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_without_key_requires_session() {
+        let contract_id = Uuid::new_v4();
+        let (status, body) = call(contract_id, "/operation-requests", None).await;
+
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["error"], "Unauthorized");
+    }
+}
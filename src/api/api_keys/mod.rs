@@ -0,0 +1,20 @@
+use actix_web::{web, HttpResponse};
+
+mod delete;
+mod get;
+pub mod guard;
+mod post;
+
+pub fn api_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/api-keys")
+            .route(web::get().to(get::api_keys))
+            .route(web::post().to(post::api_key))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
+    cfg.service(
+        web::resource("/api-keys/{id}")
+            .route(web::delete().to(delete::api_key))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
+}
@@ -0,0 +1,88 @@
+use std::convert::TryFrom;
+
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use diesel::Connection;
+use log::info;
+
+use crate::{
+    api::models::{
+        api_key::{ApiKey, CreatedApiKey, NewApiKey},
+        error::APIError,
+        user::{UserKind, UserState},
+    },
+    audit,
+    auth::get_current_user,
+    db::models::{
+        api_key::{ApiKey as DBApiKey, NewApiKey as DBNewApiKey},
+        user::User,
+    },
+    settings, DbPool,
+};
+
+/// Creates an API key for a contract, acting as the user with the given address. The response
+/// contains the token, which can't be retrieved again afterwards.
+pub async fn api_key(
+    pool: web::Data<DbPool>,
+    body: web::Json<NewApiKey>,
+    server_settings: web::Data<settings::Server>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+    let new_api_key = body.into_inner();
+    current_user.require_roles(vec![UserKind::Admin], new_api_key.contract_id)?;
+    new_api_key.validate()?;
+
+    let token = DBApiKey::generate_token();
+    let mut actions = new_api_key
+        .actions
+        .iter()
+        .map(|action| (*action).into())
+        .collect::<Vec<i16>>();
+    actions.sort_unstable();
+    actions.dedup();
+    let new_db_api_key = DBNewApiKey {
+        contract_id: new_api_key.contract_id,
+        name: new_api_key.name.trim().into(),
+        address: new_api_key.address.clone(),
+        token_hash: DBApiKey::token_hash(&token)?,
+        actions,
+        created_by: current_user.address.clone(),
+    };
+
+    let actor = current_user.address;
+    let conn = pool.get()?;
+    let api_key = web::block::<_, _, APIError>(move || {
+        conn.transaction(|| {
+            let users = User::get_all(
+                &conn,
+                None,
+                Some(new_db_api_key.contract_id),
+                Some(UserState::Active),
+                Some(&new_db_api_key.address),
+                None,
+            )?;
+            if users.is_empty() {
+                return Err(APIError::InvalidValue {
+                    description: format!(
+                        "{} is not an active user of the contract",
+                        new_db_api_key.address
+                    ),
+                });
+            }
+            let api_key = DBApiKey::insert(&conn, &new_db_api_key)?;
+            audit::record(&conn, audit::api_key_created(&actor, &api_key))?;
+
+            Ok(api_key)
+        })
+    })
+    .await?;
+
+    let api_key = ApiKey::try_from(api_key)?;
+    info!(
+        "API key {} ({}) created for contract {} by {}",
+        api_key.id, api_key.name, api_key.contract_id, api_key.created_by
+    );
+
+    Ok(HttpResponse::Ok().json(CreatedApiKey { api_key, token }))
+}
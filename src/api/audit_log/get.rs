@@ -0,0 +1,173 @@
+use std::convert::TryFrom;
+
+use actix_session::Session;
+use actix_web::{web, web::Query, HttpResponse};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    api::models::{
+        audit_log::AuditLogEntry, common::ListResponse, error::APIError, user::UserKind,
+    },
+    auth::get_current_user,
+    db::models::{
+        audit_log::{AuditLogEntry as DBAuditLogEntry, AuditLogFilter},
+        contract::Contract,
+    },
+    settings, DbPool,
+};
+
+#[derive(Deserialize)]
+pub struct Info {
+    contract_id: Option<Uuid>,
+    actor: Option<String>,
+    from: Option<NaiveDate>,
+    before: Option<NaiveDate>,
+    page: Option<i64>,
+    limit: Option<i64>,
+}
+
+pub async fn audit_log(
+    pool: web::Data<DbPool>,
+    query: Query<Info>,
+    server_settings: web::Data<settings::Server>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+    let contract_ids = match query.contract_id {
+        Some(contract_id) => {
+            current_user.require_roles(vec![UserKind::Admin], contract_id)?;
+            Some(vec![contract_id])
+        }
+        None => {
+            current_user.require_one_of_roles(vec![UserKind::Admin])?;
+            let conn = pool.get()?;
+            let all_contract_ids =
+                web::block::<_, _, APIError>(move || Ok(Contract::get_all_ids(&conn)?)).await?;
+            // the entries of other contracts and those without a contract are only read by the
+            // admins of every contract
+            if current_user
+                .require_roles_on_all(vec![UserKind::Admin], &all_contract_ids)
+                .is_ok()
+            {
+                None
+            } else {
+                Some(current_user.contract_ids_with_roles(&[UserKind::Admin]))
+            }
+        }
+    };
+
+    let filter = AuditLogFilter {
+        contract_ids,
+        actor: query.actor.clone(),
+        from: query.from.map(|from| from.and_hms(0, 0, 0)),
+        before: query.before.map(|before| before.and_hms(0, 0, 0)),
+    };
+    let page = query.page.unwrap_or(0);
+    let limit = query.limit.unwrap_or(100);
+
+    let conn = pool.get()?;
+    let (entries, total_pages) =
+        web::block(move || DBAuditLogEntry::get_list(&conn, &filter, page, limit)).await?;
+
+    let results = entries
+        .into_iter()
+        .map(AuditLogEntry::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(HttpResponse::Ok().json(ListResponse {
+        page,
+        total_pages,
+        results,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+
+    use actix_session::{CookieSession, UserSession};
+    use actix_web::{dev::Service, http::StatusCode, test, web, App};
+    use serde_json::{json, Value};
+
+    use super::audit_log;
+    use crate::{
+        api::models::{audit_log::AuditEvent, user::UserKind},
+        audit,
+        auth::set_current_user,
+        db::{
+            models::{audit_log::NewAuditLogEntry, contract::NewContract},
+            test_pool,
+        },
+        fixtures,
+    };
+
+    #[actix_rt::test]
+    #[ignore]
+    async fn test_single_contract_admin_reads_only_its_entries() {
+        let pool = test_pool();
+        let (own, other) = {
+            let conn = pool.get().unwrap();
+            let own = fixtures::new_contract().save(&conn).unwrap();
+            let other = NewContract {
+                pkh: "KT1PWx2mnDueood7fEmfbBDKx1D9BAnnXitn".into(),
+                ..fixtures::new_contract()
+            }
+            .save(&conn)
+            .unwrap();
+            for contract_id in &[Some(own.id), Some(other.id), None] {
+                audit::record(
+                    &conn,
+                    NewAuditLogEntry {
+                        event: AuditEvent::ConfigSynced.into(),
+                        actor: None,
+                        contract_id: *contract_id,
+                        resource_id: None,
+                        detail: "{}".into(),
+                    },
+                )
+                .unwrap();
+            }
+
+            (own, other)
+        };
+        let user = Rc::new(fixtures::session_user(UserKind::Admin, &[own.id]));
+        let mut app = test::init_service(
+            App::new()
+                .data(pool)
+                .data(fixtures::server_settings())
+                .wrap_fn(move |req, srv| {
+                    set_current_user(&req.get_session(), &user).unwrap();
+                    srv.call(req)
+                })
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .route("/audit-log", web::get().to(audit_log)),
+        )
+        .await;
+
+        let response = test::call_service(
+            &mut app,
+            test::TestRequest::get().uri("/audit-log").to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = test::read_body_json(response).await;
+        let contract_ids = body["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["contract_id"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(contract_ids, vec![json!(own.id)]);
+
+        let response = test::call_service(
+            &mut app,
+            test::TestRequest::get()
+                .uri(&format!("/audit-log?contract_id={}", other.id))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,11 @@
+use actix_web::{web, HttpResponse};
+
+mod get;
+
+pub fn api_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/audit-log")
+            .route(web::get().to(get::audit_log))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
+}
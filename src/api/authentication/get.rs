@@ -4,6 +4,7 @@ use actix_web::{
     web::{self, Query},
     HttpResponse,
 };
+use chrono::Utc;
 use serde::Deserialize;
 
 use crate::{
@@ -12,17 +13,14 @@ use crate::{
     db::models::user::User,
     db::models::{contract::Contract, node_endpoint::NodeEndpoint},
     db::sync_keyholders,
-    tezos::{
-        coding,
-        micheline::{self, HexEncodable},
-        TzError,
-    },
     DbPool,
 };
 use crate::{
-    api::models::{authentication::AuthenticationChallenge, error::APIError},
+    api::models::{
+        authentication::{AuthenticationChallenge, SignInMessage},
+        error::APIError,
+    },
     auth::is_authenticated,
-    crypto,
     db::models::authentication_challenge::{
         AuthenticationChallenge as DBAuthenticationChallenge, NewAuthenticationChallenge,
     },
@@ -74,23 +72,11 @@ pub async fn sign_in(
     }
 
     let address = query.address.clone();
-    let branch = block_hash(&node_url).await?;
-    let forged_branch = hex::encode(coding::encode(&branch, coding::B, None)?);
-    let content = micheline::bytes(
-        format!(
-            "Tezos Signed Message: {} {} {}",
-            server_settings.domain_name,
-            chrono::Utc::now(),
-            bs58::encode(crypto::generate_random_bytes(10)).into_string()
-        )
-        .as_bytes()
-        .to_vec(),
-    )
-    .to_hex_encoded()?
-    .split_off(2);
+    let message = SignInMessage::new(&server_settings.domain_name, Utc::now());
     let new_authentication_challenge = NewAuthenticationChallenge {
         address,
-        challenge: format!("03{}11{}", forged_branch, content),
+        challenge: message.pack()?,
+        expires_at: message.expires_at.naive_utc(),
     };
 
     let conn = pool.get()?;
@@ -120,16 +106,3 @@ pub async fn me(
 
     Ok(HttpResponse::Ok().json(AuthUser::from(user, current_user)))
 }
-
-async fn block_hash(node_url: &str) -> Result<String, APIError> {
-    let url = format!("{}/chains/main/blocks/head/hash", node_url);
-
-    let response = reqwest::get(&url)
-        .await
-        .map_err(|_error| TzError::NetworkFailure)?
-        .json::<String>()
-        .await
-        .map_err(|_error| TzError::ParsingFailure)?;
-
-    Ok(response)
-}
@@ -19,4 +19,9 @@ pub fn api_config(cfg: &mut web::ServiceConfig) {
             .route(web::patch().to(patch::me))
             .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
     );
+    cfg.service(
+        web::resource("/auth/refresh")
+            .route(web::post().to(post::refresh))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
 }
@@ -2,25 +2,29 @@ use std::convert::TryInto;
 
 use actix_session::Session;
 use actix_web::{web, HttpResponse};
+use chrono::Utc;
 use log::info;
 
-use crate::{
-    api::models::{
-        authentication::AuthenticationChallengeResponse, error::APIError, user::AuthUser,
-    },
-    auth::{set_current_user, SessionUser},
-    db::models::authentication_challenge::AuthenticationChallenge,
-};
 use crate::{
     api::models::{authentication::AuthenticationChallengeState, user::UserState},
     crypto,
     db::models::user::User,
-    DbPool,
+    settings, DbPool,
+};
+use crate::{
+    api::models::{
+        authentication::{AuthenticationChallengeResponse, SessionRefresh, SignInMessage},
+        error::APIError,
+        user::AuthUser,
+    },
+    auth::{refresh_session, set_current_user, SessionUser},
+    db::models::authentication_challenge::AuthenticationChallenge,
 };
 
 pub async fn sign_in(
     pool: web::Data<DbPool>,
     body: web::Json<AuthenticationChallengeResponse>,
+    server_settings: web::Data<settings::Server>,
     session: Session,
 ) -> Result<HttpResponse, APIError> {
     let authentication_challenge_id = body.id;
@@ -46,6 +50,9 @@ pub async fn sign_in(
         return Err(APIError::Forbidden);
     }
 
+    SignInMessage::unpack(&authentication_challenge.challenge)?
+        .validate(&server_settings.domain_name, Utc::now())?;
+
     let user = users.first().unwrap();
     let challenge_bytes =
         hex::decode(authentication_challenge.challenge).map_err(|_error| APIError::Internal {
@@ -76,3 +83,12 @@ pub async fn sign_in(
 
     Ok(HttpResponse::Ok().json(AuthUser::from(user.to_owned(), session_user)))
 }
+
+pub async fn refresh(
+    server_settings: web::Data<settings::Server>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    let expires_at = refresh_session(&session, server_settings.inactivity_timeout_seconds)?;
+
+    Ok(HttpResponse::Ok().json(SessionRefresh { expires_at }))
+}
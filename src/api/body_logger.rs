@@ -0,0 +1,318 @@
+use std::{
+    cell::RefCell,
+    future::{ready, Future, Ready},
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+use actix_web::{
+    dev::{Body, Payload, ResponseBody, Service, ServiceRequest, ServiceResponse, Transform},
+    web::{Bytes, BytesMut},
+    Error,
+};
+use futures::{Stream, StreamExt};
+use log::info;
+use serde_json::Value;
+
+use crate::settings::{self, ENV};
+
+const REDACTED_FIELDS: [&str; 3] = ["signature", "ledger_hash", "token"];
+const REDACTED: &str = "<redacted>";
+
+type Sink = Rc<dyn Fn(String)>;
+
+/// Logs the JSON request and response bodies of the wrapped services, with signatures, ledger
+/// hashes and API key tokens redacted. It never logs anything in production.
+pub struct BodyLogger {
+    enabled: bool,
+    max_bytes: usize,
+    sink: Sink,
+}
+
+impl BodyLogger {
+    pub fn new(settings: Option<&settings::BodyLogging>, env: &ENV) -> Self {
+        BodyLogger::with_sink(settings, env, Rc::new(|line| info!("{}", line)))
+    }
+
+    fn with_sink(settings: Option<&settings::BodyLogging>, env: &ENV, sink: Sink) -> Self {
+        BodyLogger {
+            enabled: *env != ENV::Production && settings.map_or(false, |settings| settings.enabled),
+            max_bytes: settings.map_or(0, |settings| settings.max_bytes),
+            sink,
+        }
+    }
+}
+
+impl<S> Transform<S> for BodyLogger
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = BodyLoggerMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(BodyLoggerMiddleware {
+            service: Rc::new(RefCell::new(service)),
+            enabled: self.enabled,
+            max_bytes: self.max_bytes,
+            sink: self.sink.clone(),
+        }))
+    }
+}
+
+pub struct BodyLoggerMiddleware<S> {
+    service: Rc<RefCell<S>>,
+    enabled: bool,
+    max_bytes: usize,
+    sink: Sink,
+}
+
+impl<S> Service for BodyLoggerMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
+        if !self.enabled {
+            return Box::pin(self.service.borrow_mut().call(req));
+        }
+
+        let service = self.service.clone();
+        let max_bytes = self.max_bytes;
+        let sink = self.sink.clone();
+        Box::pin(async move {
+            let method = req.method().clone();
+            let path = req.path().to_owned();
+
+            let request_body = collect(req.take_payload()).await?;
+            sink(format!(
+                "{} {} request body: {}",
+                method,
+                path,
+                loggable_body(&request_body, max_bytes)
+            ));
+            let (_, mut payload) = actix_http::h1::Payload::create(true);
+            payload.unread_data(request_body);
+            req.set_payload(Payload::from(payload));
+
+            let response = service.borrow_mut().call(req);
+            let mut response = response.await?;
+
+            let response_body = collect(response.take_body()).await?;
+            sink(format!(
+                "{} {} response body: {}",
+                method,
+                path,
+                loggable_body(&response_body, max_bytes)
+            ));
+
+            Ok(response.map_body(move |_, _| ResponseBody::Body(Body::from(response_body))))
+        })
+    }
+}
+
+async fn collect<T, E>(mut stream: T) -> Result<Bytes, Error>
+where
+    T: Stream<Item = Result<Bytes, E>> + Unpin,
+    E: Into<Error>,
+{
+    let mut bytes = BytesMut::new();
+    while let Some(chunk) = stream.next().await {
+        bytes.extend_from_slice(&chunk.map_err(Into::into)?);
+    }
+
+    Ok(bytes.freeze())
+}
+
+fn loggable_body(body: &[u8], max_bytes: usize) -> String {
+    if body.is_empty() {
+        return "<empty>".into();
+    }
+
+    match serde_json::from_slice::<Value>(body) {
+        Ok(mut value) => {
+            redact(&mut value);
+            truncated(value.to_string(), max_bytes)
+        }
+        Err(_) => format!("<{} bytes, not JSON>", body.len()),
+    }
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (key, value) in fields.iter_mut() {
+                if REDACTED_FIELDS.contains(&key.as_str()) {
+                    *value = Value::String(REDACTED.into());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+fn truncated(mut body: String, max_bytes: usize) -> String {
+    if body.len() <= max_bytes {
+        return body;
+    }
+
+    let mut end = max_bytes;
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+    body.truncate(end);
+    body.push_str("...");
+
+    body
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, rc::Rc};
+
+    use actix_web::{test, web, App, HttpResponse};
+    use chrono::NaiveDateTime;
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+
+    use super::{loggable_body, BodyLogger};
+    use crate::{
+        api::models::api_key::{ApiKey, ApiKeyAction, CreatedApiKey},
+        settings::{BodyLogging, ENV},
+    };
+
+    async fn logged_lines(settings: BodyLogging, env: ENV) -> Vec<String> {
+        let lines = Rc::new(RefCell::new(Vec::<String>::new()));
+        let sink_lines = lines.clone();
+        let logger = BodyLogger::with_sink(
+            Some(&settings),
+            &env,
+            Rc::new(move |line| sink_lines.borrow_mut().push(line)),
+        );
+        let mut app = test::init_service(App::new().wrap(logger).route(
+            "/echo",
+            web::post().to(|body: web::Json<Value>| HttpResponse::Ok().json(body.into_inner())),
+        ))
+        .await;
+
+        let request = test::TestRequest::post()
+            .uri("/echo")
+            .set_json(&json!({ "kind": "mint", "signature": "edsig..." }))
+            .to_request();
+        let response: Value = test::read_response_json(&mut app, request).await;
+        assert_eq!(response["signature"], "edsig...");
+
+        lines.take()
+    }
+
+    #[actix_rt::test]
+    async fn test_bodies_logged_in_development() {
+        let settings = BodyLogging {
+            enabled: true,
+            max_bytes: 1024,
+        };
+        let lines = logged_lines(settings, ENV::Development).await;
+
+        assert_eq!(
+            lines,
+            vec![
+                "POST /echo request body: {\"kind\":\"mint\",\"signature\":\"<redacted>\"}",
+                "POST /echo response body: {\"kind\":\"mint\",\"signature\":\"<redacted>\"}",
+            ]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_bodies_not_logged_when_disabled() {
+        let settings = BodyLogging {
+            enabled: false,
+            max_bytes: 1024,
+        };
+        assert!(logged_lines(settings, ENV::Development).await.is_empty());
+
+        let settings = BodyLogging {
+            enabled: true,
+            max_bytes: 1024,
+        };
+        assert!(logged_lines(settings, ENV::Production).await.is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_api_key_token_not_logged() {
+        let lines = Rc::new(RefCell::new(Vec::<String>::new()));
+        let sink_lines = lines.clone();
+        let logger = BodyLogger::with_sink(
+            Some(&BodyLogging {
+                enabled: true,
+                max_bytes: 1024,
+            }),
+            &ENV::Development,
+            Rc::new(move |line| sink_lines.borrow_mut().push(line)),
+        );
+        let mut app = test::init_service(App::new().wrap(logger).route(
+            "/api-keys",
+            web::post().to(|| {
+                HttpResponse::Ok().json(CreatedApiKey {
+                    api_key: ApiKey {
+                        id: Uuid::nil(),
+                        created_at: NaiveDateTime::from_timestamp(0, 0),
+                        contract_id: Uuid::nil(),
+                        name: "minter".into(),
+                        address: "tz1YHWw8NTdQcF3Mwdyb3E6L7ivGEWHPsXEm".into(),
+                        actions: vec![ApiKeyAction::CreateOperationRequest],
+                        created_by: "tz1YHWw8NTdQcF3Mwdyb3E6L7ivGEWHPsXEm".into(),
+                        revoked_at: None,
+                    },
+                    token: "secret-token".into(),
+                })
+            }),
+        ))
+        .await;
+
+        let request = test::TestRequest::post()
+            .uri("/api-keys")
+            .set_json(&json!({ "name": "minter" }))
+            .to_request();
+        let response: Value = test::read_response_json(&mut app, request).await;
+        assert_eq!(response["token"], "secret-token");
+
+        let lines = lines.take();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].starts_with("POST /api-keys response body: "));
+        assert!(lines[1].contains("\"token\":\"<redacted>\""));
+        assert!(!lines.iter().any(|line| line.contains("secret-token")));
+    }
+
+    #[test]
+    fn test_loggable_body() {
+        let body = json!({
+            "operation_request_id": "b3b5a1c2",
+            "approvals": [{ "ledger_hash": "abc", "signature": "edsig..." }],
+        })
+        .to_string();
+
+        assert_eq!(
+            loggable_body(body.as_bytes(), 1024),
+            "{\"operation_request_id\":\"b3b5a1c2\",\"approvals\":[{\"ledger_hash\":\"<redacted>\",\"signature\":\"<redacted>\"}]}"
+        );
+        assert_eq!(loggable_body(body.as_bytes(), 10), "{\"operatio...");
+        assert_eq!(loggable_body(b"edsig...", 1024), "<8 bytes, not JSON>");
+        assert_eq!(loggable_body(b"", 1024), "<empty>");
+    }
+}
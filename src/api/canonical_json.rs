@@ -0,0 +1,99 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::crypto;
+
+use super::models::error::APIError;
+
+/// Serializes `value` to canonical JSON: object keys sorted by their bytes, no insignificant
+/// whitespace. Bodies have to be serialized with it before they are hashed, as serde_json keeps
+/// maps in insertion order (`preserve_order`) and struct fields in declaration order.
+///
+/// Imports of operation request histories are recorded in the audit log with their `hash`.
+pub fn to_vec<T: Serialize>(value: &T) -> Result<Vec<u8>, APIError> {
+    let value = serde_json::to_value(value).map_err(|error| APIError::Internal {
+        description: format!("failed to serialize value: {}", error),
+    })?;
+
+    serde_json::to_vec(&canonicalize(value)).map_err(|error| APIError::Internal {
+        description: format!("failed to serialize value: {}", error),
+    })
+}
+
+/// The blake2b hash of the canonical JSON of `value`.
+pub fn hash<T: Serialize>(value: &T) -> Result<Vec<u8>, APIError> {
+    crypto::generic_hash(&to_vec(value)?, 32).map_err(|_error| APIError::Internal {
+        description: "failed to hash value".into(),
+    })
+}
+
+fn canonicalize(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries = map.into_iter().collect::<Vec<_>>();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, canonicalize(value)))
+                    .collect::<Map<_, _>>(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(canonicalize).collect()),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::{hash, to_vec};
+    use crate::{
+        api::models::error::APIError,
+        tezos::micheline::{data, int, string, MichelsonV1Expression},
+    };
+
+    #[test]
+    fn test_key_order_and_whitespace() -> Result<(), APIError> {
+        let sorted = json!({ "a": 1, "b": { "c": [1, { "d": true, "e": null }] } });
+        let unsorted: serde_json::Value = serde_json::from_str(
+            r#"{ "b" : { "c" : [ 1, { "e": null, "d": true } ] },
+                "a": 1 }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(to_vec(&unsorted)?).unwrap(),
+            r#"{"a":1,"b":{"c":[1,{"d":true,"e":null}]}}"#
+        );
+        assert_eq!(to_vec(&sorted)?, to_vec(&unsorted)?);
+        assert_eq!(hash(&sorted)?, hash(&unsorted)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_micheline_serialization_is_stable() -> Result<(), APIError> {
+        let built = data::pair(
+            string("tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT".into()),
+            int(1000),
+        );
+        let parsed: MichelsonV1Expression = serde_json::from_str(
+            r#"{ "args": [ { "string": "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT" },
+                { "int": "1000" } ], "prim": "Pair" }"#,
+        )
+        .unwrap();
+
+        assert_eq!(built, parsed);
+        assert_eq!(to_vec(&built)?, to_vec(&parsed)?);
+        assert_eq!(to_vec(&built)?, to_vec(&built.clone())?);
+        assert_eq!(
+            String::from_utf8(to_vec(&built)?).unwrap(),
+            r#"{"args":[{"string":"tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT"},{"int":"1000"}],"prim":"Pair"}"#
+        );
+
+        Ok(())
+    }
+}
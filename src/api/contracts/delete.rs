@@ -0,0 +1,72 @@
+use actix_session::Session;
+use actix_web::{
+    http::StatusCode,
+    web::{self, Path},
+    HttpResponse,
+};
+use log::info;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    api::models::{error::APIError, operation_request::OperationRequestKind, user::UserKind},
+    auth::get_current_user,
+    db::models::{allowed_target_address::AllowedTargetAddress, capability::Capability},
+    settings, DbPool,
+};
+
+#[derive(Deserialize)]
+pub struct PathInfo {
+    id: Uuid,
+    address: String,
+}
+
+pub async fn allowed_target_address(
+    pool: web::Data<DbPool>,
+    path: Path<PathInfo>,
+    server_settings: web::Data<settings::Server>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+    let contract_id = path.id;
+    current_user.require_roles(vec![UserKind::Admin], contract_id)?;
+
+    let conn = pool.get()?;
+    let address = path.address.clone();
+    web::block(move || AllowedTargetAddress::delete(&conn, &contract_id, &address)).await?;
+
+    info!(
+        "User {} removed allowed target address {} on contract {}",
+        current_user.address, path.address, contract_id
+    );
+
+    Ok(HttpResponse::Ok().status(StatusCode::NO_CONTENT).finish())
+}
+
+#[derive(Deserialize)]
+pub struct CapabilityPathInfo {
+    id: Uuid,
+    kind: OperationRequestKind,
+}
+
+pub async fn capability(
+    pool: web::Data<DbPool>,
+    path: Path<CapabilityPathInfo>,
+    server_settings: web::Data<settings::Server>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+    let contract_id = path.id;
+    current_user.require_roles(vec![UserKind::Admin], contract_id)?;
+
+    let conn = pool.get()?;
+    let kind = path.kind;
+    web::block(move || Capability::disable(&conn, &contract_id, kind.into())).await?;
+
+    info!(
+        "User {} disabled capability {} on contract {}",
+        current_user.address, kind, contract_id
+    );
+
+    Ok(HttpResponse::Ok().status(StatusCode::NO_CONTENT).finish())
+}
@@ -1,15 +1,32 @@
-use std::convert::{TryFrom, TryInto};
+use std::{
+    collections::{BTreeMap, HashSet},
+    convert::{TryFrom, TryInto},
+};
 
 use crate::db::models::node_endpoint::NodeEndpoint;
-use crate::tezos::multisig::{self};
+use crate::tezos::{
+    self, coding::validate_address, entrypoints::entrypoint_schema, ledger, multisig,
+};
 use crate::DbPool;
 use crate::{
-    api::models::{common::ListResponse, contract::Contract, error::APIError},
+    api::models::{
+        common::ListResponse,
+        contract::{
+            AllowedTargetAddress, Contract, ContractDelegates, ContractGroup, ContractGrouping,
+            ContractNonces, ParameterSchema, TokenBalance,
+        },
+        error::APIError,
+        user::UserKind,
+    },
+    auth::get_current_user,
+    db::models::allowed_target_address::AllowedTargetAddress as DBAllowedTargetAddress,
     db::models::contract::Contract as DBContract,
     db::models::operation_request::OperationRequest,
 };
 use crate::{settings, Conn};
+use actix_session::Session;
 use actix_web::{web, web::Path, web::Query, HttpResponse};
+use futures::{stream, StreamExt};
 use serde::Deserialize;
 use uuid::Uuid;
 
@@ -17,6 +34,7 @@ use uuid::Uuid;
 pub struct Info {
     page: Option<i64>,
     limit: Option<i64>,
+    group_by: Option<ContractGrouping>,
 }
 
 pub async fn contracts(
@@ -34,9 +52,19 @@ pub async fn contracts(
     let page = query.page.unwrap_or(0);
     let limit = query.limit.unwrap_or(100);
 
-    let result = web::block(move || load_contracts(&conn, page, limit)).await?;
+    match query.group_by {
+        Some(group_by) => {
+            let result =
+                web::block(move || load_contract_groups(&conn, page, limit, group_by)).await?;
 
-    Ok(HttpResponse::Ok().json(result))
+            Ok(HttpResponse::Ok().json(result))
+        }
+        None => {
+            let result = web::block(move || load_contracts(&conn, page, limit)).await?;
+
+            Ok(HttpResponse::Ok().json(result))
+        }
+    }
 }
 
 fn load_contracts(conn: &Conn, page: i64, limit: i64) -> Result<ListResponse<Contract>, APIError> {
@@ -53,6 +81,53 @@ fn load_contracts(conn: &Conn, page: i64, limit: i64) -> Result<ListResponse<Con
     })
 }
 
+/// Groups the contracts of the requested page.
+fn load_contract_groups(
+    conn: &Conn,
+    page: i64,
+    limit: i64,
+    group_by: ContractGrouping,
+) -> Result<ListResponse<ContractGroup>, APIError> {
+    let (contracts, total_pages) = DBContract::get_list(conn, page, limit)?;
+    let keys: Vec<String> = match group_by {
+        ContractGrouping::Symbol => contracts
+            .iter()
+            .map(|(contract, _)| contract.symbol.clone())
+            .collect(),
+        ContractGrouping::Network => {
+            let selected_node = NodeEndpoint::get_selected(conn)?;
+            let nodes = NodeEndpoint::get_all(conn)?;
+            contracts
+                .iter()
+                .map(|(contract, _)| contract_network(contract, &selected_node, &nodes))
+                .collect()
+        }
+    };
+    let contract_responses = contracts
+        .into_iter()
+        .map(|contract| Contract::try_from(contract))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(ListResponse {
+        page,
+        total_pages,
+        results: ContractGroup::group(keys.into_iter().zip(contract_responses)),
+    })
+}
+
+/// The network of the node the contract uses, or the node url if it isn't a configured node.
+fn contract_network(
+    contract: &DBContract,
+    selected_node: &NodeEndpoint,
+    nodes: &[NodeEndpoint],
+) -> String {
+    let node_url = contract.node_url_or(&selected_node.url);
+    nodes
+        .iter()
+        .find(|node| node.url == node_url)
+        .map_or_else(|| node_url.to_owned(), |node| node.network.clone())
+}
+
 #[derive(Deserialize)]
 pub struct PathInfo {
     id: Uuid,
@@ -96,15 +171,260 @@ pub async fn next_usable_nonce(
 
     let conn = pool.get()?;
     let max_local_nonce = web::block::<_, _, APIError>(move || {
-        Ok(OperationRequest::max_nonce(&conn, &contract_id).unwrap_or(-1))
+        Ok(OperationRequest::max_nonce(&conn, &contract_id).ok())
     })
     .await?;
 
-    let nonce = std::cmp::max(multisig_nonce, max_local_nonce + 1);
+    let nonce = compute_next_nonce(multisig_nonce, max_local_nonce);
 
     Ok(HttpResponse::Ok().json(nonce))
 }
 
+/// At most this many contracts can be asked for with `GET /contracts/nonces` at once.
+const MAX_NONCES_CONTRACTS: usize = 100;
+
+#[derive(Deserialize)]
+pub struct NoncesInfo {
+    ids: String,
+}
+
+pub async fn contract_nonces(
+    pool: web::Data<DbPool>,
+    query: Query<NoncesInfo>,
+    server_settings: web::Data<settings::Server>,
+) -> Result<HttpResponse, APIError> {
+    let contract_ids = parse_contract_ids(&query.ids)?;
+    let conn = pool.get()?;
+    let (contracts, node_url) = web::block::<_, _, APIError>(move || {
+        let contracts = DBContract::get_all_with_ids(&conn, contract_ids.iter().collect())?;
+        if contracts.len() != contract_ids.len() {
+            return Err(APIError::NotFound);
+        }
+        let contracts = contracts
+            .into_iter()
+            .map(|contract| {
+                let max_local_nonce = OperationRequest::max_nonce(&conn, &contract.id).ok();
+                (contract, max_local_nonce)
+            })
+            .collect::<Vec<_>>();
+
+        Ok((contracts, NodeEndpoint::get_selected(&conn)?.url))
+    })
+    .await?;
+
+    let nonces = fetch_contract_nonces(
+        contracts,
+        &node_url,
+        server_settings.max_concurrent_node_requests(),
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(nonces))
+}
+
+/// Parses the comma separated contract ids of `GET /contracts/nonces`, without duplicates.
+fn parse_contract_ids(ids: &str) -> Result<Vec<Uuid>, APIError> {
+    let mut seen = HashSet::new();
+    let contract_ids = ids
+        .split(',')
+        .map(|id| {
+            Uuid::parse_str(id.trim()).map_err(|_error| APIError::InvalidValue {
+                description: format!("{:?} is not a contract id", id),
+            })
+        })
+        .filter(|id| id.as_ref().map_or(true, |id| seen.insert(*id)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if contract_ids.len() > MAX_NONCES_CONTRACTS {
+        return Err(APIError::InvalidValue {
+            description: format!(
+                "at most {} contract ids can be requested at once",
+                MAX_NONCES_CONTRACTS
+            ),
+        });
+    }
+
+    Ok(contract_ids)
+}
+
+/// Fetches the multisig nonces of the `contracts`, with at most `concurrency` node requests at a
+/// time. Each contract comes with the highest nonce of its operation requests, if it has any.
+async fn fetch_contract_nonces(
+    contracts: Vec<(DBContract, Option<i64>)>,
+    node_url: &str,
+    concurrency: usize,
+) -> Result<BTreeMap<Uuid, ContractNonces>, APIError> {
+    stream::iter(contracts)
+        .map(|(contract, max_local_nonce)| async move {
+            let mut multisig = multisig::get_multisig(
+                contract.multisig_pkh.as_ref(),
+                contract.kind.try_into()?,
+                contract.node_url_or(node_url),
+            );
+            let on_chain = multisig.nonce().await?.value();
+
+            Result::<_, APIError>::Ok((
+                contract.id,
+                ContractNonces {
+                    on_chain,
+                    next_usable: compute_next_nonce(on_chain, max_local_nonce),
+                },
+            ))
+        })
+        .buffer_unordered(concurrency)
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect()
+}
+
+/// The nonce the next operation request of a contract uses, after both the nonce of its
+/// multisig and the highest nonce of its operation requests.
+fn compute_next_nonce(multisig_nonce: i64, max_local_nonce: Option<i64>) -> i64 {
+    max_local_nonce.map_or(multisig_nonce, |max_local_nonce| {
+        std::cmp::max(multisig_nonce, max_local_nonce + 1)
+    })
+}
+
+pub async fn allowed_target_addresses(
+    pool: web::Data<DbPool>,
+    path: Path<PathInfo>,
+) -> Result<HttpResponse, APIError> {
+    let conn = pool.get()?;
+    let contract_id = path.id;
+
+    let allowed_target_addresses = web::block::<_, _, APIError>(move || {
+        let contract = DBContract::get(&conn, &contract_id)?;
+        Ok(DBAllowedTargetAddress::get_all(&conn, &contract.id)?)
+    })
+    .await?;
+    let response = allowed_target_addresses
+        .into_iter()
+        .map(AllowedTargetAddress::from)
+        .collect::<Vec<_>>();
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[derive(Deserialize)]
+pub struct BalanceInfo {
+    owner: String,
+}
+
+pub async fn balance(
+    pool: web::Data<DbPool>,
+    path: Path<PathInfo>,
+    query: Query<BalanceInfo>,
+    contract_settings: web::Data<Vec<settings::Contract>>,
+) -> Result<HttpResponse, APIError> {
+    validate_address(&query.owner)?;
+    let contract_id = path.id;
+    let conn = pool.get()?;
+    let (contract, node_url) = web::block::<_, _, APIError>(move || {
+        Ok((
+            DBContract::get(&conn, &contract_id)?,
+            NodeEndpoint::get_selected(&conn)?.url,
+        ))
+    })
+    .await?;
+
+    let ledger_big_map_id = settings::Contract::find(&contract_settings, &contract)
+        .and_then(|contract_setting| contract_setting.ledger_big_map_id)
+        .ok_or_else(|| APIError::InvalidValue {
+            description: format!("no ledger big map configured for contract {}", contract.pkh),
+        })?;
+
+    let balance = ledger::balance(
+        contract.node_url_or(&node_url),
+        ledger_big_map_id,
+        contract.kind.try_into()?,
+        &query.owner,
+        contract.token_id as i64,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(TokenBalance {
+        owner: query.owner.clone(),
+        token_id: contract.token_id,
+        balance: balance.to_string(),
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct ParameterSchemaInfo {
+    entrypoint: Option<String>,
+}
+
+pub async fn parameter_schema(
+    pool: web::Data<DbPool>,
+    path: Path<PathInfo>,
+    query: Query<ParameterSchemaInfo>,
+    server_settings: web::Data<settings::Server>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+    let contract_id = path.id;
+    current_user.require_roles(vec![UserKind::Gatekeeper, UserKind::Keyholder], contract_id)?;
+
+    let conn = pool.get()?;
+    let (contract, node_url) = web::block::<_, _, APIError>(move || {
+        Ok((
+            DBContract::get(&conn, &contract_id)?,
+            NodeEndpoint::get_selected(&conn)?.url,
+        ))
+    })
+    .await?;
+
+    let entrypoint = query
+        .entrypoint
+        .clone()
+        .unwrap_or_else(|| "mainParameter".into());
+    let schema = entrypoint_schema(
+        contract.node_url_or(&node_url),
+        &contract.multisig_pkh,
+        &entrypoint,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(ParameterSchema {
+        address: contract.multisig_pkh,
+        entrypoint,
+        michelson: schema.to_string(),
+        schema,
+    }))
+}
+
+pub async fn delegate(
+    pool: web::Data<DbPool>,
+    path: Path<PathInfo>,
+    server_settings: web::Data<settings::Server>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+    let contract_id = path.id;
+    current_user.require_roles(vec![UserKind::Gatekeeper, UserKind::Keyholder], contract_id)?;
+
+    let conn = pool.get()?;
+    let (contract, node_url) = web::block::<_, _, APIError>(move || {
+        Ok((
+            DBContract::get(&conn, &contract_id)?,
+            NodeEndpoint::get_selected(&conn)?.url,
+        ))
+    })
+    .await?;
+
+    let node_url = contract.node_url_or(&node_url);
+    let contract_delegate = tezos::delegate(node_url, &contract.pkh).await?;
+    let multisig_delegate = tezos::delegate(node_url, &contract.multisig_pkh).await?;
+
+    Ok(HttpResponse::Ok().json(ContractDelegates {
+        contract_address: contract.pkh.clone(),
+        contract_delegate,
+        multisig_address: contract.multisig_pkh.clone(),
+        multisig_delegate,
+    }))
+}
+
 async fn multisig_nonce(
     pool: &web::Data<DbPool>,
     contract_id: Uuid,
@@ -115,8 +435,118 @@ async fn multisig_nonce(
     let mut multisig = multisig::get_multisig(
         contract.multisig_pkh.as_ref(),
         contract.kind.try_into()?,
-        node_url,
+        contract.node_url_or(node_url),
     );
 
-    Ok(multisig.nonce().await?)
+    Ok(multisig.nonce().await?.value())
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{web, App, HttpResponse};
+    use uuid::Uuid;
+
+    use super::{compute_next_nonce, fetch_contract_nonces, parse_contract_ids};
+    use crate::{
+        api::models::{contract::ContractNonces, error::APIError},
+        db::models::contract::Contract as DBContract,
+        fixtures,
+    };
+
+    const FIRST_MULTISIG: &str = "KT1MeQd67djiEEkscx3USfQ7vFYg589DySpZ";
+    const SECOND_MULTISIG: &str = "KT1S4QXjASmM3ei7a6raRLkr4TbnKu7RuC9X";
+
+    fn contract(multisig_pkh: &str) -> DBContract {
+        DBContract {
+            multisig_pkh: multisig_pkh.into(),
+            display_name: multisig_pkh.into(),
+            ..fixtures::contract()
+        }
+    }
+
+    /// A node with the storage of a generic multisig at each address, whose counter is 4 for
+    /// `FIRST_MULTISIG` and 9 otherwise.
+    fn storage_node() -> actix_web::test::TestServer {
+        actix_web::test::start(|| {
+            App::new().route(
+                "/chains/main/blocks/head/context/contracts/{address}/storage/normalized",
+                web::post().to(|address: web::Path<String>| {
+                    let counter = if address.as_str() == FIRST_MULTISIG {
+                        "4"
+                    } else {
+                        "9"
+                    };
+                    HttpResponse::Ok().json(serde_json::json!({
+                        "prim": "Pair",
+                        "args": [
+                            { "int": counter },
+                            {
+                                "prim": "Pair",
+                                "args": [
+                                    { "int": "1" },
+                                    [{ "bytes": "00e2b5ecd9ce2c1ed0e5ee4d88f1bdc81b6a2a9a4b4a7a0bfa91c9c20e4d9d9b8a" }]
+                                ]
+                            }
+                        ]
+                    }))
+                }),
+            )
+        })
+    }
+
+    #[actix_rt::test]
+    async fn test_fetch_contract_nonces() -> Result<(), APIError> {
+        let node = storage_node();
+        let node_url = node.url("").trim_end_matches('/').to_owned();
+        let first = contract(FIRST_MULTISIG);
+        let second = contract(SECOND_MULTISIG);
+        let (first_id, second_id) = (first.id, second.id);
+
+        let nonces =
+            fetch_contract_nonces(vec![(first, Some(7)), (second, None)], &node_url, 1).await?;
+        assert_eq!(nonces.len(), 2);
+        assert_eq!(
+            nonces[&first_id],
+            ContractNonces {
+                on_chain: 5,
+                next_usable: 8,
+            }
+        );
+        assert_eq!(
+            nonces[&second_id],
+            ContractNonces {
+                on_chain: 10,
+                next_usable: 10,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compute_next_nonce() {
+        assert_eq!(compute_next_nonce(5, None), 5);
+        assert_eq!(compute_next_nonce(5, Some(2)), 5);
+        assert_eq!(compute_next_nonce(5, Some(5)), 6);
+    }
+
+    #[test]
+    fn test_parse_contract_ids() -> Result<(), APIError> {
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+
+        assert_eq!(
+            parse_contract_ids(&format!("{}, {},{}", first, second, first))?,
+            vec![first, second]
+        );
+        assert!(parse_contract_ids("").is_err());
+        assert!(parse_contract_ids(&format!("{},contract", first)).is_err());
+        let too_many = (0..101)
+            .map(|_| Uuid::new_v4().to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        assert!(parse_contract_ids(&too_many).is_err());
+
+        Ok(())
+    }
 }
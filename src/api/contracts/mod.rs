@@ -1,6 +1,8 @@
 use actix_web::{web, HttpResponse};
 
+mod delete;
 mod get;
+mod post;
 
 pub fn api_config(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -8,6 +10,11 @@ pub fn api_config(cfg: &mut web::ServiceConfig) {
             .route(web::get().to(get::contracts))
             .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
     );
+    cfg.service(
+        web::resource("/contracts/nonces")
+            .route(web::get().to(get::contract_nonces))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
     cfg.service(
         web::resource("/contracts/{id}")
             .route(web::get().to(get::contract))
@@ -23,4 +30,40 @@ pub fn api_config(cfg: &mut web::ServiceConfig) {
             .route(web::get().to(get::next_usable_nonce))
             .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
     );
+    cfg.service(
+        web::resource("/contracts/{id}/balance")
+            .route(web::get().to(get::balance))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
+    cfg.service(
+        web::resource("/contracts/{id}/parameter-schema")
+            .route(web::get().to(get::parameter_schema))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
+    cfg.service(
+        web::resource("/contracts/{id}/delegate")
+            .route(web::get().to(get::delegate))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
+    cfg.service(
+        web::resource("/contracts/{id}/allowed-target-addresses")
+            .route(web::get().to(get::allowed_target_addresses))
+            .route(web::post().to(post::allowed_target_address))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
+    cfg.service(
+        web::resource("/contracts/{id}/allowed-target-addresses/{address}")
+            .route(web::delete().to(delete::allowed_target_address))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
+    cfg.service(
+        web::resource("/contracts/{id}/capabilities")
+            .route(web::post().to(post::capability))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
+    cfg.service(
+        web::resource("/contracts/{id}/capabilities/{kind}")
+            .route(web::delete().to(delete::capability))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
 }
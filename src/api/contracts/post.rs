@@ -0,0 +1,104 @@
+use std::convert::TryFrom;
+
+use actix_session::Session;
+use actix_web::{
+    web::{self, Path},
+    HttpResponse,
+};
+use log::info;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    api::models::{
+        contract::{
+            AllowedTargetAddress, Contract as APIContract, ContractKind, NewAllowedTargetAddress,
+            NewCapability,
+        },
+        error::APIError,
+        user::UserKind,
+    },
+    auth::get_current_user,
+    db::models::{
+        allowed_target_address::{
+            AllowedTargetAddress as DBAllowedTargetAddress,
+            NewAllowedTargetAddress as DBNewAllowedTargetAddress,
+        },
+        capability::Capability,
+        contract::Contract,
+    },
+    settings,
+    tezos::coding::validate_address,
+    DbPool,
+};
+
+#[derive(Deserialize)]
+pub struct PathInfo {
+    id: Uuid,
+}
+
+pub async fn allowed_target_address(
+    pool: web::Data<DbPool>,
+    path: Path<PathInfo>,
+    body: web::Json<NewAllowedTargetAddress>,
+    server_settings: web::Data<settings::Server>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+    let contract_id = path.id;
+    current_user.require_roles(vec![UserKind::Admin], contract_id)?;
+
+    let address = body.into_inner().address;
+    validate_address(&address)?;
+
+    let conn = pool.get()?;
+    let allowed_target_address = web::block::<_, _, APIError>(move || {
+        let contract = Contract::get(&conn, &contract_id)?;
+        Ok(DBAllowedTargetAddress::insert(
+            &conn,
+            &DBNewAllowedTargetAddress {
+                contract_id: contract.id,
+                address,
+            },
+        )?)
+    })
+    .await?;
+
+    info!(
+        "User {} allowed target address {} on contract {}",
+        current_user.address, allowed_target_address.address, contract_id
+    );
+
+    Ok(HttpResponse::Ok().json(AllowedTargetAddress::from(allowed_target_address)))
+}
+
+pub async fn capability(
+    pool: web::Data<DbPool>,
+    path: Path<PathInfo>,
+    body: web::Json<NewCapability>,
+    server_settings: web::Data<settings::Server>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+    let contract_id = path.id;
+    current_user.require_roles(vec![UserKind::Admin], contract_id)?;
+
+    let kind = body.into_inner().operation_request_kind;
+    let conn = pool.get()?;
+    let contract = web::block::<_, _, APIError>(move || {
+        let contract = Contract::get(&conn, &contract_id)?;
+        let contract_kind = ContractKind::try_from(contract.kind)?;
+        contract_kind.validate_capability(kind, &contract.pkh)?;
+        Capability::enable(&conn, &contract.id, kind.into())?;
+
+        Ok(Contract::get_with_capabilities(&conn, &contract.id)?)
+    })
+    .await?;
+
+    info!(
+        "User {} enabled capability {} on contract {}",
+        current_user.address, kind, contract_id
+    );
+
+    Ok(HttpResponse::Ok().json(APIContract::try_from(contract)?))
+}
@@ -0,0 +1,11 @@
+use actix_web::{web, HttpResponse};
+
+mod post;
+
+pub fn api_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/debug/unpack")
+            .route(web::post().to(post::unpack))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
+}
@@ -0,0 +1,100 @@
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+
+use crate::{
+    api::models::{
+        debug::{UnpackRequest, Unpacked},
+        error::APIError,
+    },
+    auth::get_current_user,
+    settings,
+    tezos::{micheline::MichelsonV1Expression, TzError},
+};
+
+pub async fn unpack(
+    body: web::Json<UnpackRequest>,
+    server_settings: web::Data<settings::Server>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+
+    let request = body.into_inner();
+    request
+        .type_
+        .check_limits(&server_settings.micheline_limits())?;
+    let unpacked = unpack_with_type(&request)?;
+
+    Ok(HttpResponse::Ok().json(unpacked))
+}
+
+/// Any failure to decode `request.packed` as a value of `request.type_` is reported as an
+/// `InvalidValue` error.
+fn unpack_with_type(request: &UnpackRequest) -> Result<Unpacked, APIError> {
+    let micheline = MichelsonV1Expression::from_packed(&request.packed, Some(&request.type_))
+        .map_err(|error| APIError::InvalidValue {
+            description: format!(
+                "cannot unpack {} as {}: {}",
+                request.packed,
+                request.type_,
+                match error {
+                    TzError::InvalidValue { description } => description,
+                    error => error.to_string(),
+                }
+            ),
+        })?;
+    let michelson = micheline.to_string();
+
+    Ok(Unpacked {
+        micheline,
+        michelson,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::unpack_with_type;
+    use crate::{
+        api::models::{debug::UnpackRequest, error::APIError},
+        tezos::micheline::{data, int, string, types},
+    };
+
+    #[test]
+    fn test_unpack_packed_value() -> Result<(), APIError> {
+        let value = data::pair(
+            string("tz1Ts3m2dXTXB66XN7cg5ALiAvzZY6AxrFd9".into()),
+            int(100),
+        );
+        let schema = types::pair(types::address(), types::int());
+        let packed = value.pack(Some(&schema))?;
+
+        let request: UnpackRequest = serde_json::from_value(json!({
+            "packed": packed,
+            "type": schema,
+        }))
+        .unwrap();
+        let unpacked = unpack_with_type(&request)?;
+        assert_eq!(unpacked.micheline, value);
+        assert_eq!(
+            unpacked.michelson,
+            "(Pair \"tz1Ts3m2dXTXB66XN7cg5ALiAvzZY6AxrFd9\" 100)"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unpack_invalid_data() {
+        let request = UnpackRequest {
+            packed: "0505090a0000001600005a374e077b2e539f222af1e61964d7487c8b95fe00".into(),
+            type_: types::option(types::address()),
+        };
+        match unpack_with_type(&request) {
+            Err(APIError::InvalidValue { description }) => {
+                assert!(description.contains("trailing bytes"), "{}", description)
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,15 @@
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+
+use super::guard::MaintenanceState;
+use crate::{api::models::error::APIError, auth::get_current_user, settings};
+
+pub async fn maintenance(
+    state: web::Data<MaintenanceState>,
+    server_settings: web::Data<settings::Server>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+
+    Ok(HttpResponse::Ok().json(state.get()))
+}
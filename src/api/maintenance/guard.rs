@@ -0,0 +1,246 @@
+use std::{
+    cell::RefCell,
+    future::{ready, Future, Ready},
+    pin::Pin,
+    rc::Rc,
+    sync::RwLock,
+    task::{Context, Poll},
+};
+
+use actix_web::{
+    dev::{Body, Service, ServiceRequest, ServiceResponse, Transform},
+    http::Method,
+    web, Error,
+};
+
+use crate::api::models::{error::APIError, maintenance::MaintenanceMode};
+
+/// Routes that keep accepting writes in maintenance mode, so admins can still sign in and turn
+/// maintenance mode off again. `/debug/unpack` only reads its body and never writes. The paths are
+/// relative to the scope the middleware wraps, so they don't depend on `server.base_path`.
+const EXEMPT_PATHS: [&str; 4] = ["/auth", "/auth/refresh", "/debug/unpack", "/maintenance"];
+
+/// The maintenance mode the server currently runs with. It is loaded from the database on
+/// startup and updated together with it, so requests don't need a database round trip to check it.
+pub struct MaintenanceState {
+    mode: RwLock<MaintenanceMode>,
+}
+
+impl MaintenanceState {
+    pub fn new(mode: MaintenanceMode) -> Self {
+        MaintenanceState {
+            mode: RwLock::new(mode),
+        }
+    }
+
+    pub fn get(&self) -> MaintenanceMode {
+        self.mode.read().unwrap().clone()
+    }
+
+    pub fn set(&self, mode: MaintenanceMode) {
+        *self.mode.write().unwrap() = mode;
+    }
+
+    fn rejects(&self, method: &Method, path: &str) -> Option<APIError> {
+        let mode = self.mode.read().unwrap();
+        let writes = [Method::POST, Method::PUT, Method::PATCH, Method::DELETE];
+        if !mode.enabled || !writes.contains(method) || EXEMPT_PATHS.contains(&path) {
+            return None;
+        }
+
+        let description = match &mode.message {
+            Some(message) => format!("changes are disabled until maintenance ends: {}", message),
+            None => "changes are disabled until maintenance ends".into(),
+        };
+
+        Some(APIError::Maintenance { description })
+    }
+}
+
+/// Responds with `503 Service Unavailable` to the `POST`, `PUT`, `PATCH` and `DELETE` requests
+/// of the wrapped services while maintenance mode is enabled. Reads are always passed through.
+pub struct RejectWritesInMaintenance {
+    state: web::Data<MaintenanceState>,
+}
+
+impl RejectWritesInMaintenance {
+    pub fn new(state: web::Data<MaintenanceState>) -> Self {
+        RejectWritesInMaintenance { state }
+    }
+}
+
+impl<S> Transform<S> for RejectWritesInMaintenance
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RejectWritesInMaintenanceMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RejectWritesInMaintenanceMiddleware {
+            service: Rc::new(RefCell::new(service)),
+            state: self.state.clone(),
+        }))
+    }
+}
+
+pub struct RejectWritesInMaintenanceMiddleware<S> {
+    service: Rc<RefCell<S>>,
+    state: web::Data<MaintenanceState>,
+}
+
+impl<S> Service for RejectWritesInMaintenanceMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        if let Some(error) = self
+            .state
+            .rejects(req.method(), req.match_info().unprocessed())
+        {
+            return Box::pin(ready(Ok(req.error_response(error))));
+        }
+
+        Box::pin(self.service.borrow_mut().call(req))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{http::StatusCode, test, web, App, HttpResponse};
+    use serde_json::Value;
+
+    use super::{MaintenanceState, RejectWritesInMaintenance};
+    use crate::api::models::maintenance::MaintenanceMode;
+
+    fn maintenance_mode(enabled: bool) -> MaintenanceMode {
+        MaintenanceMode {
+            enabled,
+            message: Some("node failover".into()),
+            ..MaintenanceMode::default()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_rejects_writes_while_enabled() {
+        let state = web::Data::new(MaintenanceState::new(maintenance_mode(true)));
+        let mut app = test::init_service(
+            App::new().service(
+                web::scope("/api/v1")
+                    .wrap(RejectWritesInMaintenance::new(state.clone()))
+                    .service(
+                        web::resource("/operation-requests")
+                            .route(web::get().to(|| HttpResponse::Ok().finish()))
+                            .route(web::post().to(|| HttpResponse::Ok().finish())),
+                    )
+                    .route(
+                        "/operation-requests/{id}",
+                        web::delete().to(|| HttpResponse::Ok().finish()),
+                    )
+                    .route("/auth", web::post().to(|| HttpResponse::Ok().finish()))
+                    .route(
+                        "/maintenance",
+                        web::post().to(|| HttpResponse::Ok().finish()),
+                    ),
+            ),
+        )
+        .await;
+
+        let response = test::call_service(
+            &mut app,
+            test::TestRequest::get()
+                .uri("/api/v1/operation-requests")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = test::call_service(
+            &mut app,
+            test::TestRequest::post()
+                .uri("/api/v1/operation-requests")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: Value = test::read_body_json(response).await;
+        assert_eq!(body["error"], "Maintenance");
+        assert!(body["message"].as_str().unwrap().ends_with("node failover"));
+
+        let response = test::call_service(
+            &mut app,
+            test::TestRequest::delete()
+                .uri("/api/v1/operation-requests/1")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        for uri in &["/api/v1/auth", "/api/v1/maintenance"] {
+            let response =
+                test::call_service(&mut app, test::TestRequest::post().uri(uri).to_request()).await;
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        state.set(maintenance_mode(false));
+        let response = test::call_service(
+            &mut app,
+            test::TestRequest::post()
+                .uri("/api/v1/operation-requests")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn test_exempt_paths_below_base_path() {
+        let state = web::Data::new(MaintenanceState::new(maintenance_mode(true)));
+        let mut app = test::init_service(
+            App::new().service(
+                web::scope("/tzwrapped/api/v1")
+                    .wrap(RejectWritesInMaintenance::new(state.clone()))
+                    .route(
+                        "/operation-requests",
+                        web::post().to(|| HttpResponse::Ok().finish()),
+                    )
+                    .route(
+                        "/maintenance",
+                        web::post().to(|| HttpResponse::Ok().finish()),
+                    ),
+            ),
+        )
+        .await;
+
+        let response = test::call_service(
+            &mut app,
+            test::TestRequest::post()
+                .uri("/tzwrapped/api/v1/maintenance")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = test::call_service(
+            &mut app,
+            test::TestRequest::post()
+                .uri("/tzwrapped/api/v1/operation-requests")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
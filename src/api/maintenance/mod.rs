@@ -0,0 +1,14 @@
+use actix_web::{web, HttpResponse};
+
+mod get;
+pub mod guard;
+mod post;
+
+pub fn api_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/maintenance")
+            .route(web::get().to(get::maintenance))
+            .route(web::post().to(post::maintenance))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
+}
@@ -0,0 +1,147 @@
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use diesel::Connection;
+use log::info;
+
+use super::guard::MaintenanceState;
+use crate::{
+    api::models::{
+        error::APIError,
+        maintenance::{MaintenanceMode, NewMaintenanceMode},
+        user::UserKind,
+    },
+    audit,
+    auth::get_current_user,
+    db::models::{contract::Contract, maintenance_mode::MaintenanceMode as DBMaintenanceMode},
+    settings, DbPool,
+};
+
+pub async fn maintenance(
+    pool: web::Data<DbPool>,
+    state: web::Data<MaintenanceState>,
+    body: web::Json<NewMaintenanceMode>,
+    server_settings: web::Data<settings::Server>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+    // maintenance mode freezes the writes of every contract
+    let conn = pool.get()?;
+    let contract_ids =
+        web::block::<_, _, APIError>(move || Ok(Contract::get_all_ids(&conn)?)).await?;
+    current_user.require_roles_on_all(vec![UserKind::Admin], &contract_ids)?;
+
+    let new_mode = body.into_inner();
+    let actor = current_user.address;
+    let conn = pool.get()?;
+    let saved = web::block::<_, _, APIError>(move || {
+        conn.transaction(|| {
+            let message = new_mode.message.as_deref();
+            let saved = DBMaintenanceMode::save(&conn, new_mode.enabled, message, &actor)?;
+            audit::record(
+                &conn,
+                audit::maintenance_mode_changed(&actor, new_mode.enabled, message),
+            )?;
+
+            Ok(saved)
+        })
+    })
+    .await?;
+
+    let mode = MaintenanceMode::from(saved);
+    info!(
+        "Maintenance mode {} by {:?}",
+        if mode.enabled { "enabled" } else { "disabled" },
+        mode.updated_by
+    );
+    state.set(mode.clone());
+
+    Ok(HttpResponse::Ok().json(mode))
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+
+    use actix_session::{CookieSession, UserSession};
+    use actix_web::{dev::Service, http::StatusCode, test, web, App};
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::maintenance;
+    use crate::{
+        api::{
+            maintenance::guard::MaintenanceState,
+            models::{maintenance::MaintenanceMode, user::UserKind},
+        },
+        auth::set_current_user,
+        db::{models::contract::NewContract, test_pool},
+        fixtures,
+    };
+
+    /// Enables maintenance mode as an admin of the contracts at `admin_of` of two stored
+    /// contracts.
+    async fn enable_maintenance(admin_of: &[usize]) -> StatusCode {
+        let pool = test_pool();
+        let contract_ids = {
+            let conn = pool.get().unwrap();
+            [
+                "KT1S4QXjASmM3ei7a6raRLkr4TbnKu7RuC9X",
+                "KT1PWx2mnDueood7fEmfbBDKx1D9BAnnXitn",
+            ]
+            .iter()
+            .map(|pkh| {
+                NewContract {
+                    pkh: (*pkh).into(),
+                    ..fixtures::new_contract()
+                }
+                .save(&conn)
+                .unwrap()
+                .id
+            })
+            .collect::<Vec<_>>()
+        };
+        let user = Rc::new(fixtures::session_user(
+            UserKind::Admin,
+            &admin_of
+                .iter()
+                .map(|index| contract_ids[*index])
+                .collect::<Vec<Uuid>>(),
+        ));
+        let mut app = test::init_service(
+            App::new()
+                .data(pool)
+                .data(MaintenanceState::new(MaintenanceMode::default()))
+                .data(fixtures::server_settings())
+                .wrap_fn(move |req, srv| {
+                    set_current_user(&req.get_session(), &user).unwrap();
+                    srv.call(req)
+                })
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .route("/maintenance", web::post().to(maintenance)),
+        )
+        .await;
+
+        let response = test::call_service(
+            &mut app,
+            test::TestRequest::post()
+                .uri("/maintenance")
+                .set_json(&json!({ "enabled": true }))
+                .to_request(),
+        )
+        .await;
+
+        response.status()
+    }
+
+    #[actix_rt::test]
+    #[ignore]
+    async fn test_single_contract_admin_cannot_enable_maintenance() {
+        assert_eq!(enable_maintenance(&[0]).await, StatusCode::FORBIDDEN);
+    }
+
+    #[actix_rt::test]
+    #[ignore]
+    async fn test_admin_of_every_contract_enables_maintenance() {
+        assert_eq!(enable_maintenance(&[0, 1]).await, StatusCode::OK);
+    }
+}
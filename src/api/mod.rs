@@ -1,7 +1,148 @@
+use actix_web::web;
+
+use self::models::error::APIError;
+
+pub mod amount;
+pub mod api_keys;
+pub mod audit_log;
 pub mod authentication;
+pub mod body_logger;
+pub mod canonical_json;
 pub mod contracts;
+pub mod debug;
+pub mod maintenance;
 pub mod models;
 pub mod nodes;
+pub mod notifications;
+pub mod openapi;
 pub mod operation_approvals;
 pub mod operation_requests;
+pub mod request_id;
 pub mod users;
+
+/// Reports malformed request bodies, including unknown fields, as `InvalidValue` errors.
+pub fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default().error_handler(|error, _req| {
+        APIError::InvalidValue {
+            description: error.to_string(),
+        }
+        .into()
+    })
+}
+
+/// Reports malformed query strings, such as an unknown operation request `kind`, as
+/// `InvalidValue` errors.
+pub fn query_config() -> web::QueryConfig {
+    web::QueryConfig::default().error_handler(|error, _req| {
+        APIError::InvalidValue {
+            description: error.to_string(),
+        }
+        .into()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{http::StatusCode, test, web, App, HttpResponse};
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+
+    use super::{
+        json_config,
+        models::{
+            operation_approval::NewOperationApproval,
+            operation_request::{NewOperationRequest, OperationRequestKind},
+        },
+        query_config,
+    };
+
+    #[actix_rt::test]
+    async fn test_unknown_fields_rejected() {
+        let mut app = test::init_service(App::new().app_data(json_config()).route(
+            "/operation-approvals",
+            web::post().to(|_body: web::Json<NewOperationApproval>| HttpResponse::Ok().finish()),
+        ))
+        .await;
+
+        let request = test::TestRequest::post()
+            .uri("/operation-approvals")
+            .set_json(&json!({
+                "operation_request_id": "5b8c8f3e-5a2c-4d5e-9d0a-2c6f0f6c3b1a",
+                "signature": "edsig",
+                "signatur": "edsig",
+            }))
+            .to_request();
+        let response = test::call_service(&mut app, request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body: Value = test::read_body_json(response).await;
+        assert_eq!(body["error"], "InvalidValue");
+        assert!(body["message"]
+            .as_str()
+            .unwrap()
+            .contains("unknown field `signatur`"));
+    }
+
+    async fn assert_unknown_kind(response: actix_web::dev::ServiceResponse) {
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body: Value = test::read_body_json(response).await;
+        assert_eq!(body["error"], "InvalidValue");
+        let message = body["message"].as_str().unwrap();
+        assert!(
+            message.contains("unknown operation request kind \"mintt\""),
+            "{}",
+            message
+        );
+        assert!(
+            message.contains("mint, burn, update_keyholders"),
+            "{}",
+            message
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_unknown_kind_in_body() {
+        let mut app = test::init_service(App::new().app_data(json_config()).route(
+            "/operation-requests",
+            web::post().to(|_body: web::Json<NewOperationRequest>| HttpResponse::Ok().finish()),
+        ))
+        .await;
+
+        let request = test::TestRequest::post()
+            .uri("/operation-requests")
+            .set_json(&json!({
+                "contract_id": "5b8c8f3e-5a2c-4d5e-9d0a-2c6f0f6c3b1a",
+                "target_address": "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT",
+                "amount": "100",
+                "kind": "mintt",
+            }))
+            .to_request();
+        assert_unknown_kind(test::call_service(&mut app, request).await).await;
+    }
+
+    #[derive(Deserialize)]
+    struct KindQuery {
+        kind: OperationRequestKind,
+    }
+
+    #[actix_rt::test]
+    async fn test_unknown_kind_in_query() {
+        let mut app = test::init_service(App::new().app_data(query_config()).route(
+            "/operation-requests",
+            web::get().to(|query: web::Query<KindQuery>| HttpResponse::Ok().json(query.kind)),
+        ))
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/operation-requests?kind=mintt")
+            .to_request();
+        assert_unknown_kind(test::call_service(&mut app, request).await).await;
+
+        let request = test::TestRequest::get()
+            .uri("/operation-requests?kind=mint")
+            .to_request();
+        let response = test::call_service(&mut app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,123 @@
+use std::convert::{TryFrom, TryInto};
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{error::APIError, user::UserKind};
+use crate::db::models::api_key::ApiKey as DBApiKey;
+
+/// What an API key may do on its contract.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyAction {
+    CreateOperationRequest = 0,
+    ApproveOperationRequest = 1,
+    InjectOperationRequest = 2,
+}
+
+impl ApiKeyAction {
+    /// The roles of its user an API key keeps while taking this action, so the role checks of the
+    /// endpoint apply as they do to signed in users. `update_keyholders` operation requests need
+    /// a keyholder, they can't be created with an API key.
+    pub fn roles(&self) -> Vec<UserKind> {
+        match self {
+            ApiKeyAction::CreateOperationRequest => vec![UserKind::Gatekeeper],
+            ApiKeyAction::ApproveOperationRequest => vec![UserKind::Keyholder],
+            ApiKeyAction::InjectOperationRequest => vec![UserKind::Injector, UserKind::Gatekeeper],
+        }
+    }
+}
+
+impl TryFrom<i16> for ApiKeyAction {
+    type Error = APIError;
+
+    fn try_from(value: i16) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(ApiKeyAction::CreateOperationRequest),
+            1 => Ok(ApiKeyAction::ApproveOperationRequest),
+            2 => Ok(ApiKeyAction::InjectOperationRequest),
+            _ => Err(APIError::InvalidValue {
+                description: format!("api key action cannot be {}", value),
+            }),
+        }
+    }
+}
+
+impl Into<i16> for ApiKeyAction {
+    fn into(self) -> i16 {
+        match self {
+            ApiKeyAction::CreateOperationRequest => 0,
+            ApiKeyAction::ApproveOperationRequest => 1,
+            ApiKeyAction::InjectOperationRequest => 2,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub created_at: NaiveDateTime,
+    pub contract_id: Uuid,
+    pub name: String,
+    pub address: String,
+    pub actions: Vec<ApiKeyAction>,
+    pub created_by: String,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+impl TryFrom<DBApiKey> for ApiKey {
+    type Error = APIError;
+
+    fn try_from(value: DBApiKey) -> Result<Self, Self::Error> {
+        Ok(ApiKey {
+            id: value.id,
+            created_at: value.created_at,
+            contract_id: value.contract_id,
+            name: value.name,
+            address: value.address,
+            actions: value
+                .actions
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<Vec<_>, _>>()?,
+            created_by: value.created_by,
+            revoked_at: value.revoked_at,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewApiKey {
+    pub contract_id: Uuid,
+    pub name: String,
+    pub address: String,
+    pub actions: Vec<ApiKeyAction>,
+}
+
+impl NewApiKey {
+    pub fn validate(&self) -> Result<(), APIError> {
+        if self.name.trim().is_empty() {
+            return Err(APIError::InvalidValue {
+                description: "api key name cannot be empty".into(),
+            });
+        }
+        if self.actions.is_empty() {
+            return Err(APIError::InvalidValue {
+                description: "api key needs at least one action".into(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// A newly created API key with its token. The token is only returned once, only its hash is
+/// stored.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub token: String,
+}
@@ -0,0 +1,99 @@
+use std::convert::TryFrom;
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::db::models::audit_log::AuditLogEntry as DBAuditLogEntry;
+
+use super::error::APIError;
+
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEvent {
+    OperationRequestCreated = 0,
+    OperationApprovalAdded = 1,
+    OperationRequestApproved = 2,
+    OperationRequestInjected = 3,
+    OperationRequestDeleted = 4,
+    ConfigSynced = 5,
+    MaintenanceModeChanged = 6,
+    OperationRequestsImported = 7,
+    ApprovalsDropped = 8,
+    OperationRequestsExpired = 9,
+    ApiKeyCreated = 10,
+    ApiKeyRevoked = 11,
+}
+
+impl TryFrom<i16> for AuditEvent {
+    type Error = APIError;
+
+    fn try_from(value: i16) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(AuditEvent::OperationRequestCreated),
+            1 => Ok(AuditEvent::OperationApprovalAdded),
+            2 => Ok(AuditEvent::OperationRequestApproved),
+            3 => Ok(AuditEvent::OperationRequestInjected),
+            4 => Ok(AuditEvent::OperationRequestDeleted),
+            5 => Ok(AuditEvent::ConfigSynced),
+            6 => Ok(AuditEvent::MaintenanceModeChanged),
+            7 => Ok(AuditEvent::OperationRequestsImported),
+            8 => Ok(AuditEvent::ApprovalsDropped),
+            9 => Ok(AuditEvent::OperationRequestsExpired),
+            10 => Ok(AuditEvent::ApiKeyCreated),
+            11 => Ok(AuditEvent::ApiKeyRevoked),
+            _ => Err(APIError::InvalidValue {
+                description: format!("audit event cannot be {}", value),
+            }),
+        }
+    }
+}
+
+impl Into<i16> for AuditEvent {
+    fn into(self) -> i16 {
+        match self {
+            AuditEvent::OperationRequestCreated => 0,
+            AuditEvent::OperationApprovalAdded => 1,
+            AuditEvent::OperationRequestApproved => 2,
+            AuditEvent::OperationRequestInjected => 3,
+            AuditEvent::OperationRequestDeleted => 4,
+            AuditEvent::ConfigSynced => 5,
+            AuditEvent::MaintenanceModeChanged => 6,
+            AuditEvent::OperationRequestsImported => 7,
+            AuditEvent::ApprovalsDropped => 8,
+            AuditEvent::OperationRequestsExpired => 9,
+            AuditEvent::ApiKeyCreated => 10,
+            AuditEvent::ApiKeyRevoked => 11,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuditLogEntry {
+    pub id: Uuid,
+    pub created_at: NaiveDateTime,
+    pub event: AuditEvent,
+    pub actor: Option<String>,
+    pub contract_id: Option<Uuid>,
+    pub resource_id: Option<Uuid>,
+    pub detail: Value,
+}
+
+impl TryFrom<DBAuditLogEntry> for AuditLogEntry {
+    type Error = APIError;
+
+    fn try_from(value: DBAuditLogEntry) -> Result<Self, Self::Error> {
+        Ok(AuditLogEntry {
+            id: value.id,
+            created_at: value.created_at,
+            event: AuditEvent::try_from(value.event)?,
+            actor: value.actor,
+            contract_id: value.contract_id,
+            resource_id: value.resource_id,
+            detail: serde_json::from_str(&value.detail).map_err(|_error| APIError::Internal {
+                description: "failed to parse audit log detail".into(),
+            })?,
+        })
+    }
+}
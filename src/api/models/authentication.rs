@@ -1,9 +1,22 @@
 use std::convert::TryFrom;
 
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::db::models::authentication_challenge::AuthenticationChallenge as DBAuthenticationChallenge;
+use crate::{
+    crypto,
+    db::models::authentication_challenge::AuthenticationChallenge as DBAuthenticationChallenge,
+    tezos::{
+        micheline::{
+            data, extract_prim, extract_string,
+            primitive::{Data, Primitive},
+            string, HexDecodable, HexEncodable, MichelsonV1Expression,
+        },
+        utils::ConsumableHexStr,
+        TzError,
+    },
+};
 
 use super::error::APIError;
 
@@ -23,11 +36,17 @@ impl From<DBAuthenticationChallenge> for AuthenticationChallenge {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct AuthenticationChallengeResponse {
     pub id: Uuid,
     pub signature: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionRefresh {
+    pub expires_at: DateTime<Utc>,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum AuthenticationChallengeState {
     Pending = 0,
@@ -56,3 +75,159 @@ impl Into<i16> for AuthenticationChallengeState {
         }
     }
 }
+
+const SIGN_IN_MESSAGE_PREFIX: &str = "Tezos Signed Message";
+const SIGN_IN_MESSAGE_VALIDITY_MINUTES: i64 = 5;
+
+/// The sign-in challenge, packed as the Michelson value
+/// `Pair "Tezos Signed Message" (Pair domain (Pair nonce (Pair issued_at expires_at)))` so a
+/// Ledger can display it. Binding the challenge to the domain and an expiry keeps a signature
+/// from being replayed on another environment or later on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignInMessage {
+    pub domain: String,
+    pub nonce: String,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl SignInMessage {
+    pub fn new(domain: &str, issued_at: DateTime<Utc>) -> Self {
+        SignInMessage {
+            domain: domain.into(),
+            nonce: bs58::encode(crypto::generate_random_bytes(10)).into_string(),
+            issued_at,
+            expires_at: issued_at + Duration::minutes(SIGN_IN_MESSAGE_VALIDITY_MINUTES),
+        }
+    }
+
+    fn micheline(&self) -> MichelsonV1Expression {
+        data::pair(
+            string(SIGN_IN_MESSAGE_PREFIX.into()),
+            data::pair(
+                string(self.domain.clone()),
+                data::pair(
+                    string(self.nonce.clone()),
+                    data::pair(
+                        string(self.issued_at.to_rfc3339_opts(SecondsFormat::Secs, true)),
+                        string(self.expires_at.to_rfc3339_opts(SecondsFormat::Secs, true)),
+                    ),
+                ),
+            ),
+        )
+    }
+
+    pub fn pack(&self) -> Result<String, APIError> {
+        Ok(format!("05{}", self.micheline().to_hex_encoded()?))
+    }
+
+    pub fn unpack(packed: &str) -> Result<Self, APIError> {
+        let invalid = || APIError::InvalidValue {
+            description: "authentication challenge is not a sign-in message".into(),
+        };
+        let encoded = packed.strip_prefix("05").ok_or_else(invalid)?;
+        let mut consumable = ConsumableHexStr::new(encoded);
+        let micheline = MichelsonV1Expression::from_hex(&mut consumable)?;
+        let fields = comb_strings(&micheline).map_err(|_error| invalid())?;
+        if consumable.has_more() || fields.len() != 5 || fields[0] != SIGN_IN_MESSAGE_PREFIX {
+            return Err(invalid());
+        }
+        let parse_date = |value: &str| value.parse::<DateTime<Utc>>().map_err(|_error| invalid());
+
+        Ok(SignInMessage {
+            domain: fields[1].clone(),
+            nonce: fields[2].clone(),
+            issued_at: parse_date(&fields[3])?,
+            expires_at: parse_date(&fields[4])?,
+        })
+    }
+
+    /// Rejects a message issued for another domain than `domain` or expired at `now`.
+    pub fn validate(&self, domain: &str, now: DateTime<Utc>) -> Result<(), APIError> {
+        if self.domain != domain {
+            return Err(APIError::InvalidValue {
+                description: format!(
+                    "authentication challenge was issued for {}, not {}",
+                    self.domain, domain
+                ),
+            });
+        }
+        if now >= self.expires_at {
+            return Err(APIError::AuthenticationChallengeExpired);
+        }
+
+        Ok(())
+    }
+}
+
+/// The strings of a right comb of pairs, in order.
+fn comb_strings(value: &MichelsonV1Expression) -> Result<Vec<String>, TzError> {
+    let mut strings = Vec::new();
+    let mut current = value;
+    while let MichelsonV1Expression::Prim(_) = current {
+        let value = extract_prim(current)?;
+        if value.prim != Primitive::Data(Data::Pair) || value.args_count() != 2 {
+            return Err(TzError::InvalidType);
+        }
+
+        let arguments = value.args.as_ref().unwrap();
+        strings.push(extract_string(arguments.first().unwrap())?.clone());
+        current = arguments.last().unwrap();
+    }
+    strings.push(extract_string(current)?.clone());
+
+    Ok(strings)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, Utc};
+
+    use super::SignInMessage;
+    use crate::api::models::error::APIError;
+
+    const DOMAIN: &str = "tzwrapped.example.com";
+
+    #[test]
+    fn test_sign_in_message_coding() -> Result<(), APIError> {
+        let message = SignInMessage::new(DOMAIN, Utc::now());
+        let packed = message.pack()?;
+
+        assert!(packed.starts_with("050707"));
+        assert_eq!(
+            SignInMessage::unpack(&packed)?.pack()?,
+            packed,
+            "unpacking keeps every field"
+        );
+        assert!(SignInMessage::unpack("0500").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_expired_sign_in_message() -> Result<(), APIError> {
+        let issued_at = Utc::now() - Duration::minutes(10);
+        let message = SignInMessage::unpack(&SignInMessage::new(DOMAIN, issued_at).pack()?)?;
+
+        assert!(message.validate(DOMAIN, issued_at).is_ok());
+        assert!(matches!(
+            message.validate(DOMAIN, Utc::now()),
+            Err(APIError::AuthenticationChallengeExpired)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrong_domain_sign_in_message() -> Result<(), APIError> {
+        let now = Utc::now();
+        let message = SignInMessage::unpack(&SignInMessage::new(DOMAIN, now).pack()?)?;
+
+        assert!(matches!(
+            message.validate("tzwrapped.other.com", now),
+            Err(APIError::InvalidValue { .. })
+        ));
+
+        Ok(())
+    }
+}
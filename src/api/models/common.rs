@@ -6,6 +6,13 @@ use crate::tezos::multisig::SignableMessage;
 
 use super::error::APIError;
 
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct ListResponse<T> {
     pub page: i64,
@@ -13,7 +20,7 @@ pub struct ListResponse<T> {
     pub results: Vec<T>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SignableMessageInfo {
     pub message: String,
     pub tezos_client_command: String,
@@ -35,12 +42,10 @@ impl TryFrom<SignableMessage> for SignableMessageInfo {
 
     fn try_from(value: SignableMessage) -> Result<Self, Self::Error> {
         let ledger_blake2b_hash = value.ledger_blake2b_hash()?;
+        let tezos_client_command = value.tezos_client_command();
         Ok(SignableMessageInfo::new(
             value.packed_data,
-            format!(
-                "tezos-client hash data '{}' of type '{}'",
-                value.michelson_data, value.michelson_type
-            ),
+            tezos_client_command,
             ledger_blake2b_hash,
         ))
     }
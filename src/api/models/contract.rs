@@ -4,7 +4,13 @@ use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::db::models::{capability::Capability, contract::Contract as DBContract};
+use crate::{
+    db::models::{
+        allowed_target_address::AllowedTargetAddress as DBAllowedTargetAddress,
+        capability::Capability, contract::Contract as DBContract,
+    },
+    tezos::micheline::MichelsonV1Expression,
+};
 
 use super::{error::APIError, operation_request::OperationRequestKind};
 
@@ -21,6 +27,7 @@ pub struct Contract {
     pub min_approvals: i32,
     pub symbol: String,
     pub decimals: i32,
+    pub sort_order: Option<i32>,
     pub capabilities: Vec<OperationRequestKind>,
 }
 
@@ -41,19 +48,118 @@ impl TryFrom<(DBContract, Vec<Capability>)> for Contract {
             min_approvals: contract.min_approvals,
             symbol: contract.symbol,
             decimals: contract.decimals,
+            sort_order: contract.sort_order,
             capabilities: capabilities
                 .iter()
+                .filter(|cap| cap.enabled)
                 .map(|cap| cap.operation_request_kind.try_into())
                 .collect::<Result<Vec<OperationRequestKind>, APIError>>()?,
         })
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractGrouping {
+    Network,
+    Symbol,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ContractGroup {
+    pub key: String,
+    pub contracts: Vec<Contract>,
+}
+
+impl ContractGroup {
+    /// Groups contracts by key. Groups are ordered by their first contract and keep the order of
+    /// their contracts.
+    pub fn group(contracts: impl IntoIterator<Item = (String, Contract)>) -> Vec<ContractGroup> {
+        let mut groups: Vec<ContractGroup> = Vec::new();
+        for (key, contract) in contracts {
+            match groups.iter_mut().find(|group| group.key == key) {
+                Some(group) => group.contracts.push(contract),
+                None => groups.push(ContractGroup {
+                    key,
+                    contracts: vec![contract],
+                }),
+            }
+        }
+
+        groups
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AllowedTargetAddress {
+    pub id: Uuid,
+    pub created_at: NaiveDateTime,
+    pub contract_id: Uuid,
+    pub address: String,
+}
+
+impl From<DBAllowedTargetAddress> for AllowedTargetAddress {
+    fn from(value: DBAllowedTargetAddress) -> Self {
+        AllowedTargetAddress {
+            id: value.id,
+            created_at: value.created_at,
+            contract_id: value.contract_id,
+            address: value.address,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewAllowedTargetAddress {
+    pub address: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewCapability {
+    pub operation_request_kind: OperationRequestKind,
+}
+
+/// The nonce of the multisig of a contract, and the nonce its next operation request uses.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ContractNonces {
+    pub on_chain: i64,
+    pub next_usable: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TokenBalance {
+    pub owner: String,
+    pub token_id: i32,
+    pub balance: String,
+}
+
+/// The parameter type of a multisig entrypoint as reported by the node, and rendered as Michelson.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ParameterSchema {
+    pub address: String,
+    pub entrypoint: String,
+    pub schema: MichelsonV1Expression,
+    pub michelson: String,
+}
+
+/// The delegates the token contract and its multisig have set, `null` if they have none.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ContractDelegates {
+    pub contract_address: String,
+    pub contract_delegate: Option<String>,
+    pub multisig_address: String,
+    pub multisig_delegate: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Copy, Clone)]
 #[serde(rename_all = "lowercase")]
 pub enum ContractKind {
     FA1 = 0,
     FA2 = 1,
+    #[serde(rename = "fa2_single_asset")]
+    FA2SingleAsset = 2,
 }
 
 impl TryFrom<i16> for ContractKind {
@@ -63,6 +169,7 @@ impl TryFrom<i16> for ContractKind {
         match value {
             0 => Ok(ContractKind::FA1),
             1 => Ok(ContractKind::FA2),
+            2 => Ok(ContractKind::FA2SingleAsset),
             _ => Err(APIError::InvalidValue {
                 description: format!("contract kind cannot be {}", value),
             }),
@@ -77,6 +184,7 @@ impl TryFrom<&str> for ContractKind {
         match value.to_lowercase().as_ref() {
             "fa1" => Ok(ContractKind::FA1),
             "fa2" => Ok(ContractKind::FA2),
+            "fa2_single_asset" => Ok(ContractKind::FA2SingleAsset),
             _ => Err(APIError::InvalidValue {
                 description: format!("contract kind cannot be {}", value),
             }),
@@ -89,6 +197,136 @@ impl Into<i16> for ContractKind {
         match self {
             ContractKind::FA1 => 0,
             ContractKind::FA2 => 1,
+            ContractKind::FA2SingleAsset => 2,
         }
     }
 }
+
+impl ContractKind {
+    /// Rejects the capabilities the contract at `address` can't execute: batch mints need an FA2
+    /// contract, approvals the FA1.2 `approve` entrypoint, which FA2 contracts don't have.
+    pub fn validate_capability(
+        &self,
+        operation_request_kind: OperationRequestKind,
+        address: &str,
+    ) -> Result<(), APIError> {
+        let description = match (self, operation_request_kind) {
+            (ContractKind::FA1, OperationRequestKind::BatchMint) => format!(
+                "FA1 contract {} cannot have the batch_mint capability, batch mints are only supported by FA2 contracts",
+                address
+            ),
+            (ContractKind::FA2, OperationRequestKind::Approve)
+            | (ContractKind::FA2SingleAsset, OperationRequestKind::Approve) => format!(
+                "FA2 contract {} cannot have the approve capability, approvals are only supported by FA1 contracts",
+                address
+            ),
+            _ => return Ok(()),
+        };
+
+        Err(APIError::InvalidValue { description })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryFrom;
+
+    use chrono::NaiveDateTime;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::{Contract, ContractGroup, ContractKind};
+    use crate::{
+        api::models::operation_request::OperationRequestKind,
+        db::models::{capability::Capability, contract::Contract as DBContract},
+        fixtures,
+    };
+
+    fn contract(display_name: &str, symbol: &str) -> Contract {
+        Contract {
+            id: Uuid::new_v4(),
+            created_at: NaiveDateTime::from_timestamp(0, 0),
+            updated_at: NaiveDateTime::from_timestamp(0, 0),
+            pkh: "KT1S4QXjASmM3ei7a6raRLkr4TbnKu7RuC9X".into(),
+            token_id: 0,
+            multisig_pkh: "KT1MeQd67djiEEkscx3USfQ7vFYg589DySpZ".into(),
+            kind: ContractKind::FA2,
+            display_name: display_name.into(),
+            min_approvals: 2,
+            symbol: symbol.into(),
+            decimals: 8,
+            sort_order: None,
+            capabilities: vec![],
+        }
+    }
+
+    #[test]
+    fn test_group() {
+        let contracts = vec![
+            contract("tzBTC - Owner", "tzBTC"),
+            contract("USDtz - Owner", "USDtz"),
+            contract("tzBTC - Minter", "tzBTC"),
+        ];
+
+        let groups = ContractGroup::group(
+            contracts
+                .into_iter()
+                .map(|contract| (contract.symbol.clone(), contract)),
+        );
+
+        let buckets = groups
+            .iter()
+            .map(|group| {
+                let names = group
+                    .contracts
+                    .iter()
+                    .map(|contract| contract.display_name.as_str())
+                    .collect::<Vec<_>>();
+                (group.key.as_str(), names)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            buckets,
+            vec![
+                ("tzBTC", vec!["tzBTC - Owner", "tzBTC - Minter"]),
+                ("USDtz", vec!["USDtz - Owner"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_capabilities_serialized_as_kinds() {
+        let contract_id = Uuid::new_v4();
+        let capability = |kind: OperationRequestKind, enabled: bool| Capability {
+            id: Uuid::new_v4(),
+            created_at: NaiveDateTime::from_timestamp(0, 0),
+            contract_id,
+            operation_request_kind: kind.into(),
+            enabled,
+            added_at_runtime: false,
+        };
+        let db_contract = DBContract {
+            id: contract_id,
+            display_name: "tzBTC - Owner".into(),
+            symbol: "tzBTC".into(),
+            decimals: 8,
+            ..fixtures::contract()
+        };
+
+        let contract = Contract::try_from((
+            db_contract,
+            vec![
+                capability(OperationRequestKind::Mint, true),
+                capability(OperationRequestKind::UpdateKeyholders, true),
+                capability(OperationRequestKind::Burn, false),
+                capability(OperationRequestKind::BatchMint, true),
+            ],
+        ))
+        .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&contract).unwrap()["capabilities"],
+            json!(["mint", "update_keyholders", "batch_mint"])
+        );
+    }
+}
@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+use crate::tezos::micheline::MichelsonV1Expression;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct UnpackRequest {
+    pub packed: String,
+    #[serde(rename = "type")]
+    pub type_: MichelsonV1Expression,
+}
+
+/// Unpacked data, both as micheline JSON and as michelson.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Unpacked {
+    pub micheline: MichelsonV1Expression,
+    pub michelson: String,
+}
@@ -11,6 +11,8 @@ struct ErrorResponse {
     code: u16,
     error: String,
     message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
 }
 
 #[derive(Error, Display, Debug)]
@@ -48,6 +50,18 @@ pub enum APIError {
     #[display(fmt = "authentication challenge expired")]
     AuthenticationChallengeExpired,
 
+    #[display(fmt = "not found on the Tezos node: {}", path)]
+    NodeNotFound { path: String },
+
+    #[display(fmt = "the Tezos node is unavailable")]
+    NodeUnavailable,
+
+    #[display(fmt = "maintenance: {}", description)]
+    Maintenance { description: String },
+
+    #[display(fmt = "michelson error ({}): {}", kind, description)]
+    MichelsonError { kind: String, description: String },
+
     #[display(fmt = "unknown error")]
     Unknown,
 }
@@ -68,11 +82,31 @@ impl APIError {
             APIError::Unauthorized => "Unauthorized".into(),
             APIError::Forbidden => "Forbidden".into(),
             APIError::AuthenticationChallengeExpired => "AuthenticationChallengeExpired".into(),
+            APIError::NodeNotFound { path: _ } => "NodeNotFound".into(),
+            APIError::NodeUnavailable => "NodeUnavailable".into(),
+            APIError::Maintenance { description: _ } => "Maintenance".into(),
+            APIError::MichelsonError {
+                kind: _,
+                description: _,
+            } => "MichelsonError".into(),
             APIError::Unknown => "Unknown".into(),
         }
     }
 }
 
+impl APIError {
+    pub fn error_response_with_request_id(&self, request_id: Option<String>) -> HttpResponse {
+        let status_code = self.status_code();
+        let error_response = ErrorResponse {
+            code: status_code.as_u16(),
+            message: self.to_string(),
+            error: self.name(),
+            request_id,
+        };
+        HttpResponse::build(status_code).json(error_response)
+    }
+}
+
 impl ResponseError for APIError {
     fn status_code(&self) -> StatusCode {
         match self {
@@ -87,18 +121,19 @@ impl ResponseError for APIError {
             APIError::Unauthorized => StatusCode::FORBIDDEN,
             APIError::Forbidden => StatusCode::FORBIDDEN,
             APIError::AuthenticationChallengeExpired => StatusCode::BAD_REQUEST,
+            APIError::NodeNotFound { path: _ } => StatusCode::NOT_FOUND,
+            APIError::NodeUnavailable => StatusCode::BAD_GATEWAY,
+            APIError::Maintenance { description: _ } => StatusCode::SERVICE_UNAVAILABLE,
+            APIError::MichelsonError {
+                kind: _,
+                description: _,
+            } => StatusCode::INTERNAL_SERVER_ERROR,
             APIError::Unknown => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 
     fn error_response(&self) -> HttpResponse {
-        let status_code = self.status_code();
-        let error_response = ErrorResponse {
-            code: status_code.as_u16(),
-            message: self.to_string(),
-            error: self.name(),
-        };
-        HttpResponse::build(status_code).json(error_response)
+        self.error_response_with_request_id(None)
     }
 }
 
@@ -149,7 +184,18 @@ impl From<tezos::TzError> for APIError {
             tezos::TzError::InvalidPublicKey => APIError::InvalidPublicKey,
             tezos::TzError::InvalidSignature => APIError::InvalidSignature,
             tezos::TzError::InvalidValue { description } => APIError::InvalidValue { description },
+            tezos::TzError::NotFound { path } => APIError::NodeNotFound { path },
+            tezos::TzError::NetworkFailure => APIError::NodeUnavailable,
             tezos::TzError::APIError { error } => error,
+            tezos::TzError::Michelson { kind, description } => {
+                APIError::MichelsonError { kind, description }
+            }
+            tezos::TzError::InvalidType
+            | tezos::TzError::InvalidArgument
+            | tezos::TzError::ParsingFailure => APIError::MichelsonError {
+                kind: value.to_string(),
+                description: value.michelson_description().into(),
+            },
             _ => APIError::Internal {
                 description: value.to_string(),
             },
@@ -212,3 +258,41 @@ impl From<ParseIntError> for APIError {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use actix_web::{http::StatusCode, ResponseError};
+
+    use super::APIError;
+    use crate::tezos::{
+        micheline::{data, int, types},
+        TzError,
+    };
+
+    #[test]
+    fn test_packing_failure_is_michelson_error() {
+        let value = data::pair(int(10), int(10));
+        let schema = types::pair(types::list(types::nat()), types::nat());
+        let error = value
+            .pack(Some(&schema))
+            .map_err(|error| error.with_michelson_context("failed to pack the mint message"))
+            .unwrap_err();
+
+        let error = APIError::from(error);
+        match &error {
+            APIError::MichelsonError { kind, description } => {
+                assert_eq!(kind, "InvalidType");
+                assert!(description.starts_with("failed to pack the mint message"));
+            }
+            _ => panic!("expected a michelson error, got {:?}", error),
+        }
+        assert_eq!(error.name(), "MichelsonError");
+        assert_eq!(error.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let error = APIError::from(TzError::ParsingFailure);
+        assert!(matches!(error, APIError::MichelsonError { kind, .. } if kind == "ParsingFailure"));
+
+        let error = APIError::from(TzError::HashFailure);
+        assert!(matches!(error, APIError::Internal { .. }));
+    }
+}
@@ -0,0 +1,30 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::db::models::maintenance_mode::MaintenanceMode as DBMaintenanceMode;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct MaintenanceMode {
+    pub enabled: bool,
+    pub message: Option<String>,
+    pub updated_by: Option<String>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl From<DBMaintenanceMode> for MaintenanceMode {
+    fn from(value: DBMaintenanceMode) -> Self {
+        MaintenanceMode {
+            enabled: value.enabled,
+            message: value.message,
+            updated_by: value.updated_by,
+            updated_at: Some(value.updated_at),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct NewMaintenanceMode {
+    pub enabled: bool,
+    pub message: Option<String>,
+}
@@ -1,7 +1,12 @@
+pub mod api_key;
+pub mod audit_log;
 pub mod authentication;
 pub mod common;
 pub mod contract;
+pub mod debug;
 pub mod error;
+pub mod maintenance;
+pub mod notification;
 pub mod operation_approval;
 pub mod operation_request;
 pub mod tezos_node;
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct NotificationPause {
+    pub paused: bool,
+    pub paused_contracts: Vec<Uuid>,
+}
+
+impl NotificationPause {
+    /// The pause with only the `paused_contracts` among `contract_ids`.
+    pub fn restricted_to(mut self, contract_ids: &[Uuid]) -> Self {
+        self.paused_contracts
+            .retain(|contract_id| contract_ids.contains(contract_id));
+
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct NewNotificationPause {
+    pub paused: bool,
+    pub contract_id: Option<Uuid>,
+}
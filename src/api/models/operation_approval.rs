@@ -1,11 +1,14 @@
-use std::convert::TryInto;
+use std::{collections::BTreeMap, convert::TryInto};
 
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::db::models::{
-    operation_approval::OperationApproval as DBOperationApproval, user::User as DBUser,
+use crate::{
+    db::models::{
+        operation_approval::OperationApproval as DBOperationApproval, user::User as DBUser,
+    },
+    tezos::coding::validate_signature,
 };
 
 use super::error::APIError;
@@ -19,6 +22,7 @@ pub struct OperationApproval {
     pub keyholder: User,
     pub operation_request_id: Uuid,
     pub signature: String,
+    pub uploaded_by: Option<Uuid>,
 }
 
 impl OperationApproval {
@@ -33,12 +37,254 @@ impl OperationApproval {
             keyholder: keyholder.try_into()?,
             operation_request_id: operation_approval.operation_request_id,
             signature: operation_approval.signature,
+            uploaded_by: operation_approval.uploaded_by,
         })
     }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct NewOperationApproval {
     pub operation_request_id: Uuid,
     pub signature: String,
 }
+
+impl NewOperationApproval {
+    /// Trims the whitespace copied along with the signature and checks what is left is a valid
+    /// signature.
+    pub fn normalized(self) -> Result<NewOperationApproval, APIError> {
+        let signature = self.signature.trim().to_owned();
+        if signature.is_empty() {
+            return Err(APIError::InvalidValue {
+                description: "signature is empty".into(),
+            });
+        }
+        if signature.chars().any(char::is_whitespace) {
+            return Err(APIError::InvalidValue {
+                description: "signature must not contain whitespace".into(),
+            });
+        }
+        validate_signature(&signature)?;
+
+        Ok(NewOperationApproval { signature, ..self })
+    }
+}
+
+/// The most signatures that can be uploaded at once.
+pub const MAX_BULK_APPROVALS: usize = 100;
+
+/// Signatures of several operation requests, by operation request id, uploaded together.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct NewOperationApprovals {
+    pub signatures: BTreeMap<Uuid, String>,
+}
+
+impl NewOperationApprovals {
+    pub fn into_approvals(self) -> Result<Vec<NewOperationApproval>, APIError> {
+        if self.signatures.is_empty() {
+            return Err(APIError::InvalidValue {
+                description: "signatures is empty".into(),
+            });
+        }
+        if self.signatures.len() > MAX_BULK_APPROVALS {
+            return Err(APIError::InvalidValue {
+                description: format!(
+                    "at most {} signatures can be uploaded at once",
+                    MAX_BULK_APPROVALS
+                ),
+            });
+        }
+
+        Ok(self
+            .signatures
+            .into_iter()
+            .map(|(operation_request_id, signature)| NewOperationApproval {
+                operation_request_id,
+                signature,
+            })
+            .collect())
+    }
+}
+
+/// The outcome of one signature of a bulk upload: the stored approval, or the name and message
+/// of the error it was rejected with.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OperationApprovalResult {
+    pub operation_request_id: Uuid,
+    pub approval: Option<OperationApproval>,
+    pub error: Option<String>,
+    pub message: Option<String>,
+}
+
+/// The outcome of a bulk upload, `approved` lists the operation requests that reached their
+/// quorum with it.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OperationApprovalsResult {
+    pub results: Vec<OperationApprovalResult>,
+    pub approved: Vec<Uuid>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OperationApprovalVerification {
+    pub valid: bool,
+    pub signer: String,
+}
+
+/// An approval with the position of its keyholder in the multisig's approvers, which is the order
+/// `check_signatures` expects the signatures in. The index is `None` if the keyholder isn't one
+/// of the current approvers.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct OrderedOperationApproval {
+    pub operation_approval_id: Uuid,
+    pub keyholder_id: Uuid,
+    pub public_key: String,
+    pub index: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OperationApprovalOrder {
+    pub approvers: Vec<String>,
+    pub min_approvals: i64,
+    pub approvals: Vec<OrderedOperationApproval>,
+    pub complete: bool,
+}
+
+impl OperationApprovalOrder {
+    /// Sorts the approvals by their index, approvals of keyholders that aren't approvers come
+    /// last. The order is complete once approvers in at least `min_approvals` positions signed.
+    pub fn new(
+        approvers: Vec<String>,
+        min_approvals: i64,
+        approvals: &[(DBOperationApproval, DBUser)],
+    ) -> OperationApprovalOrder {
+        let mut ordered_approvals = approvals
+            .iter()
+            .map(|(approval, keyholder)| OrderedOperationApproval {
+                operation_approval_id: approval.id,
+                keyholder_id: keyholder.id,
+                public_key: keyholder.public_key.clone(),
+                index: approvers
+                    .iter()
+                    .position(|approver| approver == &keyholder.public_key),
+            })
+            .collect::<Vec<_>>();
+        ordered_approvals.sort_by_key(|approval| approval.index.unwrap_or(usize::MAX));
+
+        let filled_positions = ordered_approvals
+            .iter()
+            .filter(|approval| approval.index.is_some())
+            .count() as i64;
+
+        OperationApprovalOrder {
+            approvers,
+            min_approvals,
+            approvals: ordered_approvals,
+            complete: filled_positions >= min_approvals,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use super::{NewOperationApproval, OperationApprovalOrder};
+    use crate::{
+        api::models::error::APIError,
+        db::models::{operation_approval::OperationApproval, user::User},
+        fixtures,
+    };
+
+    const SIGNATURE: &str = "edsigtXwP1dYcbSNoTMSazxdmwnA7GsKCvXN2ak1ozjwgiGPHCqsjnCpS28KsUVwf6zjK9X6vRqT8Kx7GX28xdSsv1MGMxVvJ1X";
+
+    fn new_operation_approval(signature: &str) -> NewOperationApproval {
+        NewOperationApproval {
+            operation_request_id: Uuid::new_v4(),
+            signature: signature.into(),
+        }
+    }
+
+    fn invalid_value(signature: &str) -> String {
+        match new_operation_approval(signature).normalized() {
+            Err(APIError::InvalidValue { description }) => description,
+            result => panic!("expected {:?} to be rejected, got {:?}", signature, result),
+        }
+    }
+
+    #[test]
+    fn test_normalized_trims_whitespace() -> Result<(), APIError> {
+        assert_eq!(
+            new_operation_approval(SIGNATURE).normalized()?.signature,
+            SIGNATURE
+        );
+        assert_eq!(
+            new_operation_approval(&format!("  {}\r\n", SIGNATURE))
+                .normalized()?
+                .signature,
+            SIGNATURE
+        );
+        assert_eq!(
+            new_operation_approval(&format!("\t{} ", SIGNATURE))
+                .normalized()?
+                .signature,
+            SIGNATURE
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalized_rejects_malformed_signatures() {
+        let (start, end) = SIGNATURE.split_at(40);
+        assert_eq!(
+            invalid_value(&format!("{} {}", start, end)),
+            "signature must not contain whitespace"
+        );
+        assert_eq!(
+            invalid_value(&format!("{}\n{}", start, end)),
+            "signature must not contain whitespace"
+        );
+        assert_eq!(invalid_value(" \n"), "signature is empty");
+        assert_eq!(invalid_value("0x1234"), "0x1234 is not a valid signature");
+        assert!(invalid_value(&SIGNATURE.replace('X', "Y")).contains("is not a valid value"));
+    }
+
+    fn approval(public_key: &str) -> (OperationApproval, User) {
+        let keyholder = User {
+            display_name: "Keyholder".into(),
+            ..fixtures::keyholder(Uuid::new_v4(), public_key)
+        };
+        let approval = OperationApproval {
+            signature: SIGNATURE.into(),
+            ..fixtures::operation_approval(
+                &fixtures::operation_request(keyholder.contract_id),
+                &keyholder,
+            )
+        };
+
+        (approval, keyholder)
+    }
+
+    #[test]
+    fn test_approval_order_follows_approvers() {
+        let approvers = vec!["edpk1".to_owned(), "edpk2".into(), "edpk3".into()];
+        let approvals = vec![approval("edpk3"), approval("edpk4"), approval("edpk1")];
+
+        let order = OperationApprovalOrder::new(approvers.clone(), 2, &approvals);
+        let ordered = order
+            .approvals
+            .iter()
+            .map(|approval| (approval.public_key.as_str(), approval.index))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ordered,
+            vec![("edpk1", Some(0)), ("edpk3", Some(2)), ("edpk4", None)]
+        );
+        assert_eq!(order.approvals[0].operation_approval_id, approvals[2].0.id);
+        assert!(order.complete);
+
+        let order = OperationApprovalOrder::new(approvers, 3, &approvals);
+        assert!(!order.complete);
+    }
+}
@@ -1,15 +1,24 @@
 use std::{
+    collections::HashSet,
     convert::{TryFrom, TryInto},
     fmt::Display,
 };
 
 use chrono::NaiveDateTime;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 use uuid::Uuid;
 
-use crate::db::models::{
-    operation_approval::OperationApproval as DBOperationApproval,
-    operation_request::OperationRequest as DBOperationRequest, user::User as DBUser,
+use crate::{
+    api::amount,
+    db::models::{
+        contract::Contract as DBContract,
+        operation_approval::OperationApproval as DBOperationApproval,
+        operation_request::OperationRequest as DBOperationRequest, user::User as DBUser,
+    },
+    settings::KeyholderUpdateSafeguard,
+    tezos::{
+        edpk_to_tz1, micheline::MichelsonV1Expression, multisig::SignableMessage, mutez::Mutez,
+    },
 };
 
 use super::error::APIError;
@@ -27,21 +36,46 @@ pub struct OperationRequest {
     pub amount: Option<String>,
     pub threshold: Option<i64>,
     pub proposed_keyholders: Option<Vec<User>>,
+    pub batch_mints: Option<Vec<BatchMint>>,
+    pub xtz_amount: Option<String>,
     pub kind: OperationRequestKind,
     pub chain_id: String,
     pub nonce: i64,
+    pub ledger_blake2b_hash: Option<String>,
     pub state: OperationRequestState,
     pub operation_approvals: Vec<OperationApproval>,
     pub operation_hash: Option<String>,
+    pub confirmation_failed: bool,
+    pub approvals_received: i64,
+    pub approvals_required: i64,
+    /// The risks of a keyholder update which are only warned about, only set in the response to
+    /// its creation.
+    pub warnings: Vec<String>,
 }
 
 impl OperationRequest {
+    /// `operation_approvals` are the approvals counting towards the quorum, see
+    /// `DBOperationRequest::operation_approvals`, and `approvals_required` the `min_approvals` of
+    /// the contract, so that `approvals_received` reaches it once the request can be approved.
     pub fn from(
         operation_request: DBOperationRequest,
         gatekeeper: DBUser,
         operation_approvals: Vec<(DBOperationApproval, DBUser)>,
         proposed_keyholders: Option<Vec<DBUser>>,
+        approvals_required: i64,
     ) -> Result<OperationRequest, APIError> {
+        let batch_mints = operation_request.batch_mints().map(|mints| {
+            mints
+                .into_iter()
+                .map(|mint| BatchMint {
+                    target_address: mint.target_address,
+                    amount: mint.amount.to_string(),
+                })
+                .collect()
+        });
+
+        let approvals_received = operation_approvals.len() as i64;
+
         Ok(OperationRequest {
             id: operation_request.id,
             created_at: operation_request.created_at,
@@ -63,9 +97,14 @@ impl OperationRequest {
                         .collect::<Option<Result<Vec<User>, APIError>>>()
                 })
                 .map_or(Ok(None), |r| r.map(Some))?,
+            batch_mints,
+            xtz_amount: operation_request
+                .xtz_amount
+                .map(|xtz_amount| xtz_amount.to_string()),
             kind: operation_request.kind.try_into()?,
             chain_id: operation_request.chain_id,
             nonce: operation_request.nonce,
+            ledger_blake2b_hash: operation_request.ledger_blake2b_hash,
             state: operation_request.state.try_into()?,
             operation_approvals: operation_approvals
                 .into_iter()
@@ -74,27 +113,209 @@ impl OperationRequest {
                 })
                 .collect::<Result<Vec<OperationApproval>, APIError>>()?,
             operation_hash: operation_request.operation_hash,
+            confirmation_failed: operation_request.confirmation_failed,
+            approvals_received,
+            approvals_required,
+            warnings: vec![],
         })
     }
+
+    pub fn with_warnings(self, risks: &[KeyholderUpdateRisk]) -> OperationRequest {
+        OperationRequest {
+            warnings: risks.iter().map(|risk| risk.to_string()).collect(),
+            ..self
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct NewOperationRequest {
     pub contract_id: Uuid,
     pub target_address: Option<String>,
     pub amount: Option<String>,
     pub threshold: Option<i64>,
     pub proposed_keyholders: Option<Vec<String>>,
+    pub batch_mints: Option<Vec<BatchMint>>,
+    pub xtz_amount: Option<String>,
     pub kind: OperationRequestKind,
     pub ledger_hash: Option<String>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BatchMint {
+    pub target_address: String,
+    pub amount: String,
+}
+
+/// Parses an amount of tez to send along with the contract call, given in mutez.
+fn parse_xtz_amount(xtz_amount: &Option<String>) -> Result<Option<Mutez>, APIError> {
+    xtz_amount
+        .as_ref()
+        .map(|xtz_amount| {
+            let value = amount::to_base_units(xtz_amount, 0)?;
+
+            Ok(Mutez::try_from(&value)?)
+        })
+        .map_or(Ok(None), |r| r.map(Some))
+}
+
+impl NewOperationRequest {
+    pub fn xtz_amount(&self) -> Result<Option<Mutez>, APIError> {
+        parse_xtz_amount(&self.xtz_amount)
+    }
+
+    /// Mints may only target addresses on the contract's allowlist, unless the allowlist is empty.
+    pub fn validate_target_address(
+        &self,
+        allowed_target_addresses: &[String],
+    ) -> Result<(), APIError> {
+        if allowed_target_addresses.is_empty() {
+            return Ok(());
+        }
+        if self.kind == OperationRequestKind::BatchMint {
+            let batch_mints = self.batch_mints.as_deref().unwrap_or_default();
+            return match batch_mints
+                .iter()
+                .find(|mint| !allowed_target_addresses.contains(&mint.target_address))
+            {
+                Some(mint) => Err(APIError::InvalidOperationRequest {
+                    description: format!("target address {} is not allowed", mint.target_address),
+                }),
+                None => Ok(()),
+            };
+        }
+        if self.kind != OperationRequestKind::Mint {
+            return Ok(());
+        }
+
+        match &self.target_address {
+            Some(target_address) if allowed_target_addresses.contains(target_address) => Ok(()),
+            Some(target_address) => Err(APIError::InvalidOperationRequest {
+                description: format!("target address {} is not allowed", target_address),
+            }),
+            None => Err(APIError::InvalidOperationRequest {
+                description: "target address is required".into(),
+            }),
+        }
+    }
+
+    /// Batch mints need between one and `max_recipients` recipients.
+    pub fn validate_batch_mints(&self, max_recipients: usize) -> Result<(), APIError> {
+        if self.kind != OperationRequestKind::BatchMint {
+            return Ok(());
+        }
+
+        match &self.batch_mints {
+            Some(batch_mints) if batch_mints.is_empty() => Err(APIError::InvalidOperationRequest {
+                description: "batch_mints cannot be empty".into(),
+            }),
+            Some(batch_mints) if batch_mints.len() > max_recipients => {
+                Err(APIError::InvalidOperationRequest {
+                    description: format!(
+                        "batch mints are limited to {} recipients, got {}",
+                        max_recipients,
+                        batch_mints.len()
+                    ),
+                })
+            }
+            Some(_) => Ok(()),
+            None => Err(APIError::InvalidOperationRequest {
+                description: "batch_mints are required".into(),
+            }),
+        }
+    }
+
+    /// Checks that a keyholder update doesn't lock the current keyholders out of the multisig.
+    /// Thresholds the proposed keyholders can't meet are rejected unless `safeguard` is off, the
+    /// other risks are returned to be logged, or rejected if `safeguard` blocks them.
+    /// `current_keyholders` are the public keys of the approvers of the multisig and `proposer`
+    /// the address of the user submitting the update.
+    pub fn validate_keyholder_update(
+        &self,
+        current_keyholders: &[String],
+        proposer: &str,
+        safeguard: KeyholderUpdateSafeguard,
+    ) -> Result<Vec<KeyholderUpdateRisk>, APIError> {
+        if self.kind != OperationRequestKind::UpdateKeyholders
+            || safeguard == KeyholderUpdateSafeguard::Off
+        {
+            return Ok(vec![]);
+        }
+        let (threshold, proposed_keyholders) = match (self.threshold, &self.proposed_keyholders) {
+            (Some(threshold), Some(proposed_keyholders)) => (threshold, proposed_keyholders),
+            _ => return Ok(vec![]),
+        };
+
+        let proposed_keyholders = proposed_keyholders.iter().collect::<HashSet<_>>();
+        if threshold < 1 || threshold > proposed_keyholders.len() as i64 {
+            return Err(APIError::InvalidOperationRequest {
+                description: format!(
+                    "a threshold of {} cannot be met by {} keyholders",
+                    threshold,
+                    proposed_keyholders.len()
+                ),
+            });
+        }
+
+        let mut risks = vec![];
+        let proposer_kept = proposed_keyholders
+            .iter()
+            .any(|public_key| edpk_to_tz1(public_key).map_or(false, |address| address == proposer));
+        if !proposer_kept {
+            risks.push(KeyholderUpdateRisk::ProposerRemoved);
+        }
+        if !current_keyholders
+            .iter()
+            .any(|public_key| proposed_keyholders.contains(public_key))
+        {
+            risks.push(KeyholderUpdateRisk::AllKeyholdersReplaced);
+        }
+
+        if safeguard == KeyholderUpdateSafeguard::Block {
+            if let Some(risk) = risks.first() {
+                return Err(APIError::InvalidOperationRequest {
+                    description: format!("keyholder update rejected: {}", risk),
+                });
+            }
+        }
+
+        Ok(risks)
+    }
+}
+
+/// Ways a keyholder update could lock the current keyholders out of the multisig, see
+/// `NewOperationRequest::validate_keyholder_update`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum KeyholderUpdateRisk {
+    /// The user proposing the update is not among the new keyholders.
+    ProposerRemoved,
+    /// None of the current keyholders are among the new keyholders.
+    AllKeyholdersReplaced,
+}
+
+impl Display for KeyholderUpdateRisk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyholderUpdateRisk::ProposerRemoved => {
+                write!(f, "the proposer would no longer be a keyholder")
+            }
+            KeyholderUpdateRisk::AllKeyholdersReplaced => {
+                write!(f, "none of the current keyholders would remain")
+            }
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct PatchOperationRequest {
     pub operation_hash: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+/// Deserialized with `TryFrom<&str>`, so an unknown kind is reported with the accepted ones.
+#[derive(Debug, Serialize, Copy, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum OperationRequestKind {
     Mint = 0,
@@ -105,6 +326,8 @@ pub enum OperationRequestKind {
     SetRedeemAddress = 5,
     TransferOwnership = 6,
     AcceptOwnership = 7,
+    Approve = 8,
+    BatchMint = 9,
 }
 
 const MINT: &'static str = "mint";
@@ -115,6 +338,32 @@ const REMOVE_OPERATOR: &'static str = "remove_operator";
 const SET_REDEEM_ADDRESS: &'static str = "set_redeem_address";
 const TRANSFER_OWNERSHIP: &'static str = "transfer_ownership";
 const ACCEPT_OWNERSHIP: &'static str = "accept_ownership";
+const APPROVE: &'static str = "approve";
+const BATCH_MINT: &'static str = "batch_mint";
+
+impl OperationRequestKind {
+    pub const ALL: [OperationRequestKind; 10] = [
+        OperationRequestKind::Mint,
+        OperationRequestKind::Burn,
+        OperationRequestKind::UpdateKeyholders,
+        OperationRequestKind::AddOperator,
+        OperationRequestKind::RemoveOperator,
+        OperationRequestKind::SetRedeemAddress,
+        OperationRequestKind::TransferOwnership,
+        OperationRequestKind::AcceptOwnership,
+        OperationRequestKind::Approve,
+        OperationRequestKind::BatchMint,
+    ];
+
+    /// Whether tez can be sent along with the contract call of this kind. Batch mints make
+    /// several calls and keyholder updates none.
+    pub fn accepts_xtz_amount(&self) -> bool {
+        match self {
+            OperationRequestKind::UpdateKeyholders | OperationRequestKind::BatchMint => false,
+            _ => true,
+        }
+    }
+}
 
 impl TryFrom<&str> for OperationRequestKind {
     type Error = APIError;
@@ -129,13 +378,40 @@ impl TryFrom<&str> for OperationRequestKind {
             SET_REDEEM_ADDRESS => Ok(OperationRequestKind::SetRedeemAddress),
             TRANSFER_OWNERSHIP => Ok(OperationRequestKind::TransferOwnership),
             ACCEPT_OWNERSHIP => Ok(OperationRequestKind::AcceptOwnership),
-            _ => Err(APIError::Internal {
-                description: format!("invalid operation kind: {}", value),
+            APPROVE => Ok(OperationRequestKind::Approve),
+            BATCH_MINT => Ok(OperationRequestKind::BatchMint),
+            _ => Err(APIError::InvalidValue {
+                description: unknown_kind_description(value),
             }),
         }
     }
 }
 
+fn unknown_kind_description(value: &str) -> String {
+    let accepted = OperationRequestKind::ALL
+        .iter()
+        .map(|kind| (*kind).into())
+        .collect::<Vec<&str>>();
+
+    format!(
+        "unknown operation request kind {:?}, expected one of {}",
+        value,
+        accepted.join(", ")
+    )
+}
+
+impl<'de> Deserialize<'de> for OperationRequestKind {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+
+        OperationRequestKind::try_from(value.as_str())
+            .map_err(|_error| de::Error::custom(unknown_kind_description(&value)))
+    }
+}
+
 impl TryFrom<i16> for OperationRequestKind {
     type Error = APIError;
 
@@ -149,6 +425,8 @@ impl TryFrom<i16> for OperationRequestKind {
             5 => Ok(OperationRequestKind::SetRedeemAddress),
             6 => Ok(OperationRequestKind::TransferOwnership),
             7 => Ok(OperationRequestKind::AcceptOwnership),
+            8 => Ok(OperationRequestKind::Approve),
+            9 => Ok(OperationRequestKind::BatchMint),
             _ => Err(APIError::InvalidValue {
                 description: format!("operation kind cannot be {}", value),
             }),
@@ -167,6 +445,8 @@ impl Into<&'static str> for OperationRequestKind {
             OperationRequestKind::SetRedeemAddress => SET_REDEEM_ADDRESS,
             OperationRequestKind::TransferOwnership => TRANSFER_OWNERSHIP,
             OperationRequestKind::AcceptOwnership => ACCEPT_OWNERSHIP,
+            OperationRequestKind::Approve => APPROVE,
+            OperationRequestKind::BatchMint => BATCH_MINT,
         }
     }
 }
@@ -182,6 +462,8 @@ impl Into<i16> for OperationRequestKind {
             OperationRequestKind::SetRedeemAddress => 5,
             OperationRequestKind::TransferOwnership => 6,
             OperationRequestKind::AcceptOwnership => 7,
+            OperationRequestKind::Approve => 8,
+            OperationRequestKind::BatchMint => 9,
         }
     }
 }
@@ -197,6 +479,8 @@ impl Display for OperationRequestKind {
             OperationRequestKind::SetRedeemAddress => "Set Redeem Address",
             OperationRequestKind::TransferOwnership => "Transfer Ownership",
             OperationRequestKind::AcceptOwnership => "Accept Ownership",
+            OperationRequestKind::Approve => "Approve",
+            OperationRequestKind::BatchMint => "Batch Mint",
         };
         write!(f, "{}", value)
     }
@@ -208,11 +492,20 @@ pub enum OperationRequestState {
     Open = 0,
     Approved = 1,
     Injected = 2,
+    Expired = 3,
+}
+
+impl OperationRequestState {
+    /// Whether an operation request in this state will not change anymore.
+    pub fn is_terminal(&self) -> bool {
+        *self == OperationRequestState::Injected || *self == OperationRequestState::Expired
+    }
 }
 
 const OPEN: &'static str = "open";
 const APPROVED: &'static str = "approved";
 const INJECTED: &'static str = "injected";
+const EXPIRED: &'static str = "expired";
 
 impl TryFrom<&str> for OperationRequestState {
     type Error = APIError;
@@ -222,6 +515,7 @@ impl TryFrom<&str> for OperationRequestState {
             OPEN => Ok(OperationRequestState::Open),
             APPROVED => Ok(OperationRequestState::Approved),
             INJECTED => Ok(OperationRequestState::Injected),
+            EXPIRED => Ok(OperationRequestState::Expired),
             _ => Err(APIError::InvalidValue {
                 description: format!("operation state cannot be {}", value),
             }),
@@ -237,6 +531,7 @@ impl TryFrom<i16> for OperationRequestState {
             0 => Ok(OperationRequestState::Open),
             1 => Ok(OperationRequestState::Approved),
             2 => Ok(OperationRequestState::Injected),
+            3 => Ok(OperationRequestState::Expired),
             _ => Err(APIError::InvalidValue {
                 description: format!("operation state cannot be {}", value),
             }),
@@ -250,6 +545,7 @@ impl Into<&'static str> for OperationRequestState {
             OperationRequestState::Open => OPEN,
             OperationRequestState::Approved => APPROVED,
             OperationRequestState::Injected => INJECTED,
+            OperationRequestState::Expired => EXPIRED,
         }
     }
 }
@@ -260,6 +556,7 @@ impl Into<i16> for OperationRequestState {
             OperationRequestState::Open => 0,
             OperationRequestState::Approved => 1,
             OperationRequestState::Injected => 2,
+            OperationRequestState::Expired => 3,
         }
     }
 }
@@ -270,11 +567,112 @@ impl Display for OperationRequestState {
             OperationRequestState::Open => OPEN,
             OperationRequestState::Approved => APPROVED,
             OperationRequestState::Injected => INJECTED,
+            OperationRequestState::Expired => EXPIRED,
         };
         write!(f, "{}", value)
     }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OperationRequestCleanup {
+    pub deleted: usize,
+}
+
+/// Operation requests of a contract made before it was managed by this backend, with their
+/// approvals, see `POST /operation-requests/import`.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct OperationRequestImport {
+    pub contract_id: Uuid,
+    pub operation_requests: Vec<ImportedOperationRequest>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ImportedOperationRequest {
+    pub gatekeeper_id: Uuid,
+    pub kind: OperationRequestKind,
+    pub target_address: Option<String>,
+    pub amount: Option<String>,
+    pub threshold: Option<i64>,
+    pub batch_mints: Option<Vec<BatchMint>>,
+    pub xtz_amount: Option<String>,
+    pub chain_id: String,
+    pub nonce: i64,
+    pub state: OperationRequestState,
+    pub operation_hash: Option<String>,
+    pub approvals: Vec<ImportedOperationApproval>,
+}
+
+impl ImportedOperationRequest {
+    pub fn xtz_amount(&self) -> Result<Option<Mutez>, APIError> {
+        parse_xtz_amount(&self.xtz_amount)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ImportedOperationApproval {
+    pub keyholder_id: Uuid,
+    pub signature: String,
+}
+
+impl OperationRequestImport {
+    /// Checks that the history is consistent in itself, and returns its highest nonce. Nonces are
+    /// unique per contract, and only injected operation requests have an operation hash.
+    pub fn validate(&self) -> Result<i64, APIError> {
+        let mut nonces = HashSet::new();
+        for operation_request in &self.operation_requests {
+            if operation_request.nonce < 0 {
+                return Err(APIError::InvalidValue {
+                    description: format!("nonce cannot be {}", operation_request.nonce),
+                });
+            }
+            if !nonces.insert(operation_request.nonce) {
+                return Err(APIError::InvalidValue {
+                    description: format!(
+                        "nonce {} is used by more than one operation request",
+                        operation_request.nonce
+                    ),
+                });
+            }
+            let injected = operation_request.state == OperationRequestState::Injected;
+            if injected != operation_request.operation_hash.is_some() {
+                return Err(APIError::InvalidValue {
+                    description: format!(
+                        "operation request with nonce {} must have an operation_hash if and only if it is injected",
+                        operation_request.nonce
+                    ),
+                });
+            }
+            let mut keyholders = HashSet::new();
+            for approval in &operation_request.approvals {
+                if !keyholders.insert(approval.keyholder_id) {
+                    return Err(APIError::InvalidValue {
+                        description: format!(
+                            "operation request with nonce {} has more than one approval of keyholder {}",
+                            operation_request.nonce, approval.keyholder_id
+                        ),
+                    });
+                }
+            }
+        }
+
+        nonces
+            .into_iter()
+            .max()
+            .ok_or_else(|| APIError::InvalidValue {
+                description: "operation_requests cannot be empty".into(),
+            })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OperationRequestImportResult {
+    pub imported: usize,
+    pub max_nonce: i64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SignableOperationRequest {
     pub unsigned_operation_request: NewOperationRequest,
@@ -292,3 +690,568 @@ impl SignableOperationRequest {
         }
     }
 }
+
+pub const SIGNING_BUNDLE_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SigningBundle {
+    pub version: u32,
+    pub operation_request_id: Uuid,
+    pub kind: OperationRequestKind,
+    pub chain_id: String,
+    pub nonce: i64,
+    pub contract_address: String,
+    pub multisig_address: String,
+    pub michelson_data: MichelsonV1Expression,
+    pub michelson_type: MichelsonV1Expression,
+    pub packed_data: String,
+    pub blake2b_hash: String,
+    pub tezos_client_command: String,
+    pub min_approvals: i32,
+    pub signers: Vec<String>,
+}
+
+impl SigningBundle {
+    pub fn new(
+        operation_request: &DBOperationRequest,
+        contract: &DBContract,
+        signable_message: SignableMessage,
+        keyholders: Vec<DBUser>,
+    ) -> Result<SigningBundle, APIError> {
+        let michelson_data = signable_message.michelson_data.clone();
+        let michelson_type = signable_message.michelson_type.clone();
+        let signable_message_info: SignableMessageInfo = signable_message.try_into()?;
+
+        Ok(SigningBundle {
+            version: SIGNING_BUNDLE_VERSION,
+            operation_request_id: operation_request.id,
+            kind: operation_request.kind.try_into()?,
+            chain_id: operation_request.chain_id.clone(),
+            nonce: operation_request.nonce,
+            contract_address: contract.pkh.clone(),
+            multisig_address: contract.multisig_pkh.clone(),
+            michelson_data,
+            michelson_type,
+            packed_data: signable_message_info.message,
+            blake2b_hash: signable_message_info.blake2b_hash,
+            tezos_client_command: signable_message_info.tezos_client_command,
+            min_approvals: contract.min_approvals,
+            signers: keyholders
+                .into_iter()
+                .map(|keyholder| keyholder.public_key)
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use super::{
+        BatchMint, KeyholderUpdateRisk, NewOperationRequest, OperationRequestImport, SigningBundle,
+        SIGNING_BUNDLE_VERSION,
+    };
+    use crate::{
+        api::models::{
+            error::APIError,
+            operation_request::{OperationRequestKind, OperationRequestState},
+        },
+        db::models::{
+            operation_approval::OperationApproval, operation_request::OperationRequest, user::User,
+        },
+        fixtures,
+        settings::KeyholderUpdateSafeguard,
+        tezos::{
+            edpk_to_tz1,
+            micheline::{int, types},
+            multisig::SignableMessage,
+        },
+    };
+
+    fn keyholder(contract_id: Uuid, public_key: &str) -> User {
+        User {
+            address: "".into(),
+            ..fixtures::keyholder(contract_id, public_key)
+        }
+    }
+
+    fn operation_request(contract_id: Uuid, state: OperationRequestState) -> OperationRequest {
+        OperationRequest {
+            amount: None,
+            nonce: 3,
+            state: state.into(),
+            ..fixtures::operation_request(contract_id)
+        }
+    }
+
+    fn approvals(
+        operation_request: &OperationRequest,
+        count: usize,
+    ) -> Vec<(OperationApproval, User)> {
+        (0..count)
+            .map(|_| {
+                let keyholder = keyholder(
+                    operation_request.contract_id,
+                    "edpkuHG9N83cBavucaLSeeKX3AVjn9wDyFeFmrhaSLqvmBycP5N7Zs",
+                );
+                let approval = OperationApproval {
+                    signer_set: Some(vec![keyholder.public_key.clone()]),
+                    ..fixtures::operation_approval(operation_request, &keyholder)
+                };
+
+                (approval, keyholder)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_approval_progress() -> Result<(), APIError> {
+        let contract_id = Uuid::new_v4();
+        for (state, count) in vec![
+            (OperationRequestState::Open, 0),
+            (OperationRequestState::Open, 1),
+            (OperationRequestState::Approved, 2),
+            (OperationRequestState::Approved, 3),
+            (OperationRequestState::Injected, 2),
+        ] {
+            let db_operation_request = operation_request(contract_id, state);
+            let operation_approvals = approvals(&db_operation_request, count);
+            let gatekeeper = keyholder(contract_id, "");
+
+            let response = super::OperationRequest::from(
+                db_operation_request,
+                gatekeeper,
+                operation_approvals,
+                None,
+                2,
+            )?;
+
+            assert_eq!(response.state, state);
+            assert_eq!(response.approvals_required, 2);
+            assert_eq!(response.approvals_received, count as i64);
+            assert_eq!(
+                response.approvals_received,
+                response.operation_approvals.len() as i64
+            );
+            assert_eq!(
+                response.approvals_received >= response.approvals_required,
+                state != OperationRequestState::Open
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_signing_bundle() -> Result<(), APIError> {
+        let contract = fixtures::contract();
+        let operation_request = OperationRequest {
+            target_address: None,
+            amount: None,
+            kind: OperationRequestKind::AcceptOwnership.into(),
+            nonce: 3,
+            ..fixtures::operation_request(contract.id)
+        };
+        let signable_message = SignableMessage {
+            packed_data: "050001".into(),
+            michelson_data: int(1),
+            michelson_type: types::nat(),
+        };
+        let keyholders = vec![
+            keyholder(
+                contract.id,
+                "edpkuHG9N83cBavucaLSeeKX3AVjn9wDyFeFmrhaSLqvmBycP5N7Zs",
+            ),
+            keyholder(
+                contract.id,
+                "edpktfkToequZjyn3jz3GJobiYApkc5q4xnJiksStYbZkznUdsxDUw",
+            ),
+        ];
+
+        let bundle =
+            SigningBundle::new(&operation_request, &contract, signable_message, keyholders)?;
+
+        assert_eq!(bundle.version, SIGNING_BUNDLE_VERSION);
+        assert_eq!(bundle.packed_data, "050001");
+        assert_eq!(
+            bundle.tezos_client_command,
+            "tezos-client hash data '1' of type 'nat'"
+        );
+        assert_eq!(
+            bundle.multisig_address,
+            "KT1MeQd67djiEEkscx3USfQ7vFYg589DySpZ"
+        );
+        assert_eq!(
+            bundle.signers,
+            vec![
+                "edpkuHG9N83cBavucaLSeeKX3AVjn9wDyFeFmrhaSLqvmBycP5N7Zs",
+                "edpktfkToequZjyn3jz3GJobiYApkc5q4xnJiksStYbZkznUdsxDUw"
+            ]
+        );
+
+        Ok(())
+    }
+
+    fn new_operation_request(
+        kind: OperationRequestKind,
+        target_address: Option<&str>,
+    ) -> NewOperationRequest {
+        NewOperationRequest {
+            contract_id: Uuid::new_v4(),
+            target_address: target_address.map(|address| address.into()),
+            amount: Some("100".into()),
+            threshold: None,
+            proposed_keyholders: None,
+            batch_mints: None,
+            xtz_amount: None,
+            kind,
+            ledger_hash: None,
+        }
+    }
+
+    fn batch_mint(target_addresses: &[&str]) -> NewOperationRequest {
+        NewOperationRequest {
+            amount: None,
+            batch_mints: Some(
+                target_addresses
+                    .iter()
+                    .map(|target_address| BatchMint {
+                        target_address: String::from(*target_address),
+                        amount: "100".into(),
+                    })
+                    .collect(),
+            ),
+            ..new_operation_request(OperationRequestKind::BatchMint, None)
+        }
+    }
+
+    #[test]
+    fn test_validate_target_address() {
+        let allowed = "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT";
+        let denied = "tz1gZdWPZAaFoTVfHDJ5zo5BbdqM9F9dYDTt";
+        let allowlist = vec![allowed.to_owned()];
+
+        let mint = new_operation_request(OperationRequestKind::Mint, Some(allowed));
+        assert!(mint.validate_target_address(&allowlist).is_ok());
+
+        let mint = new_operation_request(OperationRequestKind::Mint, Some(denied));
+        assert!(matches!(
+            mint.validate_target_address(&allowlist),
+            Err(APIError::InvalidOperationRequest { .. })
+        ));
+        assert!(mint.validate_target_address(&[]).is_ok());
+
+        let burn = new_operation_request(OperationRequestKind::Burn, None);
+        assert!(burn.validate_target_address(&allowlist).is_ok());
+
+        assert!(batch_mint(&[allowed, allowed])
+            .validate_target_address(&allowlist)
+            .is_ok());
+        assert!(matches!(
+            batch_mint(&[allowed, denied]).validate_target_address(&allowlist),
+            Err(APIError::InvalidOperationRequest { .. })
+        ));
+    }
+
+    #[test]
+    fn test_validate_batch_mints() {
+        let address = "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT";
+
+        assert!(batch_mint(&[address, address])
+            .validate_batch_mints(2)
+            .is_ok());
+        assert!(matches!(
+            batch_mint(&[address, address, address]).validate_batch_mints(2),
+            Err(APIError::InvalidOperationRequest { .. })
+        ));
+        assert!(batch_mint(&[]).validate_batch_mints(2).is_err());
+        assert!(
+            new_operation_request(OperationRequestKind::BatchMint, Some(address))
+                .validate_batch_mints(2)
+                .is_err()
+        );
+        assert!(
+            new_operation_request(OperationRequestKind::Mint, Some(address))
+                .validate_batch_mints(0)
+                .is_ok()
+        );
+    }
+
+    fn update_keyholders(threshold: i64, proposed_keyholders: &[&str]) -> NewOperationRequest {
+        NewOperationRequest {
+            amount: None,
+            threshold: Some(threshold),
+            proposed_keyholders: Some(
+                proposed_keyholders
+                    .iter()
+                    .map(|public_key| String::from(*public_key))
+                    .collect(),
+            ),
+            ..new_operation_request(OperationRequestKind::UpdateKeyholders, None)
+        }
+    }
+
+    #[test]
+    fn test_validate_keyholder_update_self_removal() -> Result<(), APIError> {
+        let proposer_key = "edpktzrjdb1tx6dQecQGZL6CwhujWg1D2CXfXWBriqtJSA6kvqMwA2";
+        let other_key = "edpkuAJhbFLfJ4zWbQQWTZNGDg7hrcG1m1CBSWVB3iDHChjuzeaZB6";
+        let new_key = "edpkvGfYw3LyB1UcCahKQk4rF2tvbMUk8GFiTuMjL75uGXrpvKXhjn";
+        let proposer = edpk_to_tz1(proposer_key)?;
+        let current_keyholders = vec![proposer_key.to_owned(), other_key.to_owned()];
+
+        let update = update_keyholders(2, &[other_key, new_key]);
+        assert_eq!(
+            update.validate_keyholder_update(
+                &current_keyholders,
+                &proposer,
+                KeyholderUpdateSafeguard::Warn
+            )?,
+            vec![KeyholderUpdateRisk::ProposerRemoved]
+        );
+        assert!(matches!(
+            update.validate_keyholder_update(
+                &current_keyholders,
+                &proposer,
+                KeyholderUpdateSafeguard::Block
+            ),
+            Err(APIError::InvalidOperationRequest { .. })
+        ));
+        assert!(update
+            .validate_keyholder_update(
+                &current_keyholders,
+                &proposer,
+                KeyholderUpdateSafeguard::Off
+            )?
+            .is_empty());
+
+        let update = update_keyholders(2, &[proposer_key, new_key]);
+        assert!(update
+            .validate_keyholder_update(
+                &current_keyholders,
+                &proposer,
+                KeyholderUpdateSafeguard::Block
+            )?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_keyholder_update_warnings() -> Result<(), APIError> {
+        let proposer_key = "edpktzrjdb1tx6dQecQGZL6CwhujWg1D2CXfXWBriqtJSA6kvqMwA2";
+        let other_key = "edpkuAJhbFLfJ4zWbQQWTZNGDg7hrcG1m1CBSWVB3iDHChjuzeaZB6";
+        let new_key = "edpkvGfYw3LyB1UcCahKQk4rF2tvbMUk8GFiTuMjL75uGXrpvKXhjn";
+        let proposer = edpk_to_tz1(proposer_key)?;
+        let current_keyholders = vec![proposer_key.to_owned(), other_key.to_owned()];
+        let response = |risks: &[KeyholderUpdateRisk]| -> Result<serde_json::Value, APIError> {
+            let contract_id = Uuid::new_v4();
+            let operation_request = super::OperationRequest::from(
+                operation_request(contract_id, OperationRequestState::Open),
+                keyholder(contract_id, ""),
+                vec![],
+                None,
+                2,
+            )?;
+
+            Ok(serde_json::to_value(operation_request.with_warnings(risks)).unwrap())
+        };
+
+        let risks = update_keyholders(2, &[other_key, new_key]).validate_keyholder_update(
+            &current_keyholders,
+            &proposer,
+            KeyholderUpdateSafeguard::Warn,
+        )?;
+        assert_eq!(
+            response(&risks)?["warnings"],
+            serde_json::json!(["the proposer would no longer be a keyholder"])
+        );
+
+        let risks = update_keyholders(2, &[proposer_key, new_key]).validate_keyholder_update(
+            &current_keyholders,
+            &proposer,
+            KeyholderUpdateSafeguard::Warn,
+        )?;
+        assert_eq!(response(&risks)?["warnings"], serde_json::json!([]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_keyholder_update_unsatisfiable_threshold() -> Result<(), APIError> {
+        let proposer_key = "edpktzrjdb1tx6dQecQGZL6CwhujWg1D2CXfXWBriqtJSA6kvqMwA2";
+        let new_key = "edpkuHG9N83cBavucaLSeeKX3AVjn9wDyFeFmrhaSLqvmBycP5N7Zs";
+        let proposer = edpk_to_tz1(proposer_key)?;
+        let current_keyholders = vec![proposer_key.to_owned()];
+
+        for update in &[
+            update_keyholders(3, &[proposer_key, new_key]),
+            update_keyholders(2, &[proposer_key, proposer_key]),
+            update_keyholders(0, &[proposer_key, new_key]),
+        ] {
+            assert!(matches!(
+                update.validate_keyholder_update(
+                    &current_keyholders,
+                    &proposer,
+                    KeyholderUpdateSafeguard::Warn
+                ),
+                Err(APIError::InvalidOperationRequest { .. })
+            ));
+            assert!(update
+                .validate_keyholder_update(
+                    &current_keyholders,
+                    &proposer,
+                    KeyholderUpdateSafeguard::Off
+                )?
+                .is_empty());
+        }
+
+        let new_keyholders = update_keyholders(1, &[new_key]);
+        assert_eq!(
+            new_keyholders.validate_keyholder_update(
+                &current_keyholders,
+                &proposer,
+                KeyholderUpdateSafeguard::Warn
+            )?,
+            vec![
+                KeyholderUpdateRisk::ProposerRemoved,
+                KeyholderUpdateRisk::AllKeyholdersReplaced
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_xtz_amount() -> Result<(), APIError> {
+        let with_xtz_amount = |xtz_amount: &str| NewOperationRequest {
+            xtz_amount: Some(xtz_amount.into()),
+            ..new_operation_request(OperationRequestKind::Approve, None)
+        };
+
+        assert_eq!(
+            new_operation_request(OperationRequestKind::Approve, None).xtz_amount()?,
+            None
+        );
+        assert_eq!(
+            with_xtz_amount("1500000")
+                .xtz_amount()?
+                .map(|mutez| mutez.value()),
+            Some(1_500_000)
+        );
+        assert_eq!(
+            with_xtz_amount("9223372036854775807")
+                .xtz_amount()?
+                .map(|mutez| mutez.value()),
+            Some(i64::MAX)
+        );
+        assert!(with_xtz_amount("9223372036854775808").xtz_amount().is_err());
+        assert!(with_xtz_amount("-1").xtz_amount().is_err());
+        assert!(with_xtz_amount("1.5").xtz_amount().is_err());
+
+        assert!(OperationRequestKind::Approve.accepts_xtz_amount());
+        assert!(!OperationRequestKind::BatchMint.accepts_xtz_amount());
+        assert!(!OperationRequestKind::UpdateKeyholders.accepts_xtz_amount());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_operation_request_unknown_fields() {
+        let valid = serde_json::json!({
+            "contract_id": "5b8c8f3e-5a2c-4d5e-9d0a-2c6f0f6c3b1a",
+            "target_address": "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT",
+            "amount": "100",
+            "kind": "mint",
+        });
+        let parsed: NewOperationRequest = serde_json::from_value(valid).unwrap();
+        assert_eq!(parsed.kind, OperationRequestKind::Mint);
+
+        let misnamed = serde_json::json!({
+            "contract_id": "5b8c8f3e-5a2c-4d5e-9d0a-2c6f0f6c3b1a",
+            "targetAddress": "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT",
+            "amount": "100",
+            "kind": "mint",
+        });
+        let error = serde_json::from_value::<NewOperationRequest>(misnamed).unwrap_err();
+        assert!(error.to_string().contains("unknown field `targetAddress`"));
+    }
+
+    #[test]
+    fn test_operation_request_kind_serde() {
+        for kind in OperationRequestKind::ALL.iter() {
+            let serialized = serde_json::to_value(kind).unwrap();
+            let name: &str = (*kind).into();
+            assert_eq!(serialized, name);
+            assert_eq!(
+                serde_json::from_value::<OperationRequestKind>(serialized).unwrap(),
+                *kind
+            );
+        }
+
+        let error = serde_json::from_value::<OperationRequestKind>(serde_json::json!("mintt"))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("unknown operation request kind \"mintt\""));
+        assert!(error.contains("approve, batch_mint"));
+    }
+
+    fn history(operation_requests: serde_json::Value) -> OperationRequestImport {
+        serde_json::from_value(serde_json::json!({
+            "contract_id": "5b8c8f3e-5a2c-4d5e-9d0a-2c6f0f6c3b1a",
+            "operation_requests": operation_requests,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_validate_import() -> Result<(), APIError> {
+        let gatekeeper_id = Uuid::new_v4();
+        let keyholder_id = Uuid::new_v4();
+        let injected = |nonce: i64| {
+            serde_json::json!({
+                "gatekeeper_id": gatekeeper_id,
+                "kind": "mint",
+                "target_address": "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT",
+                "amount": "100",
+                "threshold": null,
+                "batch_mints": null,
+                "chain_id": "NetXdQprcVkpaWU",
+                "nonce": nonce,
+                "state": "injected",
+                "operation_hash": "ooZ1yJqgTXjESAvEJxrN3cGCUeWHJzGjMc7XCZWyTm5rm3ksbkU",
+                "approvals": [{ "keyholder_id": keyholder_id, "signature": "edsig" }],
+            })
+        };
+
+        let import = history(serde_json::json!([injected(0), injected(2), injected(1)]));
+        assert_eq!(import.validate()?, 2);
+
+        let duplicate_nonce = history(serde_json::json!([injected(0), injected(0)]));
+        assert!(duplicate_nonce.validate().is_err());
+
+        let mut open = injected(3);
+        open["state"] = "open".into();
+        assert!(history(serde_json::json!([injected(0), open.clone()]))
+            .validate()
+            .is_err());
+        open["operation_hash"] = serde_json::Value::Null;
+        assert_eq!(
+            history(serde_json::json!([injected(0), open])).validate()?,
+            3
+        );
+
+        let mut twice_approved = injected(0);
+        twice_approved["approvals"] = serde_json::json!([
+            { "keyholder_id": keyholder_id, "signature": "edsig" },
+            { "keyholder_id": keyholder_id, "signature": "edsig" },
+        ]);
+        assert!(history(serde_json::json!([twice_approved]))
+            .validate()
+            .is_err());
+
+        assert!(history(serde_json::json!([])).validate().is_err());
+
+        Ok(())
+    }
+}
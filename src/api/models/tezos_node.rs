@@ -2,7 +2,7 @@ use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::db::models::node_endpoint::NodeEndpoint as DBNodeEndpoint;
+use crate::{db::models::node_endpoint::NodeEndpoint as DBNodeEndpoint, tezos::NodeHealth};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TezosNode {
@@ -29,7 +29,40 @@ impl From<DBNodeEndpoint> for TezosNode {
     }
 }
 
+/// A node together with the result of checking it, listed with `GET /nodes?check=true`.
 #[derive(Debug, Serialize, Deserialize)]
+pub struct TezosNodeHealth {
+    pub id: Uuid,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub name: String,
+    pub url: String,
+    pub network: String,
+    pub selected: bool,
+    pub reachable: bool,
+    pub head_level: Option<i64>,
+    pub protocol: Option<String>,
+}
+
+impl TezosNodeHealth {
+    pub fn new(node_endpoint: DBNodeEndpoint, health: NodeHealth) -> Self {
+        TezosNodeHealth {
+            id: node_endpoint.id,
+            created_at: node_endpoint.created_at,
+            updated_at: node_endpoint.updated_at,
+            name: node_endpoint.name,
+            url: node_endpoint.url,
+            network: node_endpoint.network,
+            selected: node_endpoint.selected,
+            reachable: health.reachable,
+            head_level: health.head_level,
+            protocol: health.protocol,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct SelectedTezosNode {
     pub id: Uuid,
 }
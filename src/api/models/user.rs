@@ -30,6 +30,7 @@ impl AuthUser {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct PatchAuthUser {
     pub display_name: Option<String>,
     pub email: Option<String>,
@@ -72,11 +73,13 @@ pub enum UserKind {
     Gatekeeper = 0,
     Keyholder = 1,
     Admin = 2,
+    Injector = 3,
 }
 
 const GATEKEEPER: &'static str = "gatekeeper";
 const KEYHOLDER: &'static str = "keyholder";
 const ADMIN: &'static str = "admin";
+const INJECTOR: &'static str = "injector";
 
 impl TryFrom<&str> for UserKind {
     type Error = APIError;
@@ -86,6 +89,7 @@ impl TryFrom<&str> for UserKind {
             GATEKEEPER => Ok(UserKind::Gatekeeper),
             KEYHOLDER => Ok(UserKind::Keyholder),
             ADMIN => Ok(UserKind::Admin),
+            INJECTOR => Ok(UserKind::Injector),
             _ => Err(APIError::InvalidValue {
                 description: format!("user kind cannot be {}", value),
             }),
@@ -101,6 +105,7 @@ impl TryFrom<i16> for UserKind {
             0 => Ok(UserKind::Gatekeeper),
             1 => Ok(UserKind::Keyholder),
             2 => Ok(UserKind::Admin),
+            3 => Ok(UserKind::Injector),
             _ => Err(APIError::InvalidValue {
                 description: format!("user kind cannot be {}", value),
             }),
@@ -114,6 +119,7 @@ impl Into<&'static str> for UserKind {
             UserKind::Gatekeeper => GATEKEEPER,
             UserKind::Keyholder => KEYHOLDER,
             UserKind::Admin => ADMIN,
+            UserKind::Injector => INJECTOR,
         }
     }
 }
@@ -124,6 +130,7 @@ impl Into<i64> for UserKind {
             UserKind::Gatekeeper => 0,
             UserKind::Keyholder => 1,
             UserKind::Admin => 2,
+            UserKind::Injector => 3,
         }
     }
 }
@@ -134,6 +141,7 @@ impl Into<i16> for UserKind {
             UserKind::Gatekeeper => 0,
             UserKind::Keyholder => 1,
             UserKind::Admin => 2,
+            UserKind::Injector => 3,
         }
     }
 }
@@ -1,11 +1,29 @@
-use actix_web::{web, HttpResponse};
+use std::time::Duration;
+
+use actix_web::{web, web::Query, HttpResponse};
+use futures::future::join_all;
+use serde::Deserialize;
 
 use crate::{
-    api::models::{error::APIError, tezos_node::TezosNode},
+    api::models::{
+        common::ListResponse,
+        error::APIError,
+        tezos_node::{TezosNode, TezosNodeHealth},
+    },
     db::models::node_endpoint::NodeEndpoint,
-    DbPool,
+    tezos, DbPool,
 };
 
+/// How long each node is given to answer a health check.
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Deserialize)]
+pub struct Info {
+    page: Option<i64>,
+    limit: Option<i64>,
+    check: Option<bool>,
+}
+
 pub async fn selected_node(pool: web::Data<DbPool>) -> Result<HttpResponse, APIError> {
     let conn = pool.get()?;
     let node: TezosNode = web::block(move || NodeEndpoint::get_selected(&conn))
@@ -14,13 +32,116 @@ pub async fn selected_node(pool: web::Data<DbPool>) -> Result<HttpResponse, APIE
     Ok(HttpResponse::Ok().json(node))
 }
 
-pub async fn nodes(pool: web::Data<DbPool>) -> Result<HttpResponse, APIError> {
+pub async fn nodes(pool: web::Data<DbPool>, query: Query<Info>) -> Result<HttpResponse, APIError> {
     let conn = pool.get()?;
-    let result = web::block(move || NodeEndpoint::get_all(&conn)).await?;
-    let response: Vec<TezosNode> = result
+
+    let page = query.page.unwrap_or(0);
+    let limit = query.limit.unwrap_or(100);
+
+    let (node_endpoints, total_pages) =
+        web::block(move || NodeEndpoint::get_list(&conn, page, limit)).await?;
+
+    if query.check.unwrap_or(false) {
+        let results = check_nodes(node_endpoints, HEALTH_CHECK_TIMEOUT).await;
+
+        return Ok(HttpResponse::Ok().json(ListResponse {
+            page,
+            total_pages,
+            results,
+        }));
+    }
+
+    let results: Vec<TezosNode> = node_endpoints
         .into_iter()
         .map(|node_endpoint| node_endpoint.into())
         .collect();
 
-    Ok(HttpResponse::Ok().json(response))
+    Ok(HttpResponse::Ok().json(ListResponse {
+        page,
+        total_pages,
+        results,
+    }))
+}
+
+/// Checks all nodes at the same time, so a listing takes at most about `timeout`.
+async fn check_nodes(node_endpoints: Vec<NodeEndpoint>, timeout: Duration) -> Vec<TezosNodeHealth> {
+    join_all(node_endpoints.into_iter().map(|node_endpoint| async move {
+        let health = tezos::node_health(&node_endpoint.url, timeout).await;
+        TezosNodeHealth::new(node_endpoint, health)
+    }))
+    .await
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use actix_web::{rt::time::delay_for, web, App, HttpResponse};
+    use chrono::NaiveDateTime;
+    use uuid::Uuid;
+
+    use super::check_nodes;
+    use crate::db::models::node_endpoint::NodeEndpoint;
+
+    fn node_endpoint(name: &str, url: &str) -> NodeEndpoint {
+        NodeEndpoint {
+            id: Uuid::new_v4(),
+            created_at: NaiveDateTime::from_timestamp(0, 0),
+            updated_at: NaiveDateTime::from_timestamp(0, 0),
+            name: name.into(),
+            url: url.into(),
+            network: "mainnet".into(),
+            selected: false,
+        }
+    }
+
+    fn header_node(delay: Duration) -> actix_web::test::TestServer {
+        actix_web::test::start(move || {
+            App::new().route(
+                "/chains/main/blocks/head/header",
+                web::get().to(move || async move {
+                    delay_for(delay).await;
+                    HttpResponse::Ok().json(serde_json::json!({
+                        "level": 1500000,
+                        "protocol": "PtHangz2aRngywmSRGGvrcTyMbbdpWdpFKuS4uMWxg2RaH9i1qx",
+                    }))
+                }),
+            )
+        })
+    }
+
+    #[actix_rt::test]
+    async fn test_check_nodes() {
+        let reachable = header_node(Duration::from_millis(0));
+        let slow = header_node(Duration::from_millis(1000));
+        let failing = actix_web::test::start(|| {
+            App::new().route(
+                "/chains/main/blocks/head/header",
+                web::get().to(|| HttpResponse::InternalServerError().finish()),
+            )
+        });
+
+        let node_endpoints = vec![
+            node_endpoint("reachable", reachable.url("").trim_end_matches('/')),
+            node_endpoint("slow", slow.url("").trim_end_matches('/')),
+            node_endpoint("failing", failing.url("").trim_end_matches('/')),
+            node_endpoint("offline", "http://127.0.0.1:1"),
+        ];
+
+        let results = check_nodes(node_endpoints, Duration::from_millis(200)).await;
+
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].name, "reachable");
+        assert!(results[0].reachable);
+        assert_eq!(results[0].head_level, Some(1500000));
+        assert_eq!(
+            results[0].protocol.as_deref(),
+            Some("PtHangz2aRngywmSRGGvrcTyMbbdpWdpFKuS4uMWxg2RaH9i1qx")
+        );
+        for result in &results[1..] {
+            assert!(!result.reachable, "{} is reachable", result.name);
+            assert_eq!(result.head_level, None);
+            assert_eq!(result.protocol, None);
+        }
+    }
 }
@@ -2,6 +2,7 @@ use crate::api::models::tezos_node::SelectedTezosNode;
 use crate::api::models::tezos_node::TezosNode;
 use crate::db::models::node_endpoint::NodeEndpoint;
 use crate::settings;
+use crate::tezos::{constants::refresh_constants, entrypoints::refresh_entrypoint_schemas};
 use crate::DbPool;
 use crate::{api::models::error::APIError, api::models::user::UserKind, auth::get_current_user};
 use actix_session::Session;
@@ -26,6 +27,8 @@ pub async fn mark_selected(
     .await?;
 
     info!("Tezos node changed to: {:?}", selected.url);
+    refresh_constants();
+    refresh_entrypoint_schemas();
 
     let response: TezosNode = selected.into();
 
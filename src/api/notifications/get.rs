@@ -0,0 +1,20 @@
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+
+use crate::{
+    api::models::{error::APIError, user::UserKind},
+    auth::get_current_user,
+    notifications::NOTIFICATION_PAUSES,
+    settings,
+};
+
+pub async fn notification_pause(
+    server_settings: web::Data<settings::Server>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+    current_user.require_one_of_roles(vec![UserKind::Admin])?;
+
+    let admin_contract_ids = current_user.contract_ids_with_roles(&[UserKind::Admin]);
+    Ok(HttpResponse::Ok().json(NOTIFICATION_PAUSES.get().restricted_to(&admin_contract_ids)))
+}
@@ -0,0 +1,13 @@
+use actix_web::{web, HttpResponse};
+
+mod get;
+mod post;
+
+pub fn api_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/notifications/pause")
+            .route(web::get().to(get::notification_pause))
+            .route(web::post().to(post::notification_pause))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
+}
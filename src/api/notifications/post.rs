@@ -0,0 +1,111 @@
+use actix_session::Session;
+use actix_web::{web, HttpResponse};
+use log::info;
+use uuid::Uuid;
+
+use crate::{
+    api::models::{error::APIError, notification::NewNotificationPause, user::UserKind},
+    auth::{get_current_user, SessionUser},
+    db::models::contract::Contract,
+    notifications::NOTIFICATION_PAUSES,
+    settings, DbPool,
+};
+
+pub async fn notification_pause(
+    pool: web::Data<DbPool>,
+    body: web::Json<NewNotificationPause>,
+    server_settings: web::Data<settings::Server>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+    let pause = body.into_inner();
+    if pause.contract_id.is_none() {
+        let conn = pool.get()?;
+        let contract_ids =
+            web::block::<_, _, APIError>(move || Ok(Contract::get_all_ids(&conn)?)).await?;
+        require_pause_roles(&current_user, None, &contract_ids)?;
+    } else {
+        require_pause_roles(&current_user, pause.contract_id, &[])?;
+    }
+
+    NOTIFICATION_PAUSES.set(pause.contract_id, pause.paused);
+    info!(
+        "Notifications {} {} by {}",
+        match pause.contract_id {
+            Some(contract_id) => format!("about contract {}", contract_id),
+            None => "about all contracts".into(),
+        },
+        if pause.paused { "paused" } else { "resumed" },
+        current_user.address
+    );
+
+    let admin_contract_ids = current_user.contract_ids_with_roles(&[UserKind::Admin]);
+    Ok(HttpResponse::Ok().json(NOTIFICATION_PAUSES.get().restricted_to(&admin_contract_ids)))
+}
+
+/// Pausing the notifications about a contract needs its admin role. Pausing all of them also
+/// mutes the other contracts, so it needs the admin role of every one of `all_contract_ids`.
+fn require_pause_roles(
+    user: &SessionUser,
+    contract_id: Option<Uuid>,
+    all_contract_ids: &[Uuid],
+) -> Result<(), APIError> {
+    match contract_id {
+        Some(contract_id) => user.require_roles(vec![UserKind::Admin], contract_id),
+        None => user.require_roles_on_all(vec![UserKind::Admin], all_contract_ids),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use super::require_pause_roles;
+    use crate::{
+        api::models::{error::APIError, user::UserKind},
+        auth::{SessionUser, SessionUserRole},
+    };
+
+    fn admin_of(contract_ids: &[Uuid]) -> SessionUser {
+        SessionUser {
+            address: "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT".into(),
+            roles: contract_ids
+                .iter()
+                .map(|contract_id| SessionUserRole {
+                    contract_id: *contract_id,
+                    kind: UserKind::Admin,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_single_contract_admin_cannot_pause_globally() {
+        let contract_a = Uuid::new_v4();
+        let contract_b = Uuid::new_v4();
+        let user = admin_of(&[contract_a]);
+
+        assert!(require_pause_roles(&user, Some(contract_a), &[]).is_ok());
+        assert!(matches!(
+            require_pause_roles(&user, Some(contract_b), &[]),
+            Err(APIError::Forbidden)
+        ));
+        assert!(matches!(
+            require_pause_roles(&user, None, &[contract_a, contract_b]),
+            Err(APIError::Forbidden)
+        ));
+    }
+
+    #[test]
+    fn test_admin_of_every_contract_can_pause_globally() {
+        let contract_a = Uuid::new_v4();
+        let contract_b = Uuid::new_v4();
+        let user = admin_of(&[contract_a, contract_b]);
+
+        assert!(require_pause_roles(&user, None, &[contract_a, contract_b]).is_ok());
+        assert!(matches!(
+            require_pause_roles(&admin_of(&[]), None, &[]),
+            Err(APIError::Forbidden)
+        ));
+    }
+}
@@ -0,0 +1,1545 @@
+use actix_web::{HttpRequest, HttpResponse};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::api::models::{
+    api_key::ApiKeyAction,
+    audit_log::AuditEvent,
+    contract::{ContractGrouping, ContractKind},
+    operation_request::{OperationRequestKind, OperationRequestState},
+    user::{UserKind, UserState},
+};
+
+/// The document is served at the path the API is mounted under, see `settings::Server::api_path`,
+/// and refers to it as its server.
+pub async fn openapi(req: HttpRequest) -> HttpResponse {
+    let api_path = req.path().trim_end_matches("/openapi.json");
+
+    HttpResponse::Ok().json(document(if api_path.is_empty() { "/" } else { api_path }))
+}
+
+/// OpenAPI 3 description of the routes below `api_path` and their models.
+pub fn document(api_path: &str) -> Value {
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "tz-wrapped-backend",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "servers": [{ "url": api_path }],
+        "paths": paths(),
+        "components": { "schemas": schemas() },
+    })
+}
+
+fn paths() -> Value {
+    json!({
+        "/auth": {
+            "get": operation(
+                "Returns a challenge to sign in with, no content if already signed in",
+                vec![query("address", string(), true)],
+                None,
+                Some(reference("AuthenticationChallenge")),
+            ),
+            "post": operation(
+                "Signs in with a signed challenge",
+                vec![],
+                Some("AuthenticationChallengeResponse"),
+                Some(reference("AuthUser")),
+            ),
+            "delete": operation("Signs out", vec![], None, None),
+        },
+        "/auth/me": {
+            "get": operation(
+                "Returns the signed in user",
+                vec![],
+                None,
+                Some(reference("AuthUser")),
+            ),
+            "patch": operation(
+                "Updates the signed in user",
+                vec![],
+                Some("PatchAuthUser"),
+                Some(reference("AuthUser")),
+            ),
+        },
+        "/auth/refresh": {
+            "post": operation(
+                "Extends the session of the signed in user, fails if it already expired",
+                vec![],
+                None,
+                Some(reference("SessionRefresh")),
+            ),
+        },
+        "/contracts": {
+            "get": operation(
+                "Lists the contracts, grouped by network or symbol if `group_by` is set",
+                with_pagination(vec![query("group_by", reference("ContractGrouping"), false)]),
+                None,
+                Some(json!({
+                    "oneOf": [list_response("Contract"), list_response("ContractGroup")],
+                })),
+            ),
+        },
+        "/contracts/{id}": {
+            "get": operation(
+                "Returns a contract",
+                vec![path("id")],
+                None,
+                Some(reference("Contract")),
+            ),
+        },
+        "/contracts/nonces": {
+            "get": operation(
+                "Returns the multisig nonce and the next usable nonce of several contracts, by contract id",
+                vec![query("ids", string(), true)],
+                None,
+                Some(json!({
+                    "type": "object",
+                    "additionalProperties": reference("ContractNonces"),
+                })),
+            ),
+        },
+        "/contracts/{id}/nonce": {
+            "get": operation(
+                "Returns the nonce of the contract's multisig",
+                vec![path("id")],
+                None,
+                Some(integer("int64")),
+            ),
+        },
+        "/contracts/{id}/next-usable-nonce": {
+            "get": operation(
+                "Returns the nonce the next operation request will use",
+                vec![path("id")],
+                None,
+                Some(integer("int64")),
+            ),
+        },
+        "/contracts/{id}/balance": {
+            "get": operation(
+                "Returns the token balance of an owner",
+                vec![path("id"), query("owner", string(), true)],
+                None,
+                Some(reference("TokenBalance")),
+            ),
+        },
+        "/contracts/{id}/parameter-schema": {
+            "get": operation(
+                "Returns the parameter type of a multisig entrypoint as reported by the node",
+                vec![path("id"), query("entrypoint", string(), false)],
+                None,
+                Some(reference("ParameterSchema")),
+            ),
+        },
+        "/contracts/{id}/delegate": {
+            "get": operation(
+                "Returns the delegates of the token contract and its multisig",
+                vec![path("id")],
+                None,
+                Some(reference("ContractDelegates")),
+            ),
+        },
+        "/contracts/{id}/operation-requests": {
+            "get": operation(
+                "Lists the operation requests of the contract",
+                with_pagination(vec![
+                    path("id"),
+                    query("kind", reference("OperationRequestKind"), true),
+                    query("state", reference("OperationRequestState"), false),
+                    query("target_address", string(), false),
+                    query("match_prefix", boolean(), false),
+                ]),
+                None,
+                Some(list_response("OperationRequest")),
+            ),
+        },
+        "/contracts/{id}/allowed-target-addresses": {
+            "get": operation(
+                "Lists the addresses mints may target",
+                vec![path("id")],
+                None,
+                Some(array(reference("AllowedTargetAddress"))),
+            ),
+            "post": operation(
+                "Adds an address mints may target",
+                vec![path("id")],
+                Some("NewAllowedTargetAddress"),
+                Some(reference("AllowedTargetAddress")),
+            ),
+        },
+        "/contracts/{id}/allowed-target-addresses/{address}": {
+            "delete": operation(
+                "Removes an address mints may target",
+                vec![path("id"), path_string("address")],
+                None,
+                None,
+            ),
+        },
+        "/contracts/{id}/capabilities": {
+            "post": operation(
+                "Enables operation requests of a kind on a contract",
+                vec![path("id")],
+                Some("NewCapability"),
+                Some(reference("Contract")),
+            ),
+        },
+        "/contracts/{id}/capabilities/{kind}": {
+            "delete": operation(
+                "Disables operation requests of a kind on a contract",
+                vec![
+                    path("id"),
+                    json!({
+                        "name": "kind",
+                        "in": "path",
+                        "required": true,
+                        "schema": reference("OperationRequestKind"),
+                    }),
+                ],
+                None,
+                None,
+            ),
+        },
+        "/audit-log": {
+            "get": operation(
+                "Lists the audit log, newest first, admins only",
+                with_pagination(vec![
+                    query("contract_id", uuid(), false),
+                    query("actor", string(), false),
+                    query("from", date(), false),
+                    query("before", date(), false),
+                ]),
+                None,
+                Some(list_response("AuditLogEntry")),
+            ),
+        },
+        "/users": {
+            "get": operation(
+                "Lists the users of a contract",
+                with_pagination(vec![
+                    query("contract_id", uuid(), true),
+                    query("kind", reference("UserKind"), false),
+                    query("state", reference("UserState"), false),
+                    query("address", string(), false),
+                ]),
+                None,
+                Some(list_response("User")),
+            ),
+        },
+        "/users/{id}": {
+            "get": operation("Returns a user", vec![path("id")], None, Some(reference("User"))),
+        },
+        "/operation-requests": {
+            "get": operation(
+                "Lists the operation requests of a contract",
+                with_pagination(vec![
+                    query("contract_id", uuid(), true),
+                    query("kind", reference("OperationRequestKind"), true),
+                    query("state", reference("OperationRequestState"), false),
+                    query("target_address", string(), false),
+                    query("match_prefix", boolean(), false),
+                ]),
+                None,
+                Some(list_response("OperationRequest")),
+            ),
+            "post": operation(
+                "Creates an operation request",
+                vec![],
+                Some("NewOperationRequest"),
+                Some(reference("OperationRequest")),
+            ),
+        },
+        "/operation-requests/all": {
+            "get": operation(
+                "Lists the operation requests of all contracts the current user is a gatekeeper or keyholder of",
+                with_pagination(vec![
+                    query("kind", reference("OperationRequestKind"), false),
+                    query("state", reference("OperationRequestState"), false),
+                    query("target_address", string(), false),
+                    query("match_prefix", boolean(), false),
+                ]),
+                None,
+                Some(list_response("OperationRequest")),
+            ),
+        },
+        "/operation-requests/cleanup": {
+            "delete": operation(
+                "Deletes the terminal operation requests of a contract created before a date",
+                vec![
+                    query("contract_id", uuid(), true),
+                    query("state", reference("OperationRequestState"), true),
+                    query("before", json!({ "type": "string", "format": "date" }), true),
+                    query("confirm", boolean(), true),
+                ],
+                None,
+                Some(reference("OperationRequestCleanup")),
+            ),
+        },
+        "/operation-requests/import": {
+            "post": operation(
+                "Imports the history of a contract without operation requests",
+                vec![],
+                Some("OperationRequestImport"),
+                Some(reference("OperationRequestImportResult")),
+            ),
+        },
+        "/operation-requests/{id}": {
+            "get": operation(
+                "Returns an operation request",
+                vec![path("id")],
+                None,
+                Some(reference("OperationRequest")),
+            ),
+            "patch": operation(
+                "Records the hash of the operation injecting an approved operation request",
+                vec![path("id")],
+                Some("PatchOperationRequest"),
+                Some(reference("OperationRequest")),
+            ),
+            "delete": operation("Deletes an operation request", vec![path("id")], None, None),
+        },
+        "/operation-requests/{id}/signable-message": {
+            "get": operation(
+                "Returns the message keyholders sign to approve an operation request",
+                vec![path("id")],
+                None,
+                Some(reference("SignableMessageInfo")),
+            ),
+        },
+        "/operation-requests/{id}/signing-bundle": {
+            "get": operation(
+                "Returns everything needed to sign an operation request offline",
+                vec![path("id")],
+                None,
+                Some(reference("SigningBundle")),
+            ),
+        },
+        "/operation-requests/{id}/parameters": {
+            "get": operation(
+                "Returns the parameters of the operation executing an approved operation request",
+                vec![path("id")],
+                None,
+                Some(reference("Parameters")),
+            ),
+        },
+        "/operation-requests/{id}/approval-order": {
+            "get": operation(
+                "Returns the approvals of an operation request in the order of the multisig's approvers",
+                vec![path("id")],
+                None,
+                Some(reference("OperationApprovalOrder")),
+            ),
+        },
+        "/operation-approvals": {
+            "get": operation(
+                "Lists the approvals of an operation request",
+                with_pagination(vec![query("operation_request_id", uuid(), true)]),
+                None,
+                Some(list_response("OperationApproval")),
+            ),
+            "post": operation(
+                "Approves an operation request",
+                vec![],
+                Some("NewOperationApproval"),
+                Some(reference("OperationApproval")),
+            ),
+        },
+        "/operation-approvals/bulk": {
+            "post": operation(
+                "Approves several operation requests, reporting the outcome of each signature",
+                vec![],
+                Some("NewOperationApprovals"),
+                Some(reference("OperationApprovalsResult")),
+            ),
+        },
+        "/operation-approvals/pending/bundle": {
+            "get": {
+                "summary": "Returns a zip archive of the signing bundles of the operation requests \
+                            the signed in keyholder has yet to approve",
+                "responses": {
+                    "200": {
+                        "description": "OK",
+                        "content": {
+                            "application/zip": {
+                                "schema": { "type": "string", "format": "binary" },
+                            },
+                        },
+                    },
+                    "default": error_response(),
+                },
+            },
+        },
+        "/operation-approvals/{id}": {
+            "get": operation(
+                "Returns an approval",
+                vec![path("id")],
+                None,
+                Some(reference("OperationApproval")),
+            ),
+        },
+        "/operation-approvals/{id}/verify": {
+            "get": operation(
+                "Verifies the signature of an approval",
+                vec![path("id")],
+                None,
+                Some(reference("OperationApprovalVerification")),
+            ),
+        },
+        "/nodes": {
+            "get": operation(
+                "Lists the Tezos nodes, with the result of checking each one if `check` is set",
+                with_pagination(vec![query("check", boolean(), false)]),
+                None,
+                Some(json!({
+                    "oneOf": [list_response("TezosNode"), list_response("TezosNodeHealth")],
+                })),
+            ),
+        },
+        "/nodes/selected": {
+            "get": operation(
+                "Returns the selected Tezos node",
+                vec![],
+                None,
+                Some(reference("TezosNode")),
+            ),
+            "post": operation(
+                "Selects a Tezos node",
+                vec![],
+                Some("SelectedTezosNode"),
+                Some(reference("TezosNode")),
+            ),
+        },
+        "/maintenance": {
+            "get": operation(
+                "Returns the maintenance mode",
+                vec![],
+                None,
+                Some(reference("MaintenanceMode")),
+            ),
+            "post": operation(
+                "Enables or disables maintenance mode, which rejects changes with 503, admins only",
+                vec![],
+                Some("NewMaintenanceMode"),
+                Some(reference("MaintenanceMode")),
+            ),
+        },
+        "/notifications/pause": {
+            "get": operation(
+                "Returns which notifications are paused, admins only",
+                vec![],
+                None,
+                Some(reference("NotificationPause")),
+            ),
+            "post": operation(
+                "Pauses or resumes the notifications about a contract or all of them, admins only",
+                vec![],
+                Some("NewNotificationPause"),
+                Some(reference("NotificationPause")),
+            ),
+        },
+        "/api-keys": {
+            "get": operation(
+                "Lists the API keys of a contract, admins of the contract only",
+                vec![query("contract_id", uuid(), true)],
+                None,
+                Some(array(reference("ApiKey"))),
+            ),
+            "post": operation(
+                "Creates an API key, the token is only returned once, admins of the contract only",
+                vec![],
+                Some("NewApiKey"),
+                Some(reference("CreatedApiKey")),
+            ),
+        },
+        "/api-keys/{id}": {
+            "delete": operation(
+                "Revokes an API key, admins of the contract only",
+                vec![path("id")],
+                None,
+                Some(reference("ApiKey")),
+            ),
+        },
+        "/debug/unpack": {
+            "post": operation(
+                "Decodes packed data as a value of the given type",
+                vec![],
+                Some("UnpackRequest"),
+                Some(reference("Unpacked")),
+            ),
+        },
+        "/openapi.json": {
+            "get": operation(
+                "Returns this document",
+                vec![],
+                None,
+                Some(json!({ "type": "object" })),
+            ),
+        },
+    })
+}
+
+fn schemas() -> Value {
+    json!({
+        "ContractKind": enumeration(&[
+            ContractKind::FA1,
+            ContractKind::FA2,
+            ContractKind::FA2SingleAsset,
+        ]),
+        "ContractGrouping": enumeration(&[ContractGrouping::Network, ContractGrouping::Symbol]),
+        "OperationRequestKind": enumeration(&OperationRequestKind::ALL),
+        "OperationRequestState": enumeration(&[
+            OperationRequestState::Open,
+            OperationRequestState::Approved,
+            OperationRequestState::Injected,
+            OperationRequestState::Expired,
+        ]),
+        "AuditEvent": enumeration(&[
+            AuditEvent::OperationRequestCreated,
+            AuditEvent::OperationApprovalAdded,
+            AuditEvent::OperationRequestApproved,
+            AuditEvent::OperationRequestInjected,
+            AuditEvent::OperationRequestDeleted,
+            AuditEvent::ConfigSynced,
+            AuditEvent::MaintenanceModeChanged,
+            AuditEvent::OperationRequestsImported,
+            AuditEvent::ApprovalsDropped,
+            AuditEvent::OperationRequestsExpired,
+            AuditEvent::ApiKeyCreated,
+            AuditEvent::ApiKeyRevoked,
+        ]),
+        "AuditLogEntry": object(json!({
+            "id": uuid(),
+            "created_at": date_time(),
+            "event": reference("AuditEvent"),
+            "actor": nullable(string()),
+            "contract_id": nullable(uuid()),
+            "resource_id": nullable(uuid()),
+            "detail": { "type": "object" },
+        })),
+        "UserKind": enumeration(&[
+            UserKind::Gatekeeper,
+            UserKind::Keyholder,
+            UserKind::Admin,
+            UserKind::Injector,
+        ]),
+        "UserState": enumeration(&[UserState::Active, UserState::Inactive]),
+        "AuthenticationChallenge": object(json!({
+            "id": uuid(),
+            "message": string(),
+        })),
+        "AuthenticationChallengeResponse": request_object(
+            json!({
+                "id": uuid(),
+                "signature": string(),
+            }),
+            &["id", "signature"],
+        ),
+        "SessionRefresh": object(json!({
+            "expires_at": { "type": "string", "format": "date-time" },
+        })),
+        "AuthUser": object(json!({
+            "address": string(),
+            "display_name": string(),
+            "email": nullable(string()),
+            "roles": array(reference("SessionUserRole")),
+        })),
+        "SessionUserRole": object(json!({
+            "contract_id": uuid(),
+            "kind": reference("UserKind"),
+        })),
+        "PatchAuthUser": request_object(
+            json!({
+                "display_name": nullable(string()),
+                "email": nullable(string()),
+            }),
+            &[],
+        ),
+        "User": object(json!({
+            "id": uuid(),
+            "created_at": date_time(),
+            "updated_at": date_time(),
+            "public_key": string(),
+            "address": string(),
+            "contract_id": uuid(),
+            "kind": reference("UserKind"),
+            "state": reference("UserState"),
+            "display_name": string(),
+        })),
+        "Contract": object(json!({
+            "id": uuid(),
+            "created_at": date_time(),
+            "updated_at": date_time(),
+            "pkh": string(),
+            "token_id": integer("int32"),
+            "multisig_pkh": string(),
+            "kind": reference("ContractKind"),
+            "display_name": string(),
+            "min_approvals": integer("int32"),
+            "symbol": string(),
+            "decimals": integer("int32"),
+            "sort_order": nullable(integer("int32")),
+            "capabilities": array(reference("OperationRequestKind")),
+        })),
+        "ContractGroup": object(json!({
+            "key": string(),
+            "contracts": array(reference("Contract")),
+        })),
+        "AllowedTargetAddress": object(json!({
+            "id": uuid(),
+            "created_at": date_time(),
+            "contract_id": uuid(),
+            "address": string(),
+        })),
+        "NewAllowedTargetAddress": request_object(json!({ "address": string() }), &["address"]),
+        "NewCapability": request_object(
+            json!({ "operation_request_kind": reference("OperationRequestKind") }),
+            &["operation_request_kind"],
+        ),
+        "TokenBalance": object(json!({
+            "owner": string(),
+            "token_id": integer("int32"),
+            "balance": decimal(),
+        })),
+        "ContractDelegates": object(json!({
+            "contract_address": string(),
+            "contract_delegate": nullable(string()),
+            "multisig_address": string(),
+            "multisig_delegate": nullable(string()),
+        })),
+        "ContractNonces": object(json!({
+            "on_chain": integer("int64"),
+            "next_usable": integer("int64"),
+        })),
+        "ParameterSchema": object(json!({
+            "address": string(),
+            "entrypoint": string(),
+            "schema": reference("MichelsonV1Expression"),
+            "michelson": string(),
+        })),
+        "OperationRequest": object(json!({
+            "id": uuid(),
+            "created_at": date_time(),
+            "updated_at": date_time(),
+            "user": reference("User"),
+            "contract_id": uuid(),
+            "target_address": nullable(string()),
+            "amount": nullable(decimal()),
+            "threshold": nullable(integer("int64")),
+            "proposed_keyholders": nullable(array(reference("User"))),
+            "batch_mints": nullable(array(reference("BatchMint"))),
+            "xtz_amount": nullable(mutez()),
+            "kind": reference("OperationRequestKind"),
+            "chain_id": string(),
+            "nonce": integer("int64"),
+            "ledger_blake2b_hash": nullable(string()),
+            "state": reference("OperationRequestState"),
+            "operation_approvals": array(reference("OperationApproval")),
+            "operation_hash": nullable(string()),
+            "confirmation_failed": boolean(),
+            "approvals_received": integer("int64"),
+            "approvals_required": integer("int64"),
+            "warnings": array(string()),
+        })),
+        "NewOperationRequest": request_object(
+            json!({
+                "contract_id": uuid(),
+                "target_address": nullable(string()),
+                "amount": nullable(decimal()),
+                "threshold": nullable(integer("int64")),
+                "proposed_keyholders": nullable(array(string())),
+                "batch_mints": nullable(array(reference("BatchMint"))),
+                "xtz_amount": nullable(mutez()),
+                "kind": reference("OperationRequestKind"),
+                "ledger_hash": nullable(string()),
+            }),
+            &["contract_id", "kind"],
+        ),
+        "BatchMint": request_object(
+            json!({
+                "target_address": string(),
+                "amount": decimal(),
+            }),
+            &["target_address", "amount"],
+        ),
+        "PatchOperationRequest": request_object(
+            json!({ "operation_hash": nullable(string()) }),
+            &[],
+        ),
+        "OperationRequestCleanup": object(json!({ "deleted": integer("int64") })),
+        "OperationRequestImport": request_object(
+            json!({
+                "contract_id": uuid(),
+                "operation_requests": array(reference("ImportedOperationRequest")),
+            }),
+            &["contract_id", "operation_requests"],
+        ),
+        "ImportedOperationRequest": request_object(
+            json!({
+                "gatekeeper_id": uuid(),
+                "kind": reference("OperationRequestKind"),
+                "target_address": nullable(string()),
+                "amount": nullable(decimal()),
+                "threshold": nullable(integer("int64")),
+                "batch_mints": nullable(array(reference("BatchMint"))),
+                "xtz_amount": nullable(mutez()),
+                "chain_id": string(),
+                "nonce": integer("int64"),
+                "state": reference("OperationRequestState"),
+                "operation_hash": nullable(string()),
+                "approvals": array(reference("ImportedOperationApproval")),
+            }),
+            &[
+                "gatekeeper_id",
+                "kind",
+                "chain_id",
+                "nonce",
+                "state",
+                "approvals",
+            ],
+        ),
+        "ImportedOperationApproval": request_object(
+            json!({
+                "keyholder_id": uuid(),
+                "signature": string(),
+            }),
+            &["keyholder_id", "signature"],
+        ),
+        "OperationRequestImportResult": object(json!({
+            "imported": integer("int64"),
+            "max_nonce": integer("int64"),
+        })),
+        "OperationApproval": object(json!({
+            "id": uuid(),
+            "created_at": date_time(),
+            "updated_at": date_time(),
+            "keyholder": reference("User"),
+            "operation_request_id": uuid(),
+            "signature": string(),
+            "uploaded_by": nullable(uuid()),
+        })),
+        "NewOperationApproval": request_object(
+            json!({
+                "operation_request_id": uuid(),
+                "signature": string(),
+            }),
+            &["operation_request_id", "signature"],
+        ),
+        "NewOperationApprovals": request_object(
+            json!({
+                "signatures": {
+                    "type": "object",
+                    "description": "Signatures by operation request id",
+                    "additionalProperties": string(),
+                },
+            }),
+            &["signatures"],
+        ),
+        "OperationApprovalResult": object(json!({
+            "operation_request_id": uuid(),
+            "approval": nullable(reference("OperationApproval")),
+            "error": nullable(string()),
+            "message": nullable(string()),
+        })),
+        "OperationApprovalsResult": object(json!({
+            "results": array(reference("OperationApprovalResult")),
+            "approved": array(uuid()),
+        })),
+        "OperationApprovalVerification": object(json!({
+            "valid": boolean(),
+            "signer": string(),
+        })),
+        "OrderedOperationApproval": object(json!({
+            "operation_approval_id": uuid(),
+            "keyholder_id": uuid(),
+            "public_key": string(),
+            "index": nullable(integer("int32")),
+        })),
+        "OperationApprovalOrder": object(json!({
+            "approvers": array(string()),
+            "min_approvals": integer("int64"),
+            "approvals": array(reference("OrderedOperationApproval")),
+            "complete": boolean(),
+        })),
+        "SignableMessageInfo": object(json!({
+            "message": string(),
+            "tezos_client_command": string(),
+            "blake2b_hash": string(),
+        })),
+        "SigningBundle": object(json!({
+            "version": integer("int32"),
+            "operation_request_id": uuid(),
+            "kind": reference("OperationRequestKind"),
+            "chain_id": string(),
+            "nonce": integer("int64"),
+            "contract_address": string(),
+            "multisig_address": string(),
+            "michelson_data": reference("MichelsonV1Expression"),
+            "michelson_type": reference("MichelsonV1Expression"),
+            "packed_data": string(),
+            "blake2b_hash": string(),
+            "tezos_client_command": string(),
+            "min_approvals": integer("int32"),
+            "signers": array(string()),
+        })),
+        "Parameters": object(json!({
+            "entrypoint": string(),
+            "value": reference("MichelsonV1Expression"),
+        })),
+        "MichelsonV1Expression": {
+            "description": "Micheline expression in its JSON encoding",
+        },
+        "TezosNode": object(json!({
+            "id": uuid(),
+            "created_at": date_time(),
+            "updated_at": date_time(),
+            "name": string(),
+            "url": string(),
+            "network": string(),
+            "selected": boolean(),
+        })),
+        "TezosNodeHealth": object(json!({
+            "id": uuid(),
+            "created_at": date_time(),
+            "updated_at": date_time(),
+            "name": string(),
+            "url": string(),
+            "network": string(),
+            "selected": boolean(),
+            "reachable": boolean(),
+            "head_level": nullable(integer("int64")),
+            "protocol": nullable(string()),
+        })),
+        "SelectedTezosNode": request_object(json!({ "id": uuid() }), &["id"]),
+        "MaintenanceMode": object(json!({
+            "enabled": boolean(),
+            "message": nullable(string()),
+            "updated_by": nullable(string()),
+            "updated_at": nullable(date_time()),
+        })),
+        "NewMaintenanceMode": request_object(
+            json!({
+                "enabled": boolean(),
+                "message": nullable(string()),
+            }),
+            &["enabled"],
+        ),
+        "NotificationPause": object(json!({
+            "paused": boolean(),
+            "paused_contracts": array(uuid()),
+        })),
+        "NewNotificationPause": request_object(
+            json!({
+                "paused": boolean(),
+                "contract_id": nullable(uuid()),
+            }),
+            &["paused"],
+        ),
+        "ApiKeyAction": enumeration(&[
+            ApiKeyAction::CreateOperationRequest,
+            ApiKeyAction::ApproveOperationRequest,
+            ApiKeyAction::InjectOperationRequest,
+        ]),
+        "ApiKey": object(json!({
+            "id": uuid(),
+            "created_at": date_time(),
+            "contract_id": uuid(),
+            "name": string(),
+            "address": string(),
+            "actions": array(reference("ApiKeyAction")),
+            "created_by": string(),
+            "revoked_at": nullable(date_time()),
+        })),
+        "NewApiKey": request_object(
+            json!({
+                "contract_id": uuid(),
+                "name": string(),
+                "address": string(),
+                "actions": array(reference("ApiKeyAction")),
+            }),
+            &["contract_id", "name", "address", "actions"],
+        ),
+        "CreatedApiKey": object(json!({
+            "id": uuid(),
+            "created_at": date_time(),
+            "contract_id": uuid(),
+            "name": string(),
+            "address": string(),
+            "actions": array(reference("ApiKeyAction")),
+            "created_by": string(),
+            "revoked_at": nullable(date_time()),
+            "token": string(),
+        })),
+        "UnpackRequest": request_object(
+            json!({
+                "packed": string(),
+                "type": reference("MichelsonV1Expression"),
+            }),
+            &["packed", "type"],
+        ),
+        "Unpacked": object(json!({
+            "micheline": reference("MichelsonV1Expression"),
+            "michelson": string(),
+        })),
+        "ErrorResponse": {
+            "type": "object",
+            "properties": {
+                "code": integer("int32"),
+                "error": string(),
+                "message": string(),
+                "request_id": string(),
+            },
+            "required": ["code", "error", "message"],
+        },
+    })
+}
+
+fn operation(
+    summary: &str,
+    parameters: Vec<Value>,
+    request_body: Option<&str>,
+    response: Option<Value>,
+) -> Value {
+    let mut operation = Map::new();
+    operation.insert("summary".into(), summary.into());
+    if !parameters.is_empty() {
+        operation.insert("parameters".into(), parameters.into());
+    }
+    if let Some(request_body) = request_body {
+        operation.insert(
+            "requestBody".into(),
+            json!({
+                "required": true,
+                "content": { "application/json": { "schema": reference(request_body) } },
+            }),
+        );
+    }
+    let (status, success) = match response {
+        Some(schema) => (
+            "200",
+            json!({
+                "description": "OK",
+                "content": { "application/json": { "schema": schema } },
+            }),
+        ),
+        None => ("204", json!({ "description": "No Content" })),
+    };
+    operation.insert(
+        "responses".into(),
+        json!({
+            status: success,
+            "default": error_response(),
+        }),
+    );
+
+    Value::Object(operation)
+}
+
+fn error_response() -> Value {
+    json!({
+        "description": "Error",
+        "content": { "application/json": { "schema": reference("ErrorResponse") } },
+    })
+}
+
+fn path(name: &str) -> Value {
+    json!({ "name": name, "in": "path", "required": true, "schema": uuid() })
+}
+
+fn path_string(name: &str) -> Value {
+    json!({ "name": name, "in": "path", "required": true, "schema": string() })
+}
+
+fn query(name: &str, schema: Value, required: bool) -> Value {
+    json!({ "name": name, "in": "query", "required": required, "schema": schema })
+}
+
+fn with_pagination(mut parameters: Vec<Value>) -> Vec<Value> {
+    parameters.push(query("page", integer("int64"), false));
+    parameters.push(query("limit", integer("int64"), false));
+
+    parameters
+}
+
+fn list_response(name: &str) -> Value {
+    object(json!({
+        "page": integer("int64"),
+        "total_pages": integer("int64"),
+        "results": array(reference(name)),
+    }))
+}
+
+/// Response objects serialize every field, `null` included.
+fn object(properties: Value) -> Value {
+    let required = properties
+        .as_object()
+        .map(|properties| properties.keys().cloned().collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    json!({ "type": "object", "properties": properties, "required": required })
+}
+
+/// Request bodies reject unknown fields.
+fn request_object(properties: Value, required: &[&str]) -> Value {
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+/// The values are taken from the serde serialization of `variants`.
+fn enumeration<T: Serialize>(variants: &[T]) -> Value {
+    let values = variants
+        .iter()
+        .map(|variant| serde_json::to_value(variant).unwrap())
+        .collect::<Vec<_>>();
+
+    json!({ "type": "string", "enum": values })
+}
+
+fn reference(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+fn nullable(schema: Value) -> Value {
+    match schema {
+        Value::Object(mut schema) if !schema.contains_key("$ref") => {
+            schema.insert("nullable".into(), true.into());
+            Value::Object(schema)
+        }
+        schema => json!({ "allOf": [schema], "nullable": true }),
+    }
+}
+
+fn array(items: Value) -> Value {
+    json!({ "type": "array", "items": items })
+}
+
+fn string() -> Value {
+    json!({ "type": "string" })
+}
+
+fn decimal() -> Value {
+    json!({ "type": "string", "description": "integer amount in the token's smallest unit" })
+}
+
+fn mutez() -> Value {
+    json!({ "type": "string", "description": "amount of tez in mutez" })
+}
+
+fn uuid() -> Value {
+    json!({ "type": "string", "format": "uuid" })
+}
+
+fn date() -> Value {
+    json!({ "type": "string", "format": "date" })
+}
+
+fn date_time() -> Value {
+    json!({
+        "type": "string",
+        "description": "UTC date and time without offset, e.g. 2021-03-01T12:00:00.123456",
+    })
+}
+
+fn integer(format: &str) -> Value {
+    json!({ "type": "integer", "format": format })
+}
+
+fn boolean() -> Value {
+    json!({ "type": "boolean" })
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use actix_web::{http::StatusCode, test, web, App};
+    use chrono::{NaiveDateTime, Utc};
+    use serde::Serialize;
+    use serde_json::Value;
+    use uuid::Uuid;
+
+    use super::document;
+    use crate::{
+        api::{
+            models::{
+                api_key::{ApiKey, ApiKeyAction, CreatedApiKey, NewApiKey},
+                audit_log::{AuditEvent, AuditLogEntry},
+                authentication::SessionRefresh,
+                common::SignableMessageInfo,
+                contract::{
+                    Contract, ContractDelegates, ContractKind, ContractNonces, NewCapability,
+                    ParameterSchema,
+                },
+                debug::{UnpackRequest, Unpacked},
+                maintenance::{MaintenanceMode, NewMaintenanceMode},
+                notification::{NewNotificationPause, NotificationPause},
+                operation_approval::{
+                    NewOperationApproval, NewOperationApprovals, OperationApproval,
+                    OperationApprovalOrder, OperationApprovalResult, OperationApprovalVerification,
+                    OperationApprovalsResult, OrderedOperationApproval,
+                },
+                operation_request::{
+                    BatchMint, ImportedOperationApproval, ImportedOperationRequest,
+                    NewOperationRequest, OperationRequest, OperationRequestCleanup,
+                    OperationRequestImport, OperationRequestImportResult, OperationRequestKind,
+                    OperationRequestState, PatchOperationRequest, SigningBundle,
+                },
+                tezos_node::TezosNodeHealth,
+                user::{User, UserKind, UserState},
+            },
+            openapi::api_config,
+        },
+        tezos::{
+            micheline::{int, types},
+            multisig::Parameters,
+        },
+    };
+
+    fn assert_properties<T: Serialize>(name: &str, value: &T) {
+        let document = document("/api/v1");
+        let mut expected = document["components"]["schemas"][name]["properties"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut actual = serde_json::to_value(value)
+            .unwrap()
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        expected.sort();
+        actual.sort();
+
+        assert_eq!(actual, expected, "properties of {}", name);
+    }
+
+    fn user() -> User {
+        User {
+            id: Uuid::new_v4(),
+            created_at: NaiveDateTime::from_timestamp(0, 0),
+            updated_at: NaiveDateTime::from_timestamp(0, 0),
+            public_key: "".into(),
+            address: "".into(),
+            contract_id: Uuid::new_v4(),
+            kind: UserKind::Keyholder,
+            state: UserState::Active,
+            display_name: "".into(),
+        }
+    }
+
+    fn operation_approval() -> OperationApproval {
+        OperationApproval {
+            id: Uuid::new_v4(),
+            created_at: NaiveDateTime::from_timestamp(0, 0),
+            updated_at: NaiveDateTime::from_timestamp(0, 0),
+            keyholder: user(),
+            operation_request_id: Uuid::new_v4(),
+            signature: "".into(),
+            uploaded_by: None,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_serves_document() {
+        let mut app = test::init_service(App::new().configure(api_config)).await;
+
+        let request = test::TestRequest::get().uri("/openapi.json").to_request();
+        let response = test::call_service(&mut app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let document: Value = test::read_body_json(response).await;
+
+        assert_eq!(document["openapi"], "3.0.3");
+        assert_eq!(
+            document["paths"]["/operation-requests"]["post"]["requestBody"]["content"]
+                ["application/json"]["schema"]["$ref"],
+            "#/components/schemas/NewOperationRequest"
+        );
+        assert!(document["paths"]["/operation-approvals"]["post"].is_object());
+        assert!(document["paths"]["/operation-approvals/{id}/verify"]["get"].is_object());
+        assert!(document["paths"]["/operation-requests/{id}/signable-message"]["get"].is_object());
+    }
+
+    #[actix_rt::test]
+    async fn test_serves_document_below_api_path() {
+        let mut app = test::init_service(
+            App::new().service(web::scope("/tzwrapped/api/v1").configure(api_config)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/tzwrapped/api/v1/openapi.json")
+            .to_request();
+        let response = test::call_service(&mut app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let document: Value = test::read_body_json(response).await;
+        assert_eq!(document["servers"][0]["url"], "/tzwrapped/api/v1");
+    }
+
+    #[test]
+    fn test_enumerations() {
+        let document = document("/api/v1");
+        let schemas = &document["components"]["schemas"];
+
+        assert_eq!(
+            schemas["OperationRequestKind"]["enum"],
+            serde_json::json!([
+                "mint",
+                "burn",
+                "update_keyholders",
+                "add_operator",
+                "remove_operator",
+                "set_redeem_address",
+                "transfer_ownership",
+                "accept_ownership",
+                "approve"
+            ])
+        );
+        assert_eq!(
+            schemas["OperationRequestState"]["enum"],
+            serde_json::json!(["open", "approved", "injected", "expired"])
+        );
+        assert_eq!(
+            schemas["ContractKind"]["enum"],
+            serde_json::json!(["fa1", "fa2", "fa2_single_asset"])
+        );
+        assert_eq!(
+            schemas["ContractGrouping"]["enum"],
+            serde_json::json!(["network", "symbol"])
+        );
+        assert_eq!(
+            schemas["UserKind"]["enum"],
+            serde_json::json!(["gatekeeper", "keyholder", "admin"])
+        );
+    }
+
+    #[test]
+    fn test_properties_match_models() {
+        assert_properties("User", &user());
+        assert_properties("OperationApproval", &operation_approval());
+        assert_properties(
+            "NewOperationApproval",
+            &NewOperationApproval {
+                operation_request_id: Uuid::new_v4(),
+                signature: "".into(),
+            },
+        );
+        assert_properties(
+            "NewOperationApprovals",
+            &NewOperationApprovals {
+                signatures: BTreeMap::new(),
+            },
+        );
+        assert_properties(
+            "OperationApprovalResult",
+            &OperationApprovalResult {
+                operation_request_id: Uuid::new_v4(),
+                approval: Some(operation_approval()),
+                error: None,
+                message: None,
+            },
+        );
+        assert_properties(
+            "OperationApprovalsResult",
+            &OperationApprovalsResult {
+                results: vec![],
+                approved: vec![],
+            },
+        );
+        assert_properties(
+            "OperationApprovalVerification",
+            &OperationApprovalVerification {
+                valid: true,
+                signer: "".into(),
+            },
+        );
+        let ordered_operation_approval = OrderedOperationApproval {
+            operation_approval_id: Uuid::new_v4(),
+            keyholder_id: Uuid::new_v4(),
+            public_key: "".into(),
+            index: Some(0),
+        };
+        assert_properties("OrderedOperationApproval", &ordered_operation_approval);
+        assert_properties(
+            "OperationApprovalOrder",
+            &OperationApprovalOrder {
+                approvers: vec![],
+                min_approvals: 2,
+                approvals: vec![ordered_operation_approval],
+                complete: false,
+            },
+        );
+        assert_properties(
+            "NewOperationRequest",
+            &NewOperationRequest {
+                contract_id: Uuid::new_v4(),
+                target_address: None,
+                amount: None,
+                threshold: None,
+                proposed_keyholders: None,
+                batch_mints: None,
+                xtz_amount: None,
+                kind: OperationRequestKind::Mint,
+                ledger_hash: None,
+            },
+        );
+        assert_properties(
+            "BatchMint",
+            &BatchMint {
+                target_address: "".into(),
+                amount: "".into(),
+            },
+        );
+        assert_properties(
+            "PatchOperationRequest",
+            &PatchOperationRequest {
+                operation_hash: None,
+            },
+        );
+        assert_properties(
+            "OperationRequest",
+            &OperationRequest {
+                id: Uuid::new_v4(),
+                created_at: NaiveDateTime::from_timestamp(0, 0),
+                updated_at: NaiveDateTime::from_timestamp(0, 0),
+                user: user(),
+                contract_id: Uuid::new_v4(),
+                target_address: None,
+                amount: None,
+                threshold: None,
+                proposed_keyholders: None,
+                batch_mints: None,
+                xtz_amount: None,
+                kind: OperationRequestKind::Mint,
+                chain_id: "".into(),
+                nonce: 0,
+                ledger_blake2b_hash: None,
+                state: OperationRequestState::Open,
+                operation_approvals: vec![operation_approval()],
+                operation_hash: None,
+                confirmation_failed: false,
+                approvals_received: 1,
+                approvals_required: 2,
+                warnings: vec![],
+            },
+        );
+        assert_properties(
+            "NewCapability",
+            &NewCapability {
+                operation_request_kind: OperationRequestKind::Mint,
+            },
+        );
+        assert_properties(
+            "AuditLogEntry",
+            &AuditLogEntry {
+                id: Uuid::new_v4(),
+                created_at: NaiveDateTime::from_timestamp(0, 0),
+                event: AuditEvent::ConfigSynced,
+                actor: None,
+                contract_id: None,
+                resource_id: None,
+                detail: serde_json::json!({}),
+            },
+        );
+        assert_properties(
+            "ContractDelegates",
+            &ContractDelegates {
+                contract_address: "KT1".into(),
+                contract_delegate: None,
+                multisig_address: "KT1".into(),
+                multisig_delegate: Some("tz1".into()),
+            },
+        );
+        assert_properties(
+            "TezosNodeHealth",
+            &TezosNodeHealth {
+                id: Uuid::new_v4(),
+                created_at: NaiveDateTime::from_timestamp(0, 0),
+                updated_at: NaiveDateTime::from_timestamp(0, 0),
+                name: "".into(),
+                url: "".into(),
+                network: "".into(),
+                selected: false,
+                reachable: false,
+                head_level: None,
+                protocol: None,
+            },
+        );
+        assert_properties(
+            "ContractNonces",
+            &ContractNonces {
+                on_chain: 4,
+                next_usable: 6,
+            },
+        );
+        assert_properties(
+            "ParameterSchema",
+            &ParameterSchema {
+                address: "".into(),
+                entrypoint: "mainParameter".into(),
+                schema: types::nat(),
+                michelson: "nat".into(),
+            },
+        );
+        assert_properties("MaintenanceMode", &MaintenanceMode::default());
+        assert_properties(
+            "NewMaintenanceMode",
+            &NewMaintenanceMode {
+                enabled: true,
+                message: None,
+            },
+        );
+        assert_properties("NotificationPause", &NotificationPause::default());
+        assert_properties(
+            "NewNotificationPause",
+            &NewNotificationPause {
+                paused: true,
+                contract_id: None,
+            },
+        );
+        let api_key = || ApiKey {
+            id: Uuid::new_v4(),
+            created_at: NaiveDateTime::from_timestamp(0, 0),
+            contract_id: Uuid::new_v4(),
+            name: "".into(),
+            address: "".into(),
+            actions: vec![ApiKeyAction::CreateOperationRequest],
+            created_by: "".into(),
+            revoked_at: None,
+        };
+        assert_properties("ApiKey", &api_key());
+        assert_properties(
+            "NewApiKey",
+            &NewApiKey {
+                contract_id: Uuid::new_v4(),
+                name: "".into(),
+                address: "".into(),
+                actions: vec![],
+            },
+        );
+        assert_properties(
+            "CreatedApiKey",
+            &CreatedApiKey {
+                api_key: api_key(),
+                token: "".into(),
+            },
+        );
+        assert_properties(
+            "UnpackRequest",
+            &UnpackRequest {
+                packed: "050008".into(),
+                type_: types::int(),
+            },
+        );
+        assert_properties(
+            "Unpacked",
+            &Unpacked {
+                micheline: int(4),
+                michelson: "4".into(),
+            },
+        );
+        assert_properties(
+            "SessionRefresh",
+            &SessionRefresh {
+                expires_at: Utc::now(),
+            },
+        );
+        assert_properties(
+            "OperationRequestCleanup",
+            &OperationRequestCleanup { deleted: 1 },
+        );
+        assert_properties(
+            "OperationRequestImport",
+            &OperationRequestImport {
+                contract_id: Uuid::new_v4(),
+                operation_requests: vec![],
+            },
+        );
+        assert_properties(
+            "ImportedOperationRequest",
+            &ImportedOperationRequest {
+                gatekeeper_id: Uuid::new_v4(),
+                kind: OperationRequestKind::Mint,
+                target_address: None,
+                amount: None,
+                threshold: None,
+                batch_mints: None,
+                xtz_amount: None,
+                chain_id: "NetXdQprcVkpaWU".into(),
+                nonce: 0,
+                state: OperationRequestState::Injected,
+                operation_hash: None,
+                approvals: vec![],
+            },
+        );
+        assert_properties(
+            "ImportedOperationApproval",
+            &ImportedOperationApproval {
+                keyholder_id: Uuid::new_v4(),
+                signature: "edsig".into(),
+            },
+        );
+        assert_properties(
+            "OperationRequestImportResult",
+            &OperationRequestImportResult {
+                imported: 1,
+                max_nonce: 0,
+            },
+        );
+        assert_properties(
+            "Contract",
+            &Contract {
+                id: Uuid::new_v4(),
+                created_at: NaiveDateTime::from_timestamp(0, 0),
+                updated_at: NaiveDateTime::from_timestamp(0, 0),
+                pkh: "".into(),
+                token_id: 0,
+                multisig_pkh: "".into(),
+                kind: ContractKind::FA2,
+                display_name: "".into(),
+                min_approvals: 2,
+                symbol: "".into(),
+                decimals: 0,
+                sort_order: Some(1),
+                capabilities: vec![],
+            },
+        );
+        assert_properties(
+            "SignableMessageInfo",
+            &SignableMessageInfo::new("".into(), "".into(), "".into()),
+        );
+        assert_properties(
+            "SigningBundle",
+            &SigningBundle {
+                version: 1,
+                operation_request_id: Uuid::new_v4(),
+                kind: OperationRequestKind::Burn,
+                chain_id: "".into(),
+                nonce: 0,
+                contract_address: "".into(),
+                multisig_address: "".into(),
+                michelson_data: int(1),
+                michelson_type: types::nat(),
+                packed_data: "".into(),
+                blake2b_hash: "".into(),
+                tezos_client_command: "".into(),
+                min_approvals: 2,
+                signers: vec![],
+            },
+        );
+        assert_properties(
+            "Parameters",
+            &Parameters {
+                entrypoint: "".into(),
+                value: int(1),
+            },
+        );
+    }
+}
@@ -0,0 +1,11 @@
+use actix_web::{web, HttpResponse};
+
+mod get;
+
+pub fn api_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/openapi.json")
+            .route(web::get().to(get::openapi))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
+}
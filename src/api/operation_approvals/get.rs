@@ -1,25 +1,36 @@
+use std::convert::TryInto;
+
 use actix_session::Session;
 use actix_web::{
+    http::header,
     web,
-    web::{Path, Query},
+    web::{Bytes, Path, Query},
     HttpResponse,
 };
 use diesel::{r2d2::ConnectionManager, r2d2::PooledConnection, PgConnection};
+use futures::{stream, Stream, StreamExt};
 use serde::Deserialize;
 use uuid::Uuid;
 
 use crate::{
-    api::models::user::UserKind,
+    api::models::user::{UserKind, UserState},
     db::models::{
+        contract::Contract, node_endpoint::NodeEndpoint,
         operation_approval::OperationApproval as DBOperationApproval,
         operation_request::OperationRequest, user::User,
     },
+    tezos::multisig::{self, OperationRequestParams, SignableMessage},
 };
 use crate::{
-    api::models::{common::ListResponse, error::APIError, operation_approval::OperationApproval},
+    api::models::{
+        common::ListResponse,
+        error::APIError,
+        operation_approval::{OperationApproval, OperationApprovalVerification},
+        operation_request::SigningBundle,
+    },
     auth::get_current_user,
 };
-use crate::{settings, DbPool};
+use crate::{settings, zip::ZipWriter, DbPool};
 
 #[derive(Deserialize)]
 pub struct Info {
@@ -109,3 +120,405 @@ pub async fn operation_approval(
 
     Ok(HttpResponse::Ok().json(OperationApproval::from(approval.0, approval.1)?))
 }
+
+pub async fn verify_operation_approval(
+    pool: web::Data<DbPool>,
+    path: Path<PathInfo>,
+    server_settings: web::Data<settings::Server>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+
+    let conn = pool.get()?;
+    let id = path.id;
+
+    let (approval, keyholder, operation_request, contract, proposed_keyholders, node_url) =
+        web::block::<_, _, APIError>(move || {
+            let approval = DBOperationApproval::get(&conn, id)?;
+            let (operation_request, contract) =
+                OperationRequest::get_with_contract(&conn, &approval.operation_request_id)?;
+
+            current_user
+                .require_roles(vec![UserKind::Gatekeeper, UserKind::Keyholder], contract.id)?;
+
+            let keyholder = User::get(&conn, approval.keyholder_id)?;
+            let proposed_keyholders = operation_request.proposed_keyholders(&conn)?;
+            let node_url = NodeEndpoint::get_selected(&conn)?.url;
+
+            Ok((
+                approval,
+                keyholder,
+                operation_request,
+                contract,
+                proposed_keyholders,
+                node_url,
+            ))
+        })
+        .await?;
+
+    let multisig = multisig::get_multisig(
+        contract.multisig_pkh.as_ref(),
+        contract.kind.try_into()?,
+        contract.node_url_or(&node_url),
+    );
+    let keyholder_public_keys = proposed_keyholders.map(|keyholders| {
+        keyholders
+            .into_iter()
+            .map(|keyholder| keyholder.public_key)
+            .collect()
+    });
+    let signable_message = multisig
+        .signable_message(
+            &contract,
+            &OperationRequestParams::from(operation_request),
+            keyholder_public_keys,
+        )
+        .await?;
+
+    let verification = verify_approval(&keyholder, &signable_message, &approval.signature)?;
+
+    Ok(HttpResponse::Ok().json(verification))
+}
+
+fn verify_approval(
+    keyholder: &User,
+    signable_message: &SignableMessage,
+    signature: &str,
+) -> Result<OperationApprovalVerification, APIError> {
+    let valid = keyholder.verify_signable_message(signable_message, signature)?;
+
+    Ok(OperationApprovalVerification {
+        valid,
+        signer: keyholder.public_key.clone(),
+    })
+}
+
+struct PendingApproval {
+    operation_request: OperationRequest,
+    contract: Contract,
+    proposed_keyholders: Option<Vec<User>>,
+    keyholders: Vec<User>,
+}
+
+pub async fn pending_signing_bundles(
+    pool: web::Data<DbPool>,
+    server_settings: web::Data<settings::Server>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+    current_user.require_one_of_roles(vec![UserKind::Keyholder])?;
+
+    let conn = pool.get()?;
+    let address = current_user.address;
+    let (node_url, pending_approvals) = web::block::<_, _, APIError>(move || {
+        let node_url = NodeEndpoint::get_selected(&conn)?.url;
+        let memberships = User::get_all(
+            &conn,
+            Some(UserKind::Keyholder),
+            None,
+            Some(UserState::Active),
+            Some(&address),
+            None,
+        )?;
+
+        let mut pending_approvals = vec![];
+        for membership in memberships {
+            let contract = Contract::get(&conn, &membership.contract_id)?;
+            let keyholders = User::get_all_active(&conn, contract.id, UserKind::Keyholder)?;
+            for operation_request in
+                OperationRequest::get_pending_for_keyholder(&conn, &membership)?
+            {
+                let proposed_keyholders = operation_request.proposed_keyholders(&conn)?;
+                pending_approvals.push(PendingApproval {
+                    operation_request,
+                    contract: contract.clone(),
+                    proposed_keyholders,
+                    keyholders: keyholders.clone(),
+                });
+            }
+        }
+
+        Ok((node_url, pending_approvals))
+    })
+    .await?;
+
+    let signing_bundles = stream::iter(pending_approvals)
+        .then(move |pending_approval| pending_signing_bundle(pending_approval, node_url.clone()));
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/zip")
+        .header(
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"pending-signing-bundles.zip\"",
+        )
+        .streaming(Box::pin(zip_signing_bundles(signing_bundles))))
+}
+
+async fn pending_signing_bundle(
+    pending_approval: PendingApproval,
+    node_url: String,
+) -> Result<SigningBundle, APIError> {
+    let PendingApproval {
+        operation_request,
+        contract,
+        proposed_keyholders,
+        keyholders,
+    } = pending_approval;
+
+    let multisig = multisig::get_multisig(
+        contract.multisig_pkh.as_ref(),
+        contract.kind.try_into()?,
+        contract.node_url_or(&node_url),
+    );
+    let keyholder_public_keys = proposed_keyholders.map(|keyholders| {
+        keyholders
+            .into_iter()
+            .map(|keyholder| keyholder.public_key)
+            .collect()
+    });
+    let signable_message = multisig
+        .signable_message(
+            &contract,
+            &OperationRequestParams::from(operation_request.clone()),
+            keyholder_public_keys,
+        )
+        .await?;
+
+    SigningBundle::new(&operation_request, &contract, signable_message, keyholders)
+}
+
+/// Streams a zip archive with one `<contract address>-<nonce>.json` entry per signing bundle,
+/// writing each entry as soon as its bundle is ready. The stream ends after the first error.
+fn zip_signing_bundles<S>(signing_bundles: S) -> impl Stream<Item = Result<Bytes, APIError>>
+where
+    S: Stream<Item = Result<SigningBundle, APIError>>,
+{
+    stream::unfold(
+        Some((Box::pin(signing_bundles), ZipWriter::new())),
+        |state| async move {
+            let (mut signing_bundles, mut writer) = match state {
+                Some(state) => state,
+                None => return None,
+            };
+
+            match signing_bundles.next().await {
+                Some(signing_bundle) => {
+                    match signing_bundle.and_then(|signing_bundle| {
+                        let name = format!(
+                            "{}-{}.json",
+                            signing_bundle.contract_address, signing_bundle.nonce
+                        );
+                        let data = serde_json::to_vec_pretty(&signing_bundle).map_err(|error| {
+                            APIError::Internal {
+                                description: error.to_string(),
+                            }
+                        })?;
+
+                        writer.entry(&name, &data)
+                    }) {
+                        Ok(entry) => {
+                            Some((Ok(Bytes::from(entry)), Some((signing_bundles, writer))))
+                        }
+                        Err(error) => Some((Err(error), None)),
+                    }
+                }
+                None => Some((writer.finish().map(Bytes::from), None)),
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryInto;
+
+    use base58check::ToBase58Check;
+    use sodiumoxide::crypto::sign;
+    use uuid::Uuid;
+
+    use futures::{stream, StreamExt};
+
+    use super::{verify_approval, zip_signing_bundles};
+    use crate::{
+        api::models::{
+            error::APIError,
+            operation_request::{OperationRequestKind, SigningBundle, SIGNING_BUNDLE_VERSION},
+        },
+        crypto,
+        db::models::{contract::Contract, operation_request::OperationRequest, user::User},
+        fixtures,
+        tezos::{
+            coding,
+            micheline::{int, types},
+            multisig::{self, OperationRequestParams, SignableMessage},
+        },
+    };
+
+    fn operation_request(contract: &Contract, nonce: i64) -> OperationRequest {
+        OperationRequest {
+            nonce,
+            ..fixtures::operation_request(contract.id)
+        }
+    }
+
+    fn keyholder(contract_id: Uuid, public_key: &[u8]) -> Result<User, APIError> {
+        let mut encoded = vec![0u8];
+        encoded.extend_from_slice(public_key);
+
+        Ok(fixtures::keyholder(
+            contract_id,
+            &coding::decode_public_key(&encoded)?,
+        ))
+    }
+
+    async fn signable_message(
+        contract: &Contract,
+        operation_request: OperationRequest,
+    ) -> Result<SignableMessage, APIError> {
+        let multisig = multisig::get_multisig(
+            contract.multisig_pkh.as_ref(),
+            contract.kind.try_into()?,
+            "",
+        );
+
+        Ok(multisig
+            .signable_message(
+                contract,
+                &OperationRequestParams::from(operation_request),
+                None,
+            )
+            .await?)
+    }
+
+    fn edsig(signature: &[u8]) -> String {
+        let mut prefixed = vec![245u8, 205, 134, 18];
+        prefixed.extend_from_slice(signature);
+
+        prefixed.to_base58check(9)
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_valid_approval() -> Result<(), APIError> {
+        let contract = fixtures::contract();
+        let (public_key, secret_key) = sign::gen_keypair();
+        let keyholder = keyholder(contract.id, &public_key.0)?;
+        let message = signable_message(&contract, operation_request(&contract, 3)).await?;
+        let signature = edsig(&crypto::sign_detached(
+            &message.blake2b_hash()?,
+            secret_key.0,
+        ));
+
+        let verification = verify_approval(&keyholder, &message, &signature)?;
+
+        assert!(verification.valid);
+        assert_eq!(verification.signer, keyholder.public_key);
+
+        Ok(())
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_raw_signed_approval() -> Result<(), APIError> {
+        let contract = fixtures::contract();
+        let (public_key, secret_key) = sign::gen_keypair();
+        let keyholder = keyholder(contract.id, &public_key.0)?;
+        let message = signable_message(&contract, operation_request(&contract, 3)).await?;
+        let signature = edsig(&crypto::sign_detached(&message.bytes()?, secret_key.0));
+
+        let result = verify_approval(&keyholder, &message, &signature);
+
+        assert!(matches!(result, Err(APIError::InvalidValue { .. })));
+
+        Ok(())
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_approval_after_nonce_change() -> Result<(), APIError> {
+        let contract = fixtures::contract();
+        let (public_key, secret_key) = sign::gen_keypair();
+        let keyholder = keyholder(contract.id, &public_key.0)?;
+        let signed_message = signable_message(&contract, operation_request(&contract, 3)).await?;
+        let signature = edsig(&crypto::sign_detached(
+            &signed_message.blake2b_hash()?,
+            secret_key.0,
+        ));
+
+        let current_message = signable_message(&contract, operation_request(&contract, 4)).await?;
+        let verification = verify_approval(&keyholder, &current_message, &signature)?;
+
+        assert!(!verification.valid);
+        assert_eq!(verification.signer, keyholder.public_key);
+
+        Ok(())
+    }
+
+    fn signing_bundle(contract: &Contract, nonce: i64) -> SigningBundle {
+        let operation_request = operation_request(contract, nonce);
+
+        SigningBundle {
+            version: SIGNING_BUNDLE_VERSION,
+            operation_request_id: operation_request.id,
+            kind: OperationRequestKind::Mint,
+            chain_id: operation_request.chain_id,
+            nonce,
+            contract_address: contract.pkh.clone(),
+            multisig_address: contract.multisig_pkh.clone(),
+            michelson_data: int(nonce),
+            michelson_type: types::nat(),
+            packed_data: "".into(),
+            blake2b_hash: "".into(),
+            tezos_client_command: "".into(),
+            min_approvals: contract.min_approvals,
+            signers: vec![],
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_zip_signing_bundles() -> Result<(), APIError> {
+        let contract = fixtures::contract();
+        let signing_bundles = vec![
+            Ok(signing_bundle(&contract, 3)),
+            Ok(signing_bundle(&contract, 4)),
+            Ok(signing_bundle(&contract, 5)),
+        ];
+
+        let chunks = zip_signing_bundles(stream::iter(signing_bundles))
+            .collect::<Vec<_>>()
+            .await;
+        let mut archive = vec![];
+        for chunk in chunks {
+            archive.extend_from_slice(&chunk?);
+        }
+
+        let end_of_central_directory = &archive[archive.len() - 22..];
+        assert_eq!(&end_of_central_directory[..4], b"PK\x05\x06");
+        assert_eq!(
+            u16::from_le_bytes([end_of_central_directory[10], end_of_central_directory[11]]),
+            3
+        );
+        let archive = String::from_utf8_lossy(&archive);
+        for nonce in 3..=5 {
+            let name = format!("{}-{}.json", contract.pkh, nonce);
+            assert_eq!(archive.matches(&name).count(), 2);
+        }
+
+        Ok(())
+    }
+
+    #[actix_rt::test]
+    async fn test_zip_signing_bundles_stops_on_error() {
+        let contract = fixtures::contract();
+        let signing_bundles = vec![
+            Ok(signing_bundle(&contract, 3)),
+            Err(APIError::NotFound),
+            Ok(signing_bundle(&contract, 5)),
+        ];
+
+        let chunks = zip_signing_bundles(stream::iter(signing_bundles))
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks[0].is_ok());
+        assert!(chunks[1].is_err());
+    }
+}
@@ -10,9 +10,24 @@ pub fn api_config(cfg: &mut web::ServiceConfig) {
             .route(web::post().to(post::operation_approval))
             .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
     );
+    cfg.service(
+        web::resource("/operation-approvals/bulk")
+            .route(web::post().to(post::operation_approvals))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
+    cfg.service(
+        web::resource("/operation-approvals/pending/bundle")
+            .route(web::get().to(get::pending_signing_bundles))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
     cfg.service(
         web::resource("/operation-approvals/{id}")
             .route(web::get().to(get::operation_approval))
             .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
     );
+    cfg.service(
+        web::resource("/operation-approvals/{id}/verify")
+            .route(web::get().to(get::verify_operation_approval))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
 }
@@ -1,26 +1,37 @@
 use std::convert::TryInto;
 
 use actix_session::Session;
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
+use diesel::Connection;
 use log::info;
 use multisig::SignableMessage;
 use uuid::Uuid;
 
+use crate::audit;
 use crate::db::models::node_endpoint::NodeEndpoint;
 use crate::db::models::{
     contract::Contract, operation_approval::NewOperationApproval as DBNewOperationApproval,
     operation_approval::OperationApproval as DBOperationApproval,
     operation_request::OperationRequest, user::User,
 };
+use crate::logging::context::RequestId;
 use crate::notifications::{notify_approval_received, notify_min_approvals_received};
 use crate::settings;
-use crate::tezos::multisig::{self, OperationRequestParams};
+use crate::tezos::multisig::{self, Multisig, OperationRequestParams};
 use crate::DbPool;
-use crate::{api::models::user::UserKind, auth::get_current_user};
+use crate::{
+    api::models::{
+        api_key::ApiKeyAction, operation_request::OperationRequestState, user::UserKind,
+    },
+    auth::get_current_user_or_api_key,
+};
 use crate::{
     api::models::{
         error::APIError,
-        operation_approval::{NewOperationApproval, OperationApproval},
+        operation_approval::{
+            NewOperationApproval, NewOperationApprovals, OperationApproval,
+            OperationApprovalResult, OperationApprovalsResult,
+        },
     },
     auth::SessionUser,
 };
@@ -28,18 +39,130 @@ use crate::{
 pub async fn operation_approval(
     pool: web::Data<DbPool>,
     server_settings: web::Data<settings::Server>,
+    contract_settings: web::Data<Vec<settings::Contract>>,
     body: web::Json<NewOperationApproval>,
+    req: HttpRequest,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    let current_user = get_current_user_or_api_key(
+        &req,
+        &session,
+        server_settings.inactivity_timeout_seconds,
+        ApiKeyAction::ApproveOperationRequest,
+    )?;
+    let new_operation_approval = body.into_inner().normalized()?;
+    let outcome = approve(
+        &pool,
+        &contract_settings,
+        &current_user,
+        new_operation_approval,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(outcome.approval))
+}
+
+/// Stores the signatures of several operation requests, each one like a single approval, and
+/// reports the outcome of each of them. A rejected signature doesn't prevent the others from
+/// being stored.
+pub async fn operation_approvals(
+    pool: web::Data<DbPool>,
+    server_settings: web::Data<settings::Server>,
+    contract_settings: web::Data<Vec<settings::Contract>>,
+    body: web::Json<NewOperationApprovals>,
+    req: HttpRequest,
     session: Session,
 ) -> Result<HttpResponse, APIError> {
-    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
-    let new_operation_approval = body.into_inner();
+    let current_user = get_current_user_or_api_key(
+        &req,
+        &session,
+        server_settings.inactivity_timeout_seconds,
+        ApiKeyAction::ApproveOperationRequest,
+    )?;
+    let new_operation_approvals = body.into_inner().into_approvals()?;
+
+    let mut results = Vec::with_capacity(new_operation_approvals.len());
+    for new_operation_approval in new_operation_approvals {
+        let operation_request_id = new_operation_approval.operation_request_id;
+        let outcome = match new_operation_approval.normalized() {
+            Ok(new_operation_approval) => {
+                approve(
+                    &pool,
+                    &contract_settings,
+                    &current_user,
+                    new_operation_approval,
+                )
+                .await
+            }
+            Err(error) => Err(error),
+        };
+        results.push((operation_request_id, outcome));
+    }
+
+    Ok(HttpResponse::Ok().json(bulk_result(results)))
+}
+
+/// An approval that was stored, `reached_quorum` is set if the operation request became approved
+/// with it.
+struct ApprovalOutcome {
+    approval: OperationApproval,
+    reached_quorum: bool,
+}
+
+fn bulk_result(
+    results: Vec<(Uuid, Result<ApprovalOutcome, APIError>)>,
+) -> OperationApprovalsResult {
+    let approved = results
+        .iter()
+        .filter_map(|(operation_request_id, outcome)| match outcome {
+            Ok(outcome) if outcome.reached_quorum => Some(*operation_request_id),
+            _ => None,
+        })
+        .collect();
+    let results = results
+        .into_iter()
+        .map(|(operation_request_id, outcome)| match outcome {
+            Ok(outcome) => OperationApprovalResult {
+                operation_request_id,
+                approval: Some(outcome.approval),
+                error: None,
+                message: None,
+            },
+            Err(error) => OperationApprovalResult {
+                operation_request_id,
+                approval: None,
+                error: Some(error.name()),
+                message: Some(error.to_string()),
+            },
+        })
+        .collect();
+
+    OperationApprovalsResult { results, approved }
+}
+
+async fn approve(
+    pool: &web::Data<DbPool>,
+    contract_settings: &[settings::Contract],
+    current_user: &SessionUser,
+    new_operation_approval: NewOperationApproval,
+) -> Result<ApprovalOutcome, APIError> {
     let (operation_request, contract, proposed_keyholders) =
-        get_operation_request_and_contract(&pool, new_operation_approval.operation_request_id)
+        get_operation_request_and_contract(pool, new_operation_approval.operation_request_id)
             .await?;
 
     current_user.require_roles(vec![UserKind::Keyholder], contract.id)?;
 
-    info!("User {} submits new operation approval on contract {}:\n{:?}\nFor operation request:\n{:?}", current_user.address, contract.display_name, new_operation_approval, operation_request);
+    let expired: i16 = OperationRequestState::Expired.into();
+    if operation_request.state == expired {
+        return Err(APIError::InvalidOperationState {
+            description: "expired operation requests cannot be approved".into(),
+        });
+    }
+
+    let contract_setting = settings::Contract::find(contract_settings, &contract);
+    check_signature_scheme(contract_setting, &new_operation_approval.signature)?;
+
+    info!("[{}] User {} submits new operation approval on contract {}:\n{:?}\nFor operation request:\n{:?}", RequestId::current_label(), current_user.address, contract.display_name, new_operation_approval, operation_request);
 
     let conn = pool.get()?;
     let node_url =
@@ -47,58 +170,93 @@ pub async fn operation_approval(
     let mut multisig = multisig::get_multisig(
         contract.multisig_pkh.as_ref(),
         contract.kind.try_into()?,
-        &node_url,
+        contract.node_url_or(&node_url),
     );
 
-    let operation_request_params = OperationRequestParams::from(operation_request.clone());
-    let keyholder_public_keys = match proposed_keyholders {
-        None => None,
-        Some(keyholders) => Some(
-            keyholders
-                .into_iter()
-                .map(|keyholder| keyholder.public_key)
-                .collect(),
-        ),
-    };
-    let signable_message = multisig
-        .signable_message(&contract, &operation_request_params, keyholder_public_keys)
-        .await?;
-
     let min_approvals = multisig.min_signatures().await?;
 
-    crate::db::sync_keyholders(&pool, vec![contract.clone()], &node_url).await?;
+    crate::db::sync_keyholders(pool, vec![contract.clone()], &node_url).await?;
+    let signer_set = multisig.approvers().await?.clone();
 
     let keyholder = find_keyholder_and_validate_signature(
-        &pool,
-        &signable_message,
+        pool,
+        multisig.as_ref(),
         &contract,
+        &operation_request,
+        proposed_keyholders,
         &new_operation_approval,
     )
     .await?;
 
-    if keyholder.address != current_user.address {
+    let uploader = if keyholder.address != current_user.address {
         info!(
-            "User {} is uploading signature for keyholder: {} / {}",
-            current_user.address, keyholder.address, keyholder.public_key
+            "[{}] User {} is uploading signature for keyholder: {} / {}",
+            RequestId::current_label(),
+            current_user.address,
+            keyholder.address,
+            keyholder.public_key
         );
-    }
+        let conn = pool.get()?;
+        let contract_id = contract.id;
+        let address = current_user.address.clone();
+        Some(
+            web::block(move || User::get_active(&conn, &address, UserKind::Keyholder, contract_id))
+                .await?,
+        )
+    } else {
+        None
+    };
 
     let keyholder_id = keyholder.id;
-    let inserted_approval = store_approval(&pool, keyholder_id, new_operation_approval).await?;
+    let new_db_operation_approval = new_db_operation_approval(
+        &keyholder,
+        uploader.as_ref(),
+        signer_set,
+        new_operation_approval,
+    );
+    let actor = current_user.address.clone();
+    let inserted_approval = store_approval(
+        pool,
+        actor.clone(),
+        operation_request.clone(),
+        new_db_operation_approval,
+    )
+    .await?;
 
     let result = OperationApproval::from(inserted_approval, keyholder)?;
 
-    info!("Successfully created operation approval: {:?}", result);
+    info!(
+        "[{}] Successfully created operation approval: {:?}",
+        RequestId::current_label(),
+        result
+    );
 
     let request_id = operation_request.id;
     let conn = pool.get()?;
     let total_approvals =
         web::block(move || DBOperationApproval::count(&conn, &request_id)).await?;
 
+    let open: i16 = OperationRequestState::Open.into();
+    let reached_quorum = total_approvals >= min_approvals && operation_request.state == open;
     let conn = pool.get()?;
     if total_approvals >= min_approvals {
         web::block::<_, _, APIError>(move || {
-            OperationRequest::mark_approved(&conn, &request_id)?;
+            conn.transaction::<_, APIError, _>(|| {
+                OperationRequest::mark_approved(&conn, &request_id)?;
+
+                if reached_quorum {
+                    audit::record(
+                        &conn,
+                        audit::operation_request_approved(
+                            &actor,
+                            &operation_request,
+                            total_approvals,
+                        ),
+                    )?;
+                }
+
+                Ok(())
+            })?;
 
             let user = User::get(&conn, operation_request.user_id);
             let keyholders = User::get_all_active(&conn, contract.id, UserKind::Keyholder);
@@ -117,7 +275,8 @@ pub async fn operation_approval(
         })
         .await?;
         info!(
-            "Enough signatures collected for operation request: {:?}",
+            "[{}] Enough signatures collected for operation request: {:?}",
+            RequestId::current_label(),
             request_id
         );
     } else {
@@ -128,6 +287,7 @@ pub async fn operation_approval(
             let _ = notify_approval_received(
                 &user,
                 &approver,
+                uploader.as_ref(),
                 &keyholders,
                 &operation_request,
                 &contract,
@@ -138,29 +298,65 @@ pub async fn operation_approval(
         .await;
     }
 
-    Ok(HttpResponse::Ok().json(result))
+    Ok(ApprovalOutcome {
+        approval: result,
+        reached_quorum,
+    })
+}
+
+/// Records the uploading user when they are not the keyholder who signed, along with the
+/// multisig's signer set at the time of the approval.
+fn new_db_operation_approval(
+    keyholder: &User,
+    uploader: Option<&User>,
+    signer_set: Vec<String>,
+    operation_approval: NewOperationApproval,
+) -> DBNewOperationApproval {
+    DBNewOperationApproval {
+        keyholder_id: keyholder.id,
+        operation_request_id: operation_approval.operation_request_id,
+        signature: operation_approval.signature,
+        uploaded_by: uploader
+            .filter(|uploader| uploader.id != keyholder.id)
+            .map(|uploader| uploader.id),
+        signer_set: Some(signer_set),
+    }
 }
 
 async fn store_approval(
     pool: &web::Data<DbPool>,
-    keyholder_id: Uuid,
-    operation_approval: NewOperationApproval,
+    actor: String,
+    operation_request: OperationRequest,
+    new_operation_approval: DBNewOperationApproval,
 ) -> Result<DBOperationApproval, APIError> {
     let conn = pool.get()?;
-    let operation_approval = web::block::<_, _, diesel::result::Error>(move || {
-        let new_operation_approval = DBNewOperationApproval {
-            keyholder_id,
-            operation_request_id: operation_approval.operation_request_id,
-            signature: operation_approval.signature,
-        };
+    let operation_approval = web::block::<_, _, APIError>(move || {
+        conn.transaction(|| {
+            if DBOperationApproval::exists(
+                &conn,
+                &new_operation_approval.operation_request_id,
+                &new_operation_approval.keyholder_id,
+            )? {
+                return Err(APIError::InvalidOperationState {
+                    description: "the keyholder has already approved this operation request".into(),
+                });
+            }
+            let operation_approval = DBOperationApproval::insert(&conn, new_operation_approval)?;
+            audit::record(
+                &conn,
+                audit::operation_approval_added(&actor, &operation_request, &operation_approval),
+            )?;
 
-        DBOperationApproval::insert(&conn, new_operation_approval)
+            Ok(operation_approval)
+        })
     })
     .await?;
 
     info!(
-        "Uploaded signature for operation: {:?} from keyholder: {:?}",
-        operation_approval.operation_request_id, keyholder_id
+        "[{}] Uploaded signature for operation: {:?} from keyholder: {:?}",
+        RequestId::current_label(),
+        operation_approval.operation_request_id,
+        operation_approval.keyholder_id
     );
 
     Ok(operation_approval)
@@ -185,18 +381,48 @@ async fn find_and_validate_keyholder(
     })
     .await?;
 
-    let hashed = message.blake2b_hash()?;
-    let is_match = keyholder.verify_message(&hashed, &operation_approval.signature)?;
+    let is_match = keyholder.verify_signable_message(message, &operation_approval.signature)?;
     if is_match {
         return Ok(keyholder);
     }
     Err(APIError::InvalidSignature)
 }
 
+/// Rejects signatures the multisig of the contract would reject on injection, as configured with
+/// `signature_schemes`.
+fn check_signature_scheme(
+    contract_setting: Option<&settings::Contract>,
+    signature: &str,
+) -> Result<(), APIError> {
+    let allowed = match contract_setting {
+        Some(contract_setting) => contract_setting.signature_schemes(),
+        None => return Ok(()),
+    };
+    match settings::SignatureScheme::of(signature) {
+        Some(scheme) if allowed.contains(&scheme) => Ok(()),
+        _ => Err(APIError::InvalidValue {
+            description: format!(
+                "the multisig of this contract only accepts {} signatures",
+                allowed
+                    .iter()
+                    .map(|scheme| scheme.prefix())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }),
+    }
+}
+
+/// Finds the keyholder who made the signature of `operation_approval`. The signature is checked
+/// against the `ledger_blake2b_hash` stored with the operation request first. The signable message
+/// is only rebuilt if there is none, which happens after the nonce changed, or if the signature
+/// doesn't match it, so that signatures over the packed data are still reported.
 async fn find_keyholder_and_validate_signature(
     pool: &web::Data<DbPool>,
-    message: &SignableMessage,
+    multisig: &dyn Multisig,
     contract: &Contract,
+    operation_request: &OperationRequest,
+    proposed_keyholders: Option<Vec<User>>,
     operation_approval: &NewOperationApproval,
 ) -> Result<User, APIError> {
     let conn = pool.get()?;
@@ -211,19 +437,83 @@ async fn find_keyholder_and_validate_signature(
     })
     .await?;
 
-    let hashed = message.blake2b_hash()?;
-    let filtered_keyholders: Vec<User> = keyholders
-        .into_iter()
-        .filter(|keyholder| {
-            match keyholder.verify_message(&hashed, &operation_approval.signature) {
-                Ok(value) => value,
-                Err(_) => false,
-            }
-        })
-        .collect();
+    if let Some(hash) = stored_blake2b_hash(operation_request) {
+        let signature = &operation_approval.signature;
+        if let Ok(keyholder) = matching_keyholder_by(keyholders.clone(), |keyholder| {
+            keyholder.verify_message(&hash, signature)
+        }) {
+            return Ok(keyholder);
+        }
+    }
 
-    if filtered_keyholders.len() == 1 {
-        return Ok(filtered_keyholders[0].clone());
+    let operation_request_params = OperationRequestParams::from(operation_request.clone());
+    let keyholder_public_keys = proposed_keyholders.map(|keyholders| {
+        keyholders
+            .into_iter()
+            .map(|keyholder| keyholder.public_key)
+            .collect()
+    });
+    let signable_message = multisig
+        .signable_message(contract, &operation_request_params, keyholder_public_keys)
+        .await?;
+
+    if operation_request.ledger_blake2b_hash.is_none() {
+        let conn = pool.get()?;
+        let id = operation_request.id;
+        let nonce = operation_request.nonce;
+        let hash = signable_message.ledger_blake2b_hash()?;
+        web::block(move || OperationRequest::set_ledger_blake2b_hash(&conn, &id, nonce, &hash))
+            .await?;
+    }
+
+    matching_keyholder(keyholders, &signable_message, &operation_approval.signature)
+}
+
+/// The `ledger_blake2b_hash` stored with `operation_request`, decoded to verify signatures.
+fn stored_blake2b_hash(operation_request: &OperationRequest) -> Option<Vec<u8>> {
+    operation_request
+        .ledger_blake2b_hash
+        .as_ref()
+        .and_then(|hash| bs58::decode(hash).into_vec().ok())
+}
+
+fn matching_keyholder(
+    keyholders: Vec<User>,
+    message: &SignableMessage,
+    signature: &str,
+) -> Result<User, APIError> {
+    matching_keyholder_by(keyholders, |keyholder| {
+        keyholder.verify_signable_message(message, signature)
+    })
+}
+
+/// Picks the keyholder whose signature `verify` accepts. Rows sharing a public key belong to the
+/// same signer, so they are resolved to the oldest one instead of being treated as an ambiguous
+/// match.
+fn matching_keyholder_by<F>(keyholders: Vec<User>, verify: F) -> Result<User, APIError>
+where
+    F: Fn(&User) -> Result<bool, APIError>,
+{
+    let mut matching: Vec<User> = Vec::new();
+    for keyholder in keyholders {
+        match verify(&keyholder) {
+            Ok(true) => matching.push(keyholder),
+            Ok(false) => {}
+            Err(error @ APIError::InvalidValue { .. }) => return Err(error),
+            Err(_) => {}
+        }
+    }
+
+    matching.sort_by(|a, b| {
+        a.public_key
+            .cmp(&b.public_key)
+            .then(a.created_at.cmp(&b.created_at))
+            .then(a.id.cmp(&b.id))
+    });
+    matching.dedup_by(|a, b| a.public_key == b.public_key);
+
+    if matching.len() == 1 {
+        return Ok(matching.remove(0));
     }
 
     Err(APIError::InvalidSignature)
@@ -246,3 +536,297 @@ async fn get_operation_request_and_contract(
 
     Ok(result)
 }
+
+#[cfg(test)]
+mod test {
+    use base58check::ToBase58Check;
+    use chrono::NaiveDateTime;
+    use sodiumoxide::crypto::sign;
+    use uuid::Uuid;
+
+    use super::{
+        bulk_result, check_signature_scheme, matching_keyholder, matching_keyholder_by,
+        new_db_operation_approval, stored_blake2b_hash, ApprovalOutcome,
+    };
+    use crate::{
+        api::models::{
+            error::APIError,
+            operation_approval::{NewOperationApproval, OperationApproval},
+            operation_request::OperationRequestKind,
+        },
+        crypto,
+        db::models::{
+            operation_approval::OperationApproval as DBOperationApproval,
+            operation_request::OperationRequest, user::User,
+        },
+        fixtures, settings,
+        tezos::{
+            coding,
+            micheline::{int, types},
+            multisig::SignableMessage,
+        },
+    };
+
+    fn keyholder(contract_id: Uuid, address: &str) -> User {
+        User {
+            address: address.into(),
+            ..fixtures::keyholder(contract_id, "")
+        }
+    }
+
+    fn new_operation_approval() -> NewOperationApproval {
+        NewOperationApproval {
+            operation_request_id: Uuid::new_v4(),
+            signature: "edsig".into(),
+        }
+    }
+
+    #[test]
+    fn test_uploaded_by_other_keyholder() {
+        let contract_id = Uuid::new_v4();
+        let uploader = keyholder(contract_id, "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT");
+        let signer = keyholder(contract_id, "tz1gZdWPZAaFoTVfHDJ5zo5BbdqM9F9dYDTt");
+
+        let approval =
+            new_db_operation_approval(&signer, Some(&uploader), vec![], new_operation_approval());
+
+        assert_eq!(approval.keyholder_id, signer.id);
+        assert_eq!(approval.uploaded_by, Some(uploader.id));
+    }
+
+    #[test]
+    fn test_uploaded_by_signer() {
+        let contract_id = Uuid::new_v4();
+        let signer = keyholder(contract_id, "tz1gZdWPZAaFoTVfHDJ5zo5BbdqM9F9dYDTt");
+
+        let approval = new_db_operation_approval(&signer, None, vec![], new_operation_approval());
+        assert_eq!(approval.uploaded_by, None);
+
+        let approval =
+            new_db_operation_approval(&signer, Some(&signer), vec![], new_operation_approval());
+        assert_eq!(approval.uploaded_by, None);
+    }
+
+    fn keyholder_with_key(contract_id: Uuid, public_key: &sign::PublicKey) -> User {
+        let mut encoded = vec![0u8];
+        encoded.extend_from_slice(&public_key.0);
+
+        User {
+            public_key: coding::decode_public_key(&encoded).unwrap(),
+            ..keyholder(contract_id, "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT")
+        }
+    }
+
+    fn edsig(signature: &[u8]) -> String {
+        let mut prefixed = vec![245u8, 205, 134, 18];
+        prefixed.extend_from_slice(signature);
+
+        prefixed.to_base58check(9)
+    }
+
+    #[test]
+    fn test_matching_keyholder_with_shared_public_key() -> Result<(), APIError> {
+        let contract_id = Uuid::new_v4();
+        let (public_key, secret_key) = sign::gen_keypair();
+        let message = SignableMessage {
+            packed_data: "050007".into(),
+            michelson_data: int(7),
+            michelson_type: types::nat(),
+        };
+        let signature = edsig(&crypto::sign_detached(
+            &message.blake2b_hash()?,
+            secret_key.0,
+        ));
+
+        let mut newer = keyholder_with_key(contract_id, &public_key);
+        newer.created_at = NaiveDateTime::from_timestamp(100, 0);
+        let older = keyholder_with_key(contract_id, &public_key);
+        let (other_public_key, _) = sign::gen_keypair();
+        let other = keyholder_with_key(contract_id, &other_public_key);
+
+        let selected = matching_keyholder(
+            vec![newer.clone(), other.clone(), older.clone()],
+            &message,
+            &signature,
+        )?;
+        assert_eq!(selected.id, older.id);
+
+        let selected = matching_keyholder(vec![older.clone(), newer], &message, &signature)?;
+        assert_eq!(selected.id, older.id);
+
+        assert!(matches!(
+            matching_keyholder(vec![other], &message, &signature),
+            Err(APIError::InvalidSignature)
+        ));
+
+        Ok(())
+    }
+
+    fn contract_setting(
+        signature_schemes: Option<Vec<settings::SignatureScheme>>,
+    ) -> settings::Contract {
+        settings::Contract {
+            signature_schemes,
+            ..fixtures::contract_settings()
+        }
+    }
+
+    #[test]
+    fn test_check_signature_scheme() {
+        let edsig = "edsigtXwP1dYcbSNoTMSazxdmwnA7GsKCvXN2ak1ozjwgiGPHCqsjnCpS28KsUVwf6zjK9X6vRqT8Kx7GX28xdSsv1MGMxVvJ1X";
+        let spsig = "spsig1PPUFZucuAQybs5wsqsNQ68QNgFaBnVKMFaoZZfi1BtNnuCAWnmL9wVy5HfHkR6AeodjVGxpBVVSYcJKyMURn6K1yknYLm";
+
+        assert!(check_signature_scheme(None, spsig).is_ok());
+        assert!(check_signature_scheme(Some(&contract_setting(None)), spsig).is_ok());
+
+        let edsig_only = contract_setting(Some(vec![settings::SignatureScheme::Edsig]));
+        assert!(check_signature_scheme(Some(&edsig_only), edsig).is_ok());
+        match check_signature_scheme(Some(&edsig_only), spsig) {
+            Err(APIError::InvalidValue { description }) => assert_eq!(
+                description,
+                "the multisig of this contract only accepts edsig signatures"
+            ),
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    fn signed_message(value: i64, secret_key: &sign::SecretKey) -> (SignableMessage, String) {
+        let micheline = int(value);
+        let message = SignableMessage {
+            packed_data: micheline.pack(Some(&types::nat())).unwrap(),
+            michelson_data: micheline,
+            michelson_type: types::nat(),
+        };
+        let signature = edsig(&crypto::sign_detached(
+            &message.blake2b_hash().unwrap(),
+            secret_key.0,
+        ));
+
+        (message, signature)
+    }
+
+    #[test]
+    fn test_matching_keyholder_per_message() -> Result<(), APIError> {
+        let contract_id = Uuid::new_v4();
+        let (public_key, secret_key) = sign::gen_keypair();
+        let keyholder = keyholder_with_key(contract_id, &public_key);
+        let (first_message, first_signature) = signed_message(1, &secret_key);
+        let (second_message, second_signature) = signed_message(2, &secret_key);
+
+        let selected =
+            matching_keyholder(vec![keyholder.clone()], &first_message, &first_signature)?;
+        assert_eq!(selected.id, keyholder.id);
+        let selected =
+            matching_keyholder(vec![keyholder.clone()], &second_message, &second_signature)?;
+        assert_eq!(selected.id, keyholder.id);
+
+        assert!(matches!(
+            matching_keyholder(vec![keyholder.clone()], &first_message, &second_signature),
+            Err(APIError::InvalidSignature)
+        ));
+        assert!(matches!(
+            matching_keyholder(vec![keyholder], &second_message, &first_signature),
+            Err(APIError::InvalidSignature)
+        ));
+
+        Ok(())
+    }
+
+    fn operation_request(ledger_blake2b_hash: Option<String>) -> OperationRequest {
+        OperationRequest {
+            target_address: None,
+            amount: None,
+            kind: OperationRequestKind::AcceptOwnership.into(),
+            nonce: 3,
+            ledger_blake2b_hash,
+            ..fixtures::operation_request(Uuid::new_v4())
+        }
+    }
+
+    #[test]
+    fn test_stored_hash_matches_signable_message() -> Result<(), APIError> {
+        let contract_id = Uuid::new_v4();
+        let (public_key, secret_key) = sign::gen_keypair();
+        let keyholder = keyholder_with_key(contract_id, &public_key);
+        let (message, signature) = signed_message(3, &secret_key);
+        let (_, stale_signature) = signed_message(2, &secret_key);
+
+        let operation_request = operation_request(Some(message.ledger_blake2b_hash()?));
+        let hash = stored_blake2b_hash(&operation_request).unwrap();
+        assert_eq!(hash, message.blake2b_hash()?);
+
+        let verify = |signature: &str| {
+            matching_keyholder_by(vec![keyholder.clone()], |keyholder| {
+                keyholder.verify_message(&hash, signature)
+            })
+        };
+        assert_eq!(verify(&signature)?.id, keyholder.id);
+        assert!(matches!(
+            verify(&stale_signature),
+            Err(APIError::InvalidSignature)
+        ));
+
+        assert_eq!(stored_blake2b_hash(&operation_request(None)), None);
+
+        Ok(())
+    }
+
+    fn outcome(operation_request_id: Uuid, reached_quorum: bool) -> ApprovalOutcome {
+        let keyholder = keyholder(Uuid::new_v4(), "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT");
+        let approval = DBOperationApproval {
+            operation_request_id,
+            signer_set: Some(vec![]),
+            ..fixtures::operation_approval(
+                &fixtures::operation_request(keyholder.contract_id),
+                &keyholder,
+            )
+        };
+
+        ApprovalOutcome {
+            approval: OperationApproval::from(approval, keyholder).unwrap(),
+            reached_quorum,
+        }
+    }
+
+    #[test]
+    fn test_bulk_result() {
+        let approved = Uuid::new_v4();
+        let pending = Uuid::new_v4();
+        let invalid = Uuid::new_v4();
+        let already_approved = Uuid::new_v4();
+
+        let result = bulk_result(vec![
+            (approved, Ok(outcome(approved, true))),
+            (pending, Ok(outcome(pending, false))),
+            (invalid, Err(APIError::InvalidSignature)),
+            (
+                already_approved,
+                Err(APIError::InvalidOperationState {
+                    description: "the keyholder has already approved this operation request".into(),
+                }),
+            ),
+        ]);
+
+        assert_eq!(result.approved, vec![approved]);
+        assert_eq!(result.results.len(), 4);
+
+        assert_eq!(result.results[0].operation_request_id, approved);
+        assert!(result.results[0].approval.is_some());
+        assert_eq!(result.results[0].error, None);
+        assert!(result.results[1].approval.is_some());
+
+        assert_eq!(result.results[2].operation_request_id, invalid);
+        assert!(result.results[2].approval.is_none());
+        assert_eq!(result.results[2].error.as_deref(), Some("InvalidSignature"));
+
+        assert_eq!(result.results[3].operation_request_id, already_approved);
+        assert_eq!(
+            result.results[3].error.as_deref(),
+            Some("InvalidOperationState")
+        );
+        assert_eq!(
+            result.results[3].message.as_deref(),
+            Some("invalid operation state: the keyholder has already approved this operation request")
+        );
+    }
+}
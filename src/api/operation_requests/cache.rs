@@ -0,0 +1,167 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use uuid::Uuid;
+
+use crate::api::models::{common::SignableMessageInfo, error::APIError};
+
+/// What the signable message of an operation request depends on besides the contract.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignableMessageKey {
+    pub nonce: i64,
+    pub state: i16,
+    pub proposed_keyholders: Option<Vec<String>>,
+}
+
+struct CacheEntry {
+    key: SignableMessageKey,
+    created_at: Instant,
+    info: SignableMessageInfo,
+}
+
+/// Keeps the signable messages of operation requests for `ttl`, so polling clients don't cause
+/// node requests. An entry is only used while the nonce, state and proposed keyholders of its
+/// operation request are unchanged. A `ttl` of zero disables the cache.
+pub struct SignableMessageCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<Uuid, CacheEntry>>,
+}
+
+impl SignableMessageCache {
+    pub fn new(ttl: Duration) -> Self {
+        SignableMessageCache {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, id: &Uuid, key: &SignableMessageKey) -> Option<SignableMessageInfo> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(id)
+            .filter(|entry| &entry.key == key && entry.created_at.elapsed() < self.ttl)
+            .map(|entry| entry.info.clone())
+    }
+
+    pub fn insert(&self, id: Uuid, key: SignableMessageKey, info: SignableMessageInfo) {
+        if self.ttl == Duration::from_secs(0) {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        let ttl = self.ttl;
+        entries.retain(|_, entry| entry.created_at.elapsed() < ttl);
+        entries.insert(
+            id,
+            CacheEntry {
+                key,
+                created_at: Instant::now(),
+                info,
+            },
+        );
+    }
+
+    /// Returns the cached message, or computes and caches it.
+    pub async fn get_or_insert_with<F, Fut>(
+        &self,
+        id: Uuid,
+        key: SignableMessageKey,
+        compute: F,
+    ) -> Result<SignableMessageInfo, APIError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<SignableMessageInfo, APIError>>,
+    {
+        if let Some(info) = self.get(&id, &key) {
+            return Ok(info);
+        }
+
+        let info = compute().await?;
+        self.insert(id, key, info.clone());
+
+        Ok(info)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use actix_web::{web, App, HttpResponse};
+    use uuid::Uuid;
+
+    use super::{SignableMessageCache, SignableMessageKey};
+    use crate::{
+        api::models::{common::SignableMessageInfo, error::APIError},
+        tezos,
+    };
+
+    fn key(nonce: i64) -> SignableMessageKey {
+        SignableMessageKey {
+            nonce,
+            state: 0,
+            proposed_keyholders: None,
+        }
+    }
+
+    async fn signable_message(
+        cache: &SignableMessageCache,
+        id: Uuid,
+        nonce: i64,
+        node_url: &str,
+    ) -> Result<SignableMessageInfo, APIError> {
+        cache
+            .get_or_insert_with(id, key(nonce), || async move {
+                let block_hash = tezos::block_hash(node_url, "head").await?;
+                Ok(SignableMessageInfo::new("05".into(), "".into(), block_hash))
+            })
+            .await
+    }
+
+    #[actix_rt::test]
+    async fn test_cached_within_ttl() -> Result<(), APIError> {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let node = {
+            let fetches = fetches.clone();
+            actix_web::test::start(move || {
+                let fetches = fetches.clone();
+                App::new().route(
+                    "/chains/main/blocks/head/hash",
+                    web::get().to(move || {
+                        fetches.fetch_add(1, Ordering::SeqCst);
+                        HttpResponse::Ok()
+                            .json("BLockGenesisGenesisGenesisGenesisGenesisb83baZgbyZe")
+                    }),
+                )
+            })
+        };
+        let node_url = node.url("").trim_end_matches('/').to_owned();
+        let id = Uuid::new_v4();
+
+        let cache = SignableMessageCache::new(Duration::from_secs(60));
+        let first = signable_message(&cache, id, 1, &node_url).await?;
+        let second = signable_message(&cache, id, 1, &node_url).await?;
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        assert_eq!(first.blake2b_hash, second.blake2b_hash);
+
+        signable_message(&cache, id, 2, &node_url).await?;
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+
+        let disabled = SignableMessageCache::new(Duration::from_secs(0));
+        signable_message(&disabled, id, 1, &node_url).await?;
+        signable_message(&disabled, id, 1, &node_url).await?;
+        assert_eq!(fetches.load(Ordering::SeqCst), 4);
+
+        Ok(())
+    }
+}
@@ -1,9 +1,15 @@
 use std::convert::TryInto;
 
 use crate::{
-    api::models::{error::APIError, user::UserKind},
+    api::models::{
+        error::APIError,
+        operation_request::{OperationRequestCleanup, OperationRequestState},
+        user::UserKind,
+    },
+    audit,
     auth::get_current_user,
     db::models::{node_endpoint::NodeEndpoint, operation_request::OperationRequest},
+    logging::context::RequestId,
     settings,
     tezos::multisig,
     DbPool,
@@ -11,9 +17,11 @@ use crate::{
 use actix_session::Session;
 use actix_web::{
     http::StatusCode,
-    web::{self, Path},
+    web::{self, Path, Query},
     HttpResponse,
 };
+use chrono::NaiveDate;
+use diesel::Connection;
 use log::info;
 use serde::Deserialize;
 use uuid::Uuid;
@@ -49,22 +57,166 @@ pub async fn operation_request(
     let mut multisig = multisig::get_multisig(
         contract.multisig_pkh.as_ref(),
         contract.kind.try_into()?,
-        &node_url,
+        contract.node_url_or(&node_url),
     );
     let multisig_nonce = multisig.nonce().await?;
+    let actor = current_user.address.clone();
 
-    if operation_request.nonce < multisig_nonce {
+    if operation_request.nonce < multisig_nonce.value() {
         let conn = pool.get()?;
         let operation_request_id = operation_request.id;
-        web::block(move || OperationRequest::delete(&conn, &operation_request.id)).await?;
-        info!("Delete operation request {:?}", operation_request_id);
+        web::block::<_, _, APIError>(move || {
+            conn.transaction(|| {
+                OperationRequest::delete(&conn, &operation_request.id)?;
+                audit::record(
+                    &conn,
+                    audit::operation_request_deleted(&actor, &operation_request),
+                )
+            })
+        })
+        .await?;
+        info!(
+            "[{}] Delete operation request {:?}",
+            RequestId::current_label(),
+            operation_request_id
+        );
         return Ok(HttpResponse::Ok().status(StatusCode::NO_CONTENT).finish());
     }
 
     let conn = pool.get()?;
     let operation_request_id = operation_request.id;
-    web::block(move || operation_request.delete_and_fix_next_nonces(&conn)).await?;
-    info!("Delete operation request {:?}", operation_request_id);
+    web::block::<_, _, APIError>(move || {
+        conn.transaction(|| {
+            operation_request.delete_and_fix_next_nonces(&conn)?;
+            audit::record(
+                &conn,
+                audit::operation_request_deleted(&actor, &operation_request),
+            )
+        })
+    })
+    .await?;
+    info!(
+        "[{}] Delete operation request {:?}",
+        RequestId::current_label(),
+        operation_request_id
+    );
 
     return Ok(HttpResponse::Ok().status(StatusCode::NO_CONTENT).finish());
 }
+
+#[derive(Deserialize)]
+pub struct CleanupInfo {
+    contract_id: Uuid,
+    state: OperationRequestState,
+    before: NaiveDate,
+    confirm: Option<bool>,
+}
+
+pub async fn cleanup(
+    pool: web::Data<DbPool>,
+    query: Query<CleanupInfo>,
+    session: Session,
+    server_settings: web::Data<settings::Server>,
+) -> Result<HttpResponse, APIError> {
+    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+    current_user.require_roles(vec![UserKind::Admin], query.contract_id)?;
+    validate_cleanup(&query)?;
+
+    let conn = pool.get()?;
+    let contract_id = query.contract_id;
+    let state = query.state;
+    let before = query.before.and_hms(0, 0, 0);
+    let deleted =
+        web::block(move || OperationRequest::delete_history(&conn, &contract_id, state, before))
+            .await?;
+
+    info!(
+        "[{}] User {} deleted {} {} operation requests created before {} on contract {}",
+        RequestId::current_label(),
+        current_user.address,
+        deleted,
+        state,
+        before,
+        contract_id
+    );
+
+    Ok(HttpResponse::Ok().json(OperationRequestCleanup { deleted }))
+}
+
+/// Only operation requests which will not change anymore can be deleted, and only on purpose.
+fn validate_cleanup(query: &CleanupInfo) -> Result<(), APIError> {
+    if query.confirm != Some(true) {
+        return Err(APIError::InvalidValue {
+            description: "the cleanup needs to be confirmed with confirm=true".into(),
+        });
+    }
+    if !query.state.is_terminal() {
+        return Err(APIError::InvalidOperationState {
+            description: format!("{} operation requests cannot be cleaned up", query.state),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::web::Query;
+
+    use super::{validate_cleanup, CleanupInfo};
+    use crate::api::models::error::APIError;
+
+    const CONTRACT_ID: &str = "5b8c8f3e-5a2c-4d5e-9d0a-2c6f0f6c3b1a";
+
+    fn cleanup_info(query: &str) -> CleanupInfo {
+        Query::<CleanupInfo>::from_query(&format!("contract_id={}&{}", CONTRACT_ID, query))
+            .unwrap()
+            .into_inner()
+    }
+
+    #[test]
+    fn test_validate_cleanup() -> Result<(), APIError> {
+        let query = cleanup_info("state=injected&before=2021-06-01&confirm=true");
+        assert_eq!(query.before.to_string(), "2021-06-01");
+        validate_cleanup(&query)?;
+
+        for query in &[
+            "state=injected&before=2021-06-01",
+            "state=injected&before=2021-06-01&confirm=false",
+        ] {
+            match validate_cleanup(&cleanup_info(query)) {
+                Err(APIError::InvalidValue { description }) => {
+                    assert!(description.contains("confirm=true"))
+                }
+                result => panic!("expected {} to be rejected, got {:?}", query, result),
+            }
+        }
+
+        for state in &["open", "approved"] {
+            let query = cleanup_info(&format!("state={}&before=2021-06-01&confirm=true", state));
+            match validate_cleanup(&query) {
+                Err(APIError::InvalidOperationState { description }) => assert_eq!(
+                    description,
+                    format!("{} operation requests cannot be cleaned up", state)
+                ),
+                result => panic!("expected {} to be rejected, got {:?}", state, result),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cleanup_requires_a_date() {
+        assert!(Query::<CleanupInfo>::from_query(&format!(
+            "contract_id={}&state=injected&confirm=true",
+            CONTRACT_ID
+        ))
+        .is_err());
+        assert!(Query::<CleanupInfo>::from_query(&format!(
+            "contract_id={}&state=injected&before=yesterday&confirm=true",
+            CONTRACT_ID
+        ))
+        .is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use std::convert::TryInto;
+use std::{collections::HashMap, convert::TryInto};
 
 use actix_session::Session;
 use actix_web::{
@@ -10,24 +10,34 @@ use diesel::{r2d2::ConnectionManager, r2d2::PooledConnection, PgConnection};
 use serde::Deserialize;
 use uuid::Uuid;
 
-use crate::tezos::multisig::{self, OperationRequestParams};
+use crate::tezos::{
+    coding::{validate_address, validate_address_prefix},
+    multisig::{self, OperationRequestParams},
+};
 use crate::DbPool;
 use crate::{
     api::models::user::UserKind,
     db::models::{
-        contract::Contract, operation_request::OperationRequest as DBOperationRequest, user::User,
+        contract::Contract,
+        operation_request::{
+            OperationRequest as DBOperationRequest, OperationRequestFilter, TargetAddressFilter,
+        },
+        user::User,
     },
 };
 use crate::{
     api::models::{
-        common::{ListResponse, SignableMessageInfo},
+        common::{ListResponse, SignableMessageInfo, SortDirection},
         error::APIError,
+        operation_approval::OperationApprovalOrder,
         operation_request::OperationRequest,
-        operation_request::{OperationRequestKind, OperationRequestState},
+        operation_request::{OperationRequestKind, OperationRequestState, SigningBundle},
     },
     auth::get_current_user,
 };
 use crate::{auth::SessionUser, settings};
+
+use super::cache::{SignableMessageCache, SignableMessageKey};
 use crate::{db::models::node_endpoint::NodeEndpoint, tezos::multisig::Signature};
 
 #[derive(Deserialize)]
@@ -35,15 +45,101 @@ pub struct Info {
     kind: OperationRequestKind,
     contract_id: Uuid,
     state: Option<OperationRequestState>,
+    target_address: Option<String>,
+    match_prefix: Option<bool>,
+    page: Option<i64>,
+    limit: Option<i64>,
+}
+
+/// The query of `/contracts/{id}/operation-requests`, the contract is taken from the path.
+#[derive(Deserialize)]
+pub struct ContractInfo {
+    kind: OperationRequestKind,
+    state: Option<OperationRequestState>,
+    target_address: Option<String>,
+    match_prefix: Option<bool>,
     page: Option<i64>,
     limit: Option<i64>,
 }
 
+/// The query of `/operation-requests/all`, the contracts are those of the current user.
+#[derive(Deserialize)]
+pub struct AllInfo {
+    kind: Option<OperationRequestKind>,
+    state: Option<OperationRequestState>,
+    target_address: Option<String>,
+    match_prefix: Option<bool>,
+    page: Option<i64>,
+    limit: Option<i64>,
+}
+
+impl From<Info> for ContractInfo {
+    fn from(info: Info) -> Self {
+        ContractInfo {
+            kind: info.kind,
+            state: info.state,
+            target_address: info.target_address,
+            match_prefix: info.match_prefix,
+            page: info.page,
+            limit: info.limit,
+        }
+    }
+}
+
 pub async fn operation_requests(
     pool: web::Data<DbPool>,
     query: Query<Info>,
     server_settings: web::Data<settings::Server>,
     session: Session,
+) -> Result<HttpResponse, APIError> {
+    let query = query.into_inner();
+    let contract_id = query.contract_id;
+
+    list_operation_requests(pool, contract_id, query.into(), server_settings, session).await
+}
+
+pub async fn contract_operation_requests(
+    pool: web::Data<DbPool>,
+    path: Path<PathInfo>,
+    query: Query<ContractInfo>,
+    server_settings: web::Data<settings::Server>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    list_operation_requests(pool, path.id, query.into_inner(), server_settings, session).await
+}
+
+/// The operation requests of every contract the current user is a gatekeeper or keyholder of.
+pub async fn all_operation_requests(
+    pool: web::Data<DbPool>,
+    query: Query<AllInfo>,
+    server_settings: web::Data<settings::Server>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+
+    let conn = pool.get()?;
+
+    let query = query.into_inner();
+    let page = query.page.unwrap_or(0);
+    let limit = query.limit.unwrap_or(100);
+
+    let filter = all_operation_requests_filter(&current_user, query)?;
+    let sort_direction = server_settings
+        .default_sort_direction
+        .unwrap_or(SortDirection::Asc);
+    let result =
+        web::block(move || load_operation_requests(&conn, filter, sort_direction, page, limit))
+            .await?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+async fn list_operation_requests(
+    pool: web::Data<DbPool>,
+    contract_id: Uuid,
+    query: ContractInfo,
+    server_settings: web::Data<settings::Server>,
+    session: Session,
 ) -> Result<HttpResponse, APIError> {
     let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
 
@@ -51,39 +147,96 @@ pub async fn operation_requests(
 
     let page = query.page.unwrap_or(0);
     let limit = query.limit.unwrap_or(100);
-    let kind = query.kind;
-    let contract_id = query.contract_id;
 
     current_user.require_roles(vec![UserKind::Gatekeeper, UserKind::Keyholder], contract_id)?;
 
-    let state = query.state;
+    let filter = operation_request_filter(contract_id, query)?;
+    let sort_direction = server_settings
+        .default_sort_direction
+        .unwrap_or(SortDirection::Asc);
     let result =
-        web::block(move || load_operation_requests(&conn, page, limit, kind, contract_id, state))
+        web::block(move || load_operation_requests(&conn, filter, sort_direction, page, limit))
             .await?;
 
     Ok(HttpResponse::Ok().json(result))
 }
 
+fn operation_request_filter(
+    contract_id: Uuid,
+    query: ContractInfo,
+) -> Result<OperationRequestFilter, APIError> {
+    Ok(OperationRequestFilter {
+        kind: Some(query.kind),
+        contract_ids: vec![contract_id],
+        state: query.state,
+        target_address: target_address_filter(query.target_address, query.match_prefix)?,
+    })
+}
+
+fn all_operation_requests_filter(
+    current_user: &SessionUser,
+    query: AllInfo,
+) -> Result<OperationRequestFilter, APIError> {
+    let contract_ids =
+        current_user.contract_ids_with_roles(&[UserKind::Gatekeeper, UserKind::Keyholder]);
+    if contract_ids.is_empty() {
+        return Err(APIError::Forbidden);
+    }
+
+    Ok(OperationRequestFilter {
+        kind: query.kind,
+        contract_ids,
+        state: query.state,
+        target_address: target_address_filter(query.target_address, query.match_prefix)?,
+    })
+}
+
+fn target_address_filter(
+    target_address: Option<String>,
+    match_prefix: Option<bool>,
+) -> Result<Option<TargetAddressFilter>, APIError> {
+    Ok(match target_address {
+        Some(address) if match_prefix.unwrap_or(false) => {
+            validate_address_prefix(&address)?;
+            Some(TargetAddressFilter::Prefix(address))
+        }
+        Some(address) => {
+            validate_address(&address)?;
+            Some(TargetAddressFilter::Exact(address))
+        }
+        None => None,
+    })
+}
+
 fn load_operation_requests(
     conn: &PooledConnection<ConnectionManager<PgConnection>>,
+    filter: OperationRequestFilter,
+    sort_direction: SortDirection,
     page: i64,
     limit: i64,
-    kind: OperationRequestKind,
-    contract_id: Uuid,
-    state: Option<OperationRequestState>,
 ) -> Result<ListResponse<OperationRequest>, APIError> {
+    let min_approvals = Contract::get_all_with_ids(conn, filter.contract_ids.iter().collect())?
+        .into_iter()
+        .map(|contract| (contract.id, contract.min_approvals))
+        .collect::<HashMap<_, _>>();
     let (operation_requests, total_pages) =
-        DBOperationRequest::get_list(conn, kind, contract_id, state, page, limit)?;
+        DBOperationRequest::get_list(conn, filter, sort_direction, page, limit)?;
 
     let results = operation_requests
         .into_iter()
         .map(
             |(operation_request, gatekeeper, operation_approvals, proposed_keyholders)| {
+                let approvals_required = min_approvals
+                    .get(&operation_request.contract_id)
+                    .copied()
+                    .unwrap_or_default();
+
                 OperationRequest::from(
                     operation_request,
                     gatekeeper,
                     operation_approvals,
                     proposed_keyholders,
+                    approvals_required.into(),
                 )
             },
         )
@@ -137,7 +290,7 @@ pub async fn operation_request(
     let conn = pool.get()?;
     let id = path.id;
 
-    let (operation_request, user, operation_approvals, proposed_keyholders) =
+    let (operation_request, user, operation_approvals, proposed_keyholders, min_approvals) =
         web::block::<_, _, APIError>(move || {
             let (operation_request, operation_approvals, proposed_keyholders) =
                 DBOperationRequest::get_with_operation_approvals(&conn, &id)?;
@@ -148,12 +301,14 @@ pub async fn operation_request(
             )?;
 
             let user = User::get(&conn, operation_request.user_id)?;
+            let contract = Contract::get(&conn, &operation_request.contract_id)?;
 
             Ok((
                 operation_request,
                 user,
                 operation_approvals,
                 proposed_keyholders,
+                contract.min_approvals,
             ))
         })
         .await?;
@@ -163,10 +318,66 @@ pub async fn operation_request(
         user,
         operation_approvals,
         proposed_keyholders,
+        min_approvals.into(),
     )?))
 }
 
 pub async fn signable_message(
+    pool: web::Data<DbPool>,
+    path: Path<PathInfo>,
+    server_settings: web::Data<settings::Server>,
+    cache: web::Data<SignableMessageCache>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+
+    let id = path.id;
+    let (operation_request, contract, proposed_keyholders) =
+        load_operation_and_contract(&pool, &id, current_user).await?;
+
+    let keyholder_public_keys: Option<Vec<String>> = match proposed_keyholders {
+        None => None,
+        Some(keyholders) => Some(
+            keyholders
+                .into_iter()
+                .map(|keyholder| keyholder.public_key)
+                .collect(),
+        ),
+    };
+    let key = SignableMessageKey {
+        nonce: operation_request.nonce,
+        state: operation_request.state,
+        proposed_keyholders: keyholder_public_keys.clone(),
+    };
+
+    let signable_message_info = cache
+        .get_or_insert_with(id, key, || async move {
+            let conn = pool.get()?;
+            let node_url =
+                web::block::<_, _, APIError>(move || Ok(NodeEndpoint::get_selected(&conn)?.url))
+                    .await?;
+
+            let multisig = multisig::get_multisig(
+                contract.multisig_pkh.as_ref(),
+                contract.kind.try_into()?,
+                contract.node_url_or(&node_url),
+            );
+
+            let operation_request_params = OperationRequestParams::from(operation_request);
+            let signable_message = multisig
+                .signable_message(&contract, &operation_request_params, keyholder_public_keys)
+                .await?;
+
+            let signable_message_info: SignableMessageInfo = signable_message.try_into()?;
+
+            Ok(signable_message_info)
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(signable_message_info))
+}
+
+pub async fn signing_bundle(
     pool: web::Data<DbPool>,
     path: Path<PathInfo>,
     server_settings: web::Data<settings::Server>,
@@ -179,16 +390,22 @@ pub async fn signable_message(
         load_operation_and_contract(&pool, &id, current_user).await?;
 
     let conn = pool.get()?;
-    let node_url =
-        web::block::<_, _, APIError>(move || Ok(NodeEndpoint::get_selected(&conn)?.url)).await?;
+    let contract_id = contract.id;
+    let (node_url, keyholders) = web::block::<_, _, APIError>(move || {
+        let node_url = NodeEndpoint::get_selected(&conn)?.url;
+        let keyholders = User::get_all_active(&conn, contract_id, UserKind::Keyholder)?;
+
+        Ok((node_url, keyholders))
+    })
+    .await?;
 
     let multisig = multisig::get_multisig(
         contract.multisig_pkh.as_ref(),
         contract.kind.try_into()?,
-        &node_url,
+        contract.node_url_or(&node_url),
     );
 
-    let operation_request_params = OperationRequestParams::from(operation_request);
+    let operation_request_params = OperationRequestParams::from(operation_request.clone());
     let keyholder_public_keys = match proposed_keyholders {
         None => None,
         Some(keyholders) => Some(
@@ -202,9 +419,10 @@ pub async fn signable_message(
         .signable_message(&contract, &operation_request_params, keyholder_public_keys)
         .await?;
 
-    let signable_message_info: SignableMessageInfo = signable_message.try_into()?;
+    let signing_bundle =
+        SigningBundle::new(&operation_request, &contract, signable_message, keyholders)?;
 
-    Ok(HttpResponse::Ok().json(signable_message_info))
+    Ok(HttpResponse::Ok().json(signing_bundle))
 }
 
 pub async fn operation_request_parameters(
@@ -241,7 +459,7 @@ pub async fn operation_request_parameters(
     let mut multisig = multisig::get_multisig(
         contract.multisig_pkh.as_ref(),
         contract.kind.try_into()?,
-        &node_url,
+        contract.node_url_or(&node_url),
     );
     let signatures = approvals
         .iter()
@@ -271,3 +489,269 @@ pub async fn operation_request_parameters(
 
     Ok(HttpResponse::Ok().json(parameters))
 }
+
+pub async fn approval_order(
+    pool: web::Data<DbPool>,
+    path: Path<PathInfo>,
+    server_settings: web::Data<settings::Server>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+
+    let conn = pool.get()?;
+    let id = path.id;
+    let (contract, approvals) = web::block::<_, _, APIError>(move || {
+        let operation_request = DBOperationRequest::get(&conn, &id)?;
+
+        current_user.require_roles(
+            vec![UserKind::Gatekeeper, UserKind::Keyholder],
+            operation_request.contract_id,
+        )?;
+
+        let contract = Contract::get(&conn, &operation_request.contract_id)?;
+        let approvals = operation_request.operation_approvals(&conn)?;
+
+        Ok((contract, approvals))
+    })
+    .await?;
+
+    let conn = pool.get()?;
+    let node_url =
+        web::block::<_, _, APIError>(move || Ok(NodeEndpoint::get_selected(&conn)?.url)).await?;
+
+    let mut multisig = multisig::get_multisig(
+        contract.multisig_pkh.as_ref(),
+        contract.kind.try_into()?,
+        contract.node_url_or(&node_url),
+    );
+    let min_approvals = multisig.min_signatures().await?;
+    let approvers = multisig.approvers().await?.clone();
+
+    Ok(HttpResponse::Ok().json(OperationApprovalOrder::new(
+        approvers,
+        min_approvals,
+        &approvals,
+    )))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        rc::Rc,
+        str::FromStr,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use actix_session::{CookieSession, UserSession};
+    use actix_web::{dev::Service, http::StatusCode, test, web, web::Query, App, HttpResponse};
+    use uuid::Uuid;
+
+    use super::{
+        all_operation_requests_filter, operation_request_filter, signable_message, AllInfo,
+        ContractInfo, Info,
+    };
+    use crate::{
+        api::{
+            models::{
+                contract::ContractKind, error::APIError, operation_request::OperationRequestKind,
+                user::UserKind,
+            },
+            operation_requests::cache::SignableMessageCache,
+        },
+        auth::{set_current_user, SessionUser, SessionUserRole},
+        db::{
+            models::{
+                contract::NewContract,
+                node_endpoint::{NewNodeEndpoint, NodeEndpoint},
+                operation_request::OperationRequest,
+                user::User,
+            },
+            test_pool,
+        },
+        fixtures,
+        tezos::micheline::types,
+    };
+
+    const CONTRACT_ID: &str = "5b8c8f3e-5a2c-4d5e-9d0a-2c6f0f6c3b1a";
+
+    #[test]
+    fn test_nested_route_filters_like_flat_route() -> Result<(), APIError> {
+        for filters in &[
+            "kind=mint",
+            "kind=burn&state=approved&page=2&limit=10",
+            "kind=mint&target_address=tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT",
+            "kind=mint&target_address=tz1Mj7&match_prefix=true",
+        ] {
+            let flat =
+                Query::<Info>::from_query(&format!("contract_id={}&{}", CONTRACT_ID, filters))
+                    .unwrap()
+                    .into_inner();
+            let nested = Query::<ContractInfo>::from_query(filters)
+                .unwrap()
+                .into_inner();
+            assert_eq!((flat.page, flat.limit), (nested.page, nested.limit));
+
+            let flat_contract_id = flat.contract_id;
+            assert_eq!(
+                operation_request_filter(flat_contract_id, flat.into())?,
+                operation_request_filter(Uuid::from_str(CONTRACT_ID).unwrap(), nested)?
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_all_operation_requests_of_user_contracts() -> Result<(), APIError> {
+        let keyholder_contract = Uuid::new_v4();
+        let gatekeeper_contract = Uuid::new_v4();
+        let admin_contract = Uuid::new_v4();
+        let role = |contract_id: Uuid, kind: UserKind| SessionUserRole { contract_id, kind };
+        let user = SessionUser {
+            address: "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT".into(),
+            roles: vec![
+                role(keyholder_contract, UserKind::Keyholder),
+                role(gatekeeper_contract, UserKind::Gatekeeper),
+                role(gatekeeper_contract, UserKind::Keyholder),
+                role(admin_contract, UserKind::Admin),
+            ],
+        };
+
+        let query = Query::<AllInfo>::from_query("state=open")
+            .unwrap()
+            .into_inner();
+        let filter = all_operation_requests_filter(&user, query)?;
+        assert_eq!(filter.kind, None);
+        assert_eq!(filter.contract_ids.len(), 2);
+        assert!(filter.contract_ids.contains(&keyholder_contract));
+        assert!(filter.contract_ids.contains(&gatekeeper_contract));
+        assert!(!filter.contract_ids.contains(&admin_contract));
+
+        let query = Query::<AllInfo>::from_query("kind=mint")
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            all_operation_requests_filter(&user, query)?.kind,
+            Some(OperationRequestKind::Mint)
+        );
+
+        let admin = SessionUser {
+            address: "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT".into(),
+            roles: vec![role(admin_contract, UserKind::Admin)],
+        };
+        let query = Query::<AllInfo>::from_query("").unwrap().into_inner();
+        assert!(matches!(
+            all_operation_requests_filter(&admin, query),
+            Err(APIError::Forbidden)
+        ));
+
+        Ok(())
+    }
+
+    #[actix_rt::test]
+    #[ignore]
+    async fn test_signable_message_fetched_once() {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let node = {
+            let fetches = fetches.clone();
+            actix_web::test::start(move || {
+                let fetches = fetches.clone();
+                App::new().route(
+                    "/chains/main/blocks/head/context/contracts/{address}/entrypoints/mainParameter",
+                    web::get().to(move || {
+                        fetches.fetch_add(1, Ordering::SeqCst);
+                        // just the branch of the FA1 multisig's parameter a mint is signed for
+                        let call = types::or(
+                            types::unit(),
+                            types::or(
+                                types::or(
+                                    types::or(
+                                        types::pair(types::address(), types::nat()),
+                                        types::unit(),
+                                    ),
+                                    types::unit(),
+                                ),
+                                types::unit(),
+                            ),
+                        );
+                        let action =
+                            types::or(types::pair(call, types::address()), types::unit());
+                        HttpResponse::Ok().json(types::pair(
+                            types::pair(types::nat(), action),
+                            types::list(types::option(types::signature())),
+                        ))
+                    }),
+                )
+            })
+        };
+        let node_url = node.url("").trim_end_matches('/').to_owned();
+
+        let pool = test_pool();
+        let (contract_id, operation_request_id) = {
+            let conn = pool.get().unwrap();
+            NodeEndpoint::insert(
+                &conn,
+                vec![NewNodeEndpoint {
+                    selected: true,
+                    ..NewNodeEndpoint::new("node".into(), &node_url, "mainnet".into()).unwrap()
+                }],
+            )
+            .unwrap();
+            let contract = NewContract {
+                kind: ContractKind::FA1.into(),
+                ..fixtures::new_contract()
+            }
+            .save(&conn)
+            .unwrap();
+            let gatekeeper = User::insert(
+                &conn,
+                vec![fixtures::new_user(contract.id, UserKind::Gatekeeper)],
+            )
+            .unwrap()
+            .remove(0);
+            let operation_request = OperationRequest::insert(
+                &conn,
+                &fixtures::new_operation_request(contract.id, gatekeeper.id),
+            )
+            .unwrap();
+
+            (contract.id, operation_request.id)
+        };
+        let user = Rc::new(fixtures::session_user(UserKind::Gatekeeper, &[contract_id]));
+        let mut app = test::init_service(
+            App::new()
+                .data(pool)
+                .data(fixtures::server_settings())
+                .data(SignableMessageCache::new(Duration::from_secs(60)))
+                .wrap_fn(move |req, srv| {
+                    set_current_user(&req.get_session(), &user).unwrap();
+                    srv.call(req)
+                })
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .route(
+                    "/operation-requests/{id}/signable-message",
+                    web::get().to(signable_message),
+                ),
+        )
+        .await;
+
+        let uri = format!(
+            "/operation-requests/{}/signable-message",
+            operation_request_id
+        );
+        let mut bodies = vec![];
+        for _ in 0..2 {
+            let response =
+                test::call_service(&mut app, test::TestRequest::get().uri(&uri).to_request()).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            bodies.push(test::read_body(response).await);
+        }
+
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        assert_eq!(bodies[0], bodies[1]);
+    }
+}
@@ -1,5 +1,6 @@
 use actix_web::{web, HttpResponse};
 
+pub mod cache;
 mod delete;
 mod get;
 mod patch;
@@ -12,6 +13,26 @@ pub fn api_config(cfg: &mut web::ServiceConfig) {
             .route(web::post().to(post::operation_request))
             .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
     );
+    cfg.service(
+        web::resource("/contracts/{id}/operation-requests")
+            .route(web::get().to(get::contract_operation_requests))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
+    cfg.service(
+        web::resource("/operation-requests/all")
+            .route(web::get().to(get::all_operation_requests))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
+    cfg.service(
+        web::resource("/operation-requests/cleanup")
+            .route(web::delete().to(delete::cleanup))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
+    cfg.service(
+        web::resource("/operation-requests/import")
+            .route(web::post().to(post::import))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
     cfg.service(
         web::resource("/operation-requests/{id}")
             .route(web::get().to(get::operation_request))
@@ -24,9 +45,19 @@ pub fn api_config(cfg: &mut web::ServiceConfig) {
             .route(web::get().to(get::signable_message))
             .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
     );
+    cfg.service(
+        web::resource("/operation-requests/{id}/signing-bundle")
+            .route(web::get().to(get::signing_bundle))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
     cfg.service(
         web::resource("/operation-requests/{id}/parameters")
             .route(web::get().to(get::operation_request_parameters))
             .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
     );
+    cfg.service(
+        web::resource("/operation-requests/{id}/approval-order")
+            .route(web::get().to(get::approval_order))
+            .route(web::head().to(|| HttpResponse::MethodNotAllowed())),
+    );
 }
@@ -3,28 +3,34 @@ use std::convert::TryInto;
 use actix_session::Session;
 use actix_web::{
     web::{self, Path},
-    HttpResponse,
+    HttpRequest, HttpResponse,
 };
-use log::info;
+use diesel::Connection;
+use log::{info, warn};
 use serde::Deserialize;
+use std::time::Duration;
 use uuid::Uuid;
 
+use crate::audit;
 use crate::notifications::notify_injection;
-use crate::tezos::coding::validate_operation_hash;
+use crate::tezos::{self, coding::validate_operation_hash, multisig};
 use crate::DbPool;
 use crate::{
     api::models::{
+        api_key::ApiKeyAction,
         error::APIError,
         operation_request::{OperationRequest, OperationRequestState, PatchOperationRequest},
         user::UserKind,
     },
-    auth::get_current_user,
+    auth::get_current_user_or_api_key,
+    logging::context::{in_current_request, RequestId},
 };
 use crate::{
     db::models::{
-        contract::Contract, operation_request::OperationRequest as DBOperationRequest, user::User,
+        node_endpoint::NodeEndpoint, operation_request::OperationRequest as DBOperationRequest,
+        user::User,
     },
-    settings,
+    settings::{self, RemovedKeyholderApprovals},
 };
 
 #[derive(Deserialize)]
@@ -37,11 +43,16 @@ pub async fn operation_request(
     path: Path<PathInfo>,
     patch_operation_request: web::Json<PatchOperationRequest>,
     server_settings: web::Data<settings::Server>,
+    req: HttpRequest,
     session: Session,
 ) -> Result<HttpResponse, APIError> {
-    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+    let current_user = get_current_user_or_api_key(
+        &req,
+        &session,
+        server_settings.inactivity_timeout_seconds,
+        ApiKeyAction::InjectOperationRequest,
+    )?;
 
-    let conn = pool.get()?;
     let operation_request_id = path.id;
 
     if let Some(operation_hash) = &patch_operation_request.operation_hash {
@@ -53,16 +64,38 @@ pub async fn operation_request(
         })?;
     }
 
+    let conn = pool.get()?;
+    let (contract, selected_node_url, has_injectors) = web::block::<_, _, APIError>(move || {
+        let (_, contract) = DBOperationRequest::get_with_contract(&conn, &operation_request_id)?;
+        let injectors = User::get_all_active(&conn, contract.id, UserKind::Injector)?;
+
+        Ok((
+            contract,
+            NodeEndpoint::get_selected(&conn)?.url,
+            !injectors.is_empty(),
+        ))
+    })
+    .await?;
+
+    current_user.require_roles(injection_roles(has_injectors), contract.id)?;
+
+    let mut multisig = multisig::get_multisig(
+        contract.multisig_pkh.as_ref(),
+        contract.kind.try_into()?,
+        contract.node_url_or(&selected_node_url),
+    );
+    let multisig_nonce = multisig.nonce().await?;
+    let node_url = contract.node_url_or(&selected_node_url).to_owned();
+    let min_approvals = contract.min_approvals;
+    let removed_keyholder_approvals = server_settings.removed_keyholder_approvals();
+    let request_id = RequestId::current_label();
+
+    let conn = pool.get()?;
     let (updated_operation, gatekeeper, operation_approvals, proposed_keyholders) =
         web::block::<_, _, APIError>(move || {
             let (operation_request, operation_approvals, proposed_keyholders) =
                 DBOperationRequest::get_with_operation_approvals(&conn, &operation_request_id)?;
 
-            current_user.require_roles(
-                vec![UserKind::Gatekeeper, UserKind::Keyholder],
-                operation_request.contract_id,
-            )?;
-
             let state: OperationRequestState = operation_request.state.try_into()?;
             if state != OperationRequestState::Approved {
                 return Err(APIError::InvalidOperationState {
@@ -73,15 +106,60 @@ pub async fn operation_request(
                     ),
                 });
             }
-            let updated_operation_request = DBOperationRequest::mark_injected(
-                &conn,
-                &operation_request_id,
-                patch_operation_request.operation_hash.clone(),
-            )?;
+            let updated_operation_request = conn.transaction::<_, APIError, _>(|| {
+                let updated_operation_request = DBOperationRequest::mark_injected(
+                    &conn,
+                    &operation_request_id,
+                    patch_operation_request.operation_hash.clone(),
+                    multisig_nonce,
+                )?;
+                audit::record(
+                    &conn,
+                    audit::operation_request_injected(
+                        &current_user.address,
+                        &updated_operation_request,
+                    ),
+                )?;
+
+                if let (RemovedKeyholderApprovals::Drop, Some(proposed_keyholders)) =
+                    (removed_keyholder_approvals, &proposed_keyholders)
+                {
+                    let keyholders = proposed_keyholders
+                        .iter()
+                        .map(|keyholder| keyholder.public_key.clone())
+                        .collect::<Vec<_>>();
+                    let dropped = DBOperationRequest::drop_removed_keyholder_approvals(
+                        &conn,
+                        &updated_operation_request.contract_id,
+                        &keyholders,
+                        updated_operation_request
+                            .threshold
+                            .unwrap_or(i64::from(min_approvals)),
+                    )?;
+                    if !dropped.approvals.is_empty() || !dropped.reopened.is_empty() {
+                        info!(
+                            "[{}] Dropped {} approvals of removed keyholders, re-opened operation requests: {:?}",
+                            request_id,
+                            dropped.approvals.len(),
+                            dropped.reopened
+                        );
+                        audit::record(
+                            &conn,
+                            audit::approvals_dropped(
+                                &current_user.address,
+                                &updated_operation_request,
+                                &dropped,
+                            ),
+                        )?;
+                    }
+                }
+
+                Ok(updated_operation_request)
+            })?;
 
             info!(
-                "Operation request has been marked as injected: {:?}",
-                updated_operation_request
+                "[{}] Operation request has been marked as injected: {:?}",
+                request_id, updated_operation_request
             );
 
             let user = User::get(&conn, operation_request.user_id)?;
@@ -91,11 +169,7 @@ pub async fn operation_request(
                 UserKind::Keyholder,
             );
             if let Ok(keyholders) = keyholders {
-                let contract = Contract::get(&conn, &updated_operation_request.contract_id);
-                if let Ok(contract) = contract {
-                    let _ =
-                        notify_injection(&user, &keyholders, &updated_operation_request, &contract);
-                }
+                let _ = notify_injection(&user, &keyholders, &updated_operation_request, &contract);
             }
 
             Ok((
@@ -107,10 +181,125 @@ pub async fn operation_request(
         })
         .await?;
 
+    if let (Some(injection_confirmation), Some(operation_hash)) = (
+        server_settings.injection_confirmation.clone(),
+        updated_operation.operation_hash.clone(),
+    ) {
+        actix_web::rt::spawn(in_current_request(confirm_injection(
+            pool.clone(),
+            node_url,
+            operation_request_id,
+            operation_hash,
+            injection_confirmation,
+        )));
+    }
+
     Ok(HttpResponse::Ok().json(OperationRequest::from(
         updated_operation,
         gatekeeper,
         operation_approvals,
         proposed_keyholders,
+        min_approvals.into(),
     )?))
 }
+
+/// The roles allowed to mark an operation request of a contract as injected. Contracts with
+/// injectors separate injecting from approving, so only the injectors may.
+fn injection_roles(has_injectors: bool) -> Vec<UserKind> {
+    if has_injectors {
+        vec![UserKind::Injector]
+    } else {
+        vec![UserKind::Gatekeeper, UserKind::Keyholder]
+    }
+}
+
+async fn confirm_injection(
+    pool: web::Data<DbPool>,
+    node_url: String,
+    operation_request_id: Uuid,
+    operation_hash: String,
+    injection_confirmation: settings::InjectionConfirmation,
+) {
+    let included = tezos::wait_for_operation(
+        &node_url,
+        &operation_hash,
+        injection_confirmation.max_attempts,
+        Duration::from_secs(injection_confirmation.interval_seconds),
+    )
+    .await;
+
+    if included {
+        info!(
+            "[{}] Operation {} of operation request {} has been included",
+            RequestId::current_label(),
+            operation_hash,
+            operation_request_id
+        );
+        return;
+    }
+
+    warn!(
+        "[{}] Operation {} of operation request {} could not be found after {} attempts",
+        RequestId::current_label(),
+        operation_hash,
+        operation_request_id,
+        injection_confirmation.max_attempts
+    );
+
+    let result = match pool.get() {
+        Ok(conn) => web::block::<_, _, APIError>(move || {
+            Ok(DBOperationRequest::mark_confirmation_failed(
+                &conn,
+                &operation_request_id,
+            )?)
+        })
+        .await
+        .map_err(APIError::from),
+        Err(error) => Err(APIError::from(error)),
+    };
+
+    if let Err(error) = result {
+        warn!(
+            "[{}] Failed to mark operation request {} as not confirmed: {}",
+            RequestId::current_label(),
+            operation_request_id,
+            error
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use super::injection_roles;
+    use crate::{
+        api::models::user::UserKind,
+        auth::SessionUser,
+        fixtures::{user, ADDRESS},
+    };
+
+    #[test]
+    fn test_injection_roles() {
+        let contract_id = Uuid::new_v4();
+        let keyholder = SessionUser::new(
+            ADDRESS.into(),
+            &vec![user(contract_id, UserKind::Keyholder)],
+        );
+        let injector =
+            SessionUser::new(ADDRESS.into(), &vec![user(contract_id, UserKind::Injector)]);
+
+        assert!(keyholder
+            .require_roles(injection_roles(false), contract_id)
+            .is_ok());
+        assert!(keyholder
+            .require_roles(injection_roles(true), contract_id)
+            .is_err());
+        assert!(injector
+            .require_roles(injection_roles(true), contract_id)
+            .is_ok());
+        assert!(injector
+            .require_roles(injection_roles(true), Uuid::new_v4())
+            .is_err());
+    }
+}
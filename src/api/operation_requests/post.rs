@@ -1,29 +1,41 @@
-use std::{collections::HashSet, convert::TryFrom, convert::TryInto, str::FromStr};
+use std::{collections::HashSet, convert::TryFrom, convert::TryInto};
 
 use actix_session::Session;
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use bigdecimal::BigDecimal;
 use diesel::Connection;
-use log::info;
-use num_bigint::BigInt;
+use log::{info, warn};
+use uuid::Uuid;
 
+use crate::audit;
 use crate::db::models::node_endpoint::NodeEndpoint;
 use crate::tezos::multisig::{self, OperationRequestParams, SignableMessage};
-use crate::DbPool;
 use crate::{
     api::models::{
+        api_key::ApiKeyAction,
         error::APIError,
         operation_request::OperationRequest,
-        operation_request::{NewOperationRequest, OperationRequestKind},
+        operation_request::{
+            ImportedOperationRequest, NewOperationRequest, OperationRequestImport,
+            OperationRequestImportResult, OperationRequestKind,
+        },
         user::{UserKind, UserState},
     },
-    auth::get_current_user,
+    auth::{get_current_user, get_current_user_or_api_key},
+};
+use crate::{
+    api::{amount, canonical_json},
+    logging::context::RequestId,
 };
 use crate::{
     db::models::{
+        allowed_target_address::AllowedTargetAddress,
+        capability::Capability,
         contract::Contract,
+        operation_approval::{NewOperationApproval, OperationApproval},
         operation_request::{
-            NewOperationRequest as DBNewOperationRequest, OperationRequest as DBOperationRequest,
+            HistoricalOperationRequest, NewOperationRequest as DBNewOperationRequest,
+            OperationRequest as DBOperationRequest,
         },
         proposed_user::ProposedUser,
         user::{NewUser, User},
@@ -31,15 +43,23 @@ use crate::{
     notifications::notify_new_operation_request,
 };
 use crate::{settings, tezos, tezos::coding::validate_edpk};
+use crate::{Conn, DbPool};
 
 pub async fn operation_request(
     pool: web::Data<DbPool>,
     new_operation_request: web::Json<NewOperationRequest>,
     server_settings: web::Data<settings::Server>,
+    contract_settings: web::Data<Vec<settings::Contract>>,
+    req: HttpRequest,
     session: Session,
 ) -> Result<HttpResponse, APIError> {
     let new_operation_request = new_operation_request.into_inner();
-    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+    let current_user = get_current_user_or_api_key(
+        &req,
+        &session,
+        server_settings.inactivity_timeout_seconds,
+        ApiKeyAction::CreateOperationRequest,
+    )?;
 
     let conn = pool.get()?;
     let contract_id = new_operation_request.contract_id;
@@ -50,55 +70,98 @@ pub async fn operation_request(
     current_user.require_roles(vec![required_user_kind], contract_id)?;
 
     let operation_request_kind: i16 = new_operation_request.kind.into();
-    let (contract, max_local_nonce) = web::block::<_, _, APIError>(move || {
-        let (contract, capabilities) = Contract::get_with_capabilities(&conn, &contract_id)?;
-        let capability = capabilities
-            .iter()
-            .find(|cap| cap.operation_request_kind == operation_request_kind);
-        if capability.is_none() {
-            let kind: OperationRequestKind = operation_request_kind.try_into().unwrap();
-            return Err(APIError::InvalidOperationRequest {
-                description: format!(
-                    "The multisig contract does not support operation requests of kind {}",
-                    kind
-                ),
-            });
-        }
-        let max_nonce = DBOperationRequest::max_nonce(&conn, &contract.id).unwrap_or(-1);
+    let (contract, max_local_nonce, allowed_target_addresses) =
+        web::block::<_, _, APIError>(move || {
+            let (contract, capabilities) = Contract::get_with_capabilities(&conn, &contract_id)?;
+            if !Capability::allows(&capabilities, operation_request_kind) {
+                let kind: OperationRequestKind = operation_request_kind.try_into().unwrap();
+                return Err(APIError::InvalidOperationRequest {
+                    description: format!(
+                        "The multisig contract does not support operation requests of kind {}",
+                        kind
+                    ),
+                });
+            }
+            let max_nonce = DBOperationRequest::max_nonce(&conn, &contract.id).unwrap_or(-1);
+            let allowed_target_addresses = AllowedTargetAddress::get_all(&conn, &contract.id)?
+                .into_iter()
+                .map(|allowed| allowed.address)
+                .collect::<Vec<_>>();
 
-        Ok((contract, max_nonce))
-    })
-    .await?;
+            Ok((contract, max_nonce, allowed_target_addresses))
+        })
+        .await?;
+
+    new_operation_request.validate_target_address(&allowed_target_addresses)?;
+    new_operation_request.validate_batch_mints(server_settings.max_batch_mint_recipients())?;
 
     info!(
-        "User {} submits new operation request on contract {}:\n{:?}",
-        current_user.address, contract.display_name, new_operation_request
+        "[{}] User {} submits new operation request on contract {}:\n{:?}",
+        RequestId::current_label(),
+        current_user.address,
+        contract.display_name,
+        new_operation_request
     );
 
     let conn = pool.get()?;
     let node_url =
         web::block::<_, _, APIError>(move || Ok(NodeEndpoint::get_selected(&conn)?.url)).await?;
 
-    let node_url = &node_url;
-    let mut multisig = multisig::get_multisig(
+    let node_url = contract.node_url_or(&node_url);
+    let block = tezos::block_hash(node_url, tezos::HEAD_BLOCK).await?;
+    let mut multisig = multisig::get_multisig_at_block(
         contract.multisig_pkh.as_ref(),
         contract.kind.try_into()?,
         node_url,
+        &block,
     );
 
-    let nonce = std::cmp::max(multisig.nonce().await?, max_local_nonce + 1);
+    multisig::verify_signers(multisig.as_mut()).await?;
+
+    let safeguard = settings::Contract::find(&contract_settings, &contract).map_or(
+        settings::KeyholderUpdateSafeguard::Warn,
+        settings::Contract::keyholder_update_safeguard,
+    );
+    let risks = new_operation_request.validate_keyholder_update(
+        multisig.approvers().await?,
+        &current_user.address,
+        safeguard,
+    )?;
+    for risk in &risks {
+        warn!(
+            "[{}] Keyholder update by {} on contract {} is risky: {}",
+            RequestId::current_label(),
+            current_user.address,
+            contract.display_name,
+            risk
+        );
+    }
+
+    let nonce = std::cmp::max(multisig.nonce().await?.value(), max_local_nonce + 1);
     let chain_id = tezos::chain_id(node_url).await?;
 
     let amount = new_operation_request
         .amount
         .as_ref()
-        .map(|amount| BigInt::from_str(amount.as_ref()))
+        .map(|amount| amount::to_base_units(amount, 0))
         .map_or(Ok(None), |r| r.map(Some))?;
+    let batch_amounts = new_operation_request
+        .batch_mints
+        .as_ref()
+        .map(|batch_mints| {
+            batch_mints
+                .iter()
+                .map(|mint| amount::to_base_units(&mint.amount, 0))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_or(Ok(None), |r| r.map(Some))?;
+    let xtz_amount = new_operation_request.xtz_amount()?;
 
     let conn = pool.get()?;
     let ledger_hash = new_operation_request.ledger_hash.clone();
+    let actor = current_user.address.clone();
 
-    let (new_db_operation, gatekeeper, proposed_keyholders_public_keys, contract_id) =
+    let (mut new_db_operation, gatekeeper, proposed_keyholders_public_keys, contract_id) =
         web::block::<_, _, APIError>(move || {
             conn.transaction(|| {
                 let user = User::get_active(
@@ -117,6 +180,22 @@ pub async fn operation_request(
                     kind: new_operation_request.kind.into(),
                     chain_id,
                     nonce,
+                    batch_target_addresses: new_operation_request.batch_mints.as_ref().map(
+                        |batch_mints| {
+                            batch_mints
+                                .iter()
+                                .map(|mint| mint.target_address.clone())
+                                .collect()
+                        },
+                    ),
+                    batch_amounts: batch_amounts.map(|amounts| {
+                        amounts
+                            .into_iter()
+                            .map(|amount| BigDecimal::new(amount, 0))
+                            .collect()
+                    }),
+                    xtz_amount: xtz_amount.map(|xtz_amount| xtz_amount.value()),
+                    ledger_blake2b_hash: None,
                 };
 
                 operation.validate()?;
@@ -156,12 +235,21 @@ pub async fn operation_request(
         )
         .await?;
 
-    verify_hash(&signable_message, ledger_hash)?;
+    verify_hash(
+        &signable_message,
+        ledger_hash,
+        server_settings.require_ledger_hash.unwrap_or(false),
+    )?;
+    new_db_operation.ledger_blake2b_hash = Some(signable_message.ledger_blake2b_hash()?);
 
     let conn = pool.get()?;
     let (db_operation_request, proposed_keyholders) = web::block::<_, _, APIError>(move || {
         conn.transaction(|| {
             let operation_request = DBOperationRequest::insert(&conn, &new_db_operation)?;
+            audit::record(
+                &conn,
+                audit::operation_request_created(&actor, &operation_request),
+            )?;
             let operation_request_kind = OperationRequestKind::try_from(operation_request.kind)?;
             let mut proposed_keyholder_users: Option<Vec<User>> = None;
 
@@ -230,7 +318,8 @@ pub async fn operation_request(
     .await?;
 
     info!(
-        "Successfully created operation request: {:?}",
+        "[{}] Successfully created operation request: {:?}",
+        RequestId::current_label(),
         db_operation_request
     );
 
@@ -239,7 +328,9 @@ pub async fn operation_request(
         gatekeeper,
         vec![],
         proposed_keyholders,
-    )?;
+        contract.min_approvals.into(),
+    )?
+    .with_warnings(&risks);
     let operation_request_id = operation_request.id;
 
     let conn = pool.get()?;
@@ -264,15 +355,193 @@ pub async fn operation_request(
     Ok(HttpResponse::Ok().json(operation_request))
 }
 
+/// Seeds the history of a contract which has no operation requests yet. The operation requests
+/// are stored as given, with their state, nonce and approvals, without checking them against the
+/// multisig or notifying anyone.
+pub async fn import(
+    pool: web::Data<DbPool>,
+    operation_request_import: web::Json<OperationRequestImport>,
+    server_settings: web::Data<settings::Server>,
+    session: Session,
+) -> Result<HttpResponse, APIError> {
+    let operation_request_import = operation_request_import.into_inner();
+    let current_user = get_current_user(&session, server_settings.inactivity_timeout_seconds)?;
+    let contract_id = operation_request_import.contract_id;
+    current_user.require_roles(vec![UserKind::Admin], contract_id)?;
+    operation_request_import.validate()?;
+
+    let conn = pool.get()?;
+    let actor = current_user.address.clone();
+    let (imported, max_nonce) =
+        web::block(move || import_history(&conn, operation_request_import, &actor)).await?;
+
+    info!(
+        "[{}] User {} imported {} operation requests up to nonce {} on contract {}",
+        RequestId::current_label(),
+        current_user.address,
+        imported,
+        max_nonce,
+        contract_id
+    );
+
+    Ok(HttpResponse::Ok().json(OperationRequestImportResult {
+        imported,
+        max_nonce,
+    }))
+}
+
+/// Stores the history of its contract, which has no operation requests yet, in one transaction.
+/// Returns the number of imported operation requests and the highest nonce.
+fn import_history(
+    conn: &Conn,
+    operation_request_import: OperationRequestImport,
+    actor: &str,
+) -> Result<(usize, i64), APIError> {
+    let contract_id = operation_request_import.contract_id;
+    let history_hash = hex::encode(canonical_json::hash(&operation_request_import)?);
+    conn.transaction(|| {
+        Contract::get(conn, &contract_id)?;
+        if DBOperationRequest::exists_for_contract(conn, &contract_id)? {
+            return Err(APIError::InvalidOperationState {
+                description: format!(
+                    "contract {} already has operation requests, its history can only be imported before the first one",
+                    contract_id
+                ),
+            });
+        }
+
+        let operation_requests = operation_request_import.operation_requests;
+        let imported = operation_requests.len();
+        for imported_operation_request in operation_requests {
+            import_operation_request(conn, contract_id, imported_operation_request)?;
+        }
+
+        let max_nonce = DBOperationRequest::max_nonce(conn, &contract_id)?;
+        audit::record(
+            conn,
+            audit::operation_requests_imported(
+                actor,
+                contract_id,
+                imported,
+                max_nonce,
+                &history_hash,
+            ),
+        )?;
+
+        Ok((imported, max_nonce))
+    })
+}
+
+fn import_operation_request(
+    conn: &Conn,
+    contract_id: Uuid,
+    imported: ImportedOperationRequest,
+) -> Result<(), APIError> {
+    require_user(
+        conn,
+        imported.gatekeeper_id,
+        UserKind::Gatekeeper,
+        contract_id,
+    )?;
+
+    let amount = imported
+        .amount
+        .as_ref()
+        .map(|amount| amount::to_base_units(amount, 0))
+        .map_or(Ok(None), |r| r.map(Some))?;
+    let batch_amounts = imported
+        .batch_mints
+        .as_ref()
+        .map(|batch_mints| {
+            batch_mints
+                .iter()
+                .map(|mint| amount::to_base_units(&mint.amount, 0))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_or(Ok(None), |r| r.map(Some))?;
+    let xtz_amount = imported.xtz_amount()?;
+
+    let operation_request = DBOperationRequest::insert_historical(
+        conn,
+        &HistoricalOperationRequest {
+            user_id: imported.gatekeeper_id,
+            contract_id,
+            target_address: imported.target_address,
+            amount: amount.map(|amount| BigDecimal::new(amount, 0)),
+            threshold: imported.threshold,
+            kind: imported.kind.into(),
+            chain_id: imported.chain_id,
+            nonce: imported.nonce,
+            state: imported.state.into(),
+            operation_hash: imported.operation_hash,
+            batch_target_addresses: imported.batch_mints.as_ref().map(|batch_mints| {
+                batch_mints
+                    .iter()
+                    .map(|mint| mint.target_address.clone())
+                    .collect()
+            }),
+            batch_amounts: batch_amounts.map(|amounts| {
+                amounts
+                    .into_iter()
+                    .map(|amount| BigDecimal::new(amount, 0))
+                    .collect()
+            }),
+            xtz_amount: xtz_amount.map(|xtz_amount| xtz_amount.value()),
+        },
+    )?;
+
+    for approval in imported.approvals {
+        require_user(
+            conn,
+            approval.keyholder_id,
+            UserKind::Keyholder,
+            contract_id,
+        )?;
+        OperationApproval::insert(
+            conn,
+            NewOperationApproval {
+                keyholder_id: approval.keyholder_id,
+                operation_request_id: operation_request.id,
+                signature: approval.signature,
+                uploaded_by: None,
+                signer_set: None,
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Imported records may only reference users of the contract they are imported into.
+fn require_user(conn: &Conn, id: Uuid, kind: UserKind, contract_id: Uuid) -> Result<(), APIError> {
+    let user = User::get(conn, id)?;
+    if user.contract_id != contract_id || UserKind::try_from(user.kind)? != kind {
+        return Err(APIError::InvalidValue {
+            description: format!(
+                "user {} is not a {:?} of contract {}",
+                id, kind, contract_id
+            ),
+        });
+    }
+
+    Ok(())
+}
+
 fn verify_hash(
     signable_message: &SignableMessage,
     maybe_ledger_hash: Option<String>,
+    require_ledger_hash: bool,
 ) -> Result<(), APIError> {
+    if maybe_ledger_hash.is_none() && require_ledger_hash {
+        return Err(APIError::InvalidOperationRequest {
+            description: "ledger_hash is required".to_string(),
+        });
+    }
     if let Some(ledger_hash) = maybe_ledger_hash {
         let expected_ledger_hash = signable_message.ledger_blake2b_hash()?;
         info!(
-            "Verifying provided ledger hash {} with:\nData: {}\nData type: {}\nExpected ledger hash: {}",
-            ledger_hash, signable_message.michelson_data, signable_message.michelson_type, expected_ledger_hash
+            "[{}] Verifying provided ledger hash {} with:\nData: {}\nData type: {}\nExpected ledger hash: {}",
+            RequestId::current_label(), ledger_hash, signable_message.michelson_data, signable_message.michelson_type, expected_ledger_hash
         );
         if signable_message.ledger_blake2b_hash()? != ledger_hash {
             return Err(APIError::InvalidOperationRequest {
@@ -282,3 +551,98 @@ fn verify_hash(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::{import_history, verify_hash};
+    use crate::{
+        api::models::{error::APIError, operation_request::OperationRequestImport, user::UserKind},
+        db::{
+            models::{operation_request::OperationRequest, user::User},
+            test_connection,
+        },
+        fixtures,
+        tezos::{
+            micheline::{int, types},
+            multisig::SignableMessage,
+        },
+    };
+
+    const LEDGER_HASH: &str = "5YgR7rjfSbSbzGEYhhBG9ENRHhdVSUu2TJ6RyNLawjiv";
+
+    fn signable_message() -> SignableMessage {
+        SignableMessage {
+            packed_data: "050001".into(),
+            michelson_data: int(1),
+            michelson_type: types::nat(),
+        }
+    }
+
+    #[test]
+    fn test_verify_hash_optional() {
+        let message = signable_message();
+
+        assert!(verify_hash(&message, None, false).is_ok());
+        assert!(verify_hash(&message, Some(LEDGER_HASH.into()), false).is_ok());
+        assert!(verify_hash(&message, Some("invalid".into()), false).is_err());
+    }
+
+    #[test]
+    fn test_verify_hash_required() {
+        let message = signable_message();
+
+        assert!(matches!(
+            verify_hash(&message, None, true),
+            Err(APIError::InvalidOperationRequest { .. })
+        ));
+        assert!(verify_hash(&message, Some(LEDGER_HASH.into()), true).is_ok());
+        assert!(verify_hash(&message, Some("invalid".into()), true).is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_import_history() -> Result<(), APIError> {
+        let conn = test_connection();
+        let contract = fixtures::new_contract().save(&conn)?;
+        let users = User::insert(
+            &conn,
+            vec![
+                fixtures::new_user(contract.id, UserKind::Gatekeeper),
+                fixtures::new_user(contract.id, UserKind::Keyholder),
+            ],
+        )?;
+        let injected = |nonce: i64| {
+            serde_json::json!({
+                "gatekeeper_id": users[0].id,
+                "kind": "mint",
+                "target_address": fixtures::ADDRESS,
+                "amount": "100",
+                "threshold": null,
+                "batch_mints": null,
+                "chain_id": fixtures::CHAIN_ID,
+                "nonce": nonce,
+                "state": "injected",
+                "operation_hash": "ooZ1yJqgTXjESAvEJxrN3cGCUeWHJzGjMc7XCZWyTm5rm3ksbkU",
+                "approvals": [{ "keyholder_id": users[1].id, "signature": "edsig" }],
+            })
+        };
+        let history = || -> OperationRequestImport {
+            serde_json::from_value(serde_json::json!({
+                "contract_id": contract.id,
+                "operation_requests": [injected(0), injected(2), injected(1)],
+            }))
+            .unwrap()
+        };
+
+        assert_eq!(import_history(&conn, history(), fixtures::ADDRESS)?, (3, 2));
+        assert_eq!(OperationRequest::max_nonce(&conn, &contract.id)?, 2);
+
+        assert!(matches!(
+            import_history(&conn, history(), fixtures::ADDRESS),
+            Err(APIError::InvalidOperationState { .. })
+        ));
+        assert_eq!(OperationRequest::max_nonce(&conn, &contract.id)?, 2);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,321 @@
+use std::{
+    cell::RefCell,
+    future::{ready, Future, Ready},
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use actix_web::{
+    dev::{Body, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{HeaderName, HeaderValue},
+    Error, FromRequest, HttpMessage, HttpRequest,
+};
+use log::info;
+
+use super::models::error::APIError;
+use crate::{
+    auth,
+    logging::{
+        context::{in_request, RequestId},
+        AccessLogEntry, ACCESS_LOG_TARGET,
+    },
+    settings::LogFormat,
+};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+type Sink = Rc<dyn Fn(String)>;
+
+impl FromRequest for RequestId {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Ok(req
+            .extensions()
+            .get::<RequestId>()
+            .copied()
+            .unwrap_or_else(RequestId::new)))
+    }
+}
+
+/// Assigns a `RequestId` to every request of the wrapped services and makes it available to
+/// handlers (as an extractor), to the code they await (through `RequestId::current`), to error
+/// bodies and to the `X-Request-Id` response header. Each request is logged in `format` once its
+/// response is ready, see `AccessLogEntry`.
+pub struct AssignRequestId {
+    format: LogFormat,
+    sink: Sink,
+}
+
+impl AssignRequestId {
+    pub fn new(format: LogFormat) -> Self {
+        AssignRequestId::with_sink(
+            format,
+            Rc::new(|line| info!(target: ACCESS_LOG_TARGET, "{}", line)),
+        )
+    }
+
+    fn with_sink(format: LogFormat, sink: Sink) -> Self {
+        AssignRequestId { format, sink }
+    }
+}
+
+impl<S> Transform<S> for AssignRequestId
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AssignRequestIdMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AssignRequestIdMiddleware {
+            service: Rc::new(RefCell::new(service)),
+            format: self.format,
+            sink: self.sink.clone(),
+        }))
+    }
+}
+
+pub struct AssignRequestIdMiddleware<S> {
+    service: Rc<RefCell<S>>,
+    format: LogFormat,
+    sink: Sink,
+}
+
+impl<S> Service for AssignRequestIdMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error> + 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<Body>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let started_at = Instant::now();
+        let request_id = RequestId::new();
+        req.extensions_mut().insert(request_id);
+
+        let method = req.method().to_string();
+        let path = req.path().to_owned();
+        let format = self.format;
+        let sink = self.sink.clone();
+        let response = in_request(request_id, self.service.borrow_mut().call(req));
+
+        Box::pin(async move {
+            let mut response = response.await?;
+
+            let error_response = response
+                .response()
+                .error()
+                .and_then(|error| error.as_error::<APIError>())
+                .map(|error| error.error_response_with_request_id(Some(request_id.to_string())));
+            if let Some(error_response) = error_response {
+                response = response.into_response(error_response);
+            }
+
+            response.headers_mut().insert(
+                HeaderName::from_static(REQUEST_ID_HEADER),
+                HeaderValue::from_str(&request_id.to_string())
+                    .expect("uuids are valid header values"),
+            );
+            let entry = AccessLogEntry {
+                request_id: request_id.to_string(),
+                method,
+                path,
+                status: response.status().as_u16(),
+                duration_ms: started_at.elapsed().as_millis() as u64,
+                user_address: auth::current_address(response.request()),
+            };
+            sink(entry.line(format));
+
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, rc::Rc};
+
+    use actix_session::{CookieSession, Session};
+    use actix_web::{test, web, App, HttpResponse};
+    use futures::channel::oneshot;
+    use serde_json::Value;
+
+    use super::{AssignRequestId, REQUEST_ID_HEADER};
+    use crate::{
+        api::models::error::APIError,
+        auth::{set_current_user, SessionUser},
+        logging::context::{in_current_request, RequestId},
+        settings::LogFormat,
+    };
+
+    const ADDRESS: &str = "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT";
+
+    async fn handler(request_id: RequestId) -> HttpResponse {
+        assert_eq!(RequestId::current(), Some(request_id));
+
+        HttpResponse::Ok().body(request_id.to_string())
+    }
+
+    async fn failing_handler() -> Result<HttpResponse, APIError> {
+        Err(APIError::NotFound)
+    }
+
+    async fn sign_in_handler(session: Session) -> HttpResponse {
+        set_current_user(
+            &session,
+            &SessionUser {
+                address: ADDRESS.into(),
+                roles: vec![],
+            },
+        )
+        .unwrap();
+
+        HttpResponse::Ok().finish()
+    }
+
+    async fn spawning_handler(request_id: RequestId) -> HttpResponse {
+        let (sender, receiver) = oneshot::channel();
+        actix_web::rt::spawn(in_current_request(async move {
+            let _ = sender.send(RequestId::current());
+        }));
+
+        assert_eq!(receiver.await.unwrap(), Some(request_id));
+
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_request_id_header_matches_logs() {
+        let lines = Rc::new(RefCell::new(Vec::<String>::new()));
+        let sink_lines = lines.clone();
+        let mut app = test::init_service(
+            App::new()
+                .wrap(AssignRequestId::with_sink(
+                    LogFormat::Text,
+                    Rc::new(move |line| sink_lines.borrow_mut().push(line)),
+                ))
+                .route("/ok", web::get().to(handler)),
+        )
+        .await;
+
+        let response =
+            test::call_service(&mut app, test::TestRequest::get().uri("/ok").to_request()).await;
+        let header = response
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .expect("the request id header is set")
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let body = test::read_body(response).await;
+
+        assert_eq!(body, header.as_bytes());
+        assert_eq!(*lines.borrow(), vec![format!("[{}] GET /ok 200", header)]);
+        assert_eq!(RequestId::current(), None);
+    }
+
+    #[actix_rt::test]
+    async fn test_request_id_in_error_body() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(AssignRequestId::with_sink(
+                    LogFormat::Text,
+                    Rc::new(|_line| {}),
+                ))
+                .route("/missing", web::get().to(failing_handler)),
+        )
+        .await;
+
+        let response = test::call_service(
+            &mut app,
+            test::TestRequest::get().uri("/missing").to_request(),
+        )
+        .await;
+        let header = response
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let body: Value = test::read_body_json(response).await;
+
+        assert_eq!(body["error"], "NotFound");
+        assert_eq!(body["request_id"], header.as_str());
+    }
+
+    #[actix_rt::test]
+    async fn test_json_access_log() {
+        let lines = Rc::new(RefCell::new(Vec::<String>::new()));
+        let sink_lines = lines.clone();
+        let mut app = test::init_service(
+            App::new()
+                .wrap(AssignRequestId::with_sink(
+                    LogFormat::Json,
+                    Rc::new(move |line| sink_lines.borrow_mut().push(line)),
+                ))
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .route("/sign-in", web::post().to(sign_in_handler)),
+        )
+        .await;
+
+        let response = test::call_service(
+            &mut app,
+            test::TestRequest::post().uri("/sign-in").to_request(),
+        )
+        .await;
+        let header = response
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+
+        let lines = lines.borrow();
+        assert_eq!(lines.len(), 1);
+        let line: Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(line["request_id"], header.as_str());
+        assert_eq!(line["method"], "POST");
+        assert_eq!(line["path"], "/sign-in");
+        assert_eq!(line["status"], 200);
+        assert!(line["duration_ms"].is_u64());
+        assert_eq!(line["user_address"], ADDRESS);
+    }
+
+    #[actix_rt::test]
+    async fn test_request_id_in_spawned_task() {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(AssignRequestId::with_sink(
+                    LogFormat::Text,
+                    Rc::new(|_line| {}),
+                ))
+                .route("/spawn", web::get().to(spawning_handler)),
+        )
+        .await;
+
+        let response = test::call_service(
+            &mut app,
+            test::TestRequest::get().uri("/spawn").to_request(),
+        )
+        .await;
+
+        assert_eq!(response.status(), 200);
+    }
+}
@@ -0,0 +1,295 @@
+use std::convert::TryFrom;
+
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{
+    api::models::{
+        api_key::ApiKeyAction, audit_log::AuditEvent, error::APIError,
+        operation_request::OperationRequestKind,
+    },
+    db::models::{
+        api_key::ApiKey,
+        audit_log::{AuditLogEntry, NewAuditLogEntry},
+        operation_approval::OperationApproval,
+        operation_request::{DroppedApprovals, ExpiredOperationRequests, OperationRequest},
+    },
+    Conn,
+};
+
+/// Writes an entry to the audit log. Callers record entries in the transaction of the change
+/// they describe where there is one, so a change is not made without its entry.
+pub fn record(conn: &Conn, entry: NewAuditLogEntry) -> Result<(), APIError> {
+    AuditLogEntry::insert(conn, &entry)?;
+
+    Ok(())
+}
+
+fn entry(
+    event: AuditEvent,
+    actor: Option<&str>,
+    contract_id: Option<Uuid>,
+    resource_id: Option<Uuid>,
+    detail: Value,
+) -> NewAuditLogEntry {
+    NewAuditLogEntry {
+        event: event.into(),
+        actor: actor.map(|actor| actor.to_owned()),
+        contract_id,
+        resource_id,
+        detail: detail.to_string(),
+    }
+}
+
+fn operation_request_detail(operation_request: &OperationRequest) -> Value {
+    json!({
+        "kind": OperationRequestKind::try_from(operation_request.kind).ok(),
+        "nonce": operation_request.nonce,
+        "target_address": operation_request.target_address,
+        "amount": operation_request.amount.as_ref().map(|amount| amount.to_string()),
+        "threshold": operation_request.threshold,
+        "batch_mint_count": operation_request.batch_target_addresses.as_ref().map(Vec::len),
+        "xtz_amount": operation_request.xtz_amount,
+    })
+}
+
+pub fn operation_request_created(
+    actor: &str,
+    operation_request: &OperationRequest,
+) -> NewAuditLogEntry {
+    entry(
+        AuditEvent::OperationRequestCreated,
+        Some(actor),
+        Some(operation_request.contract_id),
+        Some(operation_request.id),
+        operation_request_detail(operation_request),
+    )
+}
+
+pub fn operation_approval_added(
+    actor: &str,
+    operation_request: &OperationRequest,
+    operation_approval: &OperationApproval,
+) -> NewAuditLogEntry {
+    entry(
+        AuditEvent::OperationApprovalAdded,
+        Some(actor),
+        Some(operation_request.contract_id),
+        Some(operation_request.id),
+        json!({
+            "operation_approval_id": operation_approval.id,
+            "keyholder_id": operation_approval.keyholder_id,
+            "uploaded_by": operation_approval.uploaded_by,
+        }),
+    )
+}
+
+pub fn operation_request_approved(
+    actor: &str,
+    operation_request: &OperationRequest,
+    approvals: i64,
+) -> NewAuditLogEntry {
+    entry(
+        AuditEvent::OperationRequestApproved,
+        Some(actor),
+        Some(operation_request.contract_id),
+        Some(operation_request.id),
+        json!({ "approvals": approvals }),
+    )
+}
+
+pub fn operation_request_injected(
+    actor: &str,
+    operation_request: &OperationRequest,
+) -> NewAuditLogEntry {
+    entry(
+        AuditEvent::OperationRequestInjected,
+        Some(actor),
+        Some(operation_request.contract_id),
+        Some(operation_request.id),
+        json!({ "operation_hash": operation_request.operation_hash }),
+    )
+}
+
+pub fn operation_request_deleted(
+    actor: &str,
+    operation_request: &OperationRequest,
+) -> NewAuditLogEntry {
+    entry(
+        AuditEvent::OperationRequestDeleted,
+        Some(actor),
+        Some(operation_request.contract_id),
+        Some(operation_request.id),
+        operation_request_detail(operation_request),
+    )
+}
+
+/// Config syncs are started by the server, so they have no actor.
+pub fn config_synced(config_hash: &str) -> NewAuditLogEntry {
+    entry(
+        AuditEvent::ConfigSynced,
+        None,
+        None,
+        None,
+        json!({ "config_hash": config_hash }),
+    )
+}
+
+pub fn maintenance_mode_changed(
+    actor: &str,
+    enabled: bool,
+    message: Option<&str>,
+) -> NewAuditLogEntry {
+    entry(
+        AuditEvent::MaintenanceModeChanged,
+        Some(actor),
+        None,
+        None,
+        json!({ "enabled": enabled, "message": message }),
+    )
+}
+
+/// `history_hash` is the hex encoded hash of the canonical JSON of the import, to match the entry
+/// with the file it was imported from.
+pub fn operation_requests_imported(
+    actor: &str,
+    contract_id: Uuid,
+    imported: usize,
+    max_nonce: i64,
+    history_hash: &str,
+) -> NewAuditLogEntry {
+    entry(
+        AuditEvent::OperationRequestsImported,
+        Some(actor),
+        Some(contract_id),
+        None,
+        json!({ "imported": imported, "max_nonce": max_nonce, "history_hash": history_hash }),
+    )
+}
+
+/// Approvals of keyholders removed by the injected `update_keyholders` operation request were
+/// dropped from the other operation requests of the contract.
+pub fn approvals_dropped(
+    actor: &str,
+    operation_request: &OperationRequest,
+    dropped: &DroppedApprovals,
+) -> NewAuditLogEntry {
+    entry(
+        AuditEvent::ApprovalsDropped,
+        Some(actor),
+        Some(operation_request.contract_id),
+        Some(operation_request.id),
+        json!({
+            "operation_approval_ids": dropped.approvals,
+            "reopened_operation_request_ids": dropped.reopened,
+        }),
+    )
+}
+
+/// Operation requests of the contract expired, see `operation_request_ttl_seconds`, without an
+/// actor.
+pub fn operation_requests_expired(
+    contract_id: Uuid,
+    expired: &ExpiredOperationRequests,
+) -> NewAuditLogEntry {
+    entry(
+        AuditEvent::OperationRequestsExpired,
+        None,
+        Some(contract_id),
+        None,
+        json!({
+            "expired_operation_request_ids": expired.expired,
+            "renumbered_operation_request_ids": expired
+                .renumbered
+                .iter()
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>(),
+        }),
+    )
+}
+
+/// An admin created an API key. The detail never contains the token.
+pub fn api_key_created(actor: &str, api_key: &ApiKey) -> NewAuditLogEntry {
+    entry(
+        AuditEvent::ApiKeyCreated,
+        Some(actor),
+        Some(api_key.contract_id),
+        Some(api_key.id),
+        json!({
+            "name": api_key.name,
+            "actions": api_key
+                .actions
+                .iter()
+                .map(|action| ApiKeyAction::try_from(*action).ok())
+                .collect::<Vec<_>>(),
+        }),
+    )
+}
+
+pub fn api_key_revoked(actor: &str, api_key: &ApiKey) -> NewAuditLogEntry {
+    entry(
+        AuditEvent::ApiKeyRevoked,
+        Some(actor),
+        Some(api_key.contract_id),
+        Some(api_key.id),
+        json!({ "name": api_key.name }),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+
+    use super::{config_synced, operation_request_created};
+    use crate::{
+        api::models::audit_log::AuditEvent, db::models::operation_request::OperationRequest,
+        fixtures,
+    };
+
+    fn operation_request() -> OperationRequest {
+        OperationRequest {
+            nonce: 7,
+            ..fixtures::operation_request(Uuid::new_v4())
+        }
+    }
+
+    #[test]
+    fn test_operation_request_created() {
+        let operation_request = operation_request();
+        let entry =
+            operation_request_created("tz1gZdWPZAaFoTVfHDJ5zo5BbdqM9F9dYDTt", &operation_request);
+
+        let event: i16 = AuditEvent::OperationRequestCreated.into();
+        assert_eq!(entry.event, event);
+        assert_eq!(
+            entry.actor.as_deref(),
+            Some("tz1gZdWPZAaFoTVfHDJ5zo5BbdqM9F9dYDTt")
+        );
+        assert_eq!(entry.contract_id, Some(operation_request.contract_id));
+        assert_eq!(entry.resource_id, Some(operation_request.id));
+
+        let detail: Value = serde_json::from_str(&entry.detail).unwrap();
+        assert_eq!(
+            detail,
+            json!({
+                "kind": "mint",
+                "nonce": 7,
+                "target_address": "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT",
+                "amount": "1000",
+                "threshold": null,
+                "batch_mint_count": null,
+                "xtz_amount": null,
+            })
+        );
+    }
+
+    #[test]
+    fn test_config_synced_has_no_actor() {
+        let entry = config_synced("abc");
+
+        assert_eq!(entry.actor, None);
+        assert_eq!(entry.contract_id, None);
+        assert_eq!(entry.detail, r#"{"config_hash":"abc"}"#);
+    }
+}
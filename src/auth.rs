@@ -1,13 +1,18 @@
 use std::convert::TryInto;
 
-use actix_session::Session;
-use chrono::Utc;
+use actix_session::{CookieSession, Session, UserSession};
+use actix_web::HttpRequest;
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
-    api::models::{error::APIError, user::UserKind},
-    db::models::user::User,
+    api::{
+        api_keys::guard::ResolvedApiKey,
+        models::{api_key::ApiKeyAction, error::APIError, user::UserKind},
+    },
+    db::models::{api_key::ApiKey, user::User},
+    settings::SessionCookie,
 };
 
 const CURRENT_USER_KEY: &str = "current_user";
@@ -32,6 +37,37 @@ impl SessionUser {
         SessionUser { address, roles }
     }
 
+    /// The user an API key acts as while taking `action`: the key's address with the roles of
+    /// `users`, the active users of its contract with that address, which the action needs. Keys
+    /// not allowed to take the action are rejected.
+    pub fn for_api_key(
+        api_key: &ApiKey,
+        users: &[User],
+        action: ApiKeyAction,
+    ) -> Result<Self, APIError> {
+        let action_value: i16 = action.into();
+        if !api_key.actions.contains(&action_value) {
+            return Err(APIError::Forbidden);
+        }
+
+        let kinds = action.roles();
+        let users = users
+            .iter()
+            .filter(|user| {
+                user.address == api_key.address
+                    && user.contract_id == api_key.contract_id
+                    && user
+                        .kind
+                        .try_into()
+                        .map(|kind| kinds.contains(&kind))
+                        .unwrap_or(false)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        Ok(SessionUser::new(api_key.address.clone(), &users))
+    }
+
     pub fn require_roles(&self, kinds: Vec<UserKind>, contract_id: Uuid) -> Result<(), APIError> {
         let roles: Vec<&SessionUserRole> = self
             .roles
@@ -46,6 +82,33 @@ impl SessionUser {
         Ok(())
     }
 
+    /// Global actions also affect the contracts the user has no role on, so they need one of
+    /// `kinds` on each of `contract_ids`, the ids of all contracts.
+    pub fn require_roles_on_all(
+        &self,
+        kinds: Vec<UserKind>,
+        contract_ids: &[Uuid],
+    ) -> Result<(), APIError> {
+        self.require_one_of_roles(kinds.clone())?;
+        contract_ids
+            .iter()
+            .try_for_each(|contract_id| self.require_roles(kinds.clone(), *contract_id))
+    }
+
+    /// The contracts on which the user has one of `kinds`.
+    pub fn contract_ids_with_roles(&self, kinds: &[UserKind]) -> Vec<Uuid> {
+        let mut contract_ids = self
+            .roles
+            .iter()
+            .filter(|role| kinds.contains(&role.kind))
+            .map(|role| role.contract_id)
+            .collect::<Vec<_>>();
+        contract_ids.sort_unstable();
+        contract_ids.dedup();
+
+        contract_ids
+    }
+
     pub fn require_one_of_roles(&self, kinds: Vec<UserKind>) -> Result<(), APIError> {
         let roles: Vec<&SessionUserRole> = self
             .roles
@@ -97,6 +160,64 @@ pub fn get_current_user(session: &Session, activity_timeout: i64) -> Result<Sess
     Err(APIError::Unauthorized)
 }
 
+/// The current user of an endpoint that also accepts API keys for `action`. A request with an API
+/// key resolved by `ResolveApiKeys` acts as the key, see `SessionUser::for_api_key`, other
+/// requests need a session.
+pub fn get_current_user_or_api_key(
+    req: &HttpRequest,
+    session: &Session,
+    activity_timeout: i64,
+    action: ApiKeyAction,
+) -> Result<SessionUser, APIError> {
+    if let Some(resolved) = req.extensions().get::<ResolvedApiKey>() {
+        return SessionUser::for_api_key(&resolved.api_key, &resolved.users, action);
+    }
+
+    get_current_user(session, activity_timeout)
+}
+
+/// The address a request acts as, for the access log: the address of its API key or of the signed
+/// in user. Unlike `get_current_user`, the session isn't checked for inactivity.
+pub fn current_address(req: &HttpRequest) -> Option<String> {
+    if let Some(resolved) = req.extensions().get::<ResolvedApiKey>() {
+        return Some(resolved.api_key.address.clone());
+    }
+
+    req.get_session()
+        .get::<SessionUser>(CURRENT_USER_KEY)
+        .ok()
+        .flatten()
+        .map(|user| user.address)
+}
+
+/// Marks the session of the current user as active, returns when it expires if it stays inactive.
+/// A session that already expired is cleared and rejected, so it can't be extended.
+pub fn refresh_session(
+    session: &Session,
+    activity_timeout: i64,
+) -> Result<DateTime<Utc>, APIError> {
+    get_current_user(session, activity_timeout)?;
+
+    let now = Utc::now();
+    session
+        .set(LAST_ACTIVITY, now.timestamp())
+        .map_err(|_error| APIError::Internal {
+            description: "failed to refresh session".into(),
+        })?;
+
+    Ok(now + Duration::seconds(activity_timeout))
+}
+
+/// The session middleware, keeping the session in a private cookie encrypted with `key`.
+pub fn session_middleware(key: &[u8], cookie: &SessionCookie) -> CookieSession {
+    CookieSession::private(key)
+        .secure(cookie.secure)
+        .domain(cookie.domain.clone())
+        .path("/")
+        .http_only(true)
+        .same_site(cookie.same_site.same_site())
+}
+
 pub fn set_current_user(session: &Session, user: &SessionUser) -> Result<(), actix_web::Error> {
     session.set(CURRENT_USER_KEY, user)?;
 
@@ -125,3 +246,198 @@ fn get_last_activity_timestamp(session: &Session) -> Result<i64, APIError> {
 
     Ok(Utc::now().timestamp())
 }
+
+#[cfg(test)]
+mod test {
+    use actix_session::{CookieSession, Session};
+    use actix_web::{cookie::SameSite, http::StatusCode, test, web, App, HttpResponse};
+    use chrono::{NaiveDateTime, Utc};
+    use uuid::Uuid;
+
+    use super::{
+        refresh_session, session_middleware, set_current_user, SessionUser, LAST_ACTIVITY,
+    };
+    use crate::{
+        api::models::{api_key::ApiKeyAction, error::APIError, user::UserKind},
+        db::models::{api_key::ApiKey, user::User},
+        fixtures,
+        settings::{CookieSameSite, SessionCookie},
+    };
+
+    const TIMEOUT: i64 = 60;
+
+    async fn refresh_after(inactive_seconds: i64) -> (StatusCode, Option<i64>) {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(CookieSession::signed(&[0; 32]).secure(false))
+                .route(
+                    "/sign-in",
+                    web::post().to(move |session: Session| async move {
+                        let user = SessionUser {
+                            address: "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT".into(),
+                            roles: vec![],
+                        };
+                        set_current_user(&session, &user).unwrap();
+                        session
+                            .set(LAST_ACTIVITY, Utc::now().timestamp() - inactive_seconds)
+                            .unwrap();
+                        HttpResponse::Ok().finish()
+                    }),
+                )
+                .route(
+                    "/refresh",
+                    web::post().to(|session: Session| async move {
+                        refresh_session(&session, TIMEOUT)
+                            .map(|expires_at| HttpResponse::Ok().json(expires_at.timestamp()))
+                    }),
+                ),
+        )
+        .await;
+
+        let response = test::call_service(
+            &mut app,
+            test::TestRequest::post().uri("/sign-in").to_request(),
+        )
+        .await;
+        let cookie = response.response().cookies().next().unwrap().into_owned();
+
+        let response = test::call_service(
+            &mut app,
+            test::TestRequest::post()
+                .uri("/refresh")
+                .cookie(cookie)
+                .to_request(),
+        )
+        .await;
+        let status = response.status();
+        if status != StatusCode::OK {
+            return (status, None);
+        }
+
+        (status, Some(test::read_body_json(response).await))
+    }
+
+    #[actix_rt::test]
+    async fn test_refresh_valid_session() {
+        let before = Utc::now().timestamp();
+        let (status, expires_at) = refresh_after(TIMEOUT / 2).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert!(expires_at.unwrap() >= before + TIMEOUT);
+    }
+
+    #[actix_rt::test]
+    async fn test_refresh_expired_session() {
+        let (status, expires_at) = refresh_after(TIMEOUT * 2).await;
+
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(expires_at, None);
+    }
+
+    #[test]
+    fn test_contract_specific_admin() {
+        let contract_id = Uuid::new_v4();
+        let other_contract_id = Uuid::new_v4();
+        // an admin configured for one contract only, not in the global admins
+        let admin = User {
+            display_name: "Contract admin".into(),
+            ..fixtures::user(contract_id, UserKind::Admin)
+        };
+        let user = SessionUser::new(admin.address.clone(), &vec![admin]);
+
+        assert!(user
+            .require_roles(vec![UserKind::Admin], contract_id)
+            .is_ok());
+        assert!(user
+            .require_roles(vec![UserKind::Admin], other_contract_id)
+            .is_err());
+        assert!(user
+            .require_roles(vec![UserKind::Gatekeeper], contract_id)
+            .is_err());
+    }
+
+    #[test]
+    fn test_api_key_roles() {
+        let contract_id = Uuid::new_v4();
+        let user = |kind: UserKind| User {
+            display_name: "Treasury".into(),
+            ..fixtures::user(contract_id, kind)
+        };
+        let users = vec![user(UserKind::Gatekeeper), user(UserKind::Keyholder)];
+        let api_key = ApiKey {
+            id: Uuid::new_v4(),
+            created_at: NaiveDateTime::from_timestamp(0, 0),
+            contract_id,
+            name: "treasury".into(),
+            address: "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT".into(),
+            token_hash: "".into(),
+            actions: vec![
+                ApiKeyAction::CreateOperationRequest.into(),
+                ApiKeyAction::InjectOperationRequest.into(),
+            ],
+            created_by: "tz1Ts3m2dXTXB66XN7cg5ALiAvzZY6AxrFd9".into(),
+            revoked_at: None,
+        };
+
+        let creator =
+            SessionUser::for_api_key(&api_key, &users, ApiKeyAction::CreateOperationRequest)
+                .unwrap();
+        assert_eq!(creator.address, api_key.address);
+        assert!(creator
+            .require_roles(vec![UserKind::Gatekeeper], contract_id)
+            .is_ok());
+        // update_keyholders needs a keyholder, even though the user of the key is one
+        assert!(creator
+            .require_roles(vec![UserKind::Keyholder], contract_id)
+            .is_err());
+
+        let injector =
+            SessionUser::for_api_key(&api_key, &users, ApiKeyAction::InjectOperationRequest)
+                .unwrap();
+        assert!(injector
+            .require_roles(vec![UserKind::Injector], contract_id)
+            .is_err());
+        assert!(injector
+            .require_roles(vec![UserKind::Gatekeeper, UserKind::Keyholder], contract_id)
+            .is_ok());
+
+        assert!(matches!(
+            SessionUser::for_api_key(&api_key, &users, ApiKeyAction::ApproveOperationRequest),
+            Err(APIError::Forbidden)
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn test_session_middleware_cookie() {
+        let cookie = SessionCookie {
+            domain: "example.com".into(),
+            secure: true,
+            same_site: CookieSameSite::Strict,
+        };
+        let mut app = test::init_service(
+            App::new()
+                .wrap(session_middleware(&[0; 32], &cookie))
+                .route(
+                    "/sign-in",
+                    web::post().to(|session: Session| async move {
+                        session.set(LAST_ACTIVITY, Utc::now().timestamp()).unwrap();
+                        HttpResponse::Ok().finish()
+                    }),
+                ),
+        )
+        .await;
+
+        let response = test::call_service(
+            &mut app,
+            test::TestRequest::post().uri("/sign-in").to_request(),
+        )
+        .await;
+        let session_cookie = response.response().cookies().next().unwrap();
+
+        assert_eq!(session_cookie.domain(), Some("example.com"));
+        assert_eq!(session_cookie.same_site(), Some(SameSite::Strict));
+        assert_eq!(session_cookie.secure(), Some(true));
+        assert_eq!(session_cookie.http_only(), Some(true));
+        assert_eq!(session_cookie.path(), Some("/"));
+    }
+}
@@ -0,0 +1,63 @@
+use super::contract::Contract;
+use crate::db::schema::allowed_target_addresses;
+use crate::Conn;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+#[derive(Queryable, Identifiable, Associations, Clone, Debug)]
+#[table_name = "allowed_target_addresses"]
+#[belongs_to(Contract, foreign_key = "contract_id")]
+pub struct AllowedTargetAddress {
+    pub id: Uuid,
+    pub created_at: NaiveDateTime,
+    pub contract_id: Uuid,
+    pub address: String,
+}
+
+impl AllowedTargetAddress {
+    pub fn get_all(
+        conn: &Conn,
+        contract_id: &Uuid,
+    ) -> Result<Vec<AllowedTargetAddress>, diesel::result::Error> {
+        allowed_target_addresses::table
+            .filter(allowed_target_addresses::dsl::contract_id.eq(contract_id))
+            .order_by(allowed_target_addresses::dsl::address.asc())
+            .load(conn)
+    }
+
+    pub fn insert(
+        conn: &Conn,
+        new_allowed_target_address: &NewAllowedTargetAddress,
+    ) -> Result<AllowedTargetAddress, diesel::result::Error> {
+        diesel::insert_into(allowed_target_addresses::table)
+            .values(new_allowed_target_address)
+            .get_result(conn)
+    }
+
+    pub fn delete(
+        conn: &Conn,
+        contract_id: &Uuid,
+        address: &str,
+    ) -> Result<(), diesel::result::Error> {
+        let deleted = diesel::delete(
+            allowed_target_addresses::table
+                .filter(allowed_target_addresses::dsl::contract_id.eq(contract_id))
+                .filter(allowed_target_addresses::dsl::address.eq(address)),
+        )
+        .execute(conn)?;
+
+        if deleted == 0 {
+            return Err(diesel::result::Error::NotFound);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "allowed_target_addresses"]
+pub struct NewAllowedTargetAddress {
+    pub contract_id: Uuid,
+    pub address: String,
+}
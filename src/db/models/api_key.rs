@@ -0,0 +1,113 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use super::contract::Contract;
+use crate::{
+    api::models::error::APIError,
+    crypto::{generate_random_bytes, generic_hash},
+    db::schema::api_keys,
+    Conn,
+};
+
+const TOKEN_PREFIX: &str = "tzw_";
+
+/// A key for non-interactive access to a single contract, acting as the users of the contract with
+/// `address`. Only the hash of its token is stored.
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(Contract, foreign_key = "contract_id")]
+#[table_name = "api_keys"]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub created_at: NaiveDateTime,
+    pub contract_id: Uuid,
+    pub name: String,
+    pub address: String,
+    pub token_hash: String,
+    pub actions: Vec<i16>,
+    pub created_by: String,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+impl ApiKey {
+    /// A new random token, to be handed out once.
+    pub fn generate_token() -> String {
+        format!("{}{}", TOKEN_PREFIX, hex::encode(generate_random_bytes(32)))
+    }
+
+    pub fn token_hash(token: &str) -> Result<String, APIError> {
+        let hash = generic_hash(token.as_bytes(), 32).map_err(|_| APIError::Internal {
+            description: "failed to hash api key token".into(),
+        })?;
+
+        Ok(hex::encode(hash))
+    }
+
+    pub fn insert(conn: &Conn, new_api_key: &NewApiKey) -> Result<ApiKey, diesel::result::Error> {
+        diesel::insert_into(api_keys::table)
+            .values(new_api_key)
+            .get_result(conn)
+    }
+
+    pub fn get(conn: &Conn, id: &Uuid) -> Result<ApiKey, diesel::result::Error> {
+        api_keys::table.find(id).first(conn)
+    }
+
+    /// The key with the token hashing to `token_hash`, unless it has been revoked.
+    pub fn get_active(
+        conn: &Conn,
+        token_hash: &str,
+    ) -> Result<Option<ApiKey>, diesel::result::Error> {
+        api_keys::table
+            .filter(api_keys::dsl::token_hash.eq(token_hash))
+            .filter(api_keys::dsl::revoked_at.is_null())
+            .first(conn)
+            .optional()
+    }
+
+    pub fn get_list(conn: &Conn, contract_id: &Uuid) -> Result<Vec<ApiKey>, diesel::result::Error> {
+        api_keys::table
+            .filter(api_keys::dsl::contract_id.eq(contract_id))
+            .order_by(api_keys::dsl::created_at)
+            .load(conn)
+    }
+
+    /// Revoked keys are kept, so the audit log can still refer to them.
+    pub fn revoke(conn: &Conn, id: &Uuid) -> Result<ApiKey, diesel::result::Error> {
+        diesel::update(api_keys::table.find(id))
+            .set(api_keys::dsl::revoked_at.eq(diesel::dsl::now.nullable()))
+            .get_result(conn)
+    }
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "api_keys"]
+pub struct NewApiKey {
+    pub contract_id: Uuid,
+    pub name: String,
+    pub address: String,
+    pub token_hash: String,
+    pub actions: Vec<i16>,
+    pub created_by: String,
+}
+
+#[cfg(test)]
+mod test {
+    use super::ApiKey;
+    use crate::api::models::error::APIError;
+
+    #[test]
+    fn test_token_hash() -> Result<(), APIError> {
+        let token = ApiKey::generate_token();
+        assert!(token.starts_with("tzw_"));
+        assert_eq!(token.len(), 4 + 64);
+        assert_ne!(token, ApiKey::generate_token());
+
+        let hash = ApiKey::token_hash(&token)?;
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, ApiKey::token_hash(&token)?);
+        assert_ne!(hash, ApiKey::token_hash(&ApiKey::generate_token())?);
+
+        Ok(())
+    }
+}
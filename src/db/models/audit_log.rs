@@ -0,0 +1,114 @@
+use chrono::NaiveDateTime;
+use diesel::{pg::Pg, prelude::*};
+use uuid::Uuid;
+
+use super::pagination::Paginate;
+use crate::{db::schema::audit_log, Conn};
+
+/// An event recorded for the audit trail. Entries are not tied to the contract or resource they
+/// refer to, so they outlive them.
+#[derive(Queryable, Identifiable, Clone, Debug)]
+#[table_name = "audit_log"]
+pub struct AuditLogEntry {
+    pub id: Uuid,
+    pub created_at: NaiveDateTime,
+    pub event: i16,
+    pub actor: Option<String>,
+    pub contract_id: Option<Uuid>,
+    pub resource_id: Option<Uuid>,
+    pub detail: String,
+}
+
+impl AuditLogEntry {
+    pub fn insert(
+        conn: &Conn,
+        new_entry: &NewAuditLogEntry,
+    ) -> Result<AuditLogEntry, diesel::result::Error> {
+        diesel::insert_into(audit_log::table)
+            .values(new_entry)
+            .get_result(conn)
+    }
+
+    /// The newest entries come first.
+    fn list_query(filter: &AuditLogFilter) -> audit_log::BoxedQuery<'static, Pg> {
+        let mut query = audit_log::table
+            .order_by(audit_log::dsl::created_at.desc())
+            .into_boxed();
+
+        if let Some(contract_ids) = filter.contract_ids.clone() {
+            query = query.filter(audit_log::dsl::contract_id.eq_any(contract_ids));
+        }
+        if let Some(actor) = filter.actor.clone() {
+            query = query.filter(audit_log::dsl::actor.eq(actor));
+        }
+        if let Some(from) = filter.from {
+            query = query.filter(audit_log::dsl::created_at.ge(from));
+        }
+        if let Some(before) = filter.before {
+            query = query.filter(audit_log::dsl::created_at.lt(before));
+        }
+
+        query
+    }
+
+    pub fn get_list(
+        conn: &Conn,
+        filter: &AuditLogFilter,
+        page: i64,
+        limit: i64,
+    ) -> Result<(Vec<AuditLogEntry>, i64), diesel::result::Error> {
+        AuditLogEntry::list_query(filter)
+            .paginate(page)
+            .per_page(limit)
+            .load_and_count_pages::<AuditLogEntry>(conn)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AuditLogFilter {
+    /// The contracts to read the entries of, entries without a contract are left out. All
+    /// entries are read without it.
+    pub contract_ids: Option<Vec<Uuid>>,
+    pub actor: Option<String>,
+    pub from: Option<NaiveDateTime>,
+    pub before: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug, Clone, PartialEq)]
+#[table_name = "audit_log"]
+pub struct NewAuditLogEntry {
+    pub event: i16,
+    pub actor: Option<String>,
+    pub contract_id: Option<Uuid>,
+    pub resource_id: Option<Uuid>,
+    pub detail: String,
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use diesel::{debug_query, pg::Pg};
+    use uuid::Uuid;
+
+    use super::{AuditLogEntry, AuditLogFilter};
+
+    #[test]
+    fn test_list_query() {
+        let sql = debug_query::<Pg, _>(&AuditLogEntry::list_query(&AuditLogFilter::default()))
+            .to_string();
+        assert!(sql.contains(r#"ORDER BY "audit_log"."created_at" DESC"#));
+        assert!(!sql.contains("WHERE"));
+
+        let filter = AuditLogFilter {
+            contract_ids: Some(vec![Uuid::new_v4()]),
+            actor: Some("tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT".into()),
+            from: Some(NaiveDate::from_ymd(2022, 3, 1).and_hms(0, 0, 0)),
+            before: Some(NaiveDate::from_ymd(2022, 4, 1).and_hms(0, 0, 0)),
+        };
+        let sql = debug_query::<Pg, _>(&AuditLogEntry::list_query(&filter)).to_string();
+        assert!(sql.contains(r#""audit_log"."contract_id" = ANY($1)"#));
+        assert!(sql.contains(r#""audit_log"."actor" = $2"#));
+        assert!(sql.contains(r#""audit_log"."created_at" >= $3"#));
+        assert!(sql.contains(r#""audit_log"."created_at" < $4"#));
+    }
+}
@@ -71,4 +71,5 @@ impl AuthenticationChallenge {
 pub struct NewAuthenticationChallenge {
     pub address: String,
     pub challenge: String,
+    pub expires_at: NaiveDateTime,
 }
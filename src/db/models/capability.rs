@@ -13,6 +13,8 @@ pub struct Capability {
     pub created_at: NaiveDateTime,
     pub contract_id: Uuid,
     pub operation_request_kind: i16,
+    pub enabled: bool,
+    pub added_at_runtime: bool,
 }
 
 impl Capability {
@@ -35,6 +37,100 @@ impl Capability {
             .execute(conn)?;
         Ok(())
     }
+
+    /// Enables the capability of `contract_id` for `operation_request_kind`, adding it if the
+    /// contract doesn't have it yet.
+    pub fn enable(
+        conn: &PooledConnection<ConnectionManager<PgConnection>>,
+        contract_id: &Uuid,
+        operation_request_kind: i16,
+    ) -> Result<Capability, diesel::result::Error> {
+        let updated = diesel::update(
+            capabilities::table
+                .filter(capabilities::dsl::contract_id.eq(contract_id))
+                .filter(capabilities::dsl::operation_request_kind.eq(operation_request_kind)),
+        )
+        .set(capabilities::dsl::enabled.eq(true))
+        .get_result(conn)
+        .optional()?;
+
+        if let Some(capability) = updated {
+            return Ok(capability);
+        }
+
+        diesel::insert_into(capabilities::table)
+            .values(&NewCapability {
+                contract_id: *contract_id,
+                operation_request_kind,
+                added_at_runtime: true,
+            })
+            .get_result(conn)
+    }
+
+    /// Disables an enabled capability. Capabilities that were added at runtime are removed, the
+    /// ones from the config are kept as disabled, so the next config sync doesn't enable them again.
+    pub fn disable(
+        conn: &PooledConnection<ConnectionManager<PgConnection>>,
+        contract_id: &Uuid,
+        operation_request_kind: i16,
+    ) -> Result<(), diesel::result::Error> {
+        let capability: Capability = capabilities::table
+            .filter(capabilities::dsl::contract_id.eq(contract_id))
+            .filter(capabilities::dsl::operation_request_kind.eq(operation_request_kind))
+            .filter(capabilities::dsl::enabled.eq(true))
+            .first(conn)?;
+
+        if capability.added_at_runtime {
+            return Capability::delete(conn, vec![capability.id]);
+        }
+
+        diesel::update(capabilities::table.find(capability.id))
+            .set(capabilities::dsl::enabled.eq(false))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    /// Whether operation requests of `operation_request_kind` can be created.
+    pub fn allows(capabilities: &[Capability], operation_request_kind: i16) -> bool {
+        capabilities.iter().any(|capability| {
+            capability.enabled && capability.operation_request_kind == operation_request_kind
+        })
+    }
+
+    /// The changes that bring the stored capabilities of `contract_id` in line with the config:
+    /// configured kinds that aren't stored are added and stored kinds that aren't configured are
+    /// removed. Runtime changes take precedence, so disabled capabilities are not enabled again
+    /// and capabilities added at runtime are kept.
+    pub fn reconcile(
+        contract_id: Uuid,
+        stored: &[Capability],
+        configured: &[i16],
+    ) -> (Vec<NewCapability>, Vec<Uuid>) {
+        let to_add = configured
+            .iter()
+            .filter(|kind| {
+                !stored
+                    .iter()
+                    .any(|capability| capability.operation_request_kind == **kind)
+            })
+            .map(|kind| NewCapability {
+                contract_id,
+                operation_request_kind: *kind,
+                added_at_runtime: false,
+            })
+            .collect::<Vec<_>>();
+        let to_remove = stored
+            .iter()
+            .filter(|capability| {
+                !capability.added_at_runtime
+                    && !configured.contains(&capability.operation_request_kind)
+            })
+            .map(|capability| capability.id)
+            .collect::<Vec<_>>();
+
+        (to_add, to_remove)
+    }
 }
 
 #[derive(Insertable)]
@@ -42,4 +138,67 @@ impl Capability {
 pub struct NewCapability {
     pub contract_id: Uuid,
     pub operation_request_kind: i16,
+    pub added_at_runtime: bool,
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDateTime;
+    use uuid::Uuid;
+
+    use super::Capability;
+
+    const MINT: i16 = 0;
+    const BURN: i16 = 1;
+
+    fn capability(
+        operation_request_kind: i16,
+        enabled: bool,
+        added_at_runtime: bool,
+    ) -> Capability {
+        Capability {
+            id: Uuid::new_v4(),
+            created_at: NaiveDateTime::from_timestamp(0, 0),
+            contract_id: Uuid::new_v4(),
+            operation_request_kind,
+            enabled,
+            added_at_runtime,
+        }
+    }
+
+    #[test]
+    fn test_disabled_kind_is_not_allowed() {
+        let capabilities = vec![
+            capability(MINT, false, false),
+            capability(BURN, true, false),
+        ];
+
+        assert!(!Capability::allows(&capabilities, MINT));
+        assert!(Capability::allows(&capabilities, BURN));
+        assert!(!Capability::allows(&[], BURN));
+    }
+
+    #[test]
+    fn test_reconcile_keeps_runtime_changes() {
+        let disabled = capability(MINT, false, false);
+        let removed_from_config = capability(BURN, true, false);
+        let added_at_runtime = capability(2, true, true);
+        let stored = vec![
+            disabled.clone(),
+            removed_from_config.clone(),
+            added_at_runtime.clone(),
+        ];
+
+        let (to_add, to_remove) = Capability::reconcile(Uuid::new_v4(), &stored, &[MINT, 3]);
+
+        assert_eq!(
+            to_add
+                .iter()
+                .map(|capability| capability.operation_request_kind)
+                .collect::<Vec<_>>(),
+            vec![3]
+        );
+        assert!(to_add.iter().all(|capability| !capability.added_at_runtime));
+        assert_eq!(to_remove, vec![removed_from_config.id]);
+    }
 }
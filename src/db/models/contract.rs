@@ -1,6 +1,6 @@
 use actix_web::web;
 use chrono::NaiveDateTime;
-use diesel::{prelude::*, r2d2::ConnectionManager, r2d2::PooledConnection};
+use diesel::{pg::Pg, prelude::*, r2d2::ConnectionManager, r2d2::PooledConnection};
 use uuid::Uuid;
 
 use super::{
@@ -11,7 +11,7 @@ use super::{
 use crate::api::models::error::APIError;
 use crate::db::schema::contracts;
 use crate::settings;
-use crate::tezos::multisig;
+use crate::tezos::{self, multisig};
 use crate::DbPool;
 
 #[derive(Queryable, Identifiable, Clone, Debug)]
@@ -27,9 +27,18 @@ pub struct Contract {
     pub min_approvals: i32,
     pub symbol: String,
     pub decimals: i32,
+    pub mint_entrypoint: String,
+    pub burn_entrypoint: String,
+    pub node_url: Option<String>,
+    pub sort_order: Option<i32>,
 }
 
 impl Contract {
+    /// The node to use for this contract, `selected_node_url` unless it has its own.
+    pub fn node_url_or<'a>(&'a self, selected_node_url: &'a str) -> &'a str {
+        self.node_url.as_deref().unwrap_or(selected_node_url)
+    }
+
     pub fn get(
         conn: &PooledConnection<ConnectionManager<PgConnection>>,
         id: &Uuid,
@@ -37,6 +46,15 @@ impl Contract {
         contracts::dsl::contracts.find(id).first(conn)
     }
 
+    pub fn get_all_with_ids(
+        conn: &PooledConnection<ConnectionManager<PgConnection>>,
+        ids: Vec<&Uuid>,
+    ) -> Result<Vec<Contract>, diesel::result::Error> {
+        contracts::dsl::contracts
+            .filter(contracts::dsl::id.eq_any(ids))
+            .load(conn)
+    }
+
     pub fn get_with_capabilities(
         conn: &PooledConnection<ConnectionManager<PgConnection>>,
         id: &Uuid,
@@ -56,6 +74,14 @@ impl Contract {
         Ok(contracts)
     }
 
+    pub fn get_all_ids(
+        conn: &PooledConnection<ConnectionManager<PgConnection>>,
+    ) -> Result<Vec<Uuid>, diesel::result::Error> {
+        contracts::dsl::contracts
+            .select(contracts::dsl::id)
+            .load(conn)
+    }
+
     pub fn get_all_with_capabilities(
         conn: &PooledConnection<ConnectionManager<PgConnection>>,
     ) -> Result<Vec<(Contract, Vec<Capability>)>, diesel::result::Error> {
@@ -71,15 +97,22 @@ impl Contract {
         Ok(result)
     }
 
+    /// Contracts with a `sort_order` come first, in that order, the others follow by name.
+    fn list_query() -> contracts::BoxedQuery<'static, Pg> {
+        contracts::dsl::contracts
+            .order_by((
+                contracts::dsl::sort_order.asc().nulls_last(),
+                contracts::dsl::display_name.asc(),
+            ))
+            .into_boxed()
+    }
+
     pub fn get_list(
         conn: &PooledConnection<ConnectionManager<PgConnection>>,
         page: i64,
         limit: i64,
     ) -> Result<(Vec<(Contract, Vec<Capability>)>, i64), diesel::result::Error> {
-        let contracts_query = contracts::dsl::contracts
-            .order_by(contracts::dsl::display_name.asc())
-            .paginate(page)
-            .per_page(limit);
+        let contracts_query = Contract::list_query().paginate(page).per_page(limit);
 
         let (contracts, page_count) = contracts_query.load_and_count_pages::<Contract>(&conn)?;
 
@@ -106,6 +139,7 @@ impl Contract {
             .map(|cap| NewCapability {
                 contract_id: contract.id,
                 operation_request_kind: cap.operation_request_kind.into(),
+                added_at_runtime: false,
             })
             .collect::<Vec<_>>();
         let capabilities = Capability::insert(conn, new_capabilities)?;
@@ -137,6 +171,13 @@ impl Contract {
         contracts: &Vec<settings::Contract>,
         node_url: &str,
     ) -> Result<(), APIError> {
+        for contract in contracts {
+            contract.validate()?;
+        }
+        for contract in contracts {
+            verify_node_chain_id(contract).await?;
+        }
+
         let conn = pool.get()?;
 
         let stored_contracts =
@@ -144,11 +185,7 @@ impl Contract {
         let to_remove: Vec<_> = stored_contracts
             .iter()
             .filter(|(stored_contract, _)| {
-                let found = contracts.iter().find(|contract| {
-                    contract.address == stored_contract.pkh
-                        && contract.multisig == stored_contract.multisig_pkh
-                        && contract.token_id == (stored_contract.token_id as i64)
-                });
+                let found = settings::Contract::find(contracts, stored_contract);
                 return found.is_none();
             })
             .map(|(contract, _)| contract.id.clone())
@@ -157,11 +194,9 @@ impl Contract {
         let new_contracts: Vec<_> = contracts
             .iter()
             .filter(|contract| {
-                let found = stored_contracts.iter().find(|(stored_contract, _)| {
-                    stored_contract.pkh == contract.address
-                        && stored_contract.multisig_pkh == contract.multisig
-                        && (stored_contract.token_id as i64) == contract.token_id
-                });
+                let found = stored_contracts
+                    .iter()
+                    .find(|(stored_contract, _)| contract.matches(stored_contract));
                 return found.is_none();
             })
             .collect();
@@ -169,7 +204,10 @@ impl Contract {
         let mut to_add = Vec::<(NewContract, Vec<settings::Capability>)>::new();
         to_add.reserve(new_contracts.len());
         for contract in new_contracts {
+            let node_url = contract.node_url_or(node_url);
+            verify_multisig_kind(contract, node_url).await?;
             let mut multisig = multisig::get_multisig(&contract.multisig, contract.kind, node_url);
+            multisig::verify_signers(multisig.as_mut()).await?;
             let min_approvals = multisig.min_signatures().await? as i32;
             let new_contract = NewContract {
                 pkh: contract.address.clone(),
@@ -180,6 +218,10 @@ impl Contract {
                 min_approvals,
                 symbol: contract.symbol.clone(),
                 decimals: contract.decimals,
+                mint_entrypoint: contract.mint_entrypoint().into(),
+                burn_entrypoint: contract.burn_entrypoint().into(),
+                node_url: contract.node.as_ref().map(|node| node.url.clone()),
+                sort_order: contract.sort_order,
             };
             to_add.push((new_contract, contract.capabilities.clone()));
         }
@@ -189,65 +231,55 @@ impl Contract {
         let mut capabilities_to_remove = Vec::<Uuid>::new();
         let mut contracts_with_higher_threshold = Vec::<Uuid>::new();
         for contract in contracts {
-            let found = stored_contracts.iter().find(|(stored_contract, _)| {
-                stored_contract.pkh == contract.address
-                    && stored_contract.multisig_pkh == contract.multisig
-                    && (stored_contract.token_id as i64) == contract.token_id
-            });
+            let found = stored_contracts
+                .iter()
+                .find(|(stored_contract, _)| contract.matches(stored_contract));
 
             if let Some((stored_contract, stored_capabilities)) = found {
+                let contract_node_url = contract.node.as_ref().map(|node| node.url.clone());
+                let node_url = contract.node_url_or(node_url);
+                let contract_kind_i16: i16 = contract.kind.into();
+                if stored_contract.kind != contract_kind_i16 {
+                    verify_multisig_kind(contract, node_url).await?;
+                }
                 let mut multisig =
                     multisig::get_multisig(&contract.multisig, contract.kind, node_url);
+                multisig::verify_signers(multisig.as_mut()).await?;
                 let min_approvals = multisig.min_signatures().await? as i32;
-                let contract_kind_i16: i16 = contract.kind.into();
                 let has_changes = stored_contract.display_name != contract.name
                     || stored_contract.kind != contract_kind_i16
                     || stored_contract.min_approvals != min_approvals
-                    || stored_contract.decimals != contract.decimals;
+                    || stored_contract.decimals != contract.decimals
+                    || stored_contract.mint_entrypoint != contract.mint_entrypoint()
+                    || stored_contract.burn_entrypoint != contract.burn_entrypoint()
+                    || stored_contract.node_url != contract_node_url
+                    || stored_contract.sort_order != contract.sort_order;
                 if has_changes {
                     to_update.push(UpdateContract {
                         id: stored_contract.id,
                         kind: contract.kind.into(),
                         display_name: contract.name.clone(),
                         min_approvals,
+                        mint_entrypoint: contract.mint_entrypoint().into(),
+                        burn_entrypoint: contract.burn_entrypoint().into(),
+                        node_url: contract_node_url,
+                        sort_order: contract.sort_order,
                     });
                     if stored_contract.min_approvals < min_approvals {
                         contracts_with_higher_threshold.push(stored_contract.id)
                     }
                 }
-                let mut new_capabilities = contract
+                let configured_capabilities = contract
                     .capabilities
                     .iter()
-                    .filter_map(|cap| {
-                        let operation_request_kind: i16 = cap.operation_request_kind.into();
-                        let found = stored_capabilities.iter().find(|stored_capability| {
-                            stored_capability.operation_request_kind == operation_request_kind
-                        });
-
-                        match found {
-                            Some(_) => None,
-                            None => Some(NewCapability {
-                                contract_id: stored_contract.id,
-                                operation_request_kind,
-                            }),
-                        }
-                    })
-                    .collect::<Vec<_>>();
+                    .map(|cap| cap.operation_request_kind.into())
+                    .collect::<Vec<i16>>();
+                let (mut new_capabilities, mut removed_capabilities) = Capability::reconcile(
+                    stored_contract.id,
+                    stored_capabilities,
+                    &configured_capabilities,
+                );
                 capabilities_to_add.append(&mut new_capabilities);
-                let mut removed_capabilities = stored_capabilities
-                    .iter()
-                    .filter_map(|stored_capability| {
-                        let found = contract.capabilities.iter().find(|cap| {
-                            let operation_request_kind: i16 = cap.operation_request_kind.into();
-                            operation_request_kind == stored_capability.operation_request_kind
-                        });
-
-                        match found {
-                            Some(_) => None,
-                            None => Some(stored_capability.id),
-                        }
-                    })
-                    .collect::<Vec<_>>();
                 capabilities_to_remove.append(&mut removed_capabilities);
             }
         }
@@ -289,6 +321,43 @@ impl Contract {
     }
 }
 
+/// Probes the multisig when a contract is added or its kind changes, so a contract configured
+/// with the wrong kind fails the sync instead of every later operation.
+async fn verify_multisig_kind(
+    contract: &settings::Contract,
+    node_url: &str,
+) -> Result<(), APIError> {
+    multisig::verify_kind(&contract.multisig, contract.kind, node_url)
+        .await
+        .map_err(|error| {
+            let description = format!("contract {} is misconfigured: {}", contract.name, error);
+            log::error!("{}", description);
+
+            APIError::InvalidValue { description }
+        })
+}
+
+/// Makes sure a contract-specific node serves the chain the contract is configured for.
+async fn verify_node_chain_id(contract: &settings::Contract) -> Result<(), APIError> {
+    let node = match contract.node.as_ref() {
+        Some(node) => node,
+        None => return Ok(()),
+    };
+
+    let chain_id = tezos::fetch_chain_id(&node.url).await?;
+    if chain_id != node.chain_id {
+        let description = format!(
+            "contract {} is misconfigured: node {} is on chain {}, expected {}",
+            contract.name, node.url, chain_id, node.chain_id
+        );
+        log::error!("{}", description);
+
+        return Err(APIError::InvalidValue { description });
+    }
+
+    Ok(())
+}
+
 #[derive(Insertable)]
 #[table_name = "contracts"]
 pub struct NewContract {
@@ -300,6 +369,10 @@ pub struct NewContract {
     pub min_approvals: i32,
     pub symbol: String,
     pub decimals: i32,
+    pub mint_entrypoint: String,
+    pub burn_entrypoint: String,
+    pub node_url: Option<String>,
+    pub sort_order: Option<i32>,
 }
 
 impl NewContract {
@@ -315,9 +388,97 @@ impl NewContract {
 
 #[derive(AsChangeset, Identifiable, Debug)]
 #[table_name = "contracts"]
+#[changeset_options(treat_none_as_null = "true")]
 pub struct UpdateContract {
     pub id: Uuid,
     pub kind: i16,
     pub display_name: String,
     pub min_approvals: i32,
+    pub mint_entrypoint: String,
+    pub burn_entrypoint: String,
+    pub node_url: Option<String>,
+    pub sort_order: Option<i32>,
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{web, App, HttpResponse};
+
+    use diesel::{debug_query, pg::Pg};
+
+    use super::{verify_node_chain_id, Contract};
+    use crate::{api::models::error::APIError, fixtures, settings};
+
+    const MAINNET: &str = "NetXdQprcVkpaWU";
+    const TESTNET: &str = "NetXm8tYqnMWky1";
+
+    fn mock_node(chain_id: &'static str) -> actix_web::test::TestServer {
+        actix_web::test::start(move || {
+            App::new().route(
+                "/chains/main/chain_id",
+                web::get().to(move || HttpResponse::Ok().json(chain_id)),
+            )
+        })
+    }
+
+    fn contract(address: &str, node: Option<settings::ContractNode>) -> settings::Contract {
+        settings::Contract {
+            address: address.into(),
+            name: address.into(),
+            node,
+            ..fixtures::contract_settings()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_contracts_on_different_nodes() -> Result<(), APIError> {
+        let mainnet_node = mock_node(MAINNET);
+        let mainnet_url = mainnet_node.url("").trim_end_matches('/').to_owned();
+        let testnet_node = mock_node(TESTNET);
+        let testnet_url = testnet_node.url("").trim_end_matches('/').to_owned();
+
+        let mainnet_contract = contract("KT1S4QXjASmM3ei7a6raRLkr4TbnKu7RuC9X", None);
+        let testnet_contract = contract(
+            "KT192P1oDzf3fNb7BSEiC1d74KvQf4HrivBE",
+            Some(settings::ContractNode {
+                url: testnet_url.clone(),
+                chain_id: TESTNET.into(),
+            }),
+        );
+
+        verify_node_chain_id(&mainnet_contract).await?;
+        verify_node_chain_id(&testnet_contract).await?;
+        assert_eq!(mainnet_contract.node_url_or(&mainnet_url), mainnet_url);
+        assert_eq!(testnet_contract.node_url_or(&mainnet_url), testnet_url);
+
+        Ok(())
+    }
+
+    #[actix_rt::test]
+    async fn test_contract_node_on_wrong_chain() {
+        let testnet_node = mock_node(TESTNET);
+        let testnet_url = testnet_node.url("").trim_end_matches('/').to_owned();
+
+        let contract = contract(
+            "KT192P1oDzf3fNb7BSEiC1d74KvQf4HrivBE",
+            Some(settings::ContractNode {
+                url: testnet_url,
+                chain_id: MAINNET.into(),
+            }),
+        );
+
+        assert!(matches!(
+            verify_node_chain_id(&contract).await,
+            Err(APIError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_list_order() {
+        let sql = debug_query::<Pg, _>(&Contract::list_query()).to_string();
+
+        assert!(sql.contains(
+            r#"ORDER BY "contracts"."sort_order" ASC NULLS LAST, "contracts"."display_name" ASC"#
+        ));
+    }
 }
@@ -0,0 +1,45 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::{db::schema::maintenance_mode, Conn};
+
+/// The persisted maintenance mode. There is at most one row, no row means maintenance mode was
+/// never enabled.
+#[derive(Queryable, Identifiable, Debug, Clone)]
+#[table_name = "maintenance_mode"]
+pub struct MaintenanceMode {
+    pub id: i16,
+    pub enabled: bool,
+    pub message: Option<String>,
+    pub updated_by: Option<String>,
+    pub updated_at: NaiveDateTime,
+}
+
+impl MaintenanceMode {
+    pub fn get(conn: &Conn) -> Result<Option<MaintenanceMode>, diesel::result::Error> {
+        maintenance_mode::table.first(conn).optional()
+    }
+
+    pub fn save(
+        conn: &Conn,
+        enabled: bool,
+        message: Option<&str>,
+        updated_by: &str,
+    ) -> Result<MaintenanceMode, diesel::result::Error> {
+        diesel::insert_into(maintenance_mode::table)
+            .values((
+                maintenance_mode::dsl::enabled.eq(enabled),
+                maintenance_mode::dsl::message.eq(message),
+                maintenance_mode::dsl::updated_by.eq(updated_by),
+            ))
+            .on_conflict(maintenance_mode::dsl::id)
+            .do_update()
+            .set((
+                maintenance_mode::dsl::enabled.eq(enabled),
+                maintenance_mode::dsl::message.eq(message),
+                maintenance_mode::dsl::updated_by.eq(updated_by),
+                maintenance_mode::dsl::updated_at.eq(diesel::dsl::now),
+            ))
+            .get_result(conn)
+    }
+}
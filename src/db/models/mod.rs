@@ -1,9 +1,14 @@
+pub mod allowed_target_address;
+pub mod api_key;
+pub mod audit_log;
 pub mod authentication_challenge;
 pub mod capability;
 pub mod contract;
+pub mod maintenance_mode;
 pub mod node_endpoint;
 pub mod operation_approval;
 pub mod operation_request;
 pub mod pagination;
 pub mod proposed_user;
+pub mod sync_marker;
 pub mod user;
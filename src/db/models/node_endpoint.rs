@@ -1,5 +1,6 @@
+use crate::api::models::error::APIError;
 use crate::db::schema::node_endpoints;
-use crate::settings::TezosNode;
+use crate::settings::{normalize_node_url, TezosNode};
 use crate::Conn;
 use chrono::NaiveDateTime;
 use diesel::dsl::any;
@@ -81,8 +82,19 @@ impl NodeEndpoint {
         node_endpoints::table.load(conn)
     }
 
-    pub fn sync(conn: &Conn, tezos_nodes: &Vec<TezosNode>) -> Result<usize, diesel::result::Error> {
+    /// The URLs of the `tezos_nodes` are normalized before they are compared with the stored ones.
+    pub fn sync(conn: &Conn, tezos_nodes: &Vec<TezosNode>) -> Result<usize, APIError> {
         let stored_endpoints = NodeEndpoint::get_all(conn)?;
+        let tezos_nodes = tezos_nodes
+            .iter()
+            .map(|tezos_node| {
+                NewNodeEndpoint::new(
+                    tezos_node.name.clone(),
+                    &tezos_node.url,
+                    tezos_node.network.clone(),
+                )
+            })
+            .collect::<Result<Vec<_>, APIError>>()?;
 
         let to_remove: Vec<_> = stored_endpoints
             .iter()
@@ -103,12 +115,7 @@ impl NodeEndpoint {
                     .find(|stored_endpoint| tezos_node.url == stored_endpoint.url)
                     .is_none()
             })
-            .map(|tezos_node| NewNodeEndpoint {
-                name: tezos_node.name.clone(),
-                url: tezos_node.url.clone(),
-                network: tezos_node.network.clone(),
-                selected: false,
-            })
+            .cloned()
             .collect();
 
         let to_update: Vec<_> = tezos_nodes
@@ -180,7 +187,7 @@ impl NodeEndpoint {
     }
 }
 
-#[derive(Insertable)]
+#[derive(Insertable, Clone, Debug)]
 #[table_name = "node_endpoints"]
 pub struct NewNodeEndpoint {
     pub name: String,
@@ -189,6 +196,18 @@ pub struct NewNodeEndpoint {
     pub selected: bool,
 }
 
+impl NewNodeEndpoint {
+    /// An unselected node endpoint, with its `url` normalized by `normalize_node_url`.
+    pub fn new(name: String, url: &str, network: String) -> Result<Self, APIError> {
+        Ok(NewNodeEndpoint {
+            name,
+            url: normalize_node_url(url)?,
+            network,
+            selected: false,
+        })
+    }
+}
+
 #[derive(AsChangeset, Identifiable, Debug)]
 #[table_name = "node_endpoints"]
 pub struct UpdateNodeEndpoint {
@@ -197,3 +216,34 @@ pub struct UpdateNodeEndpoint {
     pub network: Option<String>,
     pub selected: Option<bool>,
 }
+
+#[cfg(test)]
+mod test {
+    use super::NewNodeEndpoint;
+    use crate::api::models::error::APIError;
+
+    #[test]
+    fn test_new_node_endpoint_url() -> Result<(), APIError> {
+        let node_endpoint = NewNodeEndpoint::new(
+            "Papers".into(),
+            "https://tezos-node.prod.gke.papers.tech/",
+            "mainnet".into(),
+        )?;
+        assert_eq!(node_endpoint.url, "https://tezos-node.prod.gke.papers.tech");
+        assert!(!node_endpoint.selected);
+
+        for url in &[
+            "ftp://tezos-node.example.com",
+            "tezos-node.example.com",
+            "https://",
+            "",
+        ] {
+            assert!(matches!(
+                NewNodeEndpoint::new("Node".into(), url, "mainnet".into()),
+                Err(APIError::InvalidValue { .. })
+            ));
+        }
+
+        Ok(())
+    }
+}
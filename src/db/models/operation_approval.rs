@@ -1,6 +1,6 @@
 use crate::db::schema::*;
 use crate::{
-    api::models::user::UserState,
+    api::models::{operation_request::OperationRequestState, user::UserState},
     db::models::{operation_request::OperationRequest, user::User},
 };
 use chrono::NaiveDateTime;
@@ -20,6 +20,8 @@ pub struct OperationApproval {
     pub keyholder_id: Uuid,
     pub operation_request_id: Uuid,
     pub signature: String,
+    pub uploaded_by: Option<Uuid>,
+    pub signer_set: Option<Vec<String>>,
 }
 
 impl OperationApproval {
@@ -27,14 +29,30 @@ impl OperationApproval {
         conn: &PooledConnection<ConnectionManager<PgConnection>>,
         operation_request_id: &Uuid,
     ) -> Result<i64, diesel::result::Error> {
-        let count = operation_approvals::dsl::operation_approvals
-            .filter(operation_approvals::dsl::operation_request_id.eq(operation_request_id))
-            .inner_join(users::table)
-            .filter(users::dsl::state.eq::<i16>(UserState::Active.into()))
-            .count()
-            .get_result::<i64>(conn)?;
+        let operation_request = OperationRequest::get(conn, operation_request_id)?;
+        let approvals = operation_request.operation_approvals(conn)?;
+
+        Ok(approvals.len() as i64)
+    }
+
+    /// Whether this approval counts towards the quorum of a request in `state`.
+    ///
+    /// Approvals of injected requests always count. Otherwise an approval counts if its
+    /// keyholder was part of the signer set recorded when the approval was given, so it is
+    /// not dropped when the keyholder is deactivated afterwards. Approvals recorded before
+    /// signer sets were stored fall back to the keyholder's current state.
+    pub fn is_counted(&self, keyholder: &User, state: OperationRequestState) -> bool {
+        if state == OperationRequestState::Injected {
+            return true;
+        }
 
-        Ok(count)
+        match &self.signer_set {
+            Some(signer_set) => signer_set.contains(&keyholder.public_key),
+            None => {
+                let active_state: i16 = UserState::Active.into();
+                keyholder.state == active_state
+            }
+        }
     }
 
     pub fn get(
@@ -64,6 +82,20 @@ impl OperationApproval {
         approvals_query.load_and_count_pages::<(OperationApproval, User)>(conn)
     }
 
+    /// Whether `keyholder_id` has already approved the operation request.
+    pub fn exists(
+        conn: &PooledConnection<ConnectionManager<PgConnection>>,
+        operation_request_id: &Uuid,
+        keyholder_id: &Uuid,
+    ) -> Result<bool, diesel::result::Error> {
+        diesel::select(diesel::dsl::exists(
+            operation_approvals::table
+                .filter(operation_approvals::dsl::operation_request_id.eq(operation_request_id))
+                .filter(operation_approvals::dsl::keyholder_id.eq(keyholder_id)),
+        ))
+        .get_result(conn)
+    }
+
     pub fn insert(
         conn: &PooledConnection<ConnectionManager<PgConnection>>,
         new_operation_approval: NewOperationApproval,
@@ -80,4 +112,128 @@ pub struct NewOperationApproval {
     pub keyholder_id: Uuid,
     pub operation_request_id: Uuid,
     pub signature: String,
+    pub uploaded_by: Option<Uuid>,
+    pub signer_set: Option<Vec<String>>,
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDateTime;
+    use diesel::{prelude::*, result::Error};
+    use uuid::Uuid;
+
+    use super::{NewOperationApproval, OperationApproval};
+    use crate::{
+        api::models::{
+            operation_request::OperationRequestState,
+            user::{UserKind, UserState},
+        },
+        db::{
+            models::{operation_request::OperationRequest, user::User},
+            schema::operation_requests,
+            test_connection,
+        },
+        fixtures::{self, PUBLIC_KEY},
+    };
+
+    fn keyholder(state: UserState) -> User {
+        User {
+            state: state.into(),
+            ..fixtures::keyholder(Uuid::new_v4(), PUBLIC_KEY)
+        }
+    }
+
+    fn approval(keyholder: &User, signer_set: Option<Vec<&str>>) -> OperationApproval {
+        OperationApproval {
+            signer_set: signer_set
+                .map(|signer_set| signer_set.into_iter().map(String::from).collect()),
+            ..fixtures::operation_approval(
+                &fixtures::operation_request(keyholder.contract_id),
+                keyholder,
+            )
+        }
+    }
+
+    #[test]
+    fn test_approval_of_deactivated_keyholder_remains_counted() {
+        let keyholder = keyholder(UserState::Inactive);
+        let approval = approval(
+            &keyholder,
+            Some(vec![
+                PUBLIC_KEY,
+                "edpktfkToequZjyn3jz3GJobiYApkc5q4xnJiksStYbZkznUdsxDUw",
+            ]),
+        );
+
+        assert!(approval.is_counted(&keyholder, OperationRequestState::Open));
+        assert!(approval.is_counted(&keyholder, OperationRequestState::Approved));
+        assert!(approval.is_counted(&keyholder, OperationRequestState::Injected));
+    }
+
+    #[test]
+    fn test_approval_outside_of_signer_set_is_not_counted() {
+        let keyholder = keyholder(UserState::Active);
+        let approval = approval(
+            &keyholder,
+            Some(vec![
+                "edpktfkToequZjyn3jz3GJobiYApkc5q4xnJiksStYbZkznUdsxDUw",
+            ]),
+        );
+
+        assert!(!approval.is_counted(&keyholder, OperationRequestState::Approved));
+        assert!(approval.is_counted(&keyholder, OperationRequestState::Injected));
+    }
+
+    #[test]
+    fn test_approval_without_signer_set_uses_current_state() {
+        let active = keyholder(UserState::Active);
+        assert!(approval(&active, None).is_counted(&active, OperationRequestState::Approved));
+
+        let inactive = keyholder(UserState::Inactive);
+        let legacy_approval = approval(&inactive, None);
+        assert!(!legacy_approval.is_counted(&inactive, OperationRequestState::Open));
+        assert!(!legacy_approval.is_counted(&inactive, OperationRequestState::Approved));
+        assert!(legacy_approval.is_counted(&inactive, OperationRequestState::Injected));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_insert_touches_operation_request() -> Result<(), Error> {
+        let conn = test_connection();
+        let contract = fixtures::new_contract().save(&conn)?;
+        let keyholder = User::insert(
+            &conn,
+            vec![fixtures::new_user(contract.id, UserKind::Keyholder)],
+        )?
+        .remove(0);
+        let operation_request = OperationRequest::insert(
+            &conn,
+            &fixtures::new_operation_request(contract.id, keyholder.id),
+        )?;
+
+        // `CURRENT_TIMESTAMP` is the start of the test transaction, so the request is backdated to
+        // see the trigger move it forward
+        let long_ago = NaiveDateTime::from_timestamp(0, 0);
+        diesel::update(operation_requests::table.find(operation_request.id))
+            .set(operation_requests::updated_at.eq(long_ago))
+            .execute(&conn)?;
+
+        OperationApproval::insert(
+            &conn,
+            NewOperationApproval {
+                keyholder_id: keyholder.id,
+                operation_request_id: operation_request.id,
+                signature: "edsig".into(),
+                uploaded_by: None,
+                signer_set: None,
+            },
+        )?;
+
+        let touched: OperationRequest = operation_requests::table
+            .find(operation_request.id)
+            .first(&conn)?;
+        assert!(touched.updated_at > long_ago);
+
+        Ok(())
+    }
 }
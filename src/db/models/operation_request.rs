@@ -1,7 +1,10 @@
-use std::convert::TryInto;
+use std::{
+    convert::{TryFrom, TryInto},
+    time::Duration,
+};
 
 use bigdecimal::BigDecimal;
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, Utc};
 use diesel::{prelude::*, r2d2::ConnectionManager, r2d2::PooledConnection};
 use uuid::Uuid;
 
@@ -10,9 +13,16 @@ use crate::{
     db::models::{contract::Contract, operation_approval::OperationApproval, user::User},
 };
 use crate::{
-    api::models::{operation_request::OperationRequestKind, user::UserState},
-    db::schema::{contracts, operation_requests, proposed_users, users},
-    tezos::TzError,
+    api::models::{
+        common::SortDirection, operation_request::OperationRequestKind, user::UserState,
+    },
+    db::schema::{contracts, operation_approvals, operation_requests, proposed_users, users},
+    tezos::{
+        coding::{validate_address, validate_contract},
+        mutez::Mutez,
+        nonce::MultisigNonce,
+        TzError,
+    },
 };
 
 use super::{pagination::Paginate, proposed_user::ProposedUser};
@@ -34,9 +44,45 @@ pub struct OperationRequest {
     pub nonce: i64,
     pub state: i16,
     pub operation_hash: Option<String>,
+    pub confirmation_failed: bool,
+    pub batch_target_addresses: Option<Vec<String>>,
+    pub batch_amounts: Option<Vec<BigDecimal>>,
+    pub xtz_amount: Option<i64>,
+    pub ledger_blake2b_hash: Option<String>,
+}
+
+/// A recipient of a batch mint, stored position-wise in `batch_target_addresses` and
+/// `batch_amounts`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchMint {
+    pub target_address: String,
+    pub amount: BigDecimal,
+}
+
+fn batch_mints(
+    target_addresses: &Option<Vec<String>>,
+    amounts: &Option<Vec<BigDecimal>>,
+) -> Option<Vec<BatchMint>> {
+    match (target_addresses, amounts) {
+        (Some(target_addresses), Some(amounts)) => Some(
+            target_addresses
+                .iter()
+                .zip(amounts)
+                .map(|(target_address, amount)| BatchMint {
+                    target_address: target_address.clone(),
+                    amount: amount.clone(),
+                })
+                .collect(),
+        ),
+        _ => None,
+    }
 }
 
 impl OperationRequest {
+    pub fn batch_mints(&self) -> Option<Vec<BatchMint>> {
+        batch_mints(&self.batch_target_addresses, &self.batch_amounts)
+    }
+
     pub fn get(
         conn: &PooledConnection<ConnectionManager<PgConnection>>,
         id: &Uuid,
@@ -82,17 +128,79 @@ impl OperationRequest {
         Ok(())
     }
 
+    /// Marks the operation request as injected and renumbers the other operation requests of the
+    /// contract whose nonce can't be used anymore, see `stale_nonces`, clearing their
+    /// `ledger_blake2b_hash` as the message to sign changes with the nonce. `multisig_nonce` is
+    /// the nonce of the multisig contract before the injection is included.
     pub fn mark_injected(
         conn: &PooledConnection<ConnectionManager<PgConnection>>,
         id: &Uuid,
         operation_hash: Option<String>,
+        multisig_nonce: MultisigNonce,
     ) -> Result<OperationRequest, diesel::result::Error> {
-        diesel::update(operation_requests::table.find(id))
-            .set((
-                operation_requests::dsl::state.eq::<i16>(OperationRequestState::Injected.into()),
-                operation_requests::dsl::operation_hash.eq(operation_hash),
-            ))
-            .get_result(conn)
+        conn.transaction(|| {
+            let injected: OperationRequest = diesel::update(operation_requests::table.find(id))
+                .set((
+                    operation_requests::dsl::state
+                        .eq::<i16>(OperationRequestState::Injected.into()),
+                    operation_requests::dsl::operation_hash.eq(operation_hash),
+                ))
+                .get_result(conn)?;
+
+            let operation_requests = operation_requests::table
+                .filter(operation_requests::dsl::contract_id.eq(injected.contract_id))
+                .load::<OperationRequest>(conn)?;
+            let renumbered = stale_nonces(&operation_requests, &injected, multisig_nonce.value());
+            for (id, nonce) in renumbered.iter() {
+                diesel::update(operation_requests::table.find(id))
+                    .set((
+                        operation_requests::dsl::nonce.eq(*nonce),
+                        operation_requests::dsl::state
+                            .eq::<i16>(OperationRequestState::Open.into()),
+                        operation_requests::dsl::ledger_blake2b_hash.eq(None::<String>),
+                    ))
+                    .execute(conn)?;
+            }
+            if !renumbered.is_empty() {
+                let ids = renumbered.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+                diesel::delete(
+                    operation_approvals::table
+                        .filter(operation_approvals::dsl::operation_request_id.eq_any(ids)),
+                )
+                .execute(conn)?;
+            }
+
+            Ok(injected)
+        })
+    }
+
+    /// Stores the hash of the message to sign, once it has been recomputed after the nonce changed.
+    pub fn set_ledger_blake2b_hash(
+        conn: &PooledConnection<ConnectionManager<PgConnection>>,
+        id: &Uuid,
+        nonce: i64,
+        ledger_blake2b_hash: &str,
+    ) -> Result<(), diesel::result::Error> {
+        let _result = diesel::update(
+            operation_requests::table
+                .find(id)
+                .filter(operation_requests::dsl::nonce.eq(nonce)),
+        )
+        .set(operation_requests::dsl::ledger_blake2b_hash.eq(ledger_blake2b_hash))
+        .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn mark_confirmation_failed(
+        conn: &PooledConnection<ConnectionManager<PgConnection>>,
+        id: &Uuid,
+    ) -> Result<(), diesel::result::Error> {
+        let _result = diesel::update(operation_requests::table.find(id))
+            .set(operation_requests::dsl::confirmation_failed.eq(true))
+            .execute(conn)?;
+
+        Ok(())
     }
 
     pub fn max_nonce(
@@ -101,26 +209,44 @@ impl OperationRequest {
     ) -> Result<i64, diesel::result::Error> {
         let op: OperationRequest = operation_requests::table
             .filter(operation_requests::dsl::contract_id.eq(contract_id))
+            .filter(operation_requests::dsl::state.ne::<i16>(OperationRequestState::Expired.into()))
             .order_by(operation_requests::dsl::nonce.desc())
             .first(conn)?;
 
         Ok(op.nonce as i64)
     }
 
+    /// Open operation requests on the keyholder's contract that the keyholder hasn't approved yet.
+    pub fn get_pending_for_keyholder(
+        conn: &PooledConnection<ConnectionManager<PgConnection>>,
+        keyholder: &User,
+    ) -> Result<Vec<OperationRequest>, diesel::result::Error> {
+        let approved: Vec<Uuid> = operation_approvals::table
+            .filter(operation_approvals::dsl::keyholder_id.eq(keyholder.id))
+            .select(operation_approvals::dsl::operation_request_id)
+            .load(conn)?;
+
+        operation_requests::table
+            .filter(operation_requests::dsl::contract_id.eq(keyholder.contract_id))
+            .filter(operation_requests::dsl::state.eq::<i16>(OperationRequestState::Open.into()))
+            .filter(operation_requests::dsl::id.ne_all(approved))
+            .order_by(operation_requests::dsl::nonce.asc())
+            .load(conn)
+    }
+
     pub fn operation_approvals(
         &self,
         conn: &PooledConnection<ConnectionManager<PgConnection>>,
     ) -> Result<Vec<(OperationApproval, User)>, diesel::result::Error> {
-        let mut query = OperationApproval::belonging_to(self)
+        let state: OperationRequestState = self.state.try_into().expect("state needs to be valid");
+        let approvals = OperationApproval::belonging_to(self)
             .inner_join(users::table)
-            .into_boxed();
-
-        let injected_state: i16 = OperationRequestState::Injected.into();
-        if self.state != injected_state {
-            query = query.filter(users::dsl::state.eq::<i16>(UserState::Active.into()))
-        }
+            .load::<(OperationApproval, User)>(conn)?;
 
-        query.load::<(OperationApproval, User)>(conn)
+        Ok(approvals
+            .into_iter()
+            .filter(|(approval, keyholder)| approval.is_counted(keyholder, state))
+            .collect())
     }
 
     pub fn proposed_keyholders(
@@ -152,22 +278,25 @@ impl OperationRequest {
             Self::delete(conn, &self.id)?;
 
             let injected_state: i16 = OperationRequestState::Injected.into();
-            if self.state == injected_state {
+            let expired_state: i16 = OperationRequestState::Expired.into();
+            if self.state == injected_state || self.state == expired_state {
                 return Ok(());
             }
 
+            let final_states: Vec<i16> = vec![
+                OperationRequestState::Injected.into(),
+                OperationRequestState::Expired.into(),
+            ];
             let updated_operation_requests: Vec<OperationRequest> = diesel::update(
                 operation_requests::table
                     .filter(operation_requests::dsl::nonce.gt(self.nonce))
                     .filter(operation_requests::dsl::contract_id.eq(self.contract_id))
-                    .filter(
-                        operation_requests::dsl::state
-                            .ne::<i16>(OperationRequestState::Injected.into()),
-                    ),
+                    .filter(operation_requests::dsl::state.ne_all(final_states)),
             )
             .set((
                 operation_requests::dsl::nonce.eq(operation_requests::dsl::nonce - 1),
                 operation_requests::dsl::state.eq::<i16>(OperationRequestState::Open.into()),
+                operation_requests::dsl::ledger_blake2b_hash.eq(None::<String>),
             ))
             .get_results(conn)?;
 
@@ -183,6 +312,64 @@ impl OperationRequest {
         Ok(())
     }
 
+    /// Marks the open and approved operation requests of a contract created more than `ttl` ago
+    /// as expired. Their nonces are given up, so the operation requests after them move down like
+    /// in `delete_and_fix_next_nonces`, see `expired_nonces`.
+    pub fn expire_stale(
+        conn: &PooledConnection<ConnectionManager<PgConnection>>,
+        contract_id: &Uuid,
+        ttl: Duration,
+    ) -> Result<ExpiredOperationRequests, diesel::result::Error> {
+        let created_before = match chrono::Duration::from_std(ttl)
+            .ok()
+            .and_then(|ttl| Utc::now().naive_utc().checked_sub_signed(ttl))
+        {
+            Some(created_before) => created_before,
+            None => return Ok(ExpiredOperationRequests::default()),
+        };
+
+        conn.transaction(|| {
+            let operation_requests = operation_requests::table
+                .filter(operation_requests::dsl::contract_id.eq(contract_id))
+                .load::<OperationRequest>(conn)?;
+            let expired = expired_nonces(&operation_requests, created_before);
+            if expired.expired.is_empty() {
+                return Ok(expired);
+            }
+
+            diesel::update(
+                operation_requests::table
+                    .filter(operation_requests::dsl::id.eq_any(expired.expired.clone())),
+            )
+            .set(operation_requests::dsl::state.eq::<i16>(OperationRequestState::Expired.into()))
+            .execute(conn)?;
+            for (id, nonce) in expired.renumbered.iter() {
+                diesel::update(operation_requests::table.find(id))
+                    .set((
+                        operation_requests::dsl::nonce.eq(*nonce),
+                        operation_requests::dsl::state
+                            .eq::<i16>(OperationRequestState::Open.into()),
+                        operation_requests::dsl::ledger_blake2b_hash.eq(None::<String>),
+                    ))
+                    .execute(conn)?;
+            }
+            if !expired.renumbered.is_empty() {
+                let ids = expired
+                    .renumbered
+                    .iter()
+                    .map(|(id, _)| *id)
+                    .collect::<Vec<_>>();
+                diesel::delete(
+                    operation_approvals::table
+                        .filter(operation_approvals::dsl::operation_request_id.eq_any(ids)),
+                )
+                .execute(conn)?;
+            }
+
+            Ok(expired)
+        })
+    }
+
     pub fn fix_approved_state(
         conn: &PooledConnection<ConnectionManager<PgConnection>>,
         contract_id: &Uuid,
@@ -214,6 +401,56 @@ impl OperationRequest {
         Ok(())
     }
 
+    /// Removes the approvals of the operation requests of a contract that haven't been injected
+    /// given by keyholders who are not part of `keyholders`, the public keys of the new signer set,
+    /// and re-opens the approved ones whose remaining approvals don't reach `min_approvals`, the
+    /// threshold of the new signer set.
+    pub fn drop_removed_keyholder_approvals(
+        conn: &PooledConnection<ConnectionManager<PgConnection>>,
+        contract_id: &Uuid,
+        keyholders: &[String],
+        min_approvals: i64,
+    ) -> Result<DroppedApprovals, diesel::result::Error> {
+        conn.transaction(|| {
+            let pending_states: Vec<i16> = vec![
+                OperationRequestState::Open.into(),
+                OperationRequestState::Approved.into(),
+            ];
+            let operation_requests = operation_requests::table
+                .filter(operation_requests::dsl::contract_id.eq(contract_id))
+                .filter(operation_requests::dsl::state.eq_any(pending_states))
+                .load::<OperationRequest>(conn)?;
+            let approvals = OperationApproval::belonging_to(&operation_requests)
+                .inner_join(users::table)
+                .load::<(OperationApproval, User)>(conn)?
+                .grouped_by(&operation_requests);
+            let operation_requests = operation_requests
+                .into_iter()
+                .zip(approvals)
+                .collect::<Vec<_>>();
+
+            let dropped =
+                removed_keyholder_approvals(&operation_requests, keyholders, min_approvals);
+            if !dropped.approvals.is_empty() {
+                diesel::delete(
+                    operation_approvals::table
+                        .filter(operation_approvals::dsl::id.eq_any(dropped.approvals.clone())),
+                )
+                .execute(conn)?;
+            }
+            if !dropped.reopened.is_empty() {
+                diesel::update(
+                    operation_requests::table
+                        .filter(operation_requests::dsl::id.eq_any(dropped.reopened.clone())),
+                )
+                .set(operation_requests::dsl::state.eq::<i16>(OperationRequestState::Open.into()))
+                .execute(conn)?;
+            }
+
+            Ok(dropped)
+        })
+    }
+
     pub fn insert(
         conn: &PooledConnection<ConnectionManager<PgConnection>>,
         new_operation_request: &NewOperationRequest,
@@ -223,11 +460,29 @@ impl OperationRequest {
             .get_result(conn)
     }
 
+    pub fn insert_historical(
+        conn: &PooledConnection<ConnectionManager<PgConnection>>,
+        historical_operation_request: &HistoricalOperationRequest,
+    ) -> Result<OperationRequest, diesel::result::Error> {
+        diesel::insert_into(operation_requests::table)
+            .values(historical_operation_request)
+            .get_result(conn)
+    }
+
+    pub fn exists_for_contract(
+        conn: &PooledConnection<ConnectionManager<PgConnection>>,
+        contract_id: &Uuid,
+    ) -> Result<bool, diesel::result::Error> {
+        diesel::select(diesel::dsl::exists(
+            operation_requests::table.filter(operation_requests::dsl::contract_id.eq(contract_id)),
+        ))
+        .get_result(conn)
+    }
+
     pub fn get_list(
         conn: &PooledConnection<ConnectionManager<PgConnection>>,
-        kind: OperationRequestKind,
-        contract_id: Uuid,
-        state: Option<OperationRequestState>,
+        filter: OperationRequestFilter,
+        sort_direction: SortDirection,
         page: i64,
         limit: i64,
     ) -> Result<
@@ -243,16 +498,34 @@ impl OperationRequest {
         diesel::result::Error,
     > {
         let mut query = operation_requests::table
-            .filter(operation_requests::dsl::kind.eq::<i16>(kind.into()))
-            .filter(operation_requests::dsl::contract_id.eq(contract_id))
-            .order_by(operation_requests::dsl::created_at)
+            .filter(operation_requests::dsl::contract_id.eq_any(filter.contract_ids))
             .inner_join(users::table)
             .into_boxed();
 
-        if let Some(state) = state {
+        if let Some(kind) = filter.kind {
+            query = query.filter(operation_requests::dsl::kind.eq::<i16>(kind.into()));
+        }
+
+        query = match sort_direction {
+            SortDirection::Asc => query.order_by(operation_requests::dsl::created_at.asc()),
+            SortDirection::Desc => query.order_by(operation_requests::dsl::created_at.desc()),
+        };
+
+        if let Some(state) = filter.state {
             query = query.filter(operation_requests::dsl::state.eq::<i16>(state.into()));
         }
 
+        match filter.target_address {
+            Some(TargetAddressFilter::Exact(address)) => {
+                query = query.filter(operation_requests::dsl::target_address.eq(address));
+            }
+            Some(TargetAddressFilter::Prefix(prefix)) => {
+                query = query
+                    .filter(operation_requests::dsl::target_address.like(format!("{}%", prefix)));
+            }
+            None => {}
+        }
+
         let query = query.paginate(page).per_page(limit);
 
         let (result, page_count) = query.load_and_count_pages::<(OperationRequest, User)>(&conn)?;
@@ -303,7 +576,11 @@ impl OperationRequest {
                 .collect::<Vec<_>>();
 
         let mut proposed_keyholders: Option<Vec<Vec<(ProposedUser, User)>>> = None;
-        if kind == OperationRequestKind::UpdateKeyholders {
+        let update_keyholders_kind: i16 = OperationRequestKind::UpdateKeyholders.into();
+        if filter
+            .kind
+            .map_or(true, |kind| kind == OperationRequestKind::UpdateKeyholders)
+        {
             let proposed_users: Vec<ProposedUser> =
                 ProposedUser::belonging_to(&operation_requests).load(conn)?;
 
@@ -352,6 +629,9 @@ impl OperationRequest {
                 .into_iter()
                 .zip(proposed_keyholders)
                 .map(|(operation_request, mut proposed)| {
+                    if operation_request.0.kind != update_keyholders_kind {
+                        return operation_request;
+                    }
                     proposed.sort_unstable_by(|a, b| a.0.position.cmp(&b.0.position));
                     let proposed_keyholders = proposed
                         .into_iter()
@@ -377,6 +657,169 @@ impl OperationRequest {
         diesel::delete(operation_requests::table.find(id)).execute(conn)?;
         Ok(())
     }
+
+    /// Deletes the operation requests of the contract in `state` created before `before`, together
+    /// with their approvals, and returns how many operation requests were deleted.
+    pub fn delete_history(
+        conn: &PooledConnection<ConnectionManager<PgConnection>>,
+        contract_id: &Uuid,
+        state: OperationRequestState,
+        before: NaiveDateTime,
+    ) -> Result<usize, diesel::result::Error> {
+        conn.transaction(|| {
+            let ids: Vec<Uuid> = operation_requests::table
+                .filter(operation_requests::dsl::contract_id.eq(contract_id))
+                .filter(operation_requests::dsl::state.eq::<i16>(state.into()))
+                .filter(operation_requests::dsl::created_at.lt(before))
+                .select(operation_requests::dsl::id)
+                .load(conn)?;
+
+            diesel::delete(
+                operation_approvals::table
+                    .filter(operation_approvals::dsl::operation_request_id.eq_any(ids.clone())),
+            )
+            .execute(conn)?;
+
+            diesel::delete(
+                operation_requests::table.filter(operation_requests::dsl::id.eq_any(ids)),
+            )
+            .execute(conn)
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TargetAddressFilter {
+    Exact(String),
+    Prefix(String),
+}
+
+/// The approvals removed from the operation requests of a contract because their keyholder left
+/// the signer set, and the approved operation requests re-opened as they lost their quorum.
+#[derive(Debug, Default, PartialEq)]
+pub struct DroppedApprovals {
+    pub approvals: Vec<Uuid>,
+    pub reopened: Vec<Uuid>,
+}
+
+/// The approvals of `operation_requests` given by keyholders who are not part of `keyholders`, and
+/// the approved operation requests whose remaining counted approvals don't reach `min_approvals`,
+/// the threshold of the new signer set.
+fn removed_keyholder_approvals(
+    operation_requests: &[(OperationRequest, Vec<(OperationApproval, User)>)],
+    keyholders: &[String],
+    min_approvals: i64,
+) -> DroppedApprovals {
+    let approved: i16 = OperationRequestState::Approved.into();
+    let mut dropped = DroppedApprovals::default();
+    for (operation_request, approvals) in operation_requests {
+        let (removed, kept): (Vec<_>, Vec<_>) = approvals
+            .iter()
+            .partition(|(_, keyholder)| !keyholders.contains(&keyholder.public_key));
+        dropped
+            .approvals
+            .extend(removed.iter().map(|(approval, _)| approval.id));
+
+        if operation_request.state != approved {
+            continue;
+        }
+        let counted = kept
+            .iter()
+            .filter(|(approval, keyholder)| {
+                approval.is_counted(keyholder, OperationRequestState::Approved)
+            })
+            .count() as i64;
+        if counted < min_approvals {
+            dropped.reopened.push(operation_request.id);
+        }
+    }
+
+    dropped
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ExpiredOperationRequests {
+    pub expired: Vec<Uuid>,
+    pub renumbered: Vec<(Uuid, i64)>,
+}
+
+/// The open and approved operation requests created before `created_before`, and the remaining
+/// operation requests that haven't been injected or expired with the nonces they move to. Each of
+/// them moves down by the number of expired nonces below its own, in ascending order of nonce, so
+/// no two of them share a nonce at any time.
+fn expired_nonces(
+    operation_requests: &[OperationRequest],
+    created_before: NaiveDateTime,
+) -> ExpiredOperationRequests {
+    let pending_states: Vec<i16> = vec![
+        OperationRequestState::Open.into(),
+        OperationRequestState::Approved.into(),
+    ];
+    let mut pending = operation_requests
+        .iter()
+        .filter(|operation_request| pending_states.contains(&operation_request.state))
+        .collect::<Vec<_>>();
+    pending.sort_by_key(|operation_request| operation_request.nonce);
+
+    let mut result = ExpiredOperationRequests::default();
+    for operation_request in pending {
+        if operation_request.created_at < created_before {
+            result.expired.push(operation_request.id);
+        } else if !result.expired.is_empty() {
+            result.renumbered.push((
+                operation_request.id,
+                operation_request.nonce - result.expired.len() as i64,
+            ));
+        }
+    }
+
+    result
+}
+
+/// The operation requests that haven't been injected but whose nonce is lower than the next nonce
+/// of the multisig after `injected`, with the nonces they move to. Their approvals were signed
+/// for a nonce the multisig won't accept anymore. They keep their order and move behind the other
+/// operation requests, as nonces are unique per contract.
+fn stale_nonces(
+    operation_requests: &[OperationRequest],
+    injected: &OperationRequest,
+    multisig_nonce: i64,
+) -> Vec<(Uuid, i64)> {
+    let injected_state: i16 = OperationRequestState::Injected.into();
+    let expired_state: i16 = OperationRequestState::Expired.into();
+    let next_nonce = std::cmp::max(multisig_nonce, injected.nonce + 1);
+    let mut stale = operation_requests
+        .iter()
+        .filter(|operation_request| {
+            operation_request.id != injected.id
+                && operation_request.state != injected_state
+                && operation_request.state != expired_state
+                && operation_request.nonce < next_nonce
+        })
+        .collect::<Vec<_>>();
+    stale.sort_by_key(|operation_request| operation_request.nonce);
+
+    let max_nonce = operation_requests
+        .iter()
+        .filter(|operation_request| operation_request.state != expired_state)
+        .map(|operation_request| operation_request.nonce)
+        .max()
+        .unwrap_or(injected.nonce);
+    let first_free_nonce = std::cmp::max(max_nonce + 1, next_nonce);
+
+    stale
+        .into_iter()
+        .zip(first_free_nonce..)
+        .map(|(operation_request, nonce)| (operation_request.id, nonce))
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+pub struct OperationRequestFilter {
+    pub kind: Option<OperationRequestKind>,
+    pub contract_ids: Vec<Uuid>,
+    pub state: Option<OperationRequestState>,
+    pub target_address: Option<TargetAddressFilter>,
 }
 
 #[derive(Insertable, Debug, Clone)]
@@ -390,22 +833,55 @@ pub struct NewOperationRequest {
     pub kind: i16,
     pub chain_id: String,
     pub nonce: i64,
+    pub batch_target_addresses: Option<Vec<String>>,
+    pub batch_amounts: Option<Vec<BigDecimal>>,
+    pub xtz_amount: Option<i64>,
+    pub ledger_blake2b_hash: Option<String>,
+}
+
+/// An operation request imported with the state it reached before the contract was managed by
+/// this backend.
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "operation_requests"]
+pub struct HistoricalOperationRequest {
+    pub user_id: Uuid,
+    pub contract_id: Uuid,
+    pub target_address: Option<String>,
+    pub amount: Option<BigDecimal>,
+    pub threshold: Option<i64>,
+    pub kind: i16,
+    pub chain_id: String,
+    pub nonce: i64,
+    pub state: i16,
+    pub operation_hash: Option<String>,
+    pub batch_target_addresses: Option<Vec<String>>,
+    pub batch_amounts: Option<Vec<BigDecimal>>,
+    pub xtz_amount: Option<i64>,
 }
 
 impl NewOperationRequest {
+    pub fn batch_mints(&self) -> Option<Vec<BatchMint>> {
+        batch_mints(&self.batch_target_addresses, &self.batch_amounts)
+    }
+
     pub fn validate(&self) -> Result<(), TzError> {
         let operation_request_kind: OperationRequestKind = self.kind.try_into()?;
         if self.amount.is_none()
             && (operation_request_kind == OperationRequestKind::Mint
-                || operation_request_kind == OperationRequestKind::Burn)
+                || operation_request_kind == OperationRequestKind::Burn
+                || operation_request_kind == OperationRequestKind::Approve)
         {
             return Err(TzError::InvalidValue {
-                description: "amount is required for mint and burn operation requests".to_owned(),
+                description: format!(
+                    "amount is required for {} operation requests",
+                    operation_request_kind
+                ),
             });
         }
 
         if self.target_address.is_none()
             && (operation_request_kind == OperationRequestKind::Mint
+                || operation_request_kind == OperationRequestKind::Approve
                 || operation_request_kind == OperationRequestKind::AddOperator
                 || operation_request_kind == OperationRequestKind::RemoveOperator
                 || operation_request_kind == OperationRequestKind::SetRedeemAddress
@@ -429,6 +905,508 @@ impl NewOperationRequest {
             });
         }
 
+        if operation_request_kind == OperationRequestKind::AddOperator
+            || operation_request_kind == OperationRequestKind::RemoveOperator
+            || operation_request_kind == OperationRequestKind::SetRedeemAddress
+            || operation_request_kind == OperationRequestKind::TransferOwnership
+        {
+            if let Some(target_address) = &self.target_address {
+                validate_contract(target_address)?;
+            }
+        }
+
+        if operation_request_kind == OperationRequestKind::BatchMint {
+            match (&self.batch_target_addresses, &self.batch_amounts) {
+                (Some(target_addresses), Some(amounts))
+                    if !target_addresses.is_empty() && target_addresses.len() == amounts.len() =>
+                {
+                    for (target_address, amount) in target_addresses.iter().zip(amounts) {
+                        validate_address(target_address)?;
+                        if *amount <= BigDecimal::from(0i64) {
+                            return Err(TzError::InvalidValue {
+                                description: format!(
+                                    "batch mint amount must be positive, got {}",
+                                    amount
+                                ),
+                            });
+                        }
+                    }
+                }
+                _ => {
+                    return Err(TzError::InvalidValue {
+                        description: "batch_mints are required for batch mint operation requests"
+                            .to_owned(),
+                    })
+                }
+            }
+        }
+
+        if let Some(xtz_amount) = self.xtz_amount {
+            if !operation_request_kind.accepts_xtz_amount() {
+                return Err(TzError::InvalidValue {
+                    description: format!(
+                        "xtz_amount is not supported for {} operation requests",
+                        operation_request_kind
+                    ),
+                });
+            }
+            Mutez::try_from(xtz_amount)?;
+        }
+
+        if operation_request_kind == OperationRequestKind::Approve {
+            if let Some(spender) = &self.target_address {
+                validate_address(spender)?;
+            }
+            if let Some(value) = &self.amount {
+                if *value < BigDecimal::from(0i64) {
+                    return Err(TzError::InvalidValue {
+                        description: format!("approve value cannot be negative, got {}", value),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bigdecimal::BigDecimal;
+    use chrono::NaiveDateTime;
+    use uuid::Uuid;
+
+    use super::{
+        expired_nonces, removed_keyholder_approvals, stale_nonces, DroppedApprovals,
+        ExpiredOperationRequests, NewOperationRequest, OperationRequest, OperationRequestFilter,
+        TargetAddressFilter,
+    };
+    use crate::{
+        api::models::{
+            common::SortDirection,
+            operation_request::{OperationRequestKind, OperationRequestState},
+            user::UserKind,
+        },
+        db::{
+            models::{
+                contract::{Contract, NewContract},
+                operation_approval::OperationApproval,
+                user::User,
+            },
+            test_connection,
+        },
+        fixtures::{self, keyholder},
+    };
+
+    fn operation_request(
+        contract_id: Uuid,
+        nonce: i64,
+        state: OperationRequestState,
+    ) -> OperationRequest {
+        OperationRequest {
+            target_address: None,
+            kind: OperationRequestKind::Burn.into(),
+            nonce,
+            state: state.into(),
+            ..fixtures::operation_request(contract_id)
+        }
+    }
+
+    #[test]
+    fn test_injection_renumbers_stale_nonces() {
+        let contract_id = Uuid::new_v4();
+        let injected = operation_request(contract_id, 5, OperationRequestState::Injected);
+        let sibling = operation_request(contract_id, 5, OperationRequestState::Approved);
+        let older = operation_request(contract_id, 4, OperationRequestState::Open);
+        let previously_injected =
+            operation_request(contract_id, 3, OperationRequestState::Injected);
+        let next = operation_request(contract_id, 6, OperationRequestState::Approved);
+        let operation_requests = vec![
+            injected.clone(),
+            sibling.clone(),
+            older.clone(),
+            previously_injected,
+            next,
+        ];
+
+        assert_eq!(
+            stale_nonces(&operation_requests, &injected, 5),
+            vec![(older.id, 7), (sibling.id, 8)]
+        );
+    }
+
+    #[test]
+    fn test_injection_after_multisig_nonce_advanced() {
+        let contract_id = Uuid::new_v4();
+        let injected = operation_request(contract_id, 5, OperationRequestState::Injected);
+        let next = operation_request(contract_id, 6, OperationRequestState::Open);
+        let operation_requests = vec![injected.clone(), next.clone()];
+
+        assert!(stale_nonces(&operation_requests, &injected, 5).is_empty());
+        assert!(stale_nonces(&operation_requests, &injected, 6).is_empty());
+        assert_eq!(
+            stale_nonces(&operation_requests, &injected, 9),
+            vec![(next.id, 9)]
+        );
+    }
+
+    #[test]
+    fn test_expiry_moves_next_nonces_down() {
+        let contract_id = Uuid::new_v4();
+        let created_at = |operation_request: OperationRequest, timestamp: i64| OperationRequest {
+            created_at: NaiveDateTime::from_timestamp(timestamp, 0),
+            ..operation_request
+        };
+        let injected = created_at(
+            operation_request(contract_id, 3, OperationRequestState::Injected),
+            10,
+        );
+        let stale_open = created_at(
+            operation_request(contract_id, 4, OperationRequestState::Open),
+            10,
+        );
+        let recent = created_at(
+            operation_request(contract_id, 5, OperationRequestState::Approved),
+            200,
+        );
+        let stale_approved = created_at(
+            operation_request(contract_id, 6, OperationRequestState::Approved),
+            10,
+        );
+        let latest = created_at(
+            operation_request(contract_id, 7, OperationRequestState::Open),
+            200,
+        );
+        let previously_expired = created_at(
+            operation_request(contract_id, 5, OperationRequestState::Expired),
+            10,
+        );
+        let operation_requests = vec![
+            latest.clone(),
+            stale_approved.clone(),
+            recent.clone(),
+            stale_open.clone(),
+            injected,
+            previously_expired,
+        ];
+
+        assert_eq!(
+            expired_nonces(&operation_requests, NaiveDateTime::from_timestamp(100, 0)),
+            ExpiredOperationRequests {
+                expired: vec![stale_open.id, stale_approved.id],
+                renumbered: vec![(recent.id, 4), (latest.id, 5)],
+            }
+        );
+        assert_eq!(
+            expired_nonces(&operation_requests, NaiveDateTime::from_timestamp(5, 0)),
+            ExpiredOperationRequests::default()
+        );
+    }
+
+    fn approval(
+        operation_request: &OperationRequest,
+        keyholder: &User,
+        signer_set: &[&str],
+    ) -> (OperationApproval, User) {
+        (
+            OperationApproval {
+                signer_set: Some(signer_set.iter().map(|key| String::from(*key)).collect()),
+                ..fixtures::operation_approval(operation_request, keyholder)
+            },
+            keyholder.clone(),
+        )
+    }
+
+    #[test]
+    fn test_removed_keyholder_demotes_approved_sibling() {
+        let contract_id = Uuid::new_v4();
+        let signer_set = ["edpk1", "edpk2", "edpk3"];
+        let first = keyholder(contract_id, "edpk1");
+        let second = keyholder(contract_id, "edpk2");
+        let removed = keyholder(contract_id, "edpk3");
+
+        let approved = operation_request(contract_id, 6, OperationRequestState::Approved);
+        let removed_approval = approval(&approved, &removed, &signer_set);
+        let open = operation_request(contract_id, 7, OperationRequestState::Open);
+        let open_removed_approval = approval(&open, &removed, &signer_set);
+        let unaffected = operation_request(contract_id, 8, OperationRequestState::Approved);
+        let operation_requests = vec![
+            (
+                approved.clone(),
+                vec![
+                    approval(&approved, &first, &signer_set),
+                    removed_approval.clone(),
+                ],
+            ),
+            (open.clone(), vec![open_removed_approval.clone()]),
+            (
+                unaffected.clone(),
+                vec![
+                    approval(&unaffected, &first, &signer_set),
+                    approval(&unaffected, &second, &signer_set),
+                ],
+            ),
+        ];
+
+        assert_eq!(
+            removed_keyholder_approvals(&operation_requests, &["edpk1".into(), "edpk2".into()], 2),
+            DroppedApprovals {
+                approvals: vec![removed_approval.0.id, open_removed_approval.0.id],
+                reopened: vec![approved.id],
+            }
+        );
+        assert_eq!(
+            removed_keyholder_approvals(
+                &operation_requests,
+                &["edpk1".into(), "edpk2".into(), "edpk3".into()],
+                2
+            ),
+            DroppedApprovals::default()
+        );
+    }
+
+    #[test]
+    fn test_removed_keyholder_reopens_with_new_threshold() {
+        let contract_id = Uuid::new_v4();
+        let signer_set = ["edpk1", "edpk2", "edpk3"];
+        let first = keyholder(contract_id, "edpk1");
+        let second = keyholder(contract_id, "edpk2");
+        let removed = keyholder(contract_id, "edpk3");
+        let outside_signer_set = keyholder(contract_id, "edpk4");
+
+        let approved = operation_request(contract_id, 6, OperationRequestState::Approved);
+        let removed_approval = approval(&approved, &removed, &signer_set);
+        let uncounted = operation_request(contract_id, 7, OperationRequestState::Approved);
+        let operation_requests = vec![
+            (
+                approved.clone(),
+                vec![
+                    approval(&approved, &first, &signer_set),
+                    approval(&approved, &second, &signer_set),
+                    removed_approval.clone(),
+                ],
+            ),
+            (
+                uncounted.clone(),
+                vec![
+                    approval(&uncounted, &first, &signer_set),
+                    approval(&uncounted, &outside_signer_set, &signer_set),
+                ],
+            ),
+        ];
+        let keyholders = ["edpk1".into(), "edpk2".into(), "edpk4".into()];
+
+        // the approval outside of its signer set is kept, but not counted
+        assert_eq!(
+            removed_keyholder_approvals(&operation_requests, &keyholders, 2),
+            DroppedApprovals {
+                approvals: vec![removed_approval.0.id],
+                reopened: vec![uncounted.id],
+            }
+        );
+        // the threshold of the update applies, not the one of the previous signer set
+        assert_eq!(
+            removed_keyholder_approvals(&operation_requests, &keyholders, 3),
+            DroppedApprovals {
+                approvals: vec![removed_approval.0.id],
+                reopened: vec![approved.id, uncounted.id],
+            }
+        );
+    }
+
+    fn approve(spender: Option<&str>, value: Option<i64>) -> NewOperationRequest {
+        NewOperationRequest {
+            target_address: spender.map(String::from),
+            amount: value.map(BigDecimal::from),
+            kind: OperationRequestKind::Approve.into(),
+            ..fixtures::new_operation_request(Uuid::new_v4(), Uuid::new_v4())
+        }
+    }
+
+    fn batch_mint(mints: &[(&str, i64)]) -> NewOperationRequest {
+        NewOperationRequest {
+            target_address: None,
+            kind: OperationRequestKind::BatchMint.into(),
+            batch_target_addresses: Some(
+                mints
+                    .iter()
+                    .map(|(target_address, _)| String::from(*target_address))
+                    .collect(),
+            ),
+            batch_amounts: Some(
+                mints
+                    .iter()
+                    .map(|(_, amount)| BigDecimal::from(*amount))
+                    .collect(),
+            ),
+            ..approve(None, None)
+        }
+    }
+
+    #[test]
+    fn test_validate_target_entrypoint() {
+        let request = |kind: OperationRequestKind, target_address: &str| NewOperationRequest {
+            target_address: Some(target_address.into()),
+            kind: kind.into(),
+            ..approve(None, Some(1000))
+        };
+        let target = "KT1S4QXjASmM3ei7a6raRLkr4TbnKu7RuC9X%do";
+
+        assert!(request(OperationRequestKind::AddOperator, target)
+            .validate()
+            .is_ok());
+        assert!(request(OperationRequestKind::TransferOwnership, target)
+            .validate()
+            .is_ok());
+        assert!(request(
+            OperationRequestKind::AddOperator,
+            "KT1S4QXjASmM3ei7a6raRLkr4TbnKu7RuC9X%1do"
+        )
+        .validate()
+        .is_err());
+        assert!(request(OperationRequestKind::Approve, target)
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_validate_approve() {
+        let spender = "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT";
+
+        assert!(approve(Some(spender), Some(1000)).validate().is_ok());
+        assert!(approve(Some(spender), Some(0)).validate().is_ok());
+        assert!(approve(Some(spender), Some(-1)).validate().is_err());
+        assert!(approve(Some("tz1invalid"), Some(1000)).validate().is_err());
+        assert!(approve(None, Some(1000)).validate().is_err());
+        assert!(approve(Some(spender), None).validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_xtz_amount() {
+        let spender = "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT";
+        let with_xtz_amount = |request: NewOperationRequest, xtz_amount: i64| NewOperationRequest {
+            xtz_amount: Some(xtz_amount),
+            ..request
+        };
+
+        assert!(
+            with_xtz_amount(approve(Some(spender), Some(1000)), 1_500_000)
+                .validate()
+                .is_ok()
+        );
+        assert!(with_xtz_amount(approve(Some(spender), Some(1000)), 0)
+            .validate()
+            .is_ok());
+        assert!(with_xtz_amount(approve(Some(spender), Some(1000)), -1)
+            .validate()
+            .is_err());
+        assert!(with_xtz_amount(batch_mint(&[(spender, 1000)]), 1)
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_validate_batch_mint() {
+        let first = "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT";
+        let second = "tz1gZdWPZAaFoTVfHDJ5zo5BbdqM9F9dYDTt";
+
+        let request = batch_mint(&[(first, 1000), (second, 2000)]);
+        assert!(request.validate().is_ok());
+        assert_eq!(request.batch_mints().map(|mints| mints.len()), Some(2));
+
+        assert!(batch_mint(&[]).validate().is_err());
+        assert!(batch_mint(&[(first, 1000), ("tz1invalid", 2000)])
+            .validate()
+            .is_err());
+        assert!(batch_mint(&[(first, 0)]).validate().is_err());
+        assert!(NewOperationRequest {
+            batch_amounts: Some(vec![BigDecimal::from(1000i64)]),
+            ..batch_mint(&[(first, 1000), (second, 2000)])
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_get_list_target_address_filter() -> Result<(), diesel::result::Error> {
+        let conn = test_connection();
+        let contract = |pkh: &str| {
+            NewContract {
+                pkh: pkh.into(),
+                ..fixtures::new_contract()
+            }
+            .save(&conn)
+        };
+        let gatekeeper = |contract: &Contract| -> Result<User, diesel::result::Error> {
+            Ok(User::insert(
+                &conn,
+                vec![fixtures::new_user(contract.id, UserKind::Gatekeeper)],
+            )?
+            .remove(0))
+        };
+        let insert = |contract: &Contract, user: &User, target_address: &str, nonce: i64| {
+            OperationRequest::insert(
+                &conn,
+                &NewOperationRequest {
+                    user_id: user.id,
+                    contract_id: contract.id,
+                    nonce,
+                    ..approve(Some(target_address), Some(1000))
+                },
+            )
+        };
+        let list = |contract: &Contract, target_address: TargetAddressFilter| {
+            OperationRequest::get_list(
+                &conn,
+                OperationRequestFilter {
+                    kind: None,
+                    contract_ids: vec![contract.id],
+                    state: None,
+                    target_address: Some(target_address),
+                },
+                SortDirection::Asc,
+                0,
+                100,
+            )
+            .map(|(operation_requests, _)| {
+                // created in one transaction, the requests share their creation time
+                let mut ids = operation_requests
+                    .into_iter()
+                    .map(|(operation_request, _, _, _)| operation_request.id)
+                    .collect::<Vec<_>>();
+                ids.sort_unstable();
+
+                ids
+            })
+        };
+
+        let first = "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT";
+        let second = "tz1gZdWPZAaFoTVfHDJ5zo5BbdqM9F9dYDTt";
+        let contract_a = contract("KT1S4QXjASmM3ei7a6raRLkr4TbnKu7RuC9X")?;
+        let contract_b = contract("KT1PWx2mnDueood7fEmfbBDKx1D9BAnnXitn")?;
+        let user_a = gatekeeper(&contract_a)?;
+        let user_b = gatekeeper(&contract_b)?;
+        let to_first = insert(&contract_a, &user_a, first, 0)?;
+        let to_second = insert(&contract_a, &user_a, second, 1)?;
+        let _other_contract_to_first = insert(&contract_b, &user_b, first, 0)?;
+
+        assert_eq!(
+            list(&contract_a, TargetAddressFilter::Exact(first.into()))?,
+            vec![to_first.id]
+        );
+        assert_eq!(
+            list(&contract_a, TargetAddressFilter::Prefix("tz1Mj7".into()))?,
+            vec![to_first.id]
+        );
+        let mut both = vec![to_first.id, to_second.id];
+        both.sort_unstable();
+        assert_eq!(
+            list(&contract_a, TargetAddressFilter::Prefix("tz1".into()))?,
+            both
+        );
+        assert!(list(&contract_a, TargetAddressFilter::Prefix("tz2".into()))?.is_empty());
+
         Ok(())
     }
 }
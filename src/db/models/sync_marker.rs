@@ -0,0 +1,43 @@
+use chrono::NaiveDateTime;
+use diesel::{prelude::*, r2d2::ConnectionManager, r2d2::PooledConnection};
+
+use crate::db::schema::sync_markers;
+
+/// Records the configuration a sync last completed with.
+#[derive(Queryable, Identifiable, Debug)]
+#[primary_key(name)]
+pub struct SyncMarker {
+    pub name: String,
+    pub config_hash: String,
+    pub synced_at: NaiveDateTime,
+}
+
+impl SyncMarker {
+    pub fn get(
+        conn: &PooledConnection<ConnectionManager<PgConnection>>,
+        name: &str,
+    ) -> Result<Option<SyncMarker>, diesel::result::Error> {
+        sync_markers::table.find(name).first(conn).optional()
+    }
+
+    pub fn save(
+        conn: &PooledConnection<ConnectionManager<PgConnection>>,
+        name: &str,
+        config_hash: &str,
+    ) -> Result<(), diesel::result::Error> {
+        diesel::insert_into(sync_markers::table)
+            .values((
+                sync_markers::dsl::name.eq(name),
+                sync_markers::dsl::config_hash.eq(config_hash),
+            ))
+            .on_conflict(sync_markers::dsl::name)
+            .do_update()
+            .set((
+                sync_markers::dsl::config_hash.eq(config_hash),
+                sync_markers::dsl::synced_at.eq(diesel::dsl::now),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
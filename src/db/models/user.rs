@@ -8,7 +8,7 @@ use crate::api::models::{
 };
 use crate::crypto;
 use crate::db::schema::*;
-use crate::tezos;
+use crate::tezos::{self, multisig::SignableMessage};
 
 use super::pagination::Paginate;
 
@@ -27,6 +27,7 @@ pub struct User {
 }
 
 impl User {
+    /// Verifies a signature over `message`, which callers pass already hashed with blake2b.
     pub fn verify_message(&self, message: &[u8], signature: &str) -> Result<bool, APIError> {
         let signature_bytes = tezos::edsig_to_bytes(signature)?;
         let pk = tezos::edpk_to_bytes(&self.public_key)?;
@@ -35,6 +36,33 @@ impl User {
         Ok(is_match)
     }
 
+    /// Verifies a plain Ed25519 signature over `message` itself, without hashing it first.
+    pub fn verify_message_raw(&self, message: &[u8], signature: &str) -> Result<bool, APIError> {
+        self.verify_message(message, signature)
+    }
+
+    /// Verifies a keyholder signature of `message`. Tezos signers (`tezos-client sign bytes`, the
+    /// Tezos Wallet Ledger app, Taquito and Beacon wallets) sign the blake2b hash of the packed
+    /// bytes, and so does the multisig check signatures on chain. Generic Ed25519 tools sign the
+    /// packed bytes as they are: such a signature would be rejected by the multisig, so it is
+    /// reported with an explanation instead of being accepted.
+    pub fn verify_signable_message(
+        &self,
+        message: &SignableMessage,
+        signature: &str,
+    ) -> Result<bool, APIError> {
+        if self.verify_message(&message.blake2b_hash()?, signature)? {
+            return Ok(true);
+        }
+        if self.verify_message_raw(&message.bytes()?, signature)? {
+            return Err(APIError::InvalidValue {
+                description: "signature was made over the packed data instead of its blake2b hash, sign the hash as Tezos signers do".into(),
+            });
+        }
+
+        Ok(false)
+    }
+
     pub fn get(
         conn: &PooledConnection<ConnectionManager<PgConnection>>,
         id: Uuid,
@@ -211,6 +239,11 @@ impl User {
         Ok(changes)
     }
 
+    /// Syncs the users of one kind of a contract. A public key can have several kinds, e.g. a
+    /// gatekeeper who is also a keyholder, with one row per kind. Users synced without a name or
+    /// email, like the keyholders read from the multisig, take them from the rows of their other
+    /// kinds, and a configured name and email are copied to the rows of the other kinds that have
+    /// none, so all rows of a public key show the same user.
     // TODO: refactor and optimize this method
     pub fn sync_users(
         conn: &PooledConnection<ConnectionManager<PgConnection>>,
@@ -218,7 +251,11 @@ impl User {
         kind: UserKind,
         users: &Vec<SyncUser>,
     ) -> Result<usize, APIError> {
-        let stored_users = User::get_all(conn, Some(kind), Some(contract_id), None, None, None)?;
+        let kind_value: i16 = kind.into();
+        let (stored_users, other_kinds): (Vec<User>, Vec<User>) =
+            User::get_all(conn, None, Some(contract_id), None, None, None)?
+                .into_iter()
+                .partition(|stored_user| stored_user.kind == kind_value);
 
         let inactive_state: i16 = UserState::Inactive.into();
         let to_deactivate: Vec<_> = stored_users
@@ -244,13 +281,14 @@ impl User {
                 return found.is_none();
             })
             .map(|user| {
+                let (display_name, email) = user.metadata_or_from(&other_kinds);
                 Ok(NewUser {
                     public_key: user.public_key.clone(),
                     address: tezos::edpk_to_tz1(&user.public_key)?,
                     contract_id,
                     kind: kind.into(),
-                    display_name: user.display_name.clone(),
-                    email: user.email.clone(),
+                    display_name,
+                    email,
                     state: UserState::Active.into(),
                 })
             })
@@ -272,6 +310,8 @@ impl User {
             })
             .collect();
 
+        let to_complete = metadata_updates(users, &other_kinds);
+
         let mut changes: usize = 0;
 
         if !to_deactivate.is_empty() {
@@ -299,8 +339,34 @@ impl User {
             }
         }
 
+        changes += User::update(conn, to_complete)?;
+
         Ok(changes)
     }
+
+    fn has_metadata(&self) -> bool {
+        !self.display_name.is_empty() || self.email.is_some()
+    }
+}
+
+/// Copies the name and email of the synced users that have them to the rows of their other kinds
+/// that have neither.
+fn metadata_updates(users: &[SyncUser], other_kinds: &[User]) -> Vec<UpdateUser> {
+    other_kinds
+        .iter()
+        .filter(|stored_user| !stored_user.has_metadata())
+        .filter_map(|stored_user| {
+            users
+                .iter()
+                .find(|user| user.public_key == stored_user.public_key && user.has_metadata())
+                .map(|user| UpdateUser {
+                    id: stored_user.id,
+                    state: stored_user.state,
+                    display_name: user.display_name.clone(),
+                    email: user.email.clone(),
+                })
+        })
+        .collect()
 }
 
 #[derive(Insertable)]
@@ -330,3 +396,84 @@ pub struct SyncUser {
     pub display_name: String,
     pub email: Option<String>,
 }
+
+impl SyncUser {
+    fn has_metadata(&self) -> bool {
+        !self.display_name.is_empty() || self.email.is_some()
+    }
+
+    /// The name and email of the user, or those of a row of another kind with the same public
+    /// key if the user has neither.
+    fn metadata_or_from(&self, other_kinds: &[User]) -> (String, Option<String>) {
+        if self.has_metadata() {
+            return (self.display_name.clone(), self.email.clone());
+        }
+
+        other_kinds
+            .iter()
+            .find(|stored_user| {
+                stored_user.public_key == self.public_key && stored_user.has_metadata()
+            })
+            .map_or((self.display_name.clone(), None), |stored_user| {
+                (stored_user.display_name.clone(), stored_user.email.clone())
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use super::{metadata_updates, SyncUser, User};
+    use crate::{
+        api::models::user::UserKind,
+        fixtures::{self, PUBLIC_KEY},
+    };
+
+    fn stored_user(kind: UserKind, display_name: &str, email: Option<&str>) -> User {
+        User {
+            display_name: display_name.into(),
+            email: email.map(|email| email.into()),
+            ..fixtures::user(Uuid::new_v4(), kind)
+        }
+    }
+
+    fn sync_user(display_name: &str, email: Option<&str>) -> SyncUser {
+        SyncUser {
+            public_key: PUBLIC_KEY.into(),
+            display_name: display_name.into(),
+            email: email.map(|email| email.into()),
+        }
+    }
+
+    #[test]
+    fn test_gatekeeper_who_is_keyholder() {
+        let gatekeeper = stored_user(UserKind::Gatekeeper, "Alice", Some("alice@example.com"));
+
+        // keyholders are synced from the multisig without a name or email
+        let keyholder = sync_user("", None);
+        assert_eq!(
+            keyholder.metadata_or_from(&[gatekeeper.clone()]),
+            ("Alice".into(), Some("alice@example.com".into()))
+        );
+        assert_eq!(keyholder.metadata_or_from(&[]), ("".into(), None));
+
+        // a configured name and email are kept
+        let configured = sync_user("Alice (gatekeeper)", None);
+        assert_eq!(
+            configured.metadata_or_from(&[gatekeeper.clone()]),
+            ("Alice (gatekeeper)".into(), None)
+        );
+
+        // the gatekeeper was configured after the keyholder row was created
+        let keyholder_row = stored_user(UserKind::Keyholder, "", None);
+        let updates = metadata_updates(
+            &[sync_user("Alice", Some("alice@example.com"))],
+            &[keyholder_row.clone(), gatekeeper],
+        );
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].id, keyholder_row.id);
+        assert_eq!(updates[0].display_name, "Alice");
+        assert_eq!(updates[0].email.as_deref(), Some("alice@example.com"));
+    }
+}
@@ -1,3 +1,38 @@
+table! {
+    allowed_target_addresses (id) {
+        id -> Uuid,
+        created_at -> Timestamp,
+        contract_id -> Uuid,
+        address -> Varchar,
+    }
+}
+
+table! {
+    api_keys (id) {
+        id -> Uuid,
+        created_at -> Timestamp,
+        contract_id -> Uuid,
+        name -> Varchar,
+        address -> Varchar,
+        token_hash -> Varchar,
+        actions -> Array<Int2>,
+        created_by -> Varchar,
+        revoked_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    audit_log (id) {
+        id -> Uuid,
+        created_at -> Timestamp,
+        event -> Int2,
+        actor -> Nullable<Varchar>,
+        contract_id -> Nullable<Uuid>,
+        resource_id -> Nullable<Uuid>,
+        detail -> Text,
+    }
+}
+
 table! {
     authentication_challenges (id) {
         id -> Uuid,
@@ -16,6 +51,8 @@ table! {
         created_at -> Timestamp,
         contract_id -> Uuid,
         operation_request_kind -> Int2,
+        enabled -> Bool,
+        added_at_runtime -> Bool,
     }
 }
 
@@ -32,6 +69,20 @@ table! {
         min_approvals -> Int4,
         symbol -> Varchar,
         decimals -> Int4,
+        mint_entrypoint -> Varchar,
+        burn_entrypoint -> Varchar,
+        node_url -> Nullable<Varchar>,
+        sort_order -> Nullable<Int4>,
+    }
+}
+
+table! {
+    maintenance_mode (id) {
+        id -> Int2,
+        enabled -> Bool,
+        message -> Nullable<Text>,
+        updated_by -> Nullable<Varchar>,
+        updated_at -> Timestamp,
     }
 }
 
@@ -55,6 +106,8 @@ table! {
         keyholder_id -> Uuid,
         operation_request_id -> Uuid,
         signature -> Varchar,
+        uploaded_by -> Nullable<Uuid>,
+        signer_set -> Nullable<Array<Text>>,
     }
 }
 
@@ -73,6 +126,11 @@ table! {
         nonce -> Int8,
         state -> Int2,
         operation_hash -> Nullable<Varchar>,
+        confirmation_failed -> Bool,
+        batch_target_addresses -> Nullable<Array<Text>>,
+        batch_amounts -> Nullable<Array<Numeric>>,
+        xtz_amount -> Nullable<Int8>,
+        ledger_blake2b_hash -> Nullable<Varchar>,
     }
 }
 
@@ -86,6 +144,14 @@ table! {
     }
 }
 
+table! {
+    sync_markers (name) {
+        name -> Varchar,
+        config_hash -> Varchar,
+        synced_at -> Timestamp,
+    }
+}
+
 table! {
     users (id) {
         id -> Uuid,
@@ -101,6 +167,8 @@ table! {
     }
 }
 
+joinable!(allowed_target_addresses -> contracts (contract_id));
+joinable!(api_keys -> contracts (contract_id));
 joinable!(capabilities -> contracts (contract_id));
 joinable!(operation_approvals -> operation_requests (operation_request_id));
 joinable!(operation_approvals -> users (keyholder_id));
@@ -111,12 +179,17 @@ joinable!(proposed_users -> users (user_id));
 joinable!(users -> contracts (contract_id));
 
 allow_tables_to_appear_in_same_query!(
+    allowed_target_addresses,
+    api_keys,
+    audit_log,
     authentication_challenges,
     capabilities,
     contracts,
+    maintenance_mode,
     node_endpoints,
     operation_approvals,
     operation_requests,
     proposed_users,
+    sync_markers,
     users,
 );
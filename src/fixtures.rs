@@ -0,0 +1,246 @@
+//! Stored models and settings for tests. Tests adapt them with struct update syntax, so a new
+//! column or setting only needs a default here.
+
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use crate::{
+    api::models::{
+        contract::ContractKind,
+        operation_request::{OperationRequestKind, OperationRequestState},
+        user::{UserKind, UserState},
+    },
+    auth::{SessionUser, SessionUserRole},
+    db::models::{
+        contract::{Contract, NewContract},
+        operation_approval::OperationApproval,
+        operation_request::{NewOperationRequest, OperationRequest},
+        user::{NewUser, User},
+    },
+    settings,
+};
+
+pub const CONTRACT_ADDRESS: &str = "KT1S4QXjASmM3ei7a6raRLkr4TbnKu7RuC9X";
+pub const MULTISIG_ADDRESS: &str = "KT1MeQd67djiEEkscx3USfQ7vFYg589DySpZ";
+pub const ADDRESS: &str = "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT";
+pub const PUBLIC_KEY: &str = "edpkuHG9N83cBavucaLSeeKX3AVjn9wDyFeFmrhaSLqvmBycP5N7Zs";
+pub const CHAIN_ID: &str = "NetXm8tYqnMWky1";
+
+pub fn timestamp() -> NaiveDateTime {
+    NaiveDateTime::from_timestamp(0, 0)
+}
+
+/// Server settings with an inactivity timeout of 30 minutes, without any optional setting.
+pub fn server_settings() -> settings::Server {
+    settings::Server {
+        address: "0.0.0.0:80".into(),
+        domain_name: "api.example.com".into(),
+        base_path: None,
+        inactivity_timeout_seconds: 1800,
+        admins: None,
+        injection_confirmation: None,
+        body_logging: None,
+        log_format: None,
+        require_ledger_hash: None,
+        default_sort_direction: None,
+        shutdown_timeout_seconds: None,
+        notification_max_fraction_digits: None,
+        notification_templates: None,
+        notifications_paused: None,
+        removed_keyholder_approvals: None,
+        force_sync: None,
+        compression: None,
+        max_concurrent_node_requests: None,
+        signable_message_cache_ttl_seconds: None,
+        operation_request_ttl_seconds: None,
+        max_batch_mint_recipients: None,
+        max_micheline_depth: None,
+        max_micheline_nodes: None,
+        verify_packed_data: None,
+        tls_cert: None,
+        tls_key: None,
+    }
+}
+
+/// A signed in user with `ADDRESS` and the role `kind` on each of `contract_ids`.
+pub fn session_user(kind: UserKind, contract_ids: &[Uuid]) -> SessionUser {
+    SessionUser {
+        address: ADDRESS.into(),
+        roles: contract_ids
+            .iter()
+            .map(|contract_id| SessionUserRole {
+                contract_id: *contract_id,
+                kind,
+            })
+            .collect(),
+    }
+}
+
+/// An FA2 contract with token id 0 and a threshold of 2.
+pub fn contract() -> Contract {
+    Contract {
+        id: Uuid::new_v4(),
+        created_at: timestamp(),
+        updated_at: timestamp(),
+        pkh: CONTRACT_ADDRESS.into(),
+        token_id: 0,
+        multisig_pkh: MULTISIG_ADDRESS.into(),
+        kind: ContractKind::FA2.into(),
+        display_name: "".into(),
+        min_approvals: 2,
+        symbol: "".into(),
+        decimals: 0,
+        mint_entrypoint: "mint".into(),
+        burn_entrypoint: "burn".into(),
+        node_url: None,
+        sort_order: None,
+    }
+}
+
+/// `contract()` before it is stored.
+pub fn new_contract() -> NewContract {
+    let contract = contract();
+
+    NewContract {
+        pkh: contract.pkh,
+        token_id: contract.token_id,
+        multisig_pkh: contract.multisig_pkh,
+        kind: contract.kind,
+        display_name: contract.display_name,
+        min_approvals: contract.min_approvals,
+        symbol: contract.symbol,
+        decimals: contract.decimals,
+        mint_entrypoint: contract.mint_entrypoint,
+        burn_entrypoint: contract.burn_entrypoint,
+        node_url: contract.node_url,
+        sort_order: contract.sort_order,
+    }
+}
+
+/// The settings of an FA2 contract with token id 0, without any optional setting.
+pub fn contract_settings() -> settings::Contract {
+    settings::Contract {
+        address: CONTRACT_ADDRESS.into(),
+        multisig: MULTISIG_ADDRESS.into(),
+        name: "".into(),
+        kind: ContractKind::FA2,
+        token_id: 0,
+        gatekeepers: vec![],
+        capabilities: vec![],
+        symbol: "".into(),
+        decimals: 0,
+        ledger_big_map_id: None,
+        mint_entrypoint: None,
+        burn_entrypoint: None,
+        node: None,
+        sort_order: None,
+        notification_ccs: None,
+        notification_channels: None,
+        webhook_urls: None,
+        admins: None,
+        injectors: None,
+        signature_schemes: None,
+        keyholder_update_safeguard: None,
+    }
+}
+
+/// An active user of `contract_id` with `ADDRESS` and `PUBLIC_KEY`.
+pub fn user(contract_id: Uuid, kind: UserKind) -> User {
+    User {
+        id: Uuid::new_v4(),
+        created_at: timestamp(),
+        updated_at: timestamp(),
+        public_key: PUBLIC_KEY.into(),
+        address: ADDRESS.into(),
+        contract_id,
+        kind: kind.into(),
+        state: UserState::Active.into(),
+        display_name: "".into(),
+        email: None,
+    }
+}
+
+/// An active keyholder of `contract_id` with `public_key`.
+pub fn keyholder(contract_id: Uuid, public_key: &str) -> User {
+    User {
+        public_key: public_key.into(),
+        ..user(contract_id, UserKind::Keyholder)
+    }
+}
+
+/// `user()` before it is stored.
+pub fn new_user(contract_id: Uuid, kind: UserKind) -> NewUser {
+    let user = user(contract_id, kind);
+
+    NewUser {
+        public_key: user.public_key,
+        address: user.address,
+        contract_id: user.contract_id,
+        kind: user.kind,
+        display_name: user.display_name,
+        email: user.email,
+        state: user.state,
+    }
+}
+
+/// An open operation request of `contract_id` minting 1000 to `ADDRESS` with nonce 0.
+pub fn operation_request(contract_id: Uuid) -> OperationRequest {
+    OperationRequest {
+        id: Uuid::new_v4(),
+        created_at: timestamp(),
+        updated_at: timestamp(),
+        user_id: Uuid::new_v4(),
+        contract_id,
+        target_address: Some(ADDRESS.into()),
+        amount: Some(BigDecimal::from(1000i64)),
+        threshold: None,
+        kind: OperationRequestKind::Mint.into(),
+        chain_id: CHAIN_ID.into(),
+        nonce: 0,
+        state: OperationRequestState::Open.into(),
+        operation_hash: None,
+        confirmation_failed: false,
+        batch_target_addresses: None,
+        batch_amounts: None,
+        xtz_amount: None,
+        ledger_blake2b_hash: None,
+    }
+}
+
+/// `operation_request()` created by `user_id` before it is stored.
+pub fn new_operation_request(contract_id: Uuid, user_id: Uuid) -> NewOperationRequest {
+    let operation_request = operation_request(contract_id);
+
+    NewOperationRequest {
+        user_id,
+        contract_id: operation_request.contract_id,
+        target_address: operation_request.target_address,
+        amount: operation_request.amount,
+        threshold: operation_request.threshold,
+        kind: operation_request.kind,
+        chain_id: operation_request.chain_id,
+        nonce: operation_request.nonce,
+        batch_target_addresses: operation_request.batch_target_addresses,
+        batch_amounts: operation_request.batch_amounts,
+        xtz_amount: operation_request.xtz_amount,
+        ledger_blake2b_hash: operation_request.ledger_blake2b_hash,
+    }
+}
+
+/// An approval of `operation_request` signed by `keyholder`, without uploader or signer set.
+pub fn operation_approval(
+    operation_request: &OperationRequest,
+    keyholder: &User,
+) -> OperationApproval {
+    OperationApproval {
+        id: Uuid::new_v4(),
+        created_at: timestamp(),
+        updated_at: timestamp(),
+        keyholder_id: keyholder.id,
+        operation_request_id: operation_request.id,
+        signature: "edsig".into(),
+        uploaded_by: None,
+        signer_set: None,
+    }
+}
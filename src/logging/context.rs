@@ -0,0 +1,80 @@
+use std::{
+    cell::Cell,
+    fmt::{self, Display},
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use uuid::Uuid;
+
+thread_local! {
+    static CURRENT: Cell<Option<RequestId>> = Cell::new(None);
+}
+
+/// Identifies a single API request in the logs, in the `X-Request-Id` response header and in
+/// error response bodies.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RequestId(Uuid);
+
+impl RequestId {
+    pub fn new() -> Self {
+        RequestId(Uuid::new_v4())
+    }
+
+    /// The id of the request being handled by the current task, if any. Closures run with
+    /// `web::block` are executed on another thread and don't see it.
+    pub fn current() -> Option<RequestId> {
+        CURRENT.with(|current| current.get())
+    }
+
+    /// Same as `current`, formatted for log lines.
+    pub fn current_label() -> String {
+        RequestId::current().map_or_else(|| "-".into(), |request_id| request_id.to_string())
+    }
+}
+
+impl Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Runs `future` with `request_id` as the current request id.
+pub fn in_request<F: Future>(request_id: RequestId, future: F) -> impl Future<Output = F::Output> {
+    WithRequestId {
+        request_id,
+        inner: Box::pin(future),
+    }
+}
+
+/// Runs `future`, typically a task spawned by a handler, with the id of the current request, so
+/// its log lines are still attributed to the request.
+pub fn in_current_request<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let request_id = RequestId::current();
+
+    async move {
+        match request_id {
+            Some(request_id) => in_request(request_id, future).await,
+            None => future.await,
+        }
+    }
+}
+
+/// Makes `request_id` the current request id while `inner` is polled.
+struct WithRequestId<F> {
+    request_id: RequestId,
+    inner: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for WithRequestId<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let previous = CURRENT.with(|current| current.replace(Some(self.request_id)));
+        let result = self.inner.as_mut().poll(cx);
+        CURRENT.with(|current| current.set(previous));
+
+        result
+    }
+}
@@ -0,0 +1,153 @@
+pub mod context;
+
+use std::io::Write;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use log::Record;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use self::context::RequestId;
+use crate::settings::LogFormat;
+
+/// The target of the access log lines, one per API request.
+pub const ACCESS_LOG_TARGET: &str = "access";
+
+/// Sets up the logger writing to stdout in `format`, at the level given by `RUST_LOG`.
+pub fn init(format: LogFormat) {
+    let mut builder = env_logger::Builder::from_default_env();
+    builder.target(env_logger::Target::Stdout);
+    if format == LogFormat::Json {
+        builder.format(|buf, record| writeln!(buf, "{}", json_line(record, Utc::now())));
+    }
+
+    builder.init();
+}
+
+/// The access log line of an API request, written once its response is ready.
+#[derive(Debug, Serialize)]
+pub struct AccessLogEntry {
+    pub request_id: String,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub duration_ms: u64,
+    /// The signed in user or the address of the API key, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_address: Option<String>,
+}
+
+impl AccessLogEntry {
+    pub fn line(&self, format: LogFormat) -> String {
+        match format {
+            LogFormat::Text => format!(
+                "[{}] {} {} {}",
+                self.request_id, self.method, self.path, self.status
+            ),
+            LogFormat::Json => {
+                serde_json::to_string(self).expect("access log entries can be serialized")
+            }
+        }
+    }
+}
+
+/// Formats `record` as a JSON object with the `timestamp`, the `level`, the `target` and the
+/// `request_id` of the request being handled, if any. Access log lines are JSON objects already,
+/// their fields are added to the line, other messages are added as `message`.
+fn json_line(record: &Record, timestamp: DateTime<Utc>) -> String {
+    let mut line = Map::new();
+    line.insert(
+        "timestamp".into(),
+        timestamp
+            .to_rfc3339_opts(SecondsFormat::Millis, true)
+            .into(),
+    );
+    line.insert("level".into(), record.level().to_string().into());
+    line.insert("target".into(), record.target().into());
+    if let Some(request_id) = RequestId::current() {
+        line.insert("request_id".into(), request_id.to_string().into());
+    }
+
+    let message = record.args().to_string();
+    match serde_json::from_str::<Value>(&message) {
+        Ok(Value::Object(fields)) if record.target() == ACCESS_LOG_TARGET => line.extend(fields),
+        _ => {
+            line.insert("message".into(), message.into());
+        }
+    }
+
+    Value::Object(line).to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{TimeZone, Utc};
+    use log::{Level, Record};
+    use serde_json::{json, Value};
+
+    use super::{json_line, AccessLogEntry, ACCESS_LOG_TARGET};
+    use crate::settings::LogFormat;
+
+    #[test]
+    fn test_json_line() {
+        let timestamp = Utc.ymd(2022, 4, 12).and_hms_milli(9, 30, 0, 250);
+        let line = json_line(
+            &Record::builder()
+                .args(format_args!("Operation request {} injected", 3))
+                .level(Level::Info)
+                .target("tz_wrapped_backend::api")
+                .build(),
+            timestamp,
+        );
+
+        assert_eq!(
+            serde_json::from_str::<Value>(&line).unwrap(),
+            json!({
+                "timestamp": "2022-04-12T09:30:00.250Z",
+                "level": "INFO",
+                "target": "tz_wrapped_backend::api",
+                "message": "Operation request 3 injected"
+            })
+        );
+    }
+
+    #[test]
+    fn test_json_access_line() {
+        let entry = AccessLogEntry {
+            request_id: "6f1c5f4e-5d7b-4bb4-9c55-1f2f3b4a5c6d".into(),
+            method: "POST".into(),
+            path: "/api/v1/operation-requests".into(),
+            status: 200,
+            duration_ms: 42,
+            user_address: Some("tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT".into()),
+        };
+        let timestamp = Utc.ymd(2022, 4, 12).and_hms(9, 30, 0);
+        let line = json_line(
+            &Record::builder()
+                .args(format_args!("{}", entry.line(LogFormat::Json)))
+                .level(Level::Info)
+                .target(ACCESS_LOG_TARGET)
+                .build(),
+            timestamp,
+        );
+
+        assert_eq!(
+            serde_json::from_str::<Value>(&line).unwrap(),
+            json!({
+                "timestamp": "2022-04-12T09:30:00.000Z",
+                "level": "INFO",
+                "target": "access",
+                "request_id": "6f1c5f4e-5d7b-4bb4-9c55-1f2f3b4a5c6d",
+                "method": "POST",
+                "path": "/api/v1/operation-requests",
+                "status": 200,
+                "duration_ms": 42,
+                "user_address": "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT"
+            })
+        );
+        assert_eq!(
+            entry.line(LogFormat::Text),
+            "[6f1c5f4e-5d7b-4bb4-9c55-1f2f3b4a5c6d] POST /api/v1/operation-requests 200"
+        );
+    }
+}
@@ -1,8 +1,9 @@
 #![allow(dead_code)]
 
+use std::time::Duration;
+
 use actix_cors::Cors;
-use actix_session::CookieSession;
-use actix_web::{cookie::SameSite, http::Uri, middleware, web, App, HttpServer, Responder};
+use actix_web::{http::Uri, middleware, web, App, HttpServer, Responder};
 
 #[macro_use]
 extern crate diesel;
@@ -18,26 +19,43 @@ extern crate lettre;
 extern crate lettre_email;
 extern crate native_tls;
 
-use api::models::{error::APIError, user::UserKind};
+use api::api_keys::guard::ResolveApiKeys;
+use api::body_logger::BodyLogger;
+use api::maintenance::guard::{MaintenanceState, RejectWritesInMaintenance};
+use api::models::{error::APIError, maintenance::MaintenanceMode, user::UserKind};
+use api::operation_requests::cache::SignableMessageCache;
+use api::request_id::AssignRequestId;
 use crypto::generate_random_bytes;
 use db::models::contract;
+use db::models::maintenance_mode::MaintenanceMode as DBMaintenanceMode;
 use db::models::node_endpoint;
+use db::models::operation_request;
+use db::models::sync_marker::SyncMarker;
 use db::models::user;
 use diesel::pg::PgConnection;
 use diesel::r2d2::ConnectionManager;
+use diesel::Connection;
 use diesel_migrations::embed_migrations;
 use dotenv::dotenv;
 use r2d2::PooledConnection;
-use settings::ENV;
+use scheduler::{Scheduler, SchedulerHandle};
+use settings::{LogFormat, ENV};
 use user::SyncUser;
 
 mod api;
+mod audit;
 mod auth;
 mod crypto;
 mod db;
+#[cfg(test)]
+mod fixtures;
+mod logging;
 mod notifications;
+mod scheduler;
 mod settings;
 mod tezos;
+mod tls;
+mod zip;
 
 type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
 type Conn = PooledConnection<ConnectionManager<PgConnection>>;
@@ -54,9 +72,13 @@ fn database_url() -> String {
     let user = &CONFIG.database.user;
     let password = &CONFIG.database.password;
     let host = &CONFIG.database.host;
+    let port = CONFIG.database.port;
     let name = &CONFIG.database.name;
 
-    format!("postgres://{}:{}@{}:5432/{}", user, password, host, name)
+    format!(
+        "postgres://{}:{}@{}:{}/{}",
+        user, password, host, port, name
+    )
 }
 
 async fn health() -> impl Responder {
@@ -66,14 +88,12 @@ async fn health() -> impl Responder {
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     std::env::set_var("RUST_LOG", "info");
-    env_logger::Builder::from_default_env()
-        .target(env_logger::Target::Stdout)
-        .init();
+    logging::init(CONFIG.server.log_format());
 
     let database_url = database_url();
     let manager = ConnectionManager::<PgConnection>::new(database_url);
-    let pool = r2d2::Pool::builder()
-        .max_size(5)
+    let pool = db::pool_builder(&CONFIG.database)
+        .expect("Invalid database settings.")
         .build(manager)
         .expect("Failed to create pool.");
 
@@ -84,24 +104,43 @@ async fn main() -> std::io::Result<()> {
         &mut std::io::stdout(),
     );
 
-    sync_db(&pool)
+    sync_db_on_startup(&pool)
         .await
         .map_err(|error| std::io::Error::new(std::io::ErrorKind::Other, error))?;
 
+    let maintenance_state = web::Data::new(MaintenanceState::new(
+        load_maintenance_mode(&pool)
+            .await
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::Other, error))?,
+    ));
+
+    let mut scheduler = Scheduler::new(CONFIG.jobs.clone().unwrap_or_default());
+    let sync_pool = pool.clone();
+    scheduler.register("sync_db", None, move || {
+        let pool = sync_pool.clone();
+        async move { sync_db(&pool).await }
+    });
+    if let Some(ttl) = CONFIG.server.operation_request_ttl() {
+        let expiry_pool = pool.clone();
+        scheduler.register(
+            "expire_operation_requests",
+            Some(Duration::from_secs(60)),
+            move || {
+                let pool = expiry_pool.clone();
+                async move { expire_operation_requests(&pool, ttl).await }
+            },
+        );
+    }
+    let scheduler = scheduler.start();
+
+    let signable_message_cache = web::Data::new(SignableMessageCache::new(
+        CONFIG.server.signable_message_cache_ttl(),
+    ));
+    let tls_acceptor = tls::acceptor(&CONFIG.server).expect("Invalid TLS settings.");
     let key = generate_random_bytes(32);
-    HttpServer::new(move || {
-        let secure = CONFIG.env != ENV::Local;
-        let same_site = if CONFIG.env == ENV::Production || !secure {
-            SameSite::Lax
-        } else {
-            SameSite::None // this allows to run the frontend on localhost and connect to the DEV instance
-        };
-        let session = CookieSession::private(&key)
-            .secure(secure)
-            .domain(CONFIG.server.domain_name.clone())
-            .path("/")
-            .http_only(true)
-            .same_site(same_site);
+    let session_cookie = CONFIG.session_cookie();
+    let server = HttpServer::new(move || {
+        let session = auth::session_middleware(&key, &session_cookie);
 
         let domain_suffix: &str = domain_suffix();
         let allowed_origins: Vec<(&str, &str)> = match CONFIG.env {
@@ -125,25 +164,72 @@ async fn main() -> std::io::Result<()> {
         App::new()
             .route("/", web::get().to(health))
             .data(pool.clone())
-            .wrap(middleware::Logger::default())
+            .wrap(middleware::Condition::new(
+                CONFIG.server.log_format() == LogFormat::Text,
+                middleware::Logger::default(),
+            ))
             .wrap(session)
             .wrap(cors)
-            .wrap(middleware::Compress::default())
+            .wrap(middleware::Compress::new(
+                CONFIG.server.compression().content_encoding(),
+            ))
             .service(
-                web::scope("/api/v1")
+                web::scope(&CONFIG.server.api_path())
+                    .wrap(ResolveApiKeys::new(pool.clone()))
+                    .wrap(RejectWritesInMaintenance::new(maintenance_state.clone()))
+                    .wrap(BodyLogger::new(
+                        CONFIG.server.body_logging.as_ref(),
+                        &CONFIG.env,
+                    ))
+                    .wrap(AssignRequestId::new(CONFIG.server.log_format()))
+                    .app_data(api::json_config())
+                    .app_data(api::query_config())
+                    .app_data(signable_message_cache.clone())
+                    .app_data(maintenance_state.clone())
                     .data(CONFIG.server.clone())
                     .data(CONFIG.contracts.clone())
+                    .configure(api::api_keys::api_config)
                     .configure(api::contracts::api_config)
+                    .configure(api::debug::api_config)
                     .configure(api::users::api_config)
                     .configure(api::operation_requests::api_config)
                     .configure(api::operation_approvals::api_config)
                     .configure(api::authentication::api_config)
-                    .configure(api::nodes::api_config),
+                    .configure(api::audit_log::api_config)
+                    .configure(api::nodes::api_config)
+                    .configure(api::maintenance::api_config)
+                    .configure(api::notifications::api_config)
+                    .configure(api::openapi::api_config),
             )
     })
-    .bind(&CONFIG.server.address)?
-    .run()
-    .await
+    .shutdown_timeout(CONFIG.server.shutdown_timeout().as_secs());
+    let server = match tls_acceptor {
+        Some(tls_acceptor) => server.bind_openssl(&CONFIG.server.address, tls_acceptor)?,
+        None => server.bind(&CONFIG.server.address)?,
+    };
+    server.run().await?;
+
+    shutdown(scheduler, CONFIG.server.shutdown_timeout()).await;
+
+    Ok(())
+}
+
+/// Gives the background work a chance to finish once the server has stopped.
+async fn shutdown(scheduler: SchedulerHandle, timeout: Duration) {
+    log::info!(
+        "shutting down, waiting up to {:?} for background work",
+        timeout
+    );
+
+    if !scheduler.shutdown(timeout).await {
+        log::warn!("scheduled jobs did not finish within {:?}", timeout);
+    }
+    if !notifications::drain_email_queue(timeout) {
+        log::warn!("email queue was not drained within {:?}", timeout);
+    }
+    if !notifications::drain_webhook_queue(timeout) {
+        log::warn!("webhook queue was not drained within {:?}", timeout);
+    }
 }
 
 fn domain_suffix() -> &'static str {
@@ -157,8 +243,44 @@ fn domain_suffix() -> &'static str {
     }
 }
 
+const SYNC_MARKER: &str = "sync_db";
+
+/// The stored maintenance mode, disabled if it was never set.
+async fn load_maintenance_mode(pool: &DbPool) -> Result<MaintenanceMode, APIError> {
+    let conn = pool.get()?;
+    let maintenance_mode = web::block(move || DBMaintenanceMode::get(&conn)).await?;
+
+    Ok(maintenance_mode
+        .map(MaintenanceMode::from)
+        .unwrap_or_default())
+}
+
+/// Skips the sync when the configuration hasn't changed since the last successful one, unless
+/// `server.force_sync` is set.
+async fn sync_db_on_startup(pool: &DbPool) -> Result<(), APIError> {
+    let config_hash = config_hash()?;
+    let conn = pool.get()?;
+    let marker = web::block(move || SyncMarker::get(&conn, SYNC_MARKER)).await?;
+    let force = CONFIG.server.force_sync.unwrap_or(false);
+    if !db::needs_sync(marker.as_ref(), &config_hash, force) {
+        log::info!("configuration unchanged since the last sync, skipping DB sync");
+        return Ok(());
+    }
+
+    sync_db(pool).await
+}
+
+fn config_hash() -> Result<String, APIError> {
+    db::config_hash(
+        &CONFIG.contracts,
+        CONFIG.server.admins.as_ref(),
+        &CONFIG.tezos_nodes,
+    )
+}
+
 async fn sync_db(pool: &DbPool) -> Result<(), APIError> {
     log::info!("syncing DB");
+    let config_hash = config_hash()?;
     let contracts = &CONFIG.contracts;
     let mut conn = pool.get()?;
     node_endpoint::NodeEndpoint::sync(&conn, &CONFIG.tezos_nodes)?;
@@ -169,16 +291,16 @@ async fn sync_db(pool: &DbPool) -> Result<(), APIError> {
 
     for contract in contracts {
         let gatekeepers = &contract.gatekeepers;
-        let stored_contract = stored_contracts.iter().find(|stored_contract| {
-            stored_contract.pkh == contract.address
-                && stored_contract.multisig_pkh == contract.multisig
-                && (stored_contract.token_id as i64) == contract.token_id
-        });
+        let injectors = contract.injectors();
+        let stored_contract = stored_contracts
+            .iter()
+            .find(|stored_contract| contract.matches(stored_contract));
         if let Some(stored_contract) = stored_contract {
             conn = pool.get()?;
             let stored_contract_id = stored_contract.id.clone();
+            let admins = contract.admins(CONFIG.server.admins.as_ref());
             web::block::<_, _, APIError>(move || {
-                if let Some(admins) = &CONFIG.server.admins {
+                if let Some(admins) = admins {
                     user::User::sync_users(
                         &conn,
                         stored_contract_id,
@@ -194,6 +316,20 @@ async fn sync_db(pool: &DbPool) -> Result<(), APIError> {
                             .as_ref(),
                     )?;
                 }
+                user::User::sync_users(
+                    &conn,
+                    stored_contract_id,
+                    UserKind::Injector,
+                    injectors
+                        .iter()
+                        .map(|injector| SyncUser {
+                            public_key: injector.public_key.clone(),
+                            display_name: injector.name.clone().unwrap_or("".into()),
+                            email: injector.email.clone(),
+                        })
+                        .collect::<Vec<SyncUser>>()
+                        .as_ref(),
+                )?;
                 user::User::sync_users(
                     &conn,
                     stored_contract_id,
@@ -217,6 +353,48 @@ async fn sync_db(pool: &DbPool) -> Result<(), APIError> {
 
     db::sync_keyholders(pool, stored_contracts, &node_url).await?;
 
+    let conn = pool.get()?;
+    web::block::<_, _, APIError>(move || {
+        conn.transaction(|| {
+            SyncMarker::save(&conn, SYNC_MARKER, &config_hash)?;
+            audit::record(&conn, audit::config_synced(&config_hash))
+        })
+    })
+    .await?;
+
     log::info!("syncing DB done");
     Ok(())
 }
+
+async fn expire_operation_requests(pool: &DbPool, ttl: Duration) -> Result<(), APIError> {
+    let conn = pool.get()?;
+    web::block::<_, _, APIError>(move || {
+        for contract in contract::Contract::get_all(&conn)? {
+            let expired_operation_requests = conn.transaction::<_, APIError, _>(|| {
+                let expired_operation_requests =
+                    operation_request::OperationRequest::expire_stale(&conn, &contract.id, ttl)?;
+                if !expired_operation_requests.expired.is_empty() {
+                    audit::record(
+                        &conn,
+                        audit::operation_requests_expired(contract.id, &expired_operation_requests),
+                    )?;
+                }
+
+                Ok(expired_operation_requests)
+            })?;
+            if !expired_operation_requests.expired.is_empty() {
+                log::info!(
+                    "expired {} operation requests of contract {}, renumbered {}",
+                    expired_operation_requests.expired.len(),
+                    contract.display_name,
+                    expired_operation_requests.renumbered.len()
+                );
+            }
+        }
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(())
+}
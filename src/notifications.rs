@@ -1,4 +1,10 @@
-use std::convert::TryInto;
+use std::{
+    collections::{BTreeSet, HashSet},
+    convert::TryInto,
+    sync::{mpsc, Mutex, RwLock},
+    thread,
+    time::Duration,
+};
 
 use bigdecimal::BigDecimal;
 use lettre::smtp::ConnectionReuseParameters;
@@ -9,90 +15,181 @@ use lettre::{
 };
 use lettre::{SmtpClient, Transport};
 use lettre_email::Email;
+use log::{info, warn};
 use native_tls::{Protocol, TlsConnector};
+use num_bigint::{BigInt, Sign};
+use serde::Serialize;
+use uuid::Uuid;
 
-use crate::{
-    api::models::operation_request::OperationRequestKind,
-    db::models::operation_request::OperationRequest, CONFIG,
-};
 use crate::{
     api::models::{common::SignableMessageInfo, error::APIError},
     db::models::{contract::Contract, user::User},
 };
+use crate::{
+    api::models::{
+        notification::NotificationPause,
+        operation_request::{OperationRequestKind, OperationRequestState},
+    },
+    db::models::operation_request::OperationRequest,
+    settings::{self, NotificationChannel, NotificationEvent, NotificationTemplate},
+    CONFIG,
+};
 
-pub fn notify_new_operation_request(
-    user: &User,
-    keyholders: &Vec<User>,
-    operation_request: &OperationRequest,
-    signable_message: &SignableMessageInfo,
-    contract: &Contract,
-) -> Result<(), APIError> {
-    let destinations = keyholders
-        .iter()
-        .filter(|keyholder| keyholder.id != user.id)
-        .flat_map(|user| user.email.clone())
-        .collect::<Vec<_>>();
-
-    if destinations.is_empty() {
-        return Ok(());
-    }
-
-    let amount_line = amount_line(operation_request, contract);
-    let target_address_line = target_address_line(operation_request);
-    let operation_request_kind: OperationRequestKind = operation_request.kind.try_into()?;
-    send_email(
-        destinations,
-        format!(
-            "{}: New {} operation request #{}",
-            contract.display_name, operation_request_kind, operation_request.nonce
-        ),
-        format!(
-"\
+const NEW_OPERATION_REQUEST_SUBJECT: &str = "{contract}: New {kind} operation request #{nonce}";
+const NEW_OPERATION_REQUEST_BODY: &str = "\
 <html>
 <head/>
 <body>
 <p>
-A new {} operation request #{} for {} is waiting for approval.<br>
+A new {kind} operation request #{nonce} for {contract} is waiting for approval.<br>
 <br>
-<b>Created by:</b> {}<br>
-<b>Kind:</b> {}<br>
-{}
-{}
+<b>Created by:</b> {created_by}<br>
+<b>Kind:</b> {kind}<br>
+{amount_line}
+{target_address_line}
 <br>
 To reproduce the hash shown by the ledger when approving this operation, use the following tezos-client command.<br>
-<pre>{}</pre>
+<pre>{tezos_client_command}</pre>
 <br>
 The output of the above command should show the following data.<br>
 <br>
 <b>Raw packed data:</b><br>
-<pre>0x{}</pre><br>
+<pre>0x{packed_data}</pre><br>
 <b>Ledger Blake2b hash:</b><br>
-<pre>{}</pre><br>
+<pre>{blake2b_hash}</pre><br>
 </p>
 </body>
 </html>
-",
-            operation_request_kind,
-            operation_request.nonce,
-            contract.display_name,
-            if !user.display_name.is_empty() { &user.display_name } else { &user.address },
-            operation_request_kind,
-            amount_line,
-            target_address_line,
-            signable_message.tezos_client_command,
-            signable_message.message,
-            signable_message.blake2b_hash
-        ),
-    )
+";
+
+const APPROVAL_RECEIVED_SUBJECT: &str =
+    "{contract}: {kind} operation request #{nonce} recieved an approval";
+const APPROVAL_RECEIVED_BODY: &str = "\
+<html>
+<head/>
+<body>
+<p>
+The {kind} operation request #{nonce} for {contract} has received an approval from {approver}.<br>
+<br>
+<b>Created by:</b> {created_by}<br>
+{uploaded_by_line}
+<b>Kind:</b> {kind}<br>
+{amount_line}
+{target_address_line}
+</p>
+</body>
+</html>
+";
+
+const MIN_APPROVALS_RECEIVED_SUBJECT: &str =
+    "{contract}: {kind} operation request #{nonce} fully approved";
+const MIN_APPROVALS_RECEIVED_BODY: &str = "\
+<html>
+<head/>
+<body>
+<p>
+The {kind} operation request #{nonce} for {contract} has been approved and it is ready to be injected.<br>
+<br>
+<b>Created by:</b> {created_by}<br>
+<b>Kind:</b> {kind}<br>
+{amount_line}
+{target_address_line}
+</p>
+</body>
+</html>
+";
+
+const INJECTION_SUBJECT: &str = "{contract}: {kind} operation request #{nonce} injected";
+const INJECTION_BODY: &str = "\
+<html>
+<head/>
+<body>
+<p>
+The {kind} operation request #{nonce} for {contract} has been injected.<br>
+<br>
+<b>Created by:</b> {created_by}<br>
+<b>Kind:</b> {kind}<br>
+{amount_line}
+{target_address_line}
+{operation_hash_line}
+</p>
+</body>
+</html>
+";
+
+pub fn notify_new_operation_request(
+    user: &User,
+    keyholders: &Vec<User>,
+    operation_request: &OperationRequest,
+    signable_message: &SignableMessageInfo,
+    contract: &Contract,
+) -> Result<(), APIError> {
+    let operation_request_kind: OperationRequestKind = operation_request.kind.try_into()?;
+    let mut values = template_values(user, operation_request, operation_request_kind, contract);
+    values.push((
+        "tezos_client_command",
+        signable_message.tezos_client_command.clone(),
+    ));
+    values.push(("packed_data", signable_message.message.clone()));
+    values.push(("blake2b_hash", signable_message.blake2b_hash.clone()));
+    let (subject, message) = email_content(
+        CONFIG.server.notification_templates(),
+        NotificationEvent::NewOperationRequest,
+        operation_request_kind,
+        &values,
+    );
+
+    let mut payload = WebhookPayload::new(
+        NotificationEvent::NewOperationRequest,
+        &subject,
+        user,
+        operation_request,
+        operation_request_kind,
+        contract,
+    )?;
+    payload.packed_data = Some(signable_message.message.clone());
+    payload.blake2b_hash = Some(signable_message.blake2b_hash.clone());
+    notify_via_webhook(contract, payload);
+
+    let destinations = keyholders
+        .iter()
+        .filter(|keyholder| keyholder.id != user.id)
+        .flat_map(|user| user.email.clone())
+        .collect::<Vec<_>>();
+    notify_via_email(contract, destinations, subject, message)
 }
 
 pub fn notify_approval_received(
     user: &User,
     approver: &User,
+    uploader: Option<&User>,
     keyholders: &Vec<User>,
     operation_request: &OperationRequest,
     contract: &Contract,
 ) -> Result<(), APIError> {
+    let operation_request_kind: OperationRequestKind = operation_request.kind.try_into()?;
+    let mut values = template_values(user, operation_request, operation_request_kind, contract);
+    values.push(("approver", display_name(approver).to_owned()));
+    values.push(("uploaded_by_line", uploaded_by_line(uploader)));
+    let (subject, message) = email_content(
+        CONFIG.server.notification_templates(),
+        NotificationEvent::ApprovalReceived,
+        operation_request_kind,
+        &values,
+    );
+
+    let mut payload = WebhookPayload::new(
+        NotificationEvent::ApprovalReceived,
+        &subject,
+        user,
+        operation_request,
+        operation_request_kind,
+        contract,
+    )?;
+    payload.approver = Some(approver.into());
+    payload.uploaded_by = uploader.map(WebhookUser::from);
+    notify_via_webhook(contract, payload);
+
     let mut destinations = keyholders
         .iter()
         .flat_map(|keyholder| {
@@ -105,53 +202,7 @@ pub fn notify_approval_received(
     if let Some(user_email) = user.email.as_ref() {
         destinations.push(user_email.clone())
     }
-    if destinations.is_empty() {
-        return Ok(());
-    }
-
-    let amount_line = amount_line(operation_request, contract);
-    let target_address_line = target_address_line(operation_request);
-    let operation_request_kind: OperationRequestKind = operation_request.kind.try_into()?;
-    send_email(
-        destinations,
-        format!(
-            "{}: {} operation request #{} recieved an approval",
-            contract.display_name, operation_request_kind, operation_request.nonce
-        ),
-        format!(
-            "\
-<html>
-<head/>
-<body>
-<p>
-The {} operation request #{} for {} has received an approval from {}.<br>
-<br>
-<b>Created by:</b> {}<br>
-<b>Kind:</b> {}<br>
-{}
-{}
-</p>
-</body>
-</html>
-",
-            operation_request_kind,
-            operation_request.nonce,
-            contract.display_name,
-            if !approver.display_name.is_empty() {
-                &approver.display_name
-            } else {
-                &approver.address
-            },
-            if !user.display_name.is_empty() {
-                &user.display_name
-            } else {
-                &user.address
-            },
-            operation_request_kind,
-            amount_line,
-            target_address_line
-        ),
-    )
+    notify_via_email(contract, destinations, subject, message)
 }
 
 pub fn notify_min_approvals_received(
@@ -160,6 +211,27 @@ pub fn notify_min_approvals_received(
     operation_request: &OperationRequest,
     contract: &Contract,
 ) -> Result<(), APIError> {
+    let operation_request_kind: OperationRequestKind = operation_request.kind.try_into()?;
+    let values = template_values(user, operation_request, operation_request_kind, contract);
+    let (subject, message) = email_content(
+        CONFIG.server.notification_templates(),
+        NotificationEvent::MinApprovalsReceived,
+        operation_request_kind,
+        &values,
+    );
+
+    notify_via_webhook(
+        contract,
+        WebhookPayload::new(
+            NotificationEvent::MinApprovalsReceived,
+            &subject,
+            user,
+            operation_request,
+            operation_request_kind,
+            contract,
+        )?,
+    );
+
     let mut destinations = keyholders
         .iter()
         .filter(|keyholder| keyholder.id != user.id)
@@ -168,47 +240,7 @@ pub fn notify_min_approvals_received(
     if let Some(user_email) = user.email.as_ref() {
         destinations.push(user_email.clone())
     }
-    if destinations.is_empty() {
-        return Ok(());
-    }
-    let amount_line = amount_line(operation_request, contract);
-    let target_address_line = target_address_line(operation_request);
-    let operation_request_kind: OperationRequestKind = operation_request.kind.try_into()?;
-    send_email(
-        destinations,
-        format!(
-            "{}: {} operation request #{} fully approved",
-            contract.display_name, operation_request_kind, operation_request.nonce
-        ),
-        format!(
-            "\
-<html>
-<head/>
-<body>
-<p>
-The {} operation request #{} for {} has been approved and it is ready to be injected.<br>
-<br>
-<b>Created by:</b> {}<br>
-<b>Kind:</b> {}<br>
-{}
-{}
-</p>
-</body>
-</html>
-",
-            operation_request_kind,
-            operation_request.nonce,
-            contract.display_name,
-            if !user.display_name.is_empty() {
-                &user.display_name
-            } else {
-                &user.address
-            },
-            operation_request_kind,
-            amount_line,
-            target_address_line
-        ),
-    )
+    notify_via_email(contract, destinations, subject, message)
 }
 
 pub fn notify_injection(
@@ -217,6 +249,31 @@ pub fn notify_injection(
     operation_request: &OperationRequest,
     contract: &Contract,
 ) -> Result<(), APIError> {
+    let operation_request_kind: OperationRequestKind = operation_request.kind.try_into()?;
+    let mut values = template_values(user, operation_request, operation_request_kind, contract);
+    values.push((
+        "operation_hash_line",
+        operation_hash_line(operation_request),
+    ));
+    let (subject, message) = email_content(
+        CONFIG.server.notification_templates(),
+        NotificationEvent::Injection,
+        operation_request_kind,
+        &values,
+    );
+
+    notify_via_webhook(
+        contract,
+        WebhookPayload::new(
+            NotificationEvent::Injection,
+            &subject,
+            user,
+            operation_request,
+            operation_request_kind,
+            contract,
+        )?,
+    );
+
     let mut destinations = keyholders
         .iter()
         .filter(|keyholder| keyholder.id != user.id)
@@ -225,61 +282,465 @@ pub fn notify_injection(
     if let Some(user_email) = user.email.as_ref() {
         destinations.push(user_email.clone())
     }
+    notify_via_email(contract, destinations, subject, message)
+}
+
+/// Queues the email to `destinations` and the `notification_ccs` of `contract`, if it notifies by
+/// email and there is anyone to send it to.
+fn notify_via_email(
+    contract: &Contract,
+    destinations: Vec<String>,
+    subject: String,
+    message: String,
+) -> Result<(), APIError> {
+    let contract_settings = contract_settings(contract);
+    if !contract_settings.map_or(true, |settings| {
+        settings.notifies_via(NotificationChannel::Email)
+    }) {
+        return Ok(());
+    }
+
+    let ccs = contract_settings.map_or(&[][..], |settings| settings.notification_ccs());
+    let (destinations, ccs) = recipients(destinations, ccs);
     if destinations.is_empty() {
         return Ok(());
     }
-    let amount_line = amount_line(operation_request, contract);
-    let target_address_line = target_address_line(operation_request);
-    let operation_hash_line = operation_hash_line(operation_request);
-    let operation_request_kind: OperationRequestKind = operation_request.kind.try_into()?;
-    send_email(
-        destinations,
-        format!(
-            "{}: {} operation request #{} injected",
-            contract.display_name, operation_request_kind, operation_request.nonce
+
+    dispatch(
+        &NOTIFICATION_PAUSES,
+        &EMAIL_QUEUE,
+        contract,
+        OutgoingEmail {
+            destinations,
+            ccs,
+            subject,
+            message,
+        },
+    )
+}
+
+/// Queues `payload` for each of the `webhook_urls` of `contract`, if it notifies via webhook.
+/// Failures are only logged, webhooks never fail the request that triggered them.
+fn notify_via_webhook(contract: &Contract, payload: WebhookPayload) {
+    let urls = match contract_settings(contract) {
+        Some(settings) if settings.notifies_via(NotificationChannel::Webhook) => {
+            settings.webhook_urls()
+        }
+        _ => return,
+    };
+
+    for url in urls {
+        let webhook = OutgoingWebhook {
+            url: url.clone(),
+            payload: payload.clone(),
+        };
+        if let Err(error) =
+            dispatch_webhook(&NOTIFICATION_PAUSES, &WEBHOOK_QUEUE, contract, webhook)
+        {
+            warn!("failed to queue webhook to {}: {}", url, error);
+        }
+    }
+}
+
+/// The placeholders available in the templates of all events.
+fn template_values(
+    user: &User,
+    operation_request: &OperationRequest,
+    operation_request_kind: OperationRequestKind,
+    contract: &Contract,
+) -> Vec<(&'static str, String)> {
+    vec![
+        ("contract", contract.display_name.clone()),
+        ("kind", operation_request_kind.to_string()),
+        ("nonce", operation_request.nonce.to_string()),
+        ("created_by", display_name(user).to_owned()),
+        ("amount_line", amount_line(operation_request, contract)),
+        (
+            "target_address_line",
+            target_address_line(operation_request),
         ),
-        format!(
-            "\
-<html>
-<head/>
-<body>
-<p>
-The {} operation request #{} for {} has been injected.<br>
-<br>
-<b>Created by:</b> {}<br>
-<b>Kind:</b> {}<br>
-{}
-{}
-{}
-</p>
-</body>
-</html>
-",
-            operation_request_kind,
-            operation_request.nonce,
+    ]
+}
+
+/// The subject and body of the `event` email about an operation request of `kind`. Each is taken
+/// from the configured template for `kind` if there is one, then from the configured template
+/// for all kinds, and defaults to the built-in one.
+fn email_content(
+    templates: &[NotificationTemplate],
+    event: NotificationEvent,
+    kind: OperationRequestKind,
+    values: &[(&str, String)],
+) -> (String, String) {
+    let configured = |field: fn(&NotificationTemplate) -> Option<&String>| {
+        [Some(kind), None].iter().find_map(|template_kind| {
+            templates
+                .iter()
+                .filter(|template| template.event == event && template.kind == *template_kind)
+                .find_map(field)
+        })
+    };
+    let (default_subject, default_body) = match event {
+        NotificationEvent::NewOperationRequest => {
+            (NEW_OPERATION_REQUEST_SUBJECT, NEW_OPERATION_REQUEST_BODY)
+        }
+        NotificationEvent::ApprovalReceived => (APPROVAL_RECEIVED_SUBJECT, APPROVAL_RECEIVED_BODY),
+        NotificationEvent::MinApprovalsReceived => {
+            (MIN_APPROVALS_RECEIVED_SUBJECT, MIN_APPROVALS_RECEIVED_BODY)
+        }
+        NotificationEvent::Injection => (INJECTION_SUBJECT, INJECTION_BODY),
+    };
+    let subject =
+        configured(|template| template.subject.as_ref()).map_or(default_subject, String::as_str);
+    let body = configured(|template| template.body.as_ref()).map_or(default_body, String::as_str);
+
+    (render(subject, values), render(body, values))
+}
+
+/// Replaces the `{name}` placeholders of `template` with their `values`. This is done in a single
+/// pass, so braces in the values are inserted as they are. Unknown placeholders are kept.
+fn render(template: &str, values: &[(&str, String)]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = rest.find('}').and_then(|end| {
+            values
+                .iter()
+                .find(|(name, _)| *name == &rest[1..end])
+                .map(|(_, value)| (value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                rendered.push_str(value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+
+    rendered
+}
+
+fn display_name(user: &User) -> &str {
+    if !user.display_name.is_empty() {
+        &user.display_name
+    } else {
+        &user.address
+    }
+}
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+lazy_static! {
+    static ref EMAIL_QUEUE: EmailQueue = EmailQueue::start(deliver_email);
+    static ref WEBHOOK_QUEUE: WebhookQueue = WebhookQueue::start_with(webhook_deliverer);
+    pub static ref NOTIFICATION_PAUSES: NotificationPauses =
+        NotificationPauses::new(CONFIG.server.notifications_paused());
+}
+
+/// Which notifications are currently paused, either all of them or those about single contracts.
+/// It starts from `settings::Server::notifications_paused` and is changed by admins at runtime.
+pub struct NotificationPauses {
+    paused: RwLock<bool>,
+    paused_contracts: RwLock<BTreeSet<Uuid>>,
+}
+
+impl NotificationPauses {
+    pub fn new(paused: bool) -> Self {
+        NotificationPauses {
+            paused: RwLock::new(paused),
+            paused_contracts: RwLock::new(BTreeSet::new()),
+        }
+    }
+
+    pub fn get(&self) -> NotificationPause {
+        NotificationPause {
+            paused: *self.paused.read().unwrap(),
+            paused_contracts: self
+                .paused_contracts
+                .read()
+                .unwrap()
+                .iter()
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Pauses or resumes the notifications about `contract_id`, or all of them without one.
+    pub fn set(&self, contract_id: Option<Uuid>, paused: bool) {
+        match contract_id {
+            Some(contract_id) => {
+                let mut paused_contracts = self.paused_contracts.write().unwrap();
+                if paused {
+                    paused_contracts.insert(contract_id);
+                } else {
+                    paused_contracts.remove(&contract_id);
+                }
+            }
+            None => *self.paused.write().unwrap() = paused,
+        }
+    }
+
+    pub fn is_paused(&self, contract_id: Uuid) -> bool {
+        *self.paused.read().unwrap() || self.paused_contracts.read().unwrap().contains(&contract_id)
+    }
+}
+
+/// Queues `email` about `contract`, unless its notifications are paused, in which case the email
+/// is only logged.
+fn dispatch(
+    pauses: &NotificationPauses,
+    queue: &EmailQueue,
+    contract: &Contract,
+    email: OutgoingEmail,
+) -> Result<(), APIError> {
+    if pauses.is_paused(contract.id) {
+        info!(
+            "Notifications about {} are paused, not sending \"{}\" to {}",
             contract.display_name,
-            if !user.display_name.is_empty() {
-                &user.display_name
-            } else {
-                &user.address
-            },
-            operation_request_kind,
-            amount_line,
-            target_address_line,
-            operation_hash_line
-        ),
-    )
+            email.subject,
+            email.destinations.join(", ")
+        );
+        return Ok(());
+    }
+
+    queue.push(email)
 }
 
-pub fn send_email(
-    destinations: Vec<String>,
-    subject: String,
-    message: String,
+/// Queues `webhook` about `contract`, unless its notifications are paused, in which case the
+/// webhook is only logged.
+fn dispatch_webhook(
+    pauses: &NotificationPauses,
+    queue: &WebhookQueue,
+    contract: &Contract,
+    webhook: OutgoingWebhook,
 ) -> Result<(), APIError> {
+    if pauses.is_paused(contract.id) {
+        info!(
+            "Notifications about {} are paused, not posting \"{}\" to {}",
+            contract.display_name, webhook.payload.text, webhook.url
+        );
+        return Ok(());
+    }
+
+    queue.push(webhook)
+}
+
+#[derive(Debug, Clone)]
+pub struct OutgoingEmail {
+    pub destinations: Vec<String>,
+    pub ccs: Vec<String>,
+    pub subject: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct OutgoingWebhook {
+    pub url: String,
+    pub payload: WebhookPayload,
+}
+
+/// The JSON body posted to webhooks. `text` is the subject of the corresponding email, which chat
+/// services such as Slack show as the message.
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookPayload {
+    pub event: NotificationEvent,
+    pub text: String,
+    pub contract: WebhookContract,
+    pub operation_request: WebhookOperationRequest,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approver: Option<WebhookUser>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uploaded_by: Option<WebhookUser>,
+    /// The packed data to sign and its hash, for new operation requests.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub packed_data: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blake2b_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookContract {
+    pub id: Uuid,
+    pub display_name: String,
+    pub address: String,
+    pub multisig: String,
+    pub token_id: i32,
+    pub symbol: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookOperationRequest {
+    pub id: Uuid,
+    pub kind: OperationRequestKind,
+    pub state: OperationRequestState,
+    pub nonce: i64,
+    pub created_by: WebhookUser,
+    /// In the units of the contract, like in the emails.
+    pub amount: Option<String>,
+    pub target_address: Option<String>,
+    pub threshold: Option<i64>,
+    pub ledger_blake2b_hash: Option<String>,
+    pub operation_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookUser {
+    pub address: String,
+    pub display_name: String,
+}
+
+impl From<&User> for WebhookUser {
+    fn from(user: &User) -> Self {
+        WebhookUser {
+            address: user.address.clone(),
+            display_name: user.display_name.clone(),
+        }
+    }
+}
+
+impl WebhookPayload {
+    fn new(
+        event: NotificationEvent,
+        text: &str,
+        user: &User,
+        operation_request: &OperationRequest,
+        operation_request_kind: OperationRequestKind,
+        contract: &Contract,
+    ) -> Result<Self, APIError> {
+        Ok(WebhookPayload {
+            event,
+            text: text.to_owned(),
+            contract: WebhookContract {
+                id: contract.id,
+                display_name: contract.display_name.clone(),
+                address: contract.pkh.clone(),
+                multisig: contract.multisig_pkh.clone(),
+                token_id: contract.token_id,
+                symbol: contract.symbol.clone(),
+            },
+            operation_request: WebhookOperationRequest {
+                id: operation_request.id,
+                kind: operation_request_kind,
+                state: operation_request.state.try_into()?,
+                nonce: operation_request.nonce,
+                created_by: user.into(),
+                amount: operation_request
+                    .amount
+                    .as_ref()
+                    .map(|amount| display_amount(amount, contract.decimals, None)),
+                target_address: operation_request.target_address.clone(),
+                threshold: operation_request.threshold,
+                ledger_blake2b_hash: operation_request.ledger_blake2b_hash.clone(),
+                operation_hash: operation_request.operation_hash.clone(),
+            },
+            approver: None,
+            uploaded_by: None,
+            packed_data: None,
+            blake2b_hash: None,
+        })
+    }
+}
+
+pub type EmailQueue = DeliveryQueue<OutgoingEmail>;
+pub type WebhookQueue = DeliveryQueue<OutgoingWebhook>;
+
+/// Delivers notifications on a background thread, so requests do not wait for the SMTP server or
+/// the webhooks.
+pub struct DeliveryQueue<T> {
+    sender: Mutex<Option<mpsc::Sender<T>>>,
+    done: Mutex<mpsc::Receiver<()>>,
+}
+
+impl<T: Send + 'static> DeliveryQueue<T> {
+    pub fn start<F>(deliver: F) -> Self
+    where
+        F: Fn(T) -> Result<(), APIError> + Send + 'static,
+    {
+        DeliveryQueue::start_with(move || deliver)
+    }
+
+    /// Same as `start`, but the function delivering the notifications is made by `make_deliver`
+    /// on the queue's thread, so it can keep state which can't be sent across threads.
+    pub fn start_with<M, F>(make_deliver: M) -> Self
+    where
+        M: FnOnce() -> F + Send + 'static,
+        F: FnMut(T) -> Result<(), APIError>,
+    {
+        let (sender, receiver) = mpsc::channel::<T>();
+        let (done_sender, done) = mpsc::channel();
+        thread::spawn(move || {
+            let mut deliver = make_deliver();
+            for notification in receiver {
+                if let Err(error) = deliver(notification) {
+                    warn!("failed to send notification: {}", error);
+                }
+            }
+            let _ = done_sender.send(());
+        });
+
+        DeliveryQueue {
+            sender: Mutex::new(Some(sender)),
+            done: Mutex::new(done),
+        }
+    }
+
+    pub fn push(&self, notification: T) -> Result<(), APIError> {
+        let sender = self.sender.lock().map_err(|_error| APIError::Internal {
+            description: "notification queue is unavailable".into(),
+        })?;
+        match sender.as_ref() {
+            Some(sender) => sender
+                .send(notification)
+                .map_err(|_error| APIError::Internal {
+                    description: "notification queue is closed".into(),
+                }),
+            None => Err(APIError::Internal {
+                description: "notification queue is shutting down".into(),
+            }),
+        }
+    }
+
+    /// Stops accepting new notifications and waits up to `timeout` for the queued ones to be sent.
+    /// Returns `false` if the queue could not be drained in time.
+    pub fn drain(&self, timeout: Duration) -> bool {
+        if let Ok(mut sender) = self.sender.lock() {
+            sender.take();
+        }
+        match self.done.lock() {
+            Ok(done) => done.recv_timeout(timeout).is_ok(),
+            Err(_) => false,
+        }
+    }
+}
+
+pub fn drain_email_queue(timeout: Duration) -> bool {
+    EMAIL_QUEUE.drain(timeout)
+}
+
+pub fn drain_webhook_queue(timeout: Duration) -> bool {
+    WEBHOOK_QUEUE.drain(timeout)
+}
+
+fn deliver_email(email: OutgoingEmail) -> Result<(), APIError> {
+    let OutgoingEmail {
+        destinations,
+        ccs,
+        subject,
+        message,
+    } = email;
     let mut email_builder = Email::builder();
     for destination in destinations {
         email_builder = email_builder.to(destination);
     }
+    for cc in ccs {
+        email_builder = email_builder.cc(cc);
+    }
     let email = email_builder
         .from(CONFIG.smtp.user.as_ref())
         .subject(subject)
@@ -291,10 +752,7 @@ pub fn send_email(
     let tls_parameters = ClientTlsParameters::new(CONFIG.smtp.host.clone(), tls_builder.build()?);
 
     let mut mailer = SmtpClient::new(
-        (
-            &CONFIG.smtp.host[..],
-            u16::from_str_radix(&CONFIG.smtp.port, 10)?,
-        ),
+        (&CONFIG.smtp.host[..], CONFIG.smtp.port),
         ClientSecurity::Required(tls_parameters),
     )?
     .authentication_mechanism(Mechanism::Login)
@@ -310,24 +768,126 @@ pub fn send_email(
     Ok(())
 }
 
+/// Posts the payloads of the webhooks. The queue's thread has no runtime for the requests, so one
+/// is started with the thread and used for all of them.
+fn webhook_deliverer() -> impl FnMut(OutgoingWebhook) -> Result<(), APIError> {
+    let mut runtime = actix_web::rt::System::new("webhook");
+
+    move |webhook| runtime.block_on(post_webhook(webhook))
+}
+
+async fn post_webhook(webhook: OutgoingWebhook) -> Result<(), APIError> {
+    let failure = |error: &dyn std::fmt::Display| APIError::Internal {
+        description: format!("webhook {} failed: {}", webhook.url, error),
+    };
+    let response = reqwest::Client::builder()
+        .timeout(WEBHOOK_TIMEOUT)
+        .build()
+        .map_err(|error| failure(&error))?
+        .post(&webhook.url)
+        .json(&webhook.payload)
+        .send()
+        .await
+        .map_err(|error| failure(&error))?;
+    if !response.status().is_success() {
+        return Err(failure(&response.status()));
+    }
+
+    Ok(())
+}
+
+/// The settings of `contract`, for its notification channels and fixed recipients.
+fn contract_settings(contract: &Contract) -> Option<&'static settings::Contract> {
+    settings::Contract::find(&CONFIG.contracts, contract)
+}
+
+/// Removes duplicate addresses, ignoring their case, from `destinations` and from `ccs`, as well
+/// as the `ccs` already in `destinations`. The `ccs` become the destinations if there are no
+/// others.
+fn recipients(destinations: Vec<String>, ccs: &[String]) -> (Vec<String>, Vec<String>) {
+    let mut seen = HashSet::new();
+    let mut unique = |addresses: Vec<String>| {
+        addresses
+            .into_iter()
+            .filter(|address| seen.insert(address.to_lowercase()))
+            .collect::<Vec<_>>()
+    };
+    let destinations = unique(destinations);
+    let ccs = unique(ccs.to_vec());
+
+    if destinations.is_empty() {
+        (ccs, vec![])
+    } else {
+        (destinations, ccs)
+    }
+}
+
 fn amount_line(operation_request: &OperationRequest, contract: &Contract) -> String {
-    let amount = operation_request
-        .amount
-        .as_ref()
-        .map(|amount| amount.as_bigint_and_exponent().0);
-
-    let human_readable_amount =
-        amount.map(|amount| BigDecimal::new(amount, contract.decimals.into()).to_string());
-    match human_readable_amount {
+    match operation_request.amount.as_ref() {
         Some(amount) => format!(
             "<b>Amount:</b> {} {}<br>",
-            amount.trim_end_matches("0").trim_end_matches("."),
+            display_amount(
+                amount,
+                contract.decimals,
+                CONFIG.server.notification_max_fraction_digits
+            ),
             contract.symbol
         ),
         None => "".into(),
     }
 }
 
+/// Formats a raw token amount as stored, in the contract's units. The stored value may carry an
+/// exponent (`1e3` for `1000`), so it is rescaled to an integer before its digits are used, and
+/// negative decimals are treated as `0`.
+fn display_amount(amount: &BigDecimal, decimals: i32, max_fraction_digits: Option<u32>) -> String {
+    let (amount, _) = amount.with_scale(0).as_bigint_and_exponent();
+
+    format_amount(
+        &amount,
+        decimals.try_into().unwrap_or(0),
+        max_fraction_digits,
+    )
+}
+
+/// Formats a raw token amount with the given number of decimals, without trailing zeros.
+///
+/// When `max_fraction_digits` is set, the fractional part is truncated to that many digits,
+/// except for amounts smaller than one which keep their first significant digit.
+fn format_amount(amount: &BigInt, decimals: usize, max_fraction_digits: Option<u32>) -> String {
+    let digits = amount.magnitude().to_str_radix(10);
+    let digits = if digits.len() <= decimals {
+        format!("{}{}", "0".repeat(decimals + 1 - digits.len()), digits)
+    } else {
+        digits
+    };
+    let (integer, fraction) = digits.split_at(digits.len() - decimals);
+    let mut fraction = fraction.trim_end_matches('0');
+    if let Some(max_fraction_digits) = max_fraction_digits {
+        let max_fraction_digits = max_fraction_digits as usize;
+        if fraction.len() > max_fraction_digits {
+            let kept = if integer == "0" {
+                let first_significant = fraction.find(|digit| digit != '0').unwrap_or(0) + 1;
+                max_fraction_digits.max(first_significant)
+            } else {
+                max_fraction_digits
+            };
+            fraction = fraction[..kept].trim_end_matches('0');
+        }
+    }
+
+    let sign = if amount.sign() == Sign::Minus {
+        "-"
+    } else {
+        ""
+    };
+    if fraction.is_empty() {
+        format!("{}{}", sign, integer)
+    } else {
+        format!("{}{}.{}", sign, integer, fraction)
+    }
+}
+
 fn target_address_line(operation_request: &OperationRequest) -> String {
     match operation_request.target_address.as_ref() {
         Some(target_address) => format!("<b>To:</b> {}<br>", target_address),
@@ -335,9 +895,546 @@ fn target_address_line(operation_request: &OperationRequest) -> String {
     }
 }
 
+fn uploaded_by_line(uploader: Option<&User>) -> String {
+    match uploader {
+        Some(uploader) if !uploader.display_name.is_empty() => {
+            format!("<b>Uploaded by:</b> {}<br>", uploader.display_name)
+        }
+        Some(uploader) => format!("<b>Uploaded by:</b> {}<br>", uploader.address),
+        None => "".into(),
+    }
+}
+
 fn operation_hash_line(operation_request: &OperationRequest) -> String {
     match operation_request.operation_hash.as_ref() {
         Some(operation_hash) => format!("<b>Operation Group Hash:</b> {}<br>", operation_hash),
         None => "".into(),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{Arc, Mutex, Once},
+        thread,
+        time::Duration,
+    };
+
+    use bigdecimal::BigDecimal;
+    use num_bigint::BigInt;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::{
+        dispatch, dispatch_webhook, display_amount, email_content, format_amount, recipients,
+        render, EmailQueue, NotificationPauses, OutgoingEmail, OutgoingWebhook, WebhookPayload,
+        WebhookQueue, WebhookUser,
+    };
+    use crate::{
+        api::models::{
+            notification::NotificationPause, operation_request::OperationRequestKind,
+            user::UserKind,
+        },
+        db::models::{contract::Contract, operation_request::OperationRequest, user::User},
+        fixtures,
+        settings::{NotificationEvent, NotificationTemplate},
+    };
+
+    /// Keeps the messages logged while the tests run, so they can be checked.
+    struct CapturedLogs(Mutex<Vec<String>>);
+
+    impl log::Log for CapturedLogs {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
+
+        fn flush(&self) {}
+    }
+
+    lazy_static! {
+        static ref LOGS: CapturedLogs = CapturedLogs(Mutex::new(vec![]));
+    }
+
+    fn logs() -> &'static Mutex<Vec<String>> {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            log::set_logger(&*LOGS).unwrap();
+            log::set_max_level(log::LevelFilter::Info);
+        });
+
+        &LOGS.0
+    }
+
+    fn contract(display_name: &str) -> Contract {
+        Contract {
+            display_name: display_name.into(),
+            ..fixtures::contract()
+        }
+    }
+
+    fn email(index: usize) -> OutgoingEmail {
+        OutgoingEmail {
+            destinations: vec!["keyholder@example.com".into()],
+            ccs: vec![],
+            subject: format!("Operation request #{}", index),
+            message: "".into(),
+        }
+    }
+
+    #[test]
+    fn test_drain_flushes_queued_emails() {
+        let delivered = Arc::new(Mutex::new(Vec::<String>::new()));
+        let sink = delivered.clone();
+        let queue = EmailQueue::start(move |email| {
+            thread::sleep(Duration::from_millis(10));
+            sink.lock().unwrap().push(email.subject);
+            Ok(())
+        });
+
+        for index in 0..5 {
+            queue.push(email(index)).unwrap();
+        }
+
+        assert!(queue.drain(Duration::from_secs(2)));
+        assert_eq!(delivered.lock().unwrap().len(), 5);
+        assert!(queue.push(email(5)).is_err());
+    }
+
+    #[test]
+    fn test_deliverer_made_once_on_queue_thread() {
+        let made = Arc::new(Mutex::new(Vec::<thread::ThreadId>::new()));
+        let delivered = Arc::new(Mutex::new(Vec::<String>::new()));
+        let (made_on, sink) = (made.clone(), delivered.clone());
+        let queue = EmailQueue::start_with(move || {
+            made_on.lock().unwrap().push(thread::current().id());
+            move |email: OutgoingEmail| {
+                sink.lock().unwrap().push(email.subject);
+                Ok(())
+            }
+        });
+
+        for index in 0..3 {
+            queue.push(email(index)).unwrap();
+        }
+
+        assert!(queue.drain(Duration::from_secs(2)));
+        assert_eq!(delivered.lock().unwrap().len(), 3);
+        let made = made.lock().unwrap();
+        assert_eq!(made.len(), 1);
+        assert_ne!(made[0], thread::current().id());
+    }
+
+    #[test]
+    fn test_drain_times_out() {
+        let queue = EmailQueue::start(|_email| {
+            thread::sleep(Duration::from_millis(500));
+            Ok(())
+        });
+        queue.push(email(0)).unwrap();
+
+        assert!(!queue.drain(Duration::from_millis(20)));
+    }
+
+    #[test]
+    fn test_notification_pauses() {
+        let pauses = NotificationPauses::new(false);
+        let contract_id = Uuid::new_v4();
+        assert!(!pauses.is_paused(contract_id));
+
+        pauses.set(Some(contract_id), true);
+        assert!(pauses.is_paused(contract_id));
+        assert!(!pauses.is_paused(Uuid::new_v4()));
+        assert_eq!(
+            pauses.get(),
+            NotificationPause {
+                paused: false,
+                paused_contracts: vec![contract_id],
+            }
+        );
+
+        pauses.set(None, true);
+        assert!(pauses.is_paused(Uuid::new_v4()));
+
+        pauses.set(None, false);
+        pauses.set(Some(contract_id), false);
+        assert!(!pauses.is_paused(contract_id));
+        assert_eq!(pauses.get(), NotificationPause::default());
+
+        assert!(NotificationPauses::new(true).is_paused(contract_id));
+    }
+
+    #[test]
+    fn test_paused_notifications_are_logged_not_sent() {
+        let logs = logs();
+        let delivered = Arc::new(Mutex::new(Vec::<String>::new()));
+        let sink = delivered.clone();
+        let queue = EmailQueue::start(move |email| {
+            sink.lock().unwrap().push(email.subject);
+            Ok(())
+        });
+        let pauses = NotificationPauses::new(false);
+        let paused_contract = contract("Paused Token");
+        let other_contract = contract("Other Token");
+
+        pauses.set(Some(paused_contract.id), true);
+        dispatch(&pauses, &queue, &paused_contract, email(0)).unwrap();
+        dispatch(&pauses, &queue, &other_contract, email(1)).unwrap();
+        pauses.set(None, true);
+        dispatch(&pauses, &queue, &other_contract, email(2)).unwrap();
+
+        assert!(queue.drain(Duration::from_secs(2)));
+        assert_eq!(*delivered.lock().unwrap(), vec!["Operation request #1"]);
+
+        let logs = logs.lock().unwrap();
+        for (contract, index) in &[("Paused Token", 0), ("Other Token", 2)] {
+            let message = format!(
+                "Notifications about {} are paused, not sending \"Operation request #{}\" to keyholder@example.com",
+                contract, index
+            );
+            assert!(logs.contains(&message), "{}", message);
+        }
+        assert!(!logs
+            .iter()
+            .any(|message| message.contains("\"Operation request #1\"")));
+    }
+
+    fn user(address: &str, display_name: &str) -> User {
+        User {
+            address: address.into(),
+            display_name: display_name.into(),
+            ..fixtures::user(Uuid::new_v4(), UserKind::Gatekeeper)
+        }
+    }
+
+    fn operation_request(contract: &Contract, user: &User) -> OperationRequest {
+        OperationRequest {
+            user_id: user.id,
+            amount: Some(BigDecimal::from(1_500_000i64)),
+            nonce: 7,
+            ledger_blake2b_hash: Some("5pCeZtVdkGBPSn6GcYBqBmQ2K6X4UNPiYbHVk2MBA6J7".into()),
+            ..fixtures::operation_request(contract.id)
+        }
+    }
+
+    fn webhook(contract: &Contract, index: usize) -> OutgoingWebhook {
+        let gatekeeper = user("tz1Ts3m2dXTXB66XN7cg5ALiAvzZY6AxrFd9", "Gatekeeper");
+        OutgoingWebhook {
+            url: "https://hooks.example.com/treasury".into(),
+            payload: WebhookPayload::new(
+                NotificationEvent::Injection,
+                &format!("Operation request #{}", index),
+                &gatekeeper,
+                &operation_request(contract, &gatekeeper),
+                OperationRequestKind::Mint,
+                contract,
+            )
+            .unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_webhook_payload() {
+        let mut contract = contract("Wrapped Token");
+        contract.decimals = 6;
+        contract.symbol = "wTKN".into();
+        let gatekeeper = user("tz1Ts3m2dXTXB66XN7cg5ALiAvzZY6AxrFd9", "Gatekeeper");
+        let approver = user("tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT", "");
+        let operation_request = operation_request(&contract, &gatekeeper);
+
+        let mut payload = WebhookPayload::new(
+            NotificationEvent::ApprovalReceived,
+            "Wrapped Token: approval received",
+            &gatekeeper,
+            &operation_request,
+            OperationRequestKind::Mint,
+            &contract,
+        )
+        .unwrap();
+        payload.approver = Some(WebhookUser::from(&approver));
+
+        assert_eq!(
+            serde_json::to_value(&payload).unwrap(),
+            json!({
+                "event": "approval_received",
+                "text": "Wrapped Token: approval received",
+                "contract": {
+                    "id": contract.id,
+                    "display_name": "Wrapped Token",
+                    "address": "KT1S4QXjASmM3ei7a6raRLkr4TbnKu7RuC9X",
+                    "multisig": "KT1MeQd67djiEEkscx3USfQ7vFYg589DySpZ",
+                    "token_id": 0,
+                    "symbol": "wTKN"
+                },
+                "operation_request": {
+                    "id": operation_request.id,
+                    "kind": "mint",
+                    "state": "open",
+                    "nonce": 7,
+                    "created_by": {
+                        "address": "tz1Ts3m2dXTXB66XN7cg5ALiAvzZY6AxrFd9",
+                        "display_name": "Gatekeeper"
+                    },
+                    "amount": "1.5",
+                    "target_address": "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT",
+                    "threshold": null,
+                    "ledger_blake2b_hash": "5pCeZtVdkGBPSn6GcYBqBmQ2K6X4UNPiYbHVk2MBA6J7",
+                    "operation_hash": null
+                },
+                "approver": {
+                    "address": "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT",
+                    "display_name": ""
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_paused_webhooks_are_logged_not_sent() {
+        let logs = logs();
+        let delivered = Arc::new(Mutex::new(Vec::<String>::new()));
+        let sink = delivered.clone();
+        let queue = WebhookQueue::start(move |webhook| {
+            sink.lock().unwrap().push(webhook.payload.text);
+            Ok(())
+        });
+        let pauses = NotificationPauses::new(false);
+        let paused_contract = contract("Paused Webhook Token");
+        let other_contract = contract("Other Webhook Token");
+
+        pauses.set(Some(paused_contract.id), true);
+        dispatch_webhook(
+            &pauses,
+            &queue,
+            &paused_contract,
+            webhook(&paused_contract, 0),
+        )
+        .unwrap();
+        dispatch_webhook(
+            &pauses,
+            &queue,
+            &other_contract,
+            webhook(&other_contract, 1),
+        )
+        .unwrap();
+
+        assert!(queue.drain(Duration::from_secs(2)));
+        assert_eq!(*delivered.lock().unwrap(), vec!["Operation request #1"]);
+
+        let message = "Notifications about Paused Webhook Token are paused, not posting \"Operation request #0\" to https://hooks.example.com/treasury";
+        assert!(
+            logs.lock().unwrap().iter().any(|logged| logged == message),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn test_format_amount() {
+        let amount = |value: &str| value.parse::<BigInt>().unwrap();
+
+        assert_eq!(format_amount(&amount("0"), 6, None), "0");
+        assert_eq!(format_amount(&amount("0"), 0, None), "0");
+        assert_eq!(format_amount(&amount("100000000"), 6, None), "100");
+        assert_eq!(format_amount(&amount("1"), 6, None), "0.000001");
+        assert_eq!(format_amount(&amount("1500"), 0, None), "1500");
+        assert_eq!(
+            format_amount(&amount("123456789012345678901234567890"), 18, None),
+            "123456789012.34567890123456789"
+        );
+    }
+
+    #[test]
+    fn test_format_amount_without_decimals() {
+        let amount = |value: &str| value.parse::<BigInt>().unwrap();
+
+        assert_eq!(format_amount(&amount("1"), 0, None), "1");
+        assert_eq!(format_amount(&amount("-42"), 0, None), "-42");
+        assert_eq!(format_amount(&amount("1000"), 0, Some(0)), "1000");
+        assert_eq!(format_amount(&amount("1000"), 0, Some(2)), "1000");
+        assert_eq!(
+            format_amount(&amount("123456789012345678901234567890"), 0, None),
+            "123456789012345678901234567890"
+        );
+    }
+
+    #[test]
+    fn test_format_amount_with_18_decimals() {
+        let amount = |value: &str| value.parse::<BigInt>().unwrap();
+
+        assert_eq!(
+            format_amount(&amount("1"), 18, None),
+            "0.000000000000000001"
+        );
+        assert_eq!(
+            format_amount(&amount("1"), 18, Some(2)),
+            "0.000000000000000001"
+        );
+        assert_eq!(format_amount(&amount("1000000000000000000"), 18, None), "1");
+        assert_eq!(
+            format_amount(&amount("1000000000000000001"), 18, None),
+            "1.000000000000000001"
+        );
+        assert_eq!(
+            format_amount(&amount("-999999999999999999"), 18, None),
+            "-0.999999999999999999"
+        );
+        assert_eq!(format_amount(&amount("0"), 36, None), "0");
+    }
+
+    #[test]
+    fn test_display_amount() {
+        let amount = |value: &str| value.parse::<BigDecimal>().unwrap();
+
+        assert_eq!(display_amount(&amount("1500"), 0, None), "1500");
+        assert_eq!(display_amount(&amount("1e3"), 0, None), "1000");
+        assert_eq!(display_amount(&amount("1e3"), 3, None), "1");
+        assert_eq!(display_amount(&amount("1e18"), 18, None), "1");
+        assert_eq!(
+            display_amount(&amount("123456789012345678901234567890"), 18, Some(6)),
+            "123456789012.345678"
+        );
+        assert_eq!(display_amount(&amount("1500"), -2, None), "1500");
+    }
+
+    #[test]
+    fn test_format_amount_max_fraction_digits() {
+        let amount = |value: &str| value.parse::<BigInt>().unwrap();
+
+        assert_eq!(format_amount(&amount("0"), 6, Some(2)), "0");
+        assert_eq!(format_amount(&amount("100000000"), 6, Some(2)), "100");
+        assert_eq!(format_amount(&amount("1"), 6, Some(2)), "0.000001");
+        assert_eq!(format_amount(&amount("1234567"), 6, Some(2)), "1.23");
+        assert_eq!(format_amount(&amount("1001"), 3, Some(2)), "1");
+        assert_eq!(format_amount(&amount("1500"), 3, Some(0)), "1");
+        assert_eq!(
+            format_amount(&amount("123456789012345678901234567890"), 18, Some(6)),
+            "123456789012.345678"
+        );
+    }
+
+    #[test]
+    fn test_recipients() {
+        let ccs = vec![
+            "security@example.com".to_owned(),
+            "Keyholder@example.com".to_owned(),
+            "security@example.com".to_owned(),
+        ];
+
+        let (destinations, ccs_only) = recipients(
+            vec![
+                "keyholder@example.com".into(),
+                "gatekeeper@example.com".into(),
+                "keyholder@example.com".into(),
+            ],
+            &ccs,
+        );
+        assert_eq!(
+            destinations,
+            vec!["keyholder@example.com", "gatekeeper@example.com"]
+        );
+        assert_eq!(ccs_only, vec!["security@example.com"]);
+
+        let (destinations, ccs_only) = recipients(vec![], &ccs);
+        assert_eq!(
+            destinations,
+            vec!["security@example.com", "Keyholder@example.com"]
+        );
+        assert!(ccs_only.is_empty());
+
+        let (destinations, ccs_only) = recipients(vec!["keyholder@example.com".into()], &[]);
+        assert_eq!(destinations, vec!["keyholder@example.com"]);
+        assert!(ccs_only.is_empty());
+    }
+
+    fn template_values() -> Vec<(&'static str, String)> {
+        vec![
+            ("contract", "tzBTC".into()),
+            ("kind", "Multi Signature Update".into()),
+            ("nonce", "7".into()),
+            ("created_by", "Gatekeeper".into()),
+            ("amount_line", "".into()),
+            ("target_address_line", "".into()),
+            ("tezos_client_command", "tezos-client hash data".into()),
+            ("packed_data", "05".into()),
+            ("blake2b_hash", "hash".into()),
+        ]
+    }
+
+    #[test]
+    fn test_email_content_kind_specific_template() {
+        let templates = vec![
+            NotificationTemplate {
+                event: NotificationEvent::NewOperationRequest,
+                kind: Some(OperationRequestKind::UpdateKeyholders),
+                subject: Some("{contract}: the keyholders change with #{nonce}".into()),
+                body: None,
+            },
+            NotificationTemplate {
+                event: NotificationEvent::NewOperationRequest,
+                kind: None,
+                subject: Some("{contract}: #{nonce}".into()),
+                body: Some("{created_by} created #{nonce}".into()),
+            },
+        ];
+
+        let (subject, body) = email_content(
+            &templates,
+            NotificationEvent::NewOperationRequest,
+            OperationRequestKind::UpdateKeyholders,
+            &template_values(),
+        );
+        assert_eq!(subject, "tzBTC: the keyholders change with #7");
+        assert_eq!(body, "Gatekeeper created #7");
+
+        let (subject, _) = email_content(
+            &templates,
+            NotificationEvent::NewOperationRequest,
+            OperationRequestKind::Mint,
+            &template_values(),
+        );
+        assert_eq!(subject, "tzBTC: #7");
+
+        let (subject, body) = email_content(
+            &templates,
+            NotificationEvent::MinApprovalsReceived,
+            OperationRequestKind::UpdateKeyholders,
+            &template_values(),
+        );
+        assert_eq!(
+            subject,
+            "tzBTC: Multi Signature Update operation request #7 fully approved"
+        );
+        assert!(body.contains("<b>Created by:</b> Gatekeeper<br>"));
+    }
+
+    #[test]
+    fn test_email_content_defaults() {
+        let (subject, body) = email_content(
+            &[],
+            NotificationEvent::NewOperationRequest,
+            OperationRequestKind::UpdateKeyholders,
+            &template_values(),
+        );
+        assert_eq!(
+            subject,
+            "tzBTC: New Multi Signature Update operation request #7"
+        );
+        assert!(body.contains("<pre>tezos-client hash data</pre>"));
+        assert!(body.contains("<pre>0x05</pre>"));
+        assert!(!body.contains('{'));
+    }
+
+    #[test]
+    fn test_render() {
+        let values = vec![("name", "{nonce}".to_owned()), ("nonce", "7".to_owned())];
+
+        assert_eq!(render("{name} #{nonce}", &values), "{nonce} #7");
+        assert_eq!(render("{unknown} {nonce", &values), "{unknown} {nonce");
+        assert_eq!(render("{{nonce}}", &values), "{7}");
+        assert_eq!(render("", &values), "");
+    }
+}
@@ -0,0 +1,226 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use actix_web::rt::{self, time::delay_for};
+use log::{info, warn};
+
+use crate::{api::models::error::APIError, settings};
+
+type JobFuture = Pin<Box<dyn Future<Output = Result<(), APIError>>>>;
+
+struct Job {
+    name: String,
+    interval: Duration,
+    run: Box<dyn Fn() -> JobFuture>,
+}
+
+/// Runs named jobs periodically. Each job can be enabled, disabled or given a different
+/// interval through the `[jobs.<name>]` settings.
+pub struct Scheduler {
+    settings: HashMap<String, settings::Job>,
+    jobs: Vec<Job>,
+}
+
+impl Scheduler {
+    pub fn new(settings: HashMap<String, settings::Job>) -> Self {
+        Scheduler {
+            settings,
+            jobs: vec![],
+        }
+    }
+
+    /// Registers a job. Jobs without a `default_interval` only run if an interval is configured.
+    pub fn register<F, Fut>(&mut self, name: &str, default_interval: Option<Duration>, run: F)
+    where
+        F: Fn() -> Fut + 'static,
+        Fut: Future<Output = Result<(), APIError>> + 'static,
+    {
+        let job_settings = self.settings.get(name);
+        let enabled = job_settings
+            .and_then(|job_settings| job_settings.enabled)
+            .unwrap_or(true);
+        let interval = job_settings
+            .and_then(|job_settings| job_settings.interval_seconds)
+            .map(Duration::from_secs)
+            .or(default_interval);
+
+        match interval {
+            Some(interval) if enabled => {
+                info!("scheduling job {} every {:?}", name, interval);
+                self.jobs.push(Job {
+                    name: name.to_owned(),
+                    interval,
+                    run: Box::new(move || Box::pin(run())),
+                });
+            }
+            _ => info!("job {} is disabled", name),
+        }
+    }
+
+    pub fn start(self) -> SchedulerHandle {
+        let handle = SchedulerHandle {
+            stopping: Arc::new(AtomicBool::new(false)),
+            running: Arc::new(AtomicUsize::new(0)),
+        };
+        for job in self.jobs {
+            let stopping = handle.stopping.clone();
+            let running = handle.running.clone();
+            rt::spawn(async move {
+                loop {
+                    delay_for(job.interval).await;
+                    if stopping.load(Ordering::SeqCst) {
+                        break;
+                    }
+
+                    running.fetch_add(1, Ordering::SeqCst);
+                    let started_at = Instant::now();
+                    let result = (job.run)().await;
+                    let duration = started_at.elapsed();
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    match result {
+                        Ok(_) => info!("job {} succeeded in {:?}", job.name, duration),
+                        Err(error) => {
+                            warn!("job {} failed in {:?}: {}", job.name, duration, error)
+                        }
+                    }
+                }
+            });
+        }
+
+        handle
+    }
+}
+
+pub struct SchedulerHandle {
+    stopping: Arc<AtomicBool>,
+    running: Arc<AtomicUsize>,
+}
+
+impl SchedulerHandle {
+    /// Stops scheduling new runs and waits up to `timeout` for running jobs to finish.
+    /// Returns `false` if some jobs were still running when the timeout elapsed.
+    pub async fn shutdown(self, timeout: Duration) -> bool {
+        self.stopping.store(true, Ordering::SeqCst);
+
+        let started_at = Instant::now();
+        while self.running.load(Ordering::SeqCst) > 0 {
+            if started_at.elapsed() >= timeout {
+                return false;
+            }
+            delay_for(Duration::from_millis(10)).await;
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::HashMap,
+        future::{ready, Ready},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use actix_web::rt::time::delay_for;
+
+    use super::Scheduler;
+    use crate::{api::models::error::APIError, settings};
+
+    fn counting_job(counter: &Arc<AtomicUsize>) -> impl Fn() -> Ready<Result<(), APIError>> {
+        let counter = counter.clone();
+        move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            ready(Ok(()))
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_job_runs_at_interval_until_shutdown() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let mut scheduler = Scheduler::new(HashMap::new());
+        scheduler.register(
+            "count",
+            Some(Duration::from_millis(20)),
+            counting_job(&counter),
+        );
+        let handle = scheduler.start();
+
+        // only the order of events is checked, a slow machine may delay the runs arbitrarily
+        for _ in 0..100 {
+            if counter.load(Ordering::SeqCst) >= 2 {
+                break;
+            }
+            delay_for(Duration::from_millis(10)).await;
+        }
+        assert!(counter.load(Ordering::SeqCst) >= 2);
+
+        assert!(handle.shutdown(Duration::from_secs(1)).await);
+        let runs = counter.load(Ordering::SeqCst);
+
+        delay_for(Duration::from_millis(100)).await;
+        assert_eq!(counter.load(Ordering::SeqCst), runs);
+    }
+
+    #[actix_rt::test]
+    async fn test_disabled_job_does_not_run() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let mut job_settings = HashMap::new();
+        job_settings.insert(
+            "count".to_owned(),
+            settings::Job {
+                enabled: Some(false),
+                interval_seconds: None,
+            },
+        );
+        let mut scheduler = Scheduler::new(job_settings);
+        scheduler.register(
+            "count",
+            Some(Duration::from_millis(10)),
+            counting_job(&counter),
+        );
+        scheduler.register("unconfigured", None, counting_job(&counter));
+        scheduler.start();
+
+        delay_for(Duration::from_millis(50)).await;
+
+        assert_eq!(counter.load(Ordering::SeqCst), 0);
+    }
+
+    #[actix_rt::test]
+    async fn test_shutdown_waits_for_running_job() {
+        let finished = Arc::new(AtomicUsize::new(0));
+        let job_finished = finished.clone();
+        let mut scheduler = Scheduler::new(HashMap::new());
+        scheduler.register("slow", Some(Duration::from_millis(10)), move || {
+            let finished = job_finished.clone();
+            async move {
+                delay_for(Duration::from_millis(50)).await;
+                finished.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        });
+        let handle = scheduler.start();
+
+        delay_for(Duration::from_millis(20)).await;
+        assert!(handle.shutdown(Duration::from_secs(1)).await);
+
+        let runs = finished.load(Ordering::SeqCst);
+        assert_eq!(runs, 1);
+
+        delay_for(Duration::from_millis(100)).await;
+        assert_eq!(finished.load(Ordering::SeqCst), runs);
+    }
+}
@@ -1,35 +1,360 @@
-use std::fmt;
+use std::{collections::HashMap, convert::TryFrom, fmt, time::Duration};
 
+use actix_web::{cookie::SameSite, http::ContentEncoding};
 use config::{Config, ConfigError, Environment, File};
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, Serialize};
 
-use crate::api::models::{contract::ContractKind, operation_request::OperationRequestKind};
+use crate::api::models::{
+    common::SortDirection, contract::ContractKind, error::APIError,
+    operation_request::OperationRequestKind,
+};
+use crate::db::models::contract::Contract as DBContract;
+use crate::tezos::{
+    coding::{encode_chain_id, validate_entrypoint},
+    micheline::limits::Limits,
+};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Server {
     pub address: String,
     pub domain_name: String,
+    pub base_path: Option<String>,
     pub inactivity_timeout_seconds: i64,
     pub admins: Option<Vec<User>>,
+    pub injection_confirmation: Option<InjectionConfirmation>,
+    pub body_logging: Option<BodyLogging>,
+    pub log_format: Option<LogFormat>,
+    pub require_ledger_hash: Option<bool>,
+    pub default_sort_direction: Option<SortDirection>,
+    pub shutdown_timeout_seconds: Option<u64>,
+    pub notification_max_fraction_digits: Option<u32>,
+    pub notification_templates: Option<Vec<NotificationTemplate>>,
+    pub notifications_paused: Option<bool>,
+    pub removed_keyholder_approvals: Option<RemovedKeyholderApprovals>,
+    pub force_sync: Option<bool>,
+    pub compression: Option<Compression>,
+    pub max_concurrent_node_requests: Option<usize>,
+    pub signable_message_cache_ttl_seconds: Option<u64>,
+    pub operation_request_ttl_seconds: Option<u64>,
+    pub max_batch_mint_recipients: Option<usize>,
+    pub max_micheline_depth: Option<usize>,
+    pub max_micheline_nodes: Option<usize>,
+    pub verify_packed_data: Option<bool>,
+    pub tls_cert: Option<String>,
+    pub tls_key: Option<String>,
+}
+
+impl Server {
+    /// The path the API is served under, `/api/v1` below `base_path`.
+    pub fn api_path(&self) -> String {
+        format!(
+            "{}/api/v1",
+            self.base_path
+                .as_deref()
+                .unwrap_or("")
+                .trim_end_matches('/')
+        )
+    }
+
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.shutdown_timeout_seconds.unwrap_or(30))
+    }
+
+    pub fn log_format(&self) -> LogFormat {
+        self.log_format.unwrap_or(LogFormat::Text)
+    }
+
+    pub fn compression(&self) -> Compression {
+        self.compression.unwrap_or(Compression::Auto)
+    }
+
+    pub fn signable_message_cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.signable_message_cache_ttl_seconds.unwrap_or(30))
+    }
+
+    /// How long open and approved operation requests are kept before they expire, if they do.
+    pub fn operation_request_ttl(&self) -> Option<Duration> {
+        self.operation_request_ttl_seconds.map(Duration::from_secs)
+    }
+
+    pub fn max_concurrent_node_requests(&self) -> usize {
+        self.max_concurrent_node_requests.unwrap_or(10)
+    }
+
+    pub fn max_batch_mint_recipients(&self) -> usize {
+        self.max_batch_mint_recipients.unwrap_or(50)
+    }
+
+    pub fn notification_templates(&self) -> &[NotificationTemplate] {
+        self.notification_templates.as_deref().unwrap_or_default()
+    }
+
+    pub fn notifications_paused(&self) -> bool {
+        self.notifications_paused.unwrap_or(false)
+    }
+
+    pub fn removed_keyholder_approvals(&self) -> RemovedKeyholderApprovals {
+        self.removed_keyholder_approvals
+            .unwrap_or(RemovedKeyholderApprovals::Drop)
+    }
+
+    pub fn micheline_limits(&self) -> Limits {
+        Limits {
+            max_depth: self.max_micheline_depth.unwrap_or(100),
+            max_nodes: self.max_micheline_nodes.unwrap_or(50_000),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), APIError> {
+        if let Some(base_path) = &self.base_path {
+            if !base_path.starts_with('/') {
+                return Err(APIError::InvalidValue {
+                    description: format!("server base_path ({}) must start with /", base_path),
+                });
+            }
+        }
+        if self.inactivity_timeout_seconds <= 0 {
+            return Err(APIError::InvalidValue {
+                description: "server inactivity_timeout_seconds must be greater than 0".into(),
+            });
+        }
+        if let Some(injection_confirmation) = &self.injection_confirmation {
+            if injection_confirmation.interval_seconds == 0 {
+                return Err(APIError::InvalidValue {
+                    description:
+                        "server injection_confirmation interval_seconds must be greater than 0"
+                            .into(),
+                });
+            }
+        }
+        match (&self.tls_cert, &self.tls_key) {
+            (Some(_), None) => {
+                return Err(APIError::InvalidValue {
+                    description: "server tls_cert is set without tls_key".into(),
+                })
+            }
+            (None, Some(_)) => {
+                return Err(APIError::InvalidValue {
+                    description: "server tls_key is set without tls_cert".into(),
+                })
+            }
+            _ => {}
+        }
+        if self.operation_request_ttl_seconds == Some(0) {
+            return Err(APIError::InvalidValue {
+                description: "server operation_request_ttl_seconds must be greater than 0".into(),
+            });
+        }
+        if self.max_concurrent_node_requests() == 0 {
+            return Err(APIError::InvalidValue {
+                description: "server max_concurrent_node_requests must be greater than 0".into(),
+            });
+        }
+        if self.max_batch_mint_recipients() == 0 {
+            return Err(APIError::InvalidValue {
+                description: "server max_batch_mint_recipients must be greater than 0".into(),
+            });
+        }
+        let micheline_limits = self.micheline_limits();
+        if micheline_limits.max_depth == 0 || micheline_limits.max_nodes == 0 {
+            return Err(APIError::InvalidValue {
+                description:
+                    "server max_micheline_depth and max_micheline_nodes must be greater than 0"
+                        .into(),
+            });
+        }
+        let templates = self.notification_templates();
+        for (index, template) in templates.iter().enumerate() {
+            if template.subject.is_none() && template.body.is_none() {
+                return Err(APIError::InvalidValue {
+                    description: format!(
+                        "server notification template for {:?} needs a subject or a body",
+                        template.event
+                    ),
+                });
+            }
+            if templates[..index]
+                .iter()
+                .any(|other| other.event == template.event && other.kind == template.kind)
+            {
+                return Err(APIError::InvalidValue {
+                    description: format!(
+                        "server notification template for {:?} and {:?} is configured twice",
+                        template.event, template.kind
+                    ),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// What happens to the approvals of keyholders removed from the signer set by an injected
+/// `update_keyholders` operation request.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RemovedKeyholderApprovals {
+    /// The approvals are removed from the operation requests that haven't been injected.
+    Drop,
+    /// The approvals are kept as a record of who signed, even though the multisig rejects them.
+    Keep,
+}
+
+/// The format of the log lines written to stdout.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// The default format of `env_logger`.
+    Text,
+    /// A JSON object per line, for log aggregators.
+    Json,
+}
+
+/// The encoding applied to responses, `auto` picks the best one the client accepts.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    Auto,
+    Br,
+    Gzip,
+    Deflate,
+    Disabled,
+}
+
+impl Compression {
+    pub fn content_encoding(&self) -> ContentEncoding {
+        match self {
+            Compression::Auto => ContentEncoding::Auto,
+            Compression::Br => ContentEncoding::Br,
+            Compression::Gzip => ContentEncoding::Gzip,
+            Compression::Deflate => ContentEncoding::Deflate,
+            Compression::Disabled => ContentEncoding::Identity,
+        }
+    }
+}
+
+/// The notifications sent about operation requests.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    NewOperationRequest,
+    ApprovalReceived,
+    MinApprovalsReceived,
+    Injection,
+}
+
+/// Replaces the subject or the body of the `event` emails about operation requests of `kind`, or
+/// of all kinds if no kind is given.
+#[derive(Debug, Deserialize, Clone)]
+pub struct NotificationTemplate {
+    pub event: NotificationEvent,
+    pub kind: Option<OperationRequestKind>,
+    pub subject: Option<String>,
+    pub body: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct InjectionConfirmation {
+    pub max_attempts: u32,
+    pub interval_seconds: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct BodyLogging {
+    pub enabled: bool,
+    pub max_bytes: usize,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Database {
     pub host: String,
-    pub port: String,
+    #[serde(deserialize_with = "port")]
+    pub port: u16,
     pub user: String,
     pub password: String,
     pub name: String,
+    pub max_pool_size: Option<u32>,
+    pub min_idle: Option<u32>,
+    pub connection_timeout: Option<u64>,
+    pub idle_timeout: Option<u64>,
+}
+
+impl Database {
+    pub fn max_pool_size(&self) -> u32 {
+        self.max_pool_size.unwrap_or(5)
+    }
+
+    pub fn validate(&self) -> Result<(), APIError> {
+        if self.port == 0 {
+            return Err(APIError::InvalidValue {
+                description: "database port must be greater than 0".into(),
+            });
+        }
+        let max_pool_size = self.max_pool_size();
+        if max_pool_size == 0 {
+            return Err(APIError::InvalidValue {
+                description: "database max_pool_size must be greater than 0".into(),
+            });
+        }
+        if let Some(min_idle) = self.min_idle {
+            if min_idle > max_pool_size {
+                return Err(APIError::InvalidValue {
+                    description: format!(
+                        "database min_idle ({}) cannot be greater than max_pool_size ({})",
+                        min_idle, max_pool_size
+                    ),
+                });
+            }
+        }
+        if self.connection_timeout == Some(0) {
+            return Err(APIError::InvalidValue {
+                description: "database connection_timeout must be greater than 0".into(),
+            });
+        }
+        if self.idle_timeout == Some(0) {
+            return Err(APIError::InvalidValue {
+                description: "database idle_timeout must be greater than 0".into(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads a port as a wider integer first, so an out of range value is an error instead of being
+/// truncated.
+fn port<'de, D>(d: D) -> Result<u16, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = i64::deserialize(d)?;
+
+    u16::try_from(value)
+        .map_err(|_error| serde::de::Error::custom(format!("{} is not a valid port", value)))
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct SMTP {
     pub host: String,
-    pub port: String,
+    #[serde(deserialize_with = "port")]
+    pub port: u16,
     pub user: String,
     pub password: String,
 }
 
+impl SMTP {
+    pub fn validate(&self) -> Result<(), APIError> {
+        if self.port == 0 {
+            return Err(APIError::InvalidValue {
+                description: "smtp port must be greater than 0".into(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct TezosNode {
     pub name: String,
@@ -37,6 +362,73 @@ pub struct TezosNode {
     pub network: String,
 }
 
+/// Node URLs are joined with RPC paths that start with `/`, so they are used without a trailing
+/// slash. Only `http` and `https` URLs are accepted.
+pub fn normalize_node_url(url: &str) -> Result<String, APIError> {
+    let normalized = url.trim().trim_end_matches('/');
+    let host = normalized
+        .strip_prefix("https://")
+        .or_else(|| normalized.strip_prefix("http://"));
+    match host {
+        Some(host) if !host.is_empty() => Ok(normalized.to_owned()),
+        _ => Err(APIError::InvalidValue {
+            description: format!("node url {:?} must be an http or https url", url),
+        }),
+    }
+}
+
+/// Settings of the Tezos chain served by the `tezos_nodes`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Tezos {
+    pub chain_id: Option<String>,
+}
+
+impl Tezos {
+    pub fn validate(&self) -> Result<(), APIError> {
+        if let Some(chain_id) = &self.chain_id {
+            encode_chain_id(chain_id).map_err(|_error| APIError::InvalidValue {
+                description: format!("tezos chain_id {} is not a valid chain id", chain_id),
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Overrides of the session cookie attributes, which otherwise depend on the `env`.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Session {
+    pub cookie_domain: Option<String>,
+    pub same_site: Option<CookieSameSite>,
+    pub secure: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CookieSameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl CookieSameSite {
+    pub fn same_site(&self) -> SameSite {
+        match self {
+            CookieSameSite::Strict => SameSite::Strict,
+            CookieSameSite::Lax => SameSite::Lax,
+            CookieSameSite::None => SameSite::None,
+        }
+    }
+}
+
+/// The attributes the session cookie is set with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionCookie {
+    pub domain: String,
+    pub secure: bool,
+    pub same_site: CookieSameSite,
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct Contract {
     pub address: String,
@@ -48,6 +440,294 @@ pub struct Contract {
     pub capabilities: Vec<Capability>,
     pub symbol: String,
     pub decimals: i32,
+    pub ledger_big_map_id: Option<i64>,
+    pub mint_entrypoint: Option<String>,
+    pub burn_entrypoint: Option<String>,
+    pub node: Option<ContractNode>,
+    pub sort_order: Option<i32>,
+    pub notification_ccs: Option<Vec<String>>,
+    pub notification_channels: Option<Vec<NotificationChannel>>,
+    pub webhook_urls: Option<Vec<String>>,
+    pub admins: Option<Vec<User>>,
+    pub injectors: Option<Vec<User>>,
+    pub signature_schemes: Option<Vec<SignatureScheme>>,
+    pub keyholder_update_safeguard: Option<KeyholderUpdateSafeguard>,
+}
+
+/// How `update_keyholders` operation requests that could lock the current keyholders out of the
+/// multisig are handled.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyholderUpdateSafeguard {
+    /// No checks.
+    Off,
+    /// Thresholds the new keyholders can't meet are rejected, other risks are logged.
+    Warn,
+    /// Any risk rejects the operation request.
+    Block,
+}
+
+/// The ways notifications about the operation requests of a contract are sent.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationChannel {
+    /// Emails to the users of the contract and its `notification_ccs`.
+    Email,
+    /// JSON payloads posted to the `webhook_urls` of the contract.
+    Webhook,
+}
+
+/// The signature kinds a multisig's `check_signature` may accept, named after their prefixes.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureScheme {
+    Edsig,
+    Spsig,
+    P2sig,
+    Sig,
+}
+
+impl SignatureScheme {
+    const ALL: [SignatureScheme; 4] = [
+        SignatureScheme::Edsig,
+        SignatureScheme::Spsig,
+        SignatureScheme::P2sig,
+        SignatureScheme::Sig,
+    ];
+
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            SignatureScheme::Edsig => "edsig",
+            SignatureScheme::Spsig => "spsig",
+            SignatureScheme::P2sig => "p2sig",
+            SignatureScheme::Sig => "sig",
+        }
+    }
+
+    /// The scheme of `signature`, read from its prefix.
+    pub fn of(signature: &str) -> Option<SignatureScheme> {
+        SignatureScheme::ALL
+            .iter()
+            .copied()
+            .find(|scheme| signature.starts_with(scheme.prefix()))
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ContractNode {
+    pub url: String,
+    pub chain_id: String,
+}
+
+const DEFAULT_MINT_ENTRYPOINT: &str = "mint";
+const DEFAULT_BURN_ENTRYPOINT: &str = "burn";
+
+impl Contract {
+    /// The settings of `contract` among `contract_settings`.
+    pub fn find<'a>(
+        contract_settings: &'a [Contract],
+        contract: &DBContract,
+    ) -> Option<&'a Contract> {
+        contract_settings
+            .iter()
+            .find(|contract_setting| contract_setting.matches(contract))
+    }
+
+    /// Whether these are the settings of the stored `contract`.
+    pub fn matches(&self, contract: &DBContract) -> bool {
+        self.address == contract.pkh
+            && self.multisig == contract.multisig_pkh
+            && self.token_id == (contract.token_id as i64)
+    }
+
+    pub fn mint_entrypoint(&self) -> &str {
+        self.mint_entrypoint
+            .as_deref()
+            .unwrap_or(DEFAULT_MINT_ENTRYPOINT)
+    }
+
+    pub fn burn_entrypoint(&self) -> &str {
+        self.burn_entrypoint
+            .as_deref()
+            .unwrap_or(DEFAULT_BURN_ENTRYPOINT)
+    }
+
+    /// Addresses receiving all notifications for this contract in CC, in addition to the users.
+    pub fn notification_ccs(&self) -> &[String] {
+        self.notification_ccs.as_deref().unwrap_or_default()
+    }
+
+    /// URLs receiving all notifications for this contract as JSON payloads.
+    pub fn webhook_urls(&self) -> &[String] {
+        self.webhook_urls.as_deref().unwrap_or_default()
+    }
+
+    /// Whether notifications are sent through `channel`. Without `notification_channels`, they are
+    /// sent by email and, if the contract has `webhook_urls`, to the webhooks.
+    pub fn notifies_via(&self, channel: NotificationChannel) -> bool {
+        match self.notification_channels.as_ref() {
+            Some(channels) => channels.contains(&channel),
+            None => channel == NotificationChannel::Email || !self.webhook_urls().is_empty(),
+        }
+    }
+
+    pub fn keyholder_update_safeguard(&self) -> KeyholderUpdateSafeguard {
+        self.keyholder_update_safeguard
+            .unwrap_or(KeyholderUpdateSafeguard::Warn)
+    }
+
+    /// The admins of the contract, or the global `server.admins` if it doesn't have its own.
+    pub fn admins<'a>(&'a self, global_admins: Option<&'a Vec<User>>) -> Option<&'a Vec<User>> {
+        self.admins.as_ref().or(global_admins)
+    }
+
+    /// The users allowed to mark operation requests as injected, none if any gatekeeper or
+    /// keyholder may.
+    pub fn injectors(&self) -> &[User] {
+        self.injectors.as_deref().unwrap_or_default()
+    }
+
+    /// The signature schemes keyholders may approve with, all of them if none are configured.
+    pub fn signature_schemes(&self) -> &[SignatureScheme] {
+        self.signature_schemes
+            .as_deref()
+            .unwrap_or(&SignatureScheme::ALL)
+    }
+
+    /// A public key can be an admin, a gatekeeper and an injector of the same contract, but with
+    /// the same name and email, as they are shown for all its roles.
+    pub fn validate_users(&self, global_admins: Option<&Vec<User>>) -> Result<(), APIError> {
+        let mut users: HashMap<&str, &User> = HashMap::new();
+        let admins = self.admins(global_admins).into_iter().flatten();
+        for user in self
+            .gatekeepers
+            .iter()
+            .chain(admins)
+            .chain(self.injectors())
+        {
+            if let Some(other) = users.insert(&user.public_key, user) {
+                if other.name != user.name || other.email != user.email {
+                    return Err(APIError::InvalidValue {
+                        description: format!(
+                            "contract {} configures {} with different names or emails",
+                            self.address, user.public_key
+                        ),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The node to use for this contract, `selected_node_url` unless it has its own.
+    pub fn node_url_or<'a>(&'a self, selected_node_url: &'a str) -> &'a str {
+        self.node
+            .as_ref()
+            .map_or(selected_node_url, |node| node.url.as_str())
+    }
+
+    pub fn validate(&self) -> Result<(), APIError> {
+        validate_entrypoint(self.mint_entrypoint())?;
+        validate_entrypoint(self.burn_entrypoint())?;
+
+        if let ContractKind::FA1 = self.kind {
+            if self.token_id != 0 {
+                return Err(APIError::InvalidValue {
+                    description: format!(
+                        "FA1 contract {} cannot have token_id {}, FA1 contracts have no token ids",
+                        self.address, self.token_id
+                    ),
+                });
+            }
+        }
+
+        for capability in self.capabilities.iter() {
+            self.kind
+                .validate_capability(capability.operation_request_kind, &self.address)?;
+        }
+
+        if self.signature_schemes.as_ref().map_or(false, Vec::is_empty) {
+            return Err(APIError::InvalidValue {
+                description: format!(
+                    "contract {} has no signature_schemes, remove the setting to allow all",
+                    self.address
+                ),
+            });
+        }
+
+        for address in self.notification_ccs() {
+            validate_email(address).map_err(|_error| APIError::InvalidValue {
+                description: format!(
+                    "contract {} has an invalid notification cc {}",
+                    self.address, address
+                ),
+            })?;
+        }
+
+        if self
+            .notification_channels
+            .as_ref()
+            .map_or(false, Vec::is_empty)
+        {
+            return Err(APIError::InvalidValue {
+                description: format!(
+                    "contract {} has no notification_channels, remove the setting to use the defaults",
+                    self.address
+                ),
+            });
+        }
+        if self.notifies_via(NotificationChannel::Webhook) && self.webhook_urls().is_empty() {
+            return Err(APIError::InvalidValue {
+                description: format!(
+                    "contract {} notifies via webhook but has no webhook_urls",
+                    self.address
+                ),
+            });
+        }
+        for url in self.webhook_urls() {
+            normalize_node_url(url).map_err(|_error| APIError::InvalidValue {
+                description: format!(
+                    "contract {} has an invalid webhook url {:?}",
+                    self.address, url
+                ),
+            })?;
+        }
+
+        if let Some(node) = self.node.as_ref() {
+            normalize_node_url(&node.url).map_err(|_error| APIError::InvalidValue {
+                description: format!(
+                    "contract {} has an invalid node url {:?}",
+                    self.address, node.url
+                ),
+            })?;
+            encode_chain_id(&node.chain_id).map_err(|_error| APIError::InvalidValue {
+                description: format!(
+                    "contract {} has an invalid node chain_id {}",
+                    self.address, node.chain_id
+                ),
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A plausibility check of an email address, the SMTP server has the final say.
+fn validate_email(address: &str) -> Result<(), ()> {
+    let mut parts = address.split('@');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(local), Some(domain), None)
+            if !local.is_empty()
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !address.contains(char::is_whitespace) =>
+        {
+            Ok(())
+        }
+        _ => Err(()),
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -62,13 +742,22 @@ pub struct User {
     pub email: Option<String>,
 }
 
+#[derive(Debug, Deserialize, Clone)]
+pub struct Job {
+    pub enabled: Option<bool>,
+    pub interval_seconds: Option<u64>,
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct Settings {
     pub server: Server,
     pub database: Database,
     pub smtp: SMTP,
     pub tezos_nodes: Vec<TezosNode>,
+    pub tezos: Option<Tezos>,
+    pub session: Option<Session>,
     pub contracts: Vec<Contract>,
+    pub jobs: Option<HashMap<String, Job>>,
     pub env: ENV,
 }
 
@@ -117,6 +806,636 @@ impl Settings {
         // This makes it so "TZW_SERVER__ADDRESS overrides server.address
         s.merge(Environment::with_prefix("tzw").separator("__"))?;
 
-        s.try_into()
+        Settings::from_config(s)
+    }
+
+    /// Numeric values are parsed when the settings are deserialized and validated here, so a
+    /// misconfiguration fails on startup instead of when the value is first used.
+    fn from_config(config: Config) -> Result<Self, ConfigError> {
+        let mut settings: Settings = config.try_into()?;
+        settings
+            .normalize_node_urls()
+            .and_then(|_| settings.validate())
+            .map_err(|error| {
+                ConfigError::Message(format!("invalid settings: {}", error.to_string()))
+            })?;
+
+        Ok(settings)
+    }
+
+    /// Normalizes the URLs of the `tezos_nodes` and the contract-specific nodes with
+    /// `normalize_node_url`, so they match the URLs stored for the nodes.
+    fn normalize_node_urls(&mut self) -> Result<(), APIError> {
+        for tezos_node in self.tezos_nodes.iter_mut() {
+            tezos_node.url = normalize_node_url(&tezos_node.url)?;
+        }
+        for contract in self.contracts.iter_mut() {
+            if let Some(node) = contract.node.as_mut() {
+                node.url = normalize_node_url(&node.url)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The configured `tezos.chain_id`, if `node_url` is one of the `tezos_nodes`. Contract-specific
+    /// nodes can serve another chain, so they are not covered by it.
+    pub fn configured_chain_id(&self, node_url: &str) -> Option<&str> {
+        let chain_id = self.tezos.as_ref()?.chain_id.as_deref()?;
+
+        self.tezos_nodes
+            .iter()
+            .any(|tezos_node| tezos_node.url == node_url)
+            .then(|| chain_id)
+    }
+
+    /// The session cookie is `Secure` outside of `Local` by default. It is `SameSite=None` in
+    /// `Development` and `Testing`, so a frontend running on localhost can sign in to a deployed
+    /// server, and `SameSite=Lax` otherwise. It is set for `server.domain_name` unless
+    /// `session.cookie_domain` is configured.
+    pub fn session_cookie(&self) -> SessionCookie {
+        let session = self.session.clone().unwrap_or_default();
+        let secure = session.secure.unwrap_or(self.env != ENV::Local);
+        let same_site = session.same_site.unwrap_or({
+            if self.env == ENV::Production || !secure {
+                CookieSameSite::Lax
+            } else {
+                CookieSameSite::None
+            }
+        });
+
+        SessionCookie {
+            domain: session
+                .cookie_domain
+                .unwrap_or_else(|| self.server.domain_name.clone()),
+            secure,
+            same_site,
+        }
+    }
+
+    /// Browsers drop cookies for a domain the server isn't part of, and `SameSite=None` cookies
+    /// that aren't `Secure`.
+    fn validate_session_cookie(&self) -> Result<(), APIError> {
+        let cookie = self.session_cookie();
+        let domain = cookie.domain.trim_start_matches('.');
+        let domain_name = &self.server.domain_name;
+        if domain.is_empty()
+            || (domain_name != domain && !domain_name.ends_with(&format!(".{}", domain)))
+        {
+            return Err(APIError::InvalidValue {
+                description: format!(
+                    "session cookie_domain {} must be {} or one of its parent domains",
+                    cookie.domain, domain_name
+                ),
+            });
+        }
+        if cookie.same_site == CookieSameSite::None && !cookie.secure {
+            return Err(APIError::InvalidValue {
+                description: "session same_site none requires secure cookies".into(),
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), APIError> {
+        self.server.validate()?;
+        self.database.validate()?;
+        self.smtp.validate()?;
+        if let Some(tezos) = &self.tezos {
+            tezos.validate()?;
+        }
+        self.validate_session_cookie()?;
+        for contract in &self.contracts {
+            contract.validate_users(self.server.admins.as_ref())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{
+        http::{header, StatusCode},
+        middleware::Compress,
+        test, web, App, HttpResponse,
+    };
+
+    use config::{Config, File, FileFormat};
+
+    use super::{
+        normalize_node_url, Capability, Compression, Contract, ContractNode, CookieSameSite,
+        Database, NotificationChannel, NotificationEvent, SessionCookie, Settings, SignatureScheme,
+        User,
+    };
+    use crate::{
+        api::models::{
+            contract::ContractKind, error::APIError, operation_request::OperationRequestKind,
+        },
+        fixtures,
+    };
+
+    fn contract(kind: ContractKind, token_id: i64) -> Contract {
+        Contract {
+            address: "KT192P1oDzf3fNb7BSEiC1d74KvQf4HrivBE".into(),
+            multisig: "KT1KiJ1N9wgEVGkgPDYhLBYRBMPy1RG3pN2J".into(),
+            name: "tzBTC - Owner".into(),
+            kind,
+            token_id,
+            symbol: "tzBTC".into(),
+            decimals: 8,
+            ..fixtures::contract_settings()
+        }
+    }
+
+    fn database() -> Database {
+        Database {
+            host: "postgres".into(),
+            port: 5432,
+            user: "user".into(),
+            password: "password".into(),
+            name: "tz-wrapped".into(),
+            max_pool_size: None,
+            min_idle: None,
+            connection_timeout: None,
+            idle_timeout: None,
+        }
+    }
+
+    fn load_toml(env: &str, smtp_port: &str, extra: &str) -> Result<Settings, config::ConfigError> {
+        let toml = format!(
+            r#"
+            env = "{}"
+            tezos_nodes = []
+            contracts = []
+
+            [server]
+            address = "0.0.0.0:80"
+            domain_name = "api.example.com"
+            inactivity_timeout_seconds = 1800
+
+            [database]
+            host = "postgres"
+            port = 5432
+            user = "user"
+            password = "password"
+            name = "tz-wrapped"
+
+            [smtp]
+            host = "smtp.example.com"
+            port = {}
+            user = "user"
+            password = "password"
+
+            {}
+            "#,
+            env, smtp_port, extra
+        );
+        let mut config = Config::new();
+        config.merge(File::from_str(&toml, FileFormat::Toml))?;
+
+        Settings::from_config(config)
+    }
+
+    fn load(smtp_port: &str) -> Result<Settings, config::ConfigError> {
+        load_toml("Local", smtp_port, "")
+    }
+
+    #[test]
+    fn test_session_cookie() {
+        let local = load("587").unwrap().session_cookie();
+        assert_eq!(
+            local,
+            SessionCookie {
+                domain: "api.example.com".into(),
+                secure: false,
+                same_site: CookieSameSite::Lax,
+            }
+        );
+
+        let development = load_toml("Development", "587", "").unwrap();
+        assert!(development.session_cookie().secure);
+        assert_eq!(development.session_cookie().same_site, CookieSameSite::None);
+
+        let production = load_toml("Production", "587", "").unwrap();
+        assert!(production.session_cookie().secure);
+        assert_eq!(production.session_cookie().same_site, CookieSameSite::Lax);
+
+        let configured = load_toml(
+            "Production",
+            "587",
+            r#"
+            [session]
+            cookie_domain = "example.com"
+            same_site = "strict"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            configured.session_cookie(),
+            SessionCookie {
+                domain: "example.com".into(),
+                secure: true,
+                same_site: CookieSameSite::Strict,
+            }
+        );
+
+        let other_site = load_toml(
+            "Production",
+            "587",
+            r#"
+            [session]
+            cookie_domain = "example.org"
+            "#,
+        );
+        assert!(other_site.is_err());
+
+        let insecure_cross_site = load_toml(
+            "Development",
+            "587",
+            r#"
+            [session]
+            same_site = "none"
+            secure = false
+            "#,
+        );
+        assert!(insecure_cross_site.is_err());
+    }
+
+    #[test]
+    fn test_validate_notification_templates() {
+        let template = |kind: &str, subject: &str| {
+            format!(
+                r#"
+                [[server.notification_templates]]
+                event = "new_operation_request"
+                {}
+                {}
+                "#,
+                kind, subject
+            )
+        };
+
+        let settings = load_toml(
+            "Local",
+            "587",
+            &format!(
+                "{}{}",
+                template(r#"kind = "update_keyholders""#, r#"subject = "Keyholders""#),
+                template("", r#"body = "{contract}""#)
+            ),
+        )
+        .unwrap();
+        let templates = settings.server.notification_templates();
+        assert_eq!(templates.len(), 2);
+        assert_eq!(templates[0].event, NotificationEvent::NewOperationRequest);
+        assert_eq!(
+            templates[0].kind,
+            Some(OperationRequestKind::UpdateKeyholders)
+        );
+        assert_eq!(templates[1].kind, None);
+        assert!(load("587")
+            .unwrap()
+            .server
+            .notification_templates()
+            .is_empty());
+
+        let without_content = load_toml("Local", "587", &template("", ""));
+        assert!(without_content.is_err());
+
+        let twice = load_toml(
+            "Local",
+            "587",
+            &format!(
+                "{}{}",
+                template(r#"kind = "mint""#, r#"subject = "Mint""#),
+                template(r#"kind = "mint""#, r#"body = "Mint""#)
+            ),
+        );
+        assert!(twice.is_err());
+    }
+
+    #[test]
+    fn test_api_path() {
+        let mut settings = load("587").unwrap();
+        assert_eq!(settings.server.api_path(), "/api/v1");
+
+        for (base_path, api_path) in &[
+            ("/", "/api/v1"),
+            ("/tzwrapped", "/tzwrapped/api/v1"),
+            ("/tzwrapped/", "/tzwrapped/api/v1"),
+        ] {
+            settings.server.base_path = Some(base_path.to_string());
+            assert!(settings.server.validate().is_ok());
+            assert_eq!(settings.server.api_path(), *api_path);
+        }
+
+        settings.server.base_path = Some("tzwrapped".into());
+        assert!(settings.server.validate().is_err());
+    }
+
+    #[test]
+    fn test_load_numeric_settings() {
+        assert_eq!(load("587").unwrap().smtp.port, 587);
+        assert_eq!(load(r#""587""#).unwrap().smtp.port, 587);
+        assert!(load(r#""smtp""#).is_err());
+        assert!(load("70000").is_err());
+        assert!(load("0").is_err());
+    }
+
+    #[test]
+    fn test_validate_database_pool() {
+        let mut database = database();
+        assert!(database.validate().is_ok());
+        assert_eq!(database.max_pool_size(), 5);
+
+        database.max_pool_size = Some(0);
+        assert!(database.validate().is_err());
+
+        database.max_pool_size = Some(10);
+        database.min_idle = Some(11);
+        assert!(database.validate().is_err());
+
+        database.min_idle = Some(2);
+        assert!(database.validate().is_ok());
+
+        database.connection_timeout = Some(0);
+        assert!(database.validate().is_err());
+
+        database.connection_timeout = Some(30);
+        database.idle_timeout = Some(0);
+        assert!(database.validate().is_err());
+
+        database.idle_timeout = Some(600);
+        assert!(database.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_fa1_token_id() {
+        assert!(contract(ContractKind::FA1, 0).validate().is_ok());
+        assert!(matches!(
+            contract(ContractKind::FA1, 1).validate(),
+            Err(APIError::InvalidValue { .. })
+        ));
+        assert!(contract(ContractKind::FA2, 1).validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_notification_ccs() {
+        let mut fa2 = contract(ContractKind::FA2, 0);
+        assert!(fa2.notification_ccs().is_empty());
+
+        fa2.notification_ccs = Some(vec![
+            "security@example.com".into(),
+            "treasury@ops.example.com".into(),
+        ]);
+        assert!(fa2.validate().is_ok());
+
+        for invalid in &[
+            "security",
+            "@example.com",
+            "security@example",
+            "security@example.com.",
+            "security@@example.com",
+            "secu rity@example.com",
+        ] {
+            fa2.notification_ccs = Some(vec![(*invalid).into()]);
+            assert!(
+                matches!(fa2.validate(), Err(APIError::InvalidValue { .. })),
+                "{} is invalid",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_notification_channels() {
+        let mut fa2 = contract(ContractKind::FA2, 0);
+        assert!(fa2.notifies_via(NotificationChannel::Email));
+        assert!(!fa2.notifies_via(NotificationChannel::Webhook));
+
+        fa2.webhook_urls = Some(vec!["https://hooks.example.com/treasury".into()]);
+        assert!(fa2.validate().is_ok());
+        assert!(fa2.notifies_via(NotificationChannel::Email));
+        assert!(fa2.notifies_via(NotificationChannel::Webhook));
+
+        fa2.notification_channels = Some(vec![NotificationChannel::Webhook]);
+        assert!(fa2.validate().is_ok());
+        assert!(!fa2.notifies_via(NotificationChannel::Email));
+
+        fa2.notification_channels = Some(vec![]);
+        assert!(matches!(fa2.validate(), Err(APIError::InvalidValue { .. })));
+
+        fa2.notification_channels = Some(vec![NotificationChannel::Webhook]);
+        fa2.webhook_urls = None;
+        assert!(matches!(fa2.validate(), Err(APIError::InvalidValue { .. })));
+
+        fa2.webhook_urls = Some(vec!["hooks.example.com/treasury".into()]);
+        assert!(matches!(fa2.validate(), Err(APIError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_validate_fa1_batch_mint() {
+        let batch_mint = Capability {
+            operation_request_kind: OperationRequestKind::BatchMint,
+        };
+
+        let mut fa1 = contract(ContractKind::FA1, 0);
+        fa1.capabilities = vec![batch_mint.clone()];
+        assert!(matches!(fa1.validate(), Err(APIError::InvalidValue { .. })));
+
+        let mut fa2 = contract(ContractKind::FA2, 0);
+        fa2.capabilities = vec![batch_mint];
+        assert!(fa2.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_fa2_approve() {
+        let approve = Capability {
+            operation_request_kind: OperationRequestKind::Approve,
+        };
+
+        let mut fa1 = contract(ContractKind::FA1, 0);
+        fa1.capabilities = vec![approve.clone()];
+        assert!(fa1.validate().is_ok());
+
+        let mut fa2 = contract(ContractKind::FA2, 0);
+        fa2.capabilities = vec![approve.clone()];
+        assert!(matches!(fa2.validate(), Err(APIError::InvalidValue { .. })));
+
+        let mut fa2_single_asset = contract(ContractKind::FA2SingleAsset, 0);
+        fa2_single_asset.capabilities = vec![approve];
+        assert!(matches!(
+            fa2_single_asset.validate(),
+            Err(APIError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_validate_entrypoints() {
+        let mut fa2 = contract(ContractKind::FA2, 0);
+        assert_eq!(fa2.mint_entrypoint(), "mint");
+        assert_eq!(fa2.burn_entrypoint(), "burn");
+
+        fa2.mint_entrypoint = Some("mint_tokens".into());
+        assert!(fa2.validate().is_ok());
+        assert_eq!(fa2.mint_entrypoint(), "mint_tokens");
+
+        fa2.burn_entrypoint = Some("burn tokens".into());
+        assert!(fa2.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_contract_node() {
+        let mut fa2 = contract(ContractKind::FA2, 0);
+        assert_eq!(
+            fa2.node_url_or("https://mainnet.example.com"),
+            "https://mainnet.example.com"
+        );
+
+        fa2.node = Some(ContractNode {
+            url: "https://testnet.example.com".into(),
+            chain_id: "NetXm8tYqnMWky1".into(),
+        });
+        assert!(fa2.validate().is_ok());
+        assert_eq!(
+            fa2.node_url_or("https://mainnet.example.com"),
+            "https://testnet.example.com"
+        );
+
+        fa2.node = Some(ContractNode {
+            url: "https://testnet.example.com".into(),
+            chain_id: "testnet".into(),
+        });
+        assert!(fa2.validate().is_err());
+
+        fa2.node = Some(ContractNode {
+            url: "".into(),
+            chain_id: "NetXm8tYqnMWky1".into(),
+        });
+        assert!(fa2.validate().is_err());
+
+        fa2.node = Some(ContractNode {
+            url: "ws://testnet.example.com".into(),
+            chain_id: "NetXm8tYqnMWky1".into(),
+        });
+        assert!(fa2.validate().is_err());
+    }
+
+    #[test]
+    fn test_normalize_node_url() {
+        assert_eq!(
+            normalize_node_url("https://mainnet.example.com/").unwrap(),
+            "https://mainnet.example.com"
+        );
+        assert_eq!(
+            normalize_node_url("http://localhost:8732//").unwrap(),
+            "http://localhost:8732"
+        );
+        assert_eq!(
+            normalize_node_url("https://example.com/tezos").unwrap(),
+            "https://example.com/tezos"
+        );
+        assert!(normalize_node_url("ftp://mainnet.example.com").is_err());
+        assert!(normalize_node_url("mainnet.example.com").is_err());
+        assert!(normalize_node_url("https:///").is_err());
+    }
+
+    #[test]
+    fn test_validate_signature_schemes() {
+        let mut fa2 = contract(ContractKind::FA2, 0);
+        assert_eq!(fa2.signature_schemes().len(), 4);
+
+        fa2.signature_schemes = Some(vec![SignatureScheme::Edsig]);
+        assert!(fa2.validate().is_ok());
+        assert_eq!(fa2.signature_schemes(), &[SignatureScheme::Edsig]);
+
+        fa2.signature_schemes = Some(vec![]);
+        assert!(fa2.validate().is_err());
+    }
+
+    #[test]
+    fn test_contract_admins() {
+        let global_admins = vec![User {
+            public_key: "edpkuAJhbFLfJ4zWbQQWTZNGDg7hrcG1m1CBSWVB3iDHChjuzeaZB6".into(),
+            name: Some("Global admin".into()),
+            email: None,
+        }];
+        let mut fa2 = contract(ContractKind::FA2, 0);
+        assert_eq!(fa2.admins(None).map(Vec::len), None);
+        assert_eq!(
+            fa2.admins(Some(&global_admins)).unwrap()[0].public_key,
+            global_admins[0].public_key
+        );
+
+        fa2.admins = Some(vec![User {
+            public_key: "edpktzrjdb1tx6dQecQGZL6CwhujWg1D2CXfXWBriqtJSA6kvqMwA2".into(),
+            name: Some("Contract admin".into()),
+            email: None,
+        }]);
+        let admins = fa2.admins(Some(&global_admins)).unwrap();
+        assert_eq!(admins.len(), 1);
+        assert_eq!(
+            admins[0].public_key,
+            "edpktzrjdb1tx6dQecQGZL6CwhujWg1D2CXfXWBriqtJSA6kvqMwA2"
+        );
+    }
+
+    #[test]
+    fn test_validate_users() {
+        let user = |name: &str| User {
+            public_key: "edpktzrjdb1tx6dQecQGZL6CwhujWg1D2CXfXWBriqtJSA6kvqMwA2".into(),
+            name: Some(name.into()),
+            email: None,
+        };
+        let mut fa2 = contract(ContractKind::FA2, 0);
+        fa2.gatekeepers = vec![user("Alice")];
+        assert!(fa2.validate_users(Some(&vec![user("Alice")])).is_ok());
+        assert!(fa2.validate_users(Some(&vec![user("Bob")])).is_err());
+
+        fa2.admins = Some(vec![user("Alice")]);
+        assert!(fa2.validate_users(Some(&vec![user("Bob")])).is_ok());
+
+        fa2.injectors = Some(vec![user("Carol")]);
+        assert!(fa2.validate_users(None).is_err());
+    }
+
+    async fn compressed_response(compression: Compression) -> (Option<String>, Vec<u8>) {
+        let mut app = test::init_service(
+            App::new()
+                .wrap(Compress::new(compression.content_encoding()))
+                .route(
+                    "/",
+                    web::get().to(|| HttpResponse::Ok().body("Pair".repeat(4096))),
+                ),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/")
+            .header(header::ACCEPT_ENCODING, "gzip, deflate, br")
+            .to_request();
+        let response = test::call_service(&mut app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let content_encoding = response
+            .headers()
+            .get(header::CONTENT_ENCODING)
+            .map(|value| value.to_str().unwrap().to_owned());
+        let body = test::read_body(response).await;
+
+        (content_encoding, body.to_vec())
+    }
+
+    #[actix_rt::test]
+    async fn test_compression() {
+        let (content_encoding, body) = compressed_response(Compression::Disabled).await;
+        assert_eq!(content_encoding, None);
+        assert_eq!(body, "Pair".repeat(4096).into_bytes());
+
+        let (content_encoding, body) = compressed_response(Compression::Gzip).await;
+        assert_eq!(content_encoding.as_deref(), Some("gzip"));
+        assert!(body.len() < 4 * 4096);
     }
 }
@@ -1,4 +1,5 @@
 use super::TzError;
+use crate::crypto;
 use base58check::{FromBase58Check, ToBase58Check};
 use chrono::DateTime;
 
@@ -62,10 +63,61 @@ pub fn validate_edsig(value: &str) -> Result<(), TzError> {
     validate_value(value, EDSIG)
 }
 
+pub fn validate_signature(value: &str) -> Result<(), TzError> {
+    if value.starts_with(EncodingPrefix::EDSIG.prefix()) {
+        return validate_value(value, EDSIG);
+    }
+    if value.starts_with(EncodingPrefix::SPSIG.prefix()) {
+        return validate_value(value, SPSIG);
+    }
+    if value.starts_with(EncodingPrefix::P2SIG.prefix()) {
+        return validate_value(value, P2SIG);
+    }
+    if value.starts_with(EncodingPrefix::SIG.prefix()) {
+        return validate_value(value, SIG);
+    }
+
+    Err(TzError::InvalidValue {
+        description: format!("{} is not a valid signature", value),
+    })
+}
+
 pub fn validate_edpk(value: &str) -> Result<(), TzError> {
     validate_value(value, EDPK)
 }
 
+pub fn validate_address(value: &str) -> Result<(), TzError> {
+    let info = match value.get(..3) {
+        Some("tz1") => TZ1,
+        Some("tz2") => TZ2,
+        Some("tz3") => TZ3,
+        Some("KT1") => KT1,
+        _ => {
+            return Err(TzError::InvalidValue {
+                description: format!("{} is not a valid address", value),
+            })
+        }
+    };
+
+    validate_value(value, info)
+}
+
+pub fn validate_address_prefix(value: &str) -> Result<(), TzError> {
+    let is_valid = match value.get(..3) {
+        Some("tz1") | Some("tz2") | Some("tz3") | Some("KT1") => {
+            value.len() <= 36 && value.chars().all(|c| BASE58_ALPHABET.contains(c))
+        }
+        _ => false,
+    };
+    if !is_valid {
+        return Err(TzError::InvalidValue {
+            description: format!("{} is not a valid address prefix", value),
+        });
+    }
+
+    Ok(())
+}
+
 pub fn encode_chain_id(value: &str) -> Result<Vec<u8>, TzError> {
     encode(value, NET, None)
 }
@@ -195,13 +247,123 @@ pub fn encode_contract(value: &str) -> Result<Vec<u8>, TzError> {
     Ok(result)
 }
 
+pub fn decode_chain_id(value: &Vec<u8>) -> Result<String, TzError> {
+    if value.len() != NET.bytes_length {
+        return Err(TzError::InvalidArgument);
+    }
+
+    decode(value, NET, None)
+}
+
+/// Decodes a signature as encoded by `encode_signature`. The curve isn't part of the encoding,
+/// so the result has the generic `sig` prefix.
+pub fn decode_signature(value: &Vec<u8>) -> Result<String, TzError> {
+    if value.len() != SIG.bytes_length {
+        return Err(TzError::InvalidArgument);
+    }
+
+    decode(value, SIG, None)
+}
+
+/// Decodes an address as encoded by `encode_address`.
+pub fn decode_address(value: &[u8], tz_only: bool) -> Result<String, TzError> {
+    let (tag, encoded) = if tz_only {
+        (0, value)
+    } else {
+        let (tag, encoded) = value.split_first().ok_or(TzError::InvalidArgument)?;
+        (*tag, encoded)
+    };
+
+    match (tag, encoded) {
+        (0, [curve, hash @ ..]) if hash.len() == TZ1.bytes_length => {
+            let info = match curve {
+                0 => TZ1,
+                1 => TZ2,
+                2 => TZ3,
+                _ => return Err(TzError::InvalidType),
+            };
+
+            decode(&hash.to_vec(), info, None)
+        }
+        (1, [hash @ .., 0]) if hash.len() == KT1.bytes_length => decode(&hash.to_vec(), KT1, None),
+        _ => Err(TzError::InvalidArgument),
+    }
+}
+
+/// Decodes a contract as encoded by `encode_contract`, with its entrypoint unless it's the default.
+pub fn decode_contract(value: &Vec<u8>) -> Result<String, TzError> {
+    const ADDRESS_LENGTH: usize = 22;
+    if value.len() < ADDRESS_LENGTH {
+        return Err(TzError::InvalidArgument);
+    }
+
+    let (address, entrypoint) = value.split_at(ADDRESS_LENGTH);
+    let address = decode_address(address, false)?;
+    if entrypoint.is_empty() {
+        return Ok(address);
+    }
+    let entrypoint = std::str::from_utf8(entrypoint).map_err(|_error| TzError::InvalidArgument)?;
+
+    Ok(format!("{}%{}", address, entrypoint))
+}
+
+/// Validates an address optionally followed by a `%entrypoint`, as accepted by the `contract` type.
+pub fn validate_contract(value: &str) -> Result<(), TzError> {
+    let invalid = || TzError::InvalidValue {
+        description: format!("{} is not a valid contract", value),
+    };
+    let mut components = value.splitn(2, '%');
+    let address = components.next().unwrap_or_default();
+    validate_address(address)?;
+    if let Some(entrypoint) = components.next() {
+        if !address.starts_with("KT1") {
+            return Err(invalid());
+        }
+        validate_entrypoint(entrypoint)?;
+    }
+    encode_contract(value).map_err(|_error| invalid())?;
+
+    Ok(())
+}
+
+/// The unix timestamp of `value`, which is either an RFC 3339 date with any offset, e.g.
+/// `2020-11-10T08:49:28+01:00`, or already a number of seconds since the epoch.
 pub fn encode_timestamp(value: &str) -> Result<i64, TzError> {
+    if let Ok(seconds) = value.parse::<i64>() {
+        return Ok(seconds);
+    }
     let date_time =
         DateTime::parse_from_rfc3339(value).map_err(|_error| TzError::InvalidArgument)?;
 
     Ok(date_time.timestamp())
 }
 
+/// Entrypoint names follow the Michelson annotation syntax and are at most 31 bytes long.
+pub fn validate_entrypoint(value: &str) -> Result<(), TzError> {
+    let mut chars = value.chars();
+    let is_valid = value.len() <= 31
+        && chars
+            .next()
+            .map_or(false, |first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '%' || c == '@');
+    if !is_valid {
+        return Err(TzError::InvalidValue {
+            description: format!("{} is not a valid entrypoint name", value),
+        });
+    }
+
+    Ok(())
+}
+
+pub fn script_expr_hash(packed_key: &[u8]) -> Result<String, TzError> {
+    let hash = crypto::generic_hash(packed_key, EXPR.bytes_length)
+        .map_err(|_error| TzError::HashFailure)?;
+
+    decode(&hash, EXPR, None)
+}
+
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
 pub struct EncodingInfo {
     prefix: EncodingPrefix,
     versioned_prefix: &'static [u8],
@@ -472,3 +634,127 @@ impl EncodingPrefix {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate_address() {
+        assert!(validate_address("tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT").is_ok());
+        assert!(validate_address("KT1S4QXjASmM3ei7a6raRLkr4TbnKu7RuC9X").is_ok());
+        assert!(validate_address("tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtt").is_err());
+        assert!(
+            validate_address("edpkuHG9N83cBavucaLSeeKX3AVjn9wDyFeFmrhaSLqvmBycP5N7Zs").is_err()
+        );
+        assert!(validate_address("tz").is_err());
+    }
+
+    #[test]
+    fn test_encode_timestamp() -> Result<(), TzError> {
+        let utc = encode_timestamp("2020-11-10T07:49:28Z")?;
+        assert_eq!(utc, 1604994568);
+        assert_eq!(encode_timestamp("2020-11-10T08:49:28+01:00")?, utc);
+        assert_eq!(encode_timestamp("2020-11-09T21:19:28-10:30")?, utc);
+        assert_eq!(encode_timestamp("2020-11-10T07:49:28.000+00:00")?, utc);
+        assert_eq!(encode_timestamp("1604994568")?, utc);
+        assert_eq!(encode_timestamp("-1")?, -1);
+
+        assert!(matches!(
+            encode_timestamp("2020-13-10T07:49:28Z"),
+            Err(TzError::InvalidArgument)
+        ));
+        assert!(matches!(
+            encode_timestamp("2020-02-30T07:49:28Z"),
+            Err(TzError::InvalidArgument)
+        ));
+        assert!(matches!(
+            encode_timestamp("2020-11-10 07:49:28"),
+            Err(TzError::InvalidArgument)
+        ));
+        assert!(matches!(
+            encode_timestamp("1604994568.5"),
+            Err(TzError::InvalidArgument)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_entrypoint() {
+        assert!(validate_entrypoint("mint").is_ok());
+        assert!(validate_entrypoint("mint_tokens").is_ok());
+        assert!(validate_entrypoint("_burn.v2").is_ok());
+        assert!(validate_entrypoint("").is_err());
+        assert!(validate_entrypoint("1mint").is_err());
+        assert!(validate_entrypoint("mint tokens").is_err());
+        assert!(validate_entrypoint("mint;").is_err());
+        assert!(validate_entrypoint(&"a".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn test_validate_contract() {
+        assert!(validate_contract("KT1S4QXjASmM3ei7a6raRLkr4TbnKu7RuC9X").is_ok());
+        assert!(validate_contract("KT1S4QXjASmM3ei7a6raRLkr4TbnKu7RuC9X%do").is_ok());
+        assert!(validate_contract("tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT").is_ok());
+        assert!(validate_contract("tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT%do").is_err());
+        assert!(validate_contract("KT1S4QXjASmM3ei7a6raRLkr4TbnKu7RuC9X%").is_err());
+        assert!(validate_contract("KT1S4QXjASmM3ei7a6raRLkr4TbnKu7RuC9X%do%it").is_err());
+        assert!(validate_contract("KT1S4QXjASmM3ei7a6raRLkr4TbnKu7RuC9X%do it").is_err());
+    }
+
+    #[test]
+    fn test_script_expr_hash() -> Result<(), TzError> {
+        let packed_key = hex::decode("050000").unwrap();
+        assert_eq!(
+            script_expr_hash(&packed_key)?,
+            "exprtZBwZUeYYYfUs9B9Rg2ywHezVHnCCnmF9WsDQVrs582dSK63dC"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_address_prefix() {
+        assert!(validate_address_prefix("tz1Mj7").is_ok());
+        assert!(validate_address_prefix("KT1").is_ok());
+        assert!(validate_address_prefix("tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT").is_ok());
+        assert!(validate_address_prefix("tz1%").is_err());
+        assert!(validate_address_prefix("tz1Mj0").is_err());
+        assert!(validate_address_prefix("tz").is_err());
+    }
+
+    #[test]
+    fn test_decode_encoded_values() -> Result<(), TzError> {
+        for address in &[
+            "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT",
+            "KT1S4QXjASmM3ei7a6raRLkr4TbnKu7RuC9X",
+        ] {
+            assert_eq!(
+                decode_address(&encode_address(address, false)?, false)?,
+                *address
+            );
+            assert_eq!(decode_contract(&encode_contract(address)?)?, *address);
+        }
+        let key_hash = "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT";
+        assert_eq!(
+            decode_address(&encode_address(key_hash, true)?, true)?,
+            key_hash
+        );
+        let contract = "KT1S4QXjASmM3ei7a6raRLkr4TbnKu7RuC9X%mint";
+        assert_eq!(decode_contract(&encode_contract(contract)?)?, contract);
+        let chain_id = "NetXdQprcVkpaWU";
+        assert_eq!(decode_chain_id(&encode_chain_id(chain_id)?)?, chain_id);
+
+        let signature = "edsigtXomBKi5CTRf5cjATJWSyaRvhfYNHqSUGrn4SdbYRcGwQrUGjzEfQDTuqHhuA8b2d8NarZjz8TRf65WkpQmo423BtomS8Q";
+        let decoded = decode_signature(&encode_signature(signature)?)?;
+        assert!(decoded.starts_with("sig"));
+        assert_eq!(encode_signature(&decoded)?, encode_signature(signature)?);
+
+        assert!(decode_address(&[0, 9], false).is_err());
+        assert!(decode_contract(&vec![1; 10]).is_err());
+        assert!(decode_chain_id(&vec![0; 5]).is_err());
+
+        Ok(())
+    }
+}
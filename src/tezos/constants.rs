@@ -0,0 +1,167 @@
+use std::{collections::HashMap, convert::TryFrom, fmt::Display, str::FromStr, sync::Mutex};
+
+use serde::{Deserialize, Deserializer};
+
+use super::{check_node_response, mutez::Mutez, node_request, TzError, HEAD_BLOCK};
+
+lazy_static! {
+    static ref CONSTANTS: ConstantsCache = ConstantsCache::new();
+}
+
+/// The protocol constants of a chain, as far as the backend needs them.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Constants {
+    #[serde(deserialize_with = "from_str")]
+    pub hard_gas_limit_per_operation: i64,
+    #[serde(deserialize_with = "from_str")]
+    pub hard_gas_limit_per_block: i64,
+    #[serde(deserialize_with = "from_str")]
+    pub hard_storage_limit_per_operation: i64,
+    #[serde(deserialize_with = "mutez_from_str")]
+    pub cost_per_byte: Mutez,
+    pub origination_size: i64,
+}
+
+fn from_str<'de, D, T>(d: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = String::deserialize(d)?;
+
+    value.parse().map_err(serde::de::Error::custom)
+}
+
+fn mutez_from_str<'de, D>(d: D) -> Result<Mutez, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: i64 = from_str(d)?;
+
+    Mutez::try_from(value).map_err(serde::de::Error::custom)
+}
+
+/// Keeps the constants fetched from each node. Constants only change with the protocol, so they
+/// are kept until `clear` is called, which happens when another node is selected.
+#[derive(Default)]
+pub struct ConstantsCache {
+    entries: Mutex<HashMap<String, Constants>>,
+}
+
+impl ConstantsCache {
+    pub fn new() -> Self {
+        ConstantsCache {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn get(&self, node_url: &str) -> Result<Constants, TzError> {
+        if let Some(constants) = self.entries.lock().unwrap().get(node_url) {
+            return Ok(constants.clone());
+        }
+
+        let constants = fetch_constants(node_url).await?;
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(node_url.to_owned(), constants.clone());
+
+        Ok(constants)
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+/// The constants of the chain served by the node at `node_url`, fetched once per node.
+pub async fn constants(node_url: &str) -> Result<Constants, TzError> {
+    CONSTANTS.get(node_url).await
+}
+
+/// Drops the cached constants, so they are fetched again on next use.
+pub fn refresh_constants() {
+    CONSTANTS.clear();
+}
+
+async fn fetch_constants(node_url: &str) -> Result<Constants, TzError> {
+    let path = format!("/chains/main/blocks/{}/context/constants", HEAD_BLOCK);
+    let url = format!("{}{}", node_url, path);
+    node_request(async {
+        let response = reqwest::get(&url)
+            .await
+            .map_err(|_error| TzError::NetworkFailure)?;
+        check_node_response(response, &path)?
+            .json::<Constants>()
+            .await
+            .map_err(|_error| TzError::ParsingFailure)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        convert::TryFrom,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use actix_web::{web, App, HttpResponse};
+
+    use super::{Constants, ConstantsCache};
+    use crate::tezos::{mutez::Mutez, TzError};
+
+    #[actix_rt::test]
+    async fn test_constants() -> Result<(), TzError> {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let node = {
+            let fetches = fetches.clone();
+            actix_web::test::start(move || {
+                let fetches = fetches.clone();
+                App::new().route(
+                    "/chains/main/blocks/head/context/constants",
+                    web::get().to(move || {
+                        fetches.fetch_add(1, Ordering::SeqCst);
+                        HttpResponse::Ok().json(serde_json::json!({
+                            "proof_of_work_nonce_size": 8,
+                            "preserved_cycles": 5,
+                            "hard_gas_limit_per_operation": "1040000",
+                            "hard_gas_limit_per_block": "5200000",
+                            "hard_storage_limit_per_operation": "60000",
+                            "cost_per_byte": "250",
+                            "origination_size": 257,
+                            "minimal_block_delay": "30",
+                        }))
+                    }),
+                )
+            })
+        };
+        let node_url = node.url("").trim_end_matches('/').to_owned();
+
+        let cache = ConstantsCache::new();
+        let constants = cache.get(&node_url).await?;
+        assert_eq!(
+            constants,
+            Constants {
+                hard_gas_limit_per_operation: 1_040_000,
+                hard_gas_limit_per_block: 5_200_000,
+                hard_storage_limit_per_operation: 60_000,
+                cost_per_byte: Mutez::try_from(250)?,
+                origination_size: 257,
+            }
+        );
+
+        cache.get(&node_url).await?;
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        cache.clear();
+        cache.get(&node_url).await?;
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use super::{
+    check_node_response, coding::validate_entrypoint, micheline::MichelsonV1Expression,
+    node_request, TzError, HEAD_BLOCK,
+};
+
+lazy_static! {
+    static ref ENTRYPOINT_SCHEMAS: EntrypointSchemaCache = EntrypointSchemaCache::new();
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct EntrypointKey {
+    node_url: String,
+    address: String,
+    entrypoint: String,
+}
+
+/// Keeps the parameter types of contract entrypoints fetched from each node. The code of a
+/// contract can't change, so the types are kept until `clear` is called, which happens when
+/// another node is selected.
+#[derive(Default)]
+pub struct EntrypointSchemaCache {
+    entries: Mutex<HashMap<EntrypointKey, MichelsonV1Expression>>,
+}
+
+impl EntrypointSchemaCache {
+    pub fn new() -> Self {
+        EntrypointSchemaCache {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn get(
+        &self,
+        node_url: &str,
+        address: &str,
+        entrypoint: &str,
+    ) -> Result<MichelsonV1Expression, TzError> {
+        validate_entrypoint(entrypoint)?;
+
+        let key = EntrypointKey {
+            node_url: node_url.to_owned(),
+            address: address.to_owned(),
+            entrypoint: entrypoint.to_owned(),
+        };
+        if let Some(schema) = self.entries.lock().unwrap().get(&key) {
+            return Ok(schema.clone());
+        }
+
+        let schema = fetch_entrypoint_schema(node_url, address, entrypoint).await?;
+        self.entries.lock().unwrap().insert(key, schema.clone());
+
+        Ok(schema)
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+/// The parameter type of `entrypoint` of the contract at `address`, as reported by the node at
+/// `node_url`, fetched once per node.
+pub async fn entrypoint_schema(
+    node_url: &str,
+    address: &str,
+    entrypoint: &str,
+) -> Result<MichelsonV1Expression, TzError> {
+    ENTRYPOINT_SCHEMAS.get(node_url, address, entrypoint).await
+}
+
+/// Drops the cached entrypoint types, so they are fetched again on next use.
+pub fn refresh_entrypoint_schemas() {
+    ENTRYPOINT_SCHEMAS.clear();
+}
+
+async fn fetch_entrypoint_schema(
+    node_url: &str,
+    address: &str,
+    entrypoint: &str,
+) -> Result<MichelsonV1Expression, TzError> {
+    let path = format!(
+        "/chains/main/blocks/{}/context/contracts/{}/entrypoints/{}",
+        HEAD_BLOCK, address, entrypoint
+    );
+    let url = format!("{}{}", node_url, path);
+    node_request(async {
+        let response = reqwest::get(&url)
+            .await
+            .map_err(|_error| TzError::NetworkFailure)?;
+        check_node_response(response, &path)?
+            .json::<MichelsonV1Expression>()
+            .await
+            .map_err(|_error| TzError::ParsingFailure)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use actix_web::{web, App, HttpResponse};
+
+    use super::EntrypointSchemaCache;
+    use crate::tezos::{micheline::types, TzError};
+
+    const ADDRESS: &str = "KT1KiJ1N9wgEVGkgPDYhLBYRBMPy1RG3pN2J";
+
+    #[actix_rt::test]
+    async fn test_entrypoint_schema() -> Result<(), TzError> {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let node = {
+            let fetches = fetches.clone();
+            actix_web::test::start(move || {
+                let fetches = fetches.clone();
+                App::new().route(
+                    &format!(
+                        "/chains/main/blocks/head/context/contracts/{}/entrypoints/mainParameter",
+                        ADDRESS
+                    ),
+                    web::get().to(move || {
+                        fetches.fetch_add(1, Ordering::SeqCst);
+                        HttpResponse::Ok().json(serde_json::json!({
+                            "prim": "pair",
+                            "args": [{ "prim": "nat" }, { "prim": "address" }]
+                        }))
+                    }),
+                )
+            })
+        };
+        let node_url = node.url("").trim_end_matches('/').to_owned();
+
+        let cache = EntrypointSchemaCache::new();
+        let schema = cache.get(&node_url, ADDRESS, "mainParameter").await?;
+        assert_eq!(schema, types::pair(types::nat(), types::address()));
+        assert_eq!(schema.to_string(), "(pair nat address)");
+
+        cache.get(&node_url, ADDRESS, "mainParameter").await?;
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        assert!(cache
+            .get(&node_url, ADDRESS, "main/../storage")
+            .await
+            .is_err());
+        assert!(matches!(
+            cache.get(&node_url, ADDRESS, "missing").await,
+            Err(TzError::NotFound { .. })
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+use num_bigint::BigInt;
+
+use crate::api::models::{contract::ContractKind, error::APIError};
+
+use super::{
+    micheline::{data, int, literal::Literal, string, types, MichelsonV1Expression},
+    TzError,
+};
+
+fn ledger_key(
+    kind: ContractKind,
+    owner: &str,
+    token_id: i64,
+) -> Result<(MichelsonV1Expression, MichelsonV1Expression), TzError> {
+    match kind {
+        ContractKind::FA2 => Ok((
+            data::pair(string(owner.to_owned()), int(token_id)),
+            types::pair(types::address(), types::nat()),
+        )),
+        ContractKind::FA2SingleAsset => Ok((string(owner.to_owned()), types::address())),
+        ContractKind::FA1 => Err(TzError::APIError {
+            error: APIError::InvalidValue {
+                description: "balances can only be read from FA2 ledgers".into(),
+            },
+        }),
+    }
+}
+
+/// Reads the balance of `owner` from the ledger big map of an FA2 contract.
+/// Owners without an entry in the ledger have a balance of 0.
+pub async fn balance(
+    node_url: &str,
+    ledger_big_map_id: i64,
+    kind: ContractKind,
+    owner: &str,
+    token_id: i64,
+) -> Result<BigInt, TzError> {
+    let (key, key_type) = ledger_key(kind, owner, token_id)?;
+    let key_hash = key.script_expr_hash(Some(&key_type))?;
+
+    match super::big_map_value(node_url, ledger_big_map_id, &key_hash).await? {
+        Some(MichelsonV1Expression::Literal(Literal::Int(value))) => Ok(value),
+        Some(_) => Err(TzError::ParsingFailure),
+        None => Ok(BigInt::from(0)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{web, App, HttpResponse};
+    use num_bigint::BigInt;
+
+    use super::balance;
+    use crate::{api::models::contract::ContractKind, tezos::TzError};
+
+    const OWNER: &str = "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT";
+
+    fn mock_node() -> actix_web::test::TestServer {
+        actix_web::test::start(|| {
+            App::new().route(
+                "/chains/main/blocks/head/context/big_maps/{id}/{key_hash}",
+                web::get().to(|path: web::Path<(i64, String)>| {
+                    let (id, key_hash) = path.into_inner();
+                    if id == 17
+                        && key_hash == "expru4JymcySSY4JoZqCEJ1pHmAMJQgBFgmdp1fLdcgscZbLYuhJfS"
+                    {
+                        HttpResponse::Ok().json(serde_json::json!({ "int": "150000" }))
+                    } else {
+                        HttpResponse::NotFound().finish()
+                    }
+                }),
+            )
+        })
+    }
+
+    #[actix_rt::test]
+    async fn test_balance() -> Result<(), TzError> {
+        let node = mock_node();
+        let node_url = node.url("").trim_end_matches('/').to_owned();
+
+        let found = balance(&node_url, 17, ContractKind::FA2, OWNER, 0).await?;
+        assert_eq!(found, BigInt::from(150000));
+
+        let missing = balance(&node_url, 17, ContractKind::FA2, OWNER, 1).await?;
+        assert_eq!(missing, BigInt::from(0));
+
+        assert!(balance(&node_url, 17, ContractKind::FA1, OWNER, 0)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+}
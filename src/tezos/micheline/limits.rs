@@ -0,0 +1,77 @@
+use super::{MichelsonV1Expression, TzError};
+
+/// Bounds on the nesting depth and the number of nodes of an expression. Packing, hex encoding
+/// and displaying an expression recurse into it, so expressions built from input we don't
+/// control are checked against these bounds first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    pub max_depth: usize,
+    pub max_nodes: usize,
+}
+
+impl MichelsonV1Expression {
+    /// Walks the expression without recursing, so that checking a hostile expression can't
+    /// overflow the stack itself.
+    pub fn check_limits(&self, limits: &Limits) -> Result<(), TzError> {
+        let mut pending = vec![(self, 1usize)];
+        let mut nodes = 0usize;
+
+        while let Some((expression, depth)) = pending.pop() {
+            nodes += 1;
+            if nodes > limits.max_nodes {
+                return Err(TzError::InvalidValue {
+                    description: format!(
+                        "michelson expression has more than {} nodes",
+                        limits.max_nodes
+                    ),
+                });
+            }
+            if depth > limits.max_depth {
+                return Err(TzError::InvalidValue {
+                    description: format!(
+                        "michelson expression is nested deeper than {} levels",
+                        limits.max_depth
+                    ),
+                });
+            }
+
+            let children = match expression {
+                MichelsonV1Expression::Prim(prim) => prim.args.as_deref().unwrap_or_default(),
+                MichelsonV1Expression::Sequence(items) => items.as_slice(),
+                MichelsonV1Expression::Literal(_) => &[],
+            };
+            pending.extend(children.iter().map(|child| (child, depth + 1)));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Limits;
+    use crate::tezos::micheline::{data, int, sequence, string};
+
+    const LIMITS: Limits = Limits {
+        max_depth: 100,
+        max_nodes: 1000,
+    };
+
+    #[test]
+    fn test_check_limits() {
+        let value = data::pair(
+            string("tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT".into()),
+            int(10),
+        );
+        assert!(value.check_limits(&LIMITS).is_ok());
+
+        let wide = sequence((0..1000).map(int).collect());
+        assert!(wide.check_limits(&LIMITS).is_err());
+
+        let mut deep = int(0);
+        for _ in 0..1_000 {
+            deep = sequence(vec![deep]);
+        }
+        assert!(deep.check_limits(&LIMITS).is_err());
+    }
+}
@@ -70,7 +70,14 @@ where
 impl Display for Literal {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let value = match self {
-            Literal::String(value) => format!("\"{}\"", value),
+            Literal::String(value) => format!(
+                "\"{}\"",
+                value
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('\n', "\\n")
+                    .replace('\r', "\\r")
+            ),
             Literal::Int(value) => format!("{}", value),
             Literal::Bytes(bytes) => format!("0x{}", hex::encode(bytes)),
         };
@@ -158,7 +165,9 @@ impl Literal {
         encoded.consume_bytes(1)?; // consume prefix
         let value = encoded.consume_lengh_and_value(None)?;
         let bytes = hex::decode(value).map_err(|_error| TzError::InvalidType)?;
-        let result = String::from_utf8(bytes).map_err(|_error| TzError::InvalidType)?;
+        let result = String::from_utf8(bytes).map_err(|_error| TzError::InvalidValue {
+            description: "string is not valid UTF-8".into(),
+        })?;
 
         Ok(Literal::String(result))
     }
@@ -255,6 +264,17 @@ mod test {
         assert_eq!(bytes_json, r#"{"bytes":"0aff05"}"#)
     }
 
+    #[test]
+    fn test_display() {
+        assert_eq!(Literal::String("Test".into()).to_string(), r#""Test""#);
+        assert_eq!(
+            Literal::String("say \"hi\"\\\n".into()).to_string(),
+            r#""say \"hi\"\\\n""#
+        );
+        assert_eq!(Literal::Int((-100).into()).to_string(), "-100");
+        assert_eq!(Literal::Bytes(vec![10, 255, 5]).to_string(), "0x0aff05");
+    }
+
     #[test]
     fn test_deserialization() -> Result<(), serde_json::Error> {
         let string_json = serde_json::json!({
@@ -323,6 +343,27 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_string_hex_decoding_bounds() {
+        let mut encoded = ConsumableHexStr::new("0100000010546573");
+        assert!(matches!(
+            Literal::from_hex(&mut encoded),
+            Err(TzError::InvalidValue { .. })
+        ));
+
+        let mut encoded = ConsumableHexStr::new("0100000002c328");
+        assert!(matches!(
+            Literal::from_hex(&mut encoded),
+            Err(TzError::InvalidValue { .. })
+        ));
+
+        let mut encoded = ConsumableHexStr::new("0a7fffffff00");
+        assert!(matches!(
+            Literal::from_hex(&mut encoded),
+            Err(TzError::InvalidValue { .. })
+        ));
+    }
+
     #[test]
     fn test_int_hex_decoding_1() -> Result<(), TzError> {
         let mut encoded = ConsumableHexStr::new("00a401");
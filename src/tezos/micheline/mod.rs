@@ -4,6 +4,7 @@ use std::{
 };
 
 use num_bigint::BigInt;
+use num_traits::{Signed, ToPrimitive};
 use primitive::Primitive;
 use serde::{Deserialize, Serialize};
 
@@ -13,10 +14,12 @@ use super::{coding, TzError};
 
 pub mod data;
 pub mod instructions;
+pub mod limits;
 pub mod literal;
 pub mod prim;
 pub mod primitive;
 pub mod types;
+mod unpack;
 
 pub fn string(value: String) -> MichelsonV1Expression {
     MichelsonV1Expression::Literal(literal::Literal::String(value))
@@ -65,6 +68,17 @@ impl MichelsonV1Expression {
         Ok(format!("{}{}", PACK_PREFIX, encoded))
     }
 
+    /// Packs the expression and returns its `expr...` hash, as used to look up big map keys.
+    pub fn script_expr_hash(
+        &self,
+        schema: Option<&MichelsonV1Expression>,
+    ) -> Result<String, TzError> {
+        let packed =
+            hex::decode(self.pack(schema)?).map_err(|_error| TzError::HexDecodingFailure)?;
+
+        coding::script_expr_hash(&packed)
+    }
+
     pub fn prepack(
         &self,
         schema: &MichelsonV1Expression,
@@ -82,13 +96,7 @@ impl MichelsonV1Expression {
             Type::Map | Type::BigMap => self.prepack_map(args)?,
             Type::Lambda => self.prepack_lambda()?,
             Type::Pair => self.prepack_pair(args)?,
-            Type::Option => {
-                if let Some(prepacked) = self.prepack_option(args) {
-                    prepacked?
-                } else {
-                    self.clone()
-                }
-            }
+            Type::Option => self.prepack_option(args)?,
             Type::Or => self.prepack_or(args)?,
             Type::ChainID => {
                 if let Some(value) = string_value {
@@ -132,11 +140,41 @@ impl MichelsonV1Expression {
                     self.clone()
                 }
             }
+            Type::Nat | Type::Mutez => {
+                if let MichelsonV1Expression::Literal(literal::Literal::Int(value)) = self {
+                    if value.is_negative() {
+                        return Err(TzError::InvalidValue {
+                            description: format!(
+                                "{} is not a valid value for type {}",
+                                value,
+                                if type_ == Type::Nat { "nat" } else { "mutez" }
+                            ),
+                        });
+                    }
+                }
+                self.clone()
+            }
+            Type::Bool => {
+                let is_bool = match self {
+                    MichelsonV1Expression::Prim(prim) => {
+                        prim.args_count() == 0
+                            && (prim.prim == Primitive::Data(primitive::Data::True)
+                                || prim.prim == Primitive::Data(primitive::Data::False))
+                    }
+                    _ => false,
+                };
+                if !is_bool {
+                    return Err(TzError::InvalidValue {
+                        description: format!("{} is not a valid value for type bool", self),
+                    });
+                }
+                self.clone()
+            }
             _ => self.clone(),
         })
     }
 
-    fn normalized(self) -> Self {
+    pub fn normalized(self) -> Self {
         match self {
             MichelsonV1Expression::Prim(prim) => MichelsonV1Expression::Prim(prim.normalized()),
             _ => self,
@@ -275,47 +313,38 @@ impl MichelsonV1Expression {
         }
     }
 
+    /// `None` is a value of every option type and is left as it is, any other value has to be
+    /// `Some` with a value of the option's type.
     fn prepack_option(
         &self,
         args: Option<&Vec<MichelsonV1Expression>>,
-    ) -> Option<Result<MichelsonV1Expression, TzError>> {
+    ) -> Result<MichelsonV1Expression, TzError> {
         use primitive::Data;
 
         if let MichelsonV1Expression::Prim(value) = self {
-            if value.prim != Primitive::Data(Data::Some) {
-                return None;
+            if value.prim == Primitive::Data(Data::None) && value.args_count() == 0 {
+                return Ok(self.clone());
             }
 
-            if let None = args {
-                return Some(Err(TzError::InvalidType));
-            }
-
-            let option_types = args.unwrap();
-
-            if value.args_count() != option_types.len() && option_types.len() == 1 {
-                return Some(Err(TzError::InvalidType));
+            let option_types = args.ok_or(TzError::InvalidType)?;
+            if value.prim != Primitive::Data(Data::Some)
+                || value.args_count() != 1
+                || option_types.len() != 1
+            {
+                return Err(TzError::InvalidType);
             }
 
-            let arguments: Result<Option<Vec<MichelsonV1Expression>>, TzError> = value
+            let argument = value
                 .args
                 .as_ref()
-                .and_then(|args| {
-                    Some(
-                        args.iter()
-                            .enumerate()
-                            .map(|(index, argument)| argument.prepack(&option_types[index]))
-                            .collect::<Result<Vec<MichelsonV1Expression>, TzError>>(),
-                    )
-                })
-                .map_or(Ok(None), |r| r.map(Some));
-
-            if let Err(error) = arguments {
-                return Some(Err(error));
-            }
+                .unwrap()
+                .first()
+                .unwrap()
+                .prepack(&option_types[0])?;
 
-            Some(Ok(data::prim(Data::Some, arguments.unwrap())))
+            Ok(data::prim(Data::Some, Some(vec![argument])))
         } else {
-            Some(Err(TzError::InvalidType))
+            Err(TzError::InvalidType)
         }
     }
 
@@ -360,25 +389,6 @@ impl MichelsonV1Expression {
         }
     }
 
-    // pub fn from_packed(packed: &str, schema: Option<MichelsonV1Expression>) -> Result<Self, TzError> {
-    //     let mut encoded = ConsumableHexStr::new(packed);
-    //     let prefix = encoded.consume_bytes(1)?;
-    //     if prefix != PACK_PREFIX {
-    //         return Err(TzError::InvalidType);
-    //     }
-    //     let result = MichelsonV1Expression::from_hex(&mut encoded)?;
-
-    //     if let Some(schema) = schema {
-    //         Self::postunpack(result, schema)
-    //     } else {
-    //         Ok(result)
-    //     }
-    // }
-
-    // fn postunpack(value: MichelsonV1Expression, schema: MichelsonV1Expression) -> Result<MichelsonV1Expression, TzError> {
-    //     todo!()
-    // }
-
     fn type_info<'a>(
         &'a self,
     ) -> Result<
@@ -476,7 +486,7 @@ impl HexDecodable for Vec<MichelsonV1Expression> {
     {
         encoded.consume_bytes(1)?; // consume prefix
         let value = encoded.consume_lengh_and_value(None)?;
-        let mut consumable = ConsumableHexStr::new(value);
+        let mut consumable = encoded.nested(value);
         let mut sequence = Vec::<MichelsonV1Expression>::new();
         while consumable.has_more() {
             sequence.push(MichelsonV1Expression::from_hex(&mut consumable)?)
@@ -573,6 +583,75 @@ pub fn extract_sequence(
     }
 }
 
+/// Finds the id of the big map annotated with `annotation` (e.g. `%ledger`) in `storage`, a
+/// contract storage of type `storage_type`. Values carry no annotations, so the big map is looked
+/// up in the type and its id is read at the same position in the value.
+pub fn find_big_map_id(
+    storage_type: &MichelsonV1Expression,
+    storage: &MichelsonV1Expression,
+    annotation: &str,
+) -> Option<i64> {
+    use primitive::{Data, Type};
+
+    let annotation = format!("%{}", annotation.trim_start_matches('%'));
+    let storage_type = extract_prim(storage_type).ok()?.clone().normalized();
+    let annotated = storage_type
+        .annots
+        .as_ref()
+        .map_or(false, |annots| annots.contains(&annotation));
+    match storage_type.prim {
+        Primitive::Type(Type::BigMap) if annotated => extract_int(storage).ok()?.to_i64(),
+        Primitive::Type(Type::Pair) => {
+            let types = storage_type.args.as_ref()?;
+            let (first, second) = pair_values(storage)?;
+
+            find_big_map_id(types.first()?, &first, &annotation)
+                .or_else(|| find_big_map_id(types.last()?, &second, &annotation))
+        }
+        Primitive::Type(Type::Option) => {
+            let value = extract_prim(storage).ok()?;
+            if value.prim != Primitive::Data(Data::Some) {
+                return None;
+            }
+            // the annotation of an optional big map applies to the big map
+            let mut inner_type = extract_prim(storage_type.args.as_ref()?.first()?)
+                .ok()?
+                .clone();
+            if annotated {
+                inner_type.annots = Some(vec![annotation.clone()]);
+            }
+
+            find_big_map_id(
+                &MichelsonV1Expression::Prim(inner_type),
+                value.args.as_ref()?.first()?,
+                &annotation,
+            )
+        }
+        _ => None,
+    }
+}
+
+/// Splits a pair value, which may be written as a comb `Pair` or a sequence, in its first element
+/// and the rest.
+fn pair_values(
+    value: &MichelsonV1Expression,
+) -> Option<(MichelsonV1Expression, MichelsonV1Expression)> {
+    let items = match value {
+        MichelsonV1Expression::Prim(prim)
+            if prim.prim == Primitive::Data(primitive::Data::Pair) =>
+        {
+            prim.args.as_ref()?
+        }
+        MichelsonV1Expression::Sequence(items) => items,
+        _ => return None,
+    };
+    match items.len() {
+        0 | 1 => None,
+        2 => Some((items[0].clone(), items[1].clone())),
+        _ => Some((items[0].clone(), sequence(items[1..].to_vec()))),
+    }
+}
+
 pub trait HexEncodable {
     fn to_hex_encoded(&self) -> Result<String, TzError>;
 }
@@ -932,6 +1011,37 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_prepack_option() -> Result<(), TzError> {
+        let schema = types::option(types::address());
+
+        assert_eq!(data::none().prepack(&schema)?, data::none());
+        assert_eq!(data::none().pack(Some(&schema))?, "050306");
+        assert_eq!(
+            data::some(string("tz1Ts3m2dXTXB66XN7cg5ALiAvzZY6AxrFd9".into())).prepack(&schema)?,
+            data::some(bytes(coding::encode_contract(
+                "tz1Ts3m2dXTXB66XN7cg5ALiAvzZY6AxrFd9"
+            )?))
+        );
+
+        for invalid in vec![
+            data::left(string("tz1Ts3m2dXTXB66XN7cg5ALiAvzZY6AxrFd9".into())),
+            data::unit(),
+            data::prim(Data::None, Some(vec![int(1)])),
+            data::prim(Data::Some, None),
+            string("tz1Ts3m2dXTXB66XN7cg5ALiAvzZY6AxrFd9".into()),
+            sequence(vec![]),
+        ] {
+            assert!(
+                matches!(invalid.prepack(&schema), Err(TzError::InvalidType)),
+                "{}",
+                invalid
+            );
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_micheline_pack_3() -> Result<(), TzError> {
         let micheline = data::pair(
@@ -1054,6 +1164,12 @@ mod test {
         let packed = micheline.pack(Some(&schema))?;
         assert_eq!(packed, "05008898d2fa0b");
 
+        let with_offset = string("2020-11-10T08:49:28+01:00".into());
+        assert_eq!(with_offset.pack(Some(&schema))?, packed);
+
+        let unix = string("1604994568".into());
+        assert_eq!(unix.pack(Some(&schema))?, packed);
+
         Ok(())
     }
 
@@ -1106,6 +1222,66 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_prepack_negative_nat() {
+        let schema = types::pair(types::address(), types::nat());
+        let value = data::pair(
+            string("tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT".into()),
+            int(-1),
+        );
+        assert!(matches!(
+            value.pack(Some(&schema)),
+            Err(TzError::InvalidValue { .. })
+        ));
+
+        assert!(matches!(
+            int(-100).pack(Some(&types::mutez())),
+            Err(TzError::InvalidValue { .. })
+        ));
+        assert_eq!(int(-1).pack(Some(&types::int())).unwrap(), "050041");
+        assert_eq!(int(0).pack(Some(&types::nat())).unwrap(), "050000");
+    }
+
+    #[test]
+    fn test_prepack_bool() {
+        assert_eq!(data::true_().pack(Some(&types::bool())).unwrap(), "05030a");
+        assert_eq!(data::false_().pack(Some(&types::bool())).unwrap(), "050303");
+
+        assert!(matches!(
+            int(1).pack(Some(&types::bool())),
+            Err(TzError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            data::unit().pack(Some(&types::bool())),
+            Err(TzError::InvalidValue { .. })
+        ));
+        let schema = types::pair(types::address(), types::bool());
+        let value = data::pair(
+            string("tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT".into()),
+            string("True".into()),
+        );
+        assert!(matches!(
+            value.pack(Some(&schema)),
+            Err(TzError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_script_expr_hash() -> Result<(), TzError> {
+        let key = data::pair(
+            string("tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT".into()),
+            int(0),
+        );
+        let schema = types::pair(types::address(), types::nat());
+
+        assert_eq!(
+            key.script_expr_hash(Some(&schema))?,
+            "expru4JymcySSY4JoZqCEJ1pHmAMJQgBFgmdp1fLdcgscZbLYuhJfS"
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_display_michelson() -> Result<(), TzError> {
         let call = sequence(vec![
@@ -1158,4 +1334,230 @@ mod test {
 
         Ok(())
     }
+
+    fn display(json: serde_json::Value) -> Result<String, serde_json::Error> {
+        let expression: MichelsonV1Expression = serde_json::from_value(json)?;
+
+        Ok(format!("{}", expression))
+    }
+
+    #[test]
+    fn test_display_types() -> Result<(), serde_json::Error> {
+        assert_eq!(
+            display(serde_json::json!({
+                "prim": "lambda",
+                "args": [
+                    { "prim": "unit" },
+                    { "prim": "list", "args": [{ "prim": "operation" }] }
+                ]
+            }))?,
+            "(lambda unit (list operation))"
+        );
+        assert_eq!(
+            display(serde_json::json!({ "prim": "operation" }))?,
+            "operation"
+        );
+        assert_eq!(
+            display(serde_json::json!({ "prim": "unit", "args": [] }))?,
+            "unit"
+        );
+        assert_eq!(
+            display(serde_json::json!({
+                "prim": "big_map",
+                "args": [{ "prim": "address" }, { "prim": "nat" }]
+            }))?,
+            "(big_map address nat)"
+        );
+        assert_eq!(
+            display(serde_json::json!({
+                "prim": "contract",
+                "args": [{
+                    "prim": "pair",
+                    "args": [{ "prim": "address" }, { "prim": "nat" }]
+                }]
+            }))?,
+            "(contract (pair address nat))"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_display_annotated_schemas() -> Result<(), serde_json::Error> {
+        // parameter of the generic multisig contract
+        assert_eq!(
+            display(serde_json::json!({
+                "prim": "pair",
+                "args": [
+                    {
+                        "prim": "pair",
+                        "args": [
+                            { "prim": "nat", "annots": ["%counter"] },
+                            {
+                                "prim": "or",
+                                "args": [
+                                    {
+                                        "prim": "lambda",
+                                        "args": [
+                                            { "prim": "unit" },
+                                            { "prim": "list", "args": [{ "prim": "operation" }] }
+                                        ],
+                                        "annots": ["%operation"]
+                                    },
+                                    {
+                                        "prim": "pair",
+                                        "args": [
+                                            { "prim": "nat", "annots": ["%threshold"] },
+                                            {
+                                                "prim": "list",
+                                                "args": [{ "prim": "key" }],
+                                                "annots": ["%keys"]
+                                            }
+                                        ],
+                                        "annots": ["%change_keys"]
+                                    }
+                                ],
+                                "annots": [":action"]
+                            }
+                        ],
+                        "annots": [":payload"]
+                    },
+                    {
+                        "prim": "list",
+                        "args": [{ "prim": "option", "args": [{ "prim": "signature" }] }],
+                        "annots": ["%sigs"]
+                    }
+                ]
+            }))?,
+            "(pair (pair :payload (nat %counter) (or :action (lambda %operation unit (list operation)) (pair %change_keys (nat %threshold) (list %keys key)))) (list %sigs (option signature)))"
+        );
+        // FA1.2 approve parameter
+        assert_eq!(
+            display(serde_json::json!({
+                "prim": "pair",
+                "args": [
+                    { "prim": "address", "annots": [":spender"] },
+                    { "prim": "nat", "annots": [":value"] }
+                ],
+                "annots": ["%approve"]
+            }))?,
+            "(pair %approve (address :spender) (nat :value))"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_display_annotated_instruction() -> Result<(), serde_json::Error> {
+        assert_eq!(
+            display(serde_json::json!({
+                "prim": "CONTRACT",
+                "args": [{
+                    "prim": "pair",
+                    "args": [{ "prim": "address" }, { "prim": "nat" }]
+                }],
+                "annots": ["%transfer"]
+            }))?,
+            "CONTRACT %transfer (pair address nat)"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_big_map_id() -> Result<(), TzError> {
+        let storage_type: MichelsonV1Expression = serde_json::from_str(
+            r#"{"prim":"pair","args":[
+                {"prim":"pair","args":[
+                    {"prim":"address","annots":["%administrator"]},
+                    {"prim":"pair","args":[
+                        {"prim":"nat","annots":["%all_tokens"]},
+                        {"prim":"big_map","args":[
+                            {"prim":"pair","args":[{"prim":"address"},{"prim":"nat"}]},
+                            {"prim":"nat"}
+                        ],"annots":["%ledger"]}
+                    ]}
+                ]},
+                {"prim":"pair","args":[
+                    {"prim":"pair","args":[
+                        {"prim":"option","args":[
+                            {"prim":"big_map","args":[{"prim":"string"},{"prim":"bytes"}]}
+                        ],"annots":["%metadata"]},
+                        {"prim":"big_map","args":[
+                            {"prim":"pair","args":[
+                                {"prim":"address","annots":["%owner"]},
+                                {"prim":"address","annots":["%operator"]},
+                                {"prim":"nat","annots":["%token_id"]}
+                            ]},
+                            {"prim":"unit"}
+                        ],"annots":["%operators"]}
+                    ]},
+                    {"prim":"bool","annots":["%paused"]},
+                    {"prim":"big_map","args":[{"prim":"nat"},{"prim":"nat"}],"annots":["%token_metadata"]},
+                    {"prim":"big_map","args":[{"prim":"nat"},{"prim":"nat"}],"annots":["%total_supply"]}
+                ]}
+            ]}"#,
+        )?;
+        let storage: MichelsonV1Expression = serde_json::from_str(
+            r#"{"prim":"Pair","args":[
+                {"prim":"Pair","args":[
+                    {"string":"tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT"},
+                    {"prim":"Pair","args":[{"int":"1"},{"int":"42"}]}
+                ]},
+                [
+                    {"prim":"Pair","args":[{"prim":"None"},{"int":"43"}]},
+                    {"prim":"False"},
+                    {"int":"44"},
+                    {"int":"45"}
+                ]
+            ]}"#,
+        )?;
+
+        assert_eq!(find_big_map_id(&storage_type, &storage, "ledger"), Some(42));
+        assert_eq!(
+            find_big_map_id(&storage_type, &storage, "%ledger"),
+            Some(42)
+        );
+        assert_eq!(
+            find_big_map_id(&storage_type, &storage, "operators"),
+            Some(43)
+        );
+        assert_eq!(
+            find_big_map_id(&storage_type, &storage, "token_metadata"),
+            Some(44)
+        );
+        assert_eq!(
+            find_big_map_id(&storage_type, &storage, "total_supply"),
+            Some(45)
+        );
+        assert_eq!(find_big_map_id(&storage_type, &storage, "metadata"), None);
+        assert_eq!(find_big_map_id(&storage_type, &storage, "paused"), None);
+        assert_eq!(find_big_map_id(&storage_type, &storage, "all_tokens"), None);
+        assert_eq!(find_big_map_id(&storage_type, &storage, "balances"), None);
+
+        let with_metadata: MichelsonV1Expression = serde_json::from_str(
+            r#"{"prim":"Pair","args":[
+                {"prim":"Pair","args":[
+                    {"string":"tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT"},
+                    {"prim":"Pair","args":[{"int":"1"},{"int":"42"}]}
+                ]},
+                {"prim":"Pair","args":[
+                    {"prim":"Pair","args":[{"prim":"Some","args":[{"int":"41"}]},{"int":"43"}]},
+                    {"prim":"False"},
+                    {"int":"44"},
+                    {"int":"45"}
+                ]}
+            ]}"#,
+        )?;
+        assert_eq!(
+            find_big_map_id(&storage_type, &with_metadata, "metadata"),
+            None
+        );
+        assert_eq!(
+            find_big_map_id(&storage_type, &with_metadata, "total_supply"),
+            Some(45)
+        );
+
+        Ok(())
+    }
 }
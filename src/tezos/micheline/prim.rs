@@ -230,57 +230,31 @@ impl Prim {
 
 impl Display for Prim {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let to_michelson = |name: String| -> String {
-            if let Some(args) = self.args.as_ref() {
-                let mut michelson = format!("({} ", name.trim_matches('"'));
-                for (index, arg) in args.iter().enumerate() {
-                    michelson = format!(
-                        "{}{}{}",
-                        michelson,
-                        arg,
-                        if index == args.len() - 1 { ")" } else { " " }
-                    )
-                }
-
-                michelson
-            } else {
-                name.trim_matches('"').to_owned()
-            }
-        };
+        let name = match self.prim {
+            Primitive::Data(data) => serde_json::json!(data),
+            Primitive::Type(type_) => serde_json::json!(type_),
+            Primitive::Instruction(instruction) => serde_json::json!(instruction),
+        }
+        .to_string()
+        .trim_matches('"')
+        .to_owned();
 
-        let value = match self.prim {
-            Primitive::Data(data) => {
-                let name = serde_json::json!(data).to_string();
-                to_michelson(name)
-            }
-            Primitive::Type(type_) => {
-                let name = serde_json::json!(type_).to_string();
-                to_michelson(name)
-            }
-            Primitive::Instruction(instruction) => {
-                let name = serde_json::json!(instruction)
-                    .to_string()
-                    .trim_matches('"')
-                    .to_owned();
-                if let Some(args) = self.args.as_ref() {
-                    let mut michelson = format!("{} ", name);
-                    for (index, arg) in args.iter().enumerate() {
-                        michelson = format!(
-                            "{}{}{}",
-                            michelson,
-                            arg,
-                            if index != args.len() - 1 { " " } else { "" }
-                        )
-                    }
-
-                    michelson
-                } else {
-                    name
-                }
-            }
-        };
+        let mut words = vec![name];
+        if let Some(annots) = self.annots.as_ref() {
+            words.extend(annots.iter().cloned());
+        }
+        if let Some(args) = self.args.as_ref() {
+            words.extend(args.iter().map(|arg| arg.to_string()));
+        }
+        let michelson = words.join(" ");
 
-        write!(f, "{}", value)
+        // Like tezos-client, nested data and types are parenthesized as soon as they carry
+        // arguments or annotations, instructions never are.
+        match self.prim {
+            Primitive::Instruction(_) => write!(f, "{}", michelson),
+            _ if words.len() > 1 => write!(f, "({})", michelson),
+            _ => write!(f, "{}", michelson),
+        }
     }
 }
 
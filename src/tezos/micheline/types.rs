@@ -83,6 +83,10 @@ pub fn lambda(
     prim(Type::Lambda, Some(vec![parameter, return_type]))
 }
 
+pub fn bool() -> MichelsonV1Expression {
+    prim(Type::Bool, None)
+}
+
 pub fn unit() -> MichelsonV1Expression {
     prim(Type::Unit, None)
 }
@@ -0,0 +1,215 @@
+use super::{
+    super::coding, data, literal::Literal, primitive, primitive::Primitive, sequence, string,
+    utils::ConsumableHexStr, HexDecodable, MichelsonV1Expression, TzError, PACK_PREFIX,
+};
+
+impl MichelsonV1Expression {
+    /// Decodes data packed by `pack`. With a `schema`, the optimized encodings of addresses, keys,
+    /// signatures and chain ids are turned back into their readable strings.
+    pub fn from_packed(
+        packed: &str,
+        schema: Option<&MichelsonV1Expression>,
+    ) -> Result<Self, TzError> {
+        let packed = packed.to_lowercase();
+        if packed.len() % 2 != 0 || !packed.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(TzError::InvalidValue {
+                description: "packed data is not a hex string".into(),
+            });
+        }
+
+        let mut encoded = ConsumableHexStr::new(&packed);
+        if encoded.consume_bytes(1)? != PACK_PREFIX {
+            return Err(TzError::InvalidValue {
+                description: format!("packed data has to start with {}", PACK_PREFIX),
+            });
+        }
+        let result = MichelsonV1Expression::from_hex(&mut encoded)?;
+        if encoded.has_more() {
+            return Err(TzError::InvalidValue {
+                description: "packed data has trailing bytes".into(),
+            });
+        }
+
+        if let Some(schema) = schema {
+            result.postunpack(schema)
+        } else {
+            Ok(result)
+        }
+    }
+
+    fn postunpack(&self, schema: &MichelsonV1Expression) -> Result<Self, TzError> {
+        use primitive::{Data, Type};
+
+        let schema = schema.clone().normalized();
+        let (type_, args, _) = schema.type_info()?;
+        let types = args.map(|args| args.as_slice()).unwrap_or_default();
+
+        Ok(match (type_, self) {
+            (Type::List, MichelsonV1Expression::Sequence(items))
+            | (Type::Set, MichelsonV1Expression::Sequence(items)) => {
+                let item_type = types.first().ok_or(TzError::InvalidType)?;
+                sequence(
+                    items
+                        .iter()
+                        .map(|item| item.postunpack(item_type))
+                        .collect::<Result<Vec<_>, TzError>>()?,
+                )
+            }
+            (Type::Map, MichelsonV1Expression::Sequence(items))
+            | (Type::BigMap, MichelsonV1Expression::Sequence(items)) => sequence(
+                items
+                    .iter()
+                    .map(|item| item.postunpack_args(Data::Elt, types))
+                    .collect::<Result<Vec<_>, TzError>>()?,
+            ),
+            (Type::Pair, _) => self.postunpack_args(Data::Pair, types)?,
+            (Type::Option, MichelsonV1Expression::Prim(value))
+                if value.prim == Primitive::Data(Data::None) && value.args_count() == 0 =>
+            {
+                self.clone()
+            }
+            (Type::Option, _) => self.postunpack_args(Data::Some, types)?,
+            (Type::Or, MichelsonV1Expression::Prim(value))
+                if value.prim == Primitive::Data(Data::Left) && types.len() == 2 =>
+            {
+                self.postunpack_args(Data::Left, &types[0..1])?
+            }
+            (Type::Or, _) if types.len() == 2 => self.postunpack_args(Data::Right, &types[1..2])?,
+            (Type::List, _)
+            | (Type::Set, _)
+            | (Type::Map, _)
+            | (Type::BigMap, _)
+            | (Type::Or, _) => return Err(TzError::InvalidType),
+            (type_, MichelsonV1Expression::Literal(Literal::Bytes(value))) => match type_ {
+                Type::Address | Type::Contract => string(coding::decode_contract(value)?),
+                Type::KeyHash => string(coding::decode_address(value, true)?),
+                Type::Key => string(coding::decode_public_key(value)?),
+                Type::Signature => string(coding::decode_signature(value)?),
+                Type::ChainID => string(coding::decode_chain_id(value)?),
+                _ => self.clone(),
+            },
+            _ => self.clone(),
+        })
+    }
+
+    /// Unpacks the arguments of a `prim` value, which must have one argument per type in `types`.
+    fn postunpack_args(
+        &self,
+        prim: primitive::Data,
+        types: &[MichelsonV1Expression],
+    ) -> Result<Self, TzError> {
+        let value = match self {
+            MichelsonV1Expression::Prim(value) => value.clone().normalized(),
+            _ => return Err(TzError::InvalidType),
+        };
+        if value.prim != Primitive::Data(prim) || value.args_count() != types.len() {
+            return Err(TzError::InvalidType);
+        }
+
+        let arguments = value
+            .args
+            .unwrap_or_default()
+            .iter()
+            .zip(types)
+            .map(|(argument, type_)| argument.postunpack(type_))
+            .collect::<Result<Vec<_>, TzError>>()?;
+
+        Ok(data::prim(
+            prim,
+            if arguments.is_empty() {
+                None
+            } else {
+                Some(arguments)
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tezos::{
+        micheline::{data, int, sequence, string, types, MichelsonV1Expression},
+        TzError,
+    };
+
+    #[test]
+    fn test_unpack_round_trip() -> Result<(), TzError> {
+        let vectors = vec![
+            (
+                data::some(sequence(vec![
+                    string("test1".into()),
+                    string("test2".into()),
+                ])),
+                types::option(types::list(types::string())),
+            ),
+            (
+                data::some(sequence(vec![
+                    data::elt(string("testKey1".into()), int(100)),
+                    data::elt(string("testKey2".into()), int(200)),
+                ])),
+                types::option(types::map(types::string(), types::int())),
+            ),
+            (
+                data::pair(
+                    string("tz1Ts3m2dXTXB66XN7cg5ALiAvzZY6AxrFd9".into()),
+                    int(100),
+                ),
+                types::pair(types::address(), types::int()),
+            ),
+            (
+                data::some(string("tz1Ts3m2dXTXB66XN7cg5ALiAvzZY6AxrFd9".into())),
+                types::option(types::address()),
+            ),
+            (data::none(), types::option(types::address())),
+            (
+                data::left(data::right(int(100))),
+                types::or(types::or(types::string(), types::int()), types::int()),
+            ),
+            (string("NetXdQprcVkpaWU".into()), types::chain_id()),
+        ];
+
+        for (value, schema) in vectors {
+            let packed = value.pack(Some(&schema))?;
+            assert_eq!(
+                MichelsonV1Expression::from_packed(&packed, Some(&schema))?,
+                value
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unpack_without_schema() -> Result<(), TzError> {
+        let unpacked = MichelsonV1Expression::from_packed(
+            "0507070a0000001600005a374e077b2e539f222af1e61964d7487c8b95fe00a401",
+            None,
+        )?;
+        assert_eq!(
+            format!("{}", unpacked),
+            "(Pair 0x00005a374e077b2e539f222af1e61964d7487c8b95fe 100)"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unpack_invalid() {
+        let schema = types::option(types::address());
+        for packed in &[
+            "",
+            "05",
+            "0605090a0000001600005a374e077b2e539f222af1e61964d7487c8b95fe",
+            "0505090a0000001600005a374e077b2e539f222af1e61964d7487c8b95fe00",
+            "0505090a0000001600005a374e077b2e539f222af1e61964d7487c8b95f",
+            "05zz",
+            "0500a401",
+        ] {
+            assert!(
+                MichelsonV1Expression::from_packed(packed, Some(&schema)).is_err(),
+                "{}",
+                packed
+            );
+        }
+    }
+}
@@ -1,27 +1,79 @@
 pub mod coding;
+pub mod constants;
+pub mod entrypoints;
+pub mod ledger;
 pub mod micheline;
 pub mod multisig;
+pub mod mutez;
+pub mod nonce;
 pub mod utils;
 
+use std::{future::Future, time::Duration};
+
+use actix_web::rt::time::delay_for;
 use base58check::{FromBase58Check, ToBase58Check};
 use derive_more::{Display, Error};
+use log::warn;
+use serde::Deserialize;
 use sodiumoxide::crypto::sign;
+use tokio::sync::Semaphore;
+
+use crate::{api::models::error::APIError, crypto, logging::context::RequestId, CONFIG};
 
-use crate::{api::models::error::APIError, crypto};
+use self::micheline::MichelsonV1Expression;
 
 #[derive(Error, Display, Debug)]
 pub enum TzError {
     InvalidIndex,
     InvalidType,
     InvalidArgument,
-    InvalidValue { description: String },
+    InvalidValue {
+        description: String,
+    },
     NetworkFailure,
+    #[display(fmt = "NotFound: {}", path)]
+    NotFound {
+        path: String,
+    },
     ParsingFailure,
     InvalidPublicKey,
     InvalidSignature,
     HashFailure,
     HexDecodingFailure,
-    APIError { error: APIError },
+    APIError {
+        error: APIError,
+    },
+    #[display(fmt = "{}: {}", kind, description)]
+    Michelson {
+        kind: String,
+        description: String,
+    },
+}
+
+impl TzError {
+    /// What went wrong for the errors raised while coding Michelson values.
+    pub fn michelson_description(&self) -> &'static str {
+        match self {
+            TzError::InvalidType => "value does not match its Michelson type",
+            TzError::InvalidArgument => "invalid argument in Michelson value",
+            TzError::ParsingFailure => "failed to parse Michelson value",
+            _ => "Michelson coding failed",
+        }
+    }
+
+    /// Turns `InvalidType`, `InvalidArgument` and `ParsingFailure` into a `Michelson` error
+    /// describing what was being coded in `context`, other errors are returned as they are.
+    pub fn with_michelson_context(self, context: &str) -> TzError {
+        match self {
+            TzError::InvalidType | TzError::InvalidArgument | TzError::ParsingFailure => {
+                TzError::Michelson {
+                    kind: self.to_string(),
+                    description: format!("{}: {}", context, self.michelson_description()),
+                }
+            }
+            _ => self,
+        }
+    }
 }
 
 impl From<serde_json::Error> for TzError {
@@ -92,14 +144,490 @@ pub fn edpk_to_tz1(pk: &str) -> Result<String, TzError> {
     Ok(result.to_base58check(6))
 }
 
+pub const HEAD_BLOCK: &str = "head";
+
+lazy_static! {
+    static ref NODE_REQUESTS: NodeRequestLimiter =
+        NodeRequestLimiter::new(CONFIG.server.max_concurrent_node_requests());
+}
+
+/// Bounds the number of requests in flight against the Tezos nodes. Requests beyond the limit
+/// wait for a slot instead of failing.
+pub struct NodeRequestLimiter {
+    semaphore: Semaphore,
+}
+
+impl NodeRequestLimiter {
+    pub fn new(max_concurrent_requests: usize) -> Self {
+        NodeRequestLimiter {
+            semaphore: Semaphore::new(max_concurrent_requests),
+        }
+    }
+
+    pub async fn run<F: Future>(&self, request: F) -> F::Output {
+        let _permit = self.semaphore.acquire().await;
+        request.await
+    }
+}
+
+/// Runs `request`, which sends a request to a node and reads its response, once a slot is
+/// available (see `server.max_concurrent_node_requests`).
+pub async fn node_request<F: Future>(request: F) -> F::Output {
+    NODE_REQUESTS.run(request).await
+}
+
+fn network_failure(url: &str, error: reqwest::Error) -> TzError {
+    warn!(
+        "[{}] request to {} failed: {}",
+        RequestId::current_label(),
+        url,
+        error
+    );
+
+    TzError::NetworkFailure
+}
+
+/// Maps an unsuccessful node response: 404 to `NotFound` with the requested `path`, anything else,
+/// 5xx included, to `NetworkFailure`.
+pub fn check_node_response(
+    response: reqwest::Response,
+    path: &str,
+) -> Result<reqwest::Response, TzError> {
+    let status = response.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Err(TzError::NotFound { path: path.into() });
+    }
+    if !status.is_success() {
+        warn!(
+            "[{}] node request to {} failed with status {}",
+            RequestId::current_label(),
+            path,
+            status
+        );
+        return Err(TzError::NetworkFailure);
+    }
+
+    Ok(response)
+}
+
+pub async fn block_hash(node_url: &str, block: &str) -> Result<String, TzError> {
+    let url = format!("{}/chains/main/blocks/{}/hash", node_url, block);
+    let result = node_request(async {
+        reqwest::get(&url)
+            .await
+            .map_err(|error| network_failure(&url, error))?
+            .json::<String>()
+            .await
+            .map_err(|_error| TzError::ParsingFailure)
+    })
+    .await?;
+
+    Ok(result)
+}
+
+/// The chain id of the node at `node_url`. The configured `tezos.chain_id` is used for the
+/// configured nodes instead of asking them.
 pub async fn chain_id(node_url: &str) -> Result<String, TzError> {
+    chain_id_or_fetch(CONFIG.configured_chain_id(node_url), node_url).await
+}
+
+async fn chain_id_or_fetch(
+    configured_chain_id: Option<&str>,
+    node_url: &str,
+) -> Result<String, TzError> {
+    match configured_chain_id {
+        Some(chain_id) => Ok(chain_id.to_owned()),
+        None => fetch_chain_id(node_url).await,
+    }
+}
+
+/// Asks the node at `node_url` for its chain id.
+pub async fn fetch_chain_id(node_url: &str) -> Result<String, TzError> {
     let url = format!("{}/chains/main/chain_id", node_url);
-    let result = reqwest::get(&url)
+    let result = node_request(async {
+        reqwest::get(&url)
+            .await
+            .map_err(|error| network_failure(&url, error))?
+            .json::<String>()
+            .await
+            .map_err(|_error| TzError::ParsingFailure)
+    })
+    .await?;
+
+    Ok(result)
+}
+
+/// The delegate of the contract at `address`, `None` if it has none. Nodes answer 404 for
+/// contracts without a delegate.
+pub async fn delegate(node_url: &str, address: &str) -> Result<Option<String>, TzError> {
+    let path = format!(
+        "/chains/main/blocks/{}/context/contracts/{}/delegate",
+        HEAD_BLOCK, address
+    );
+    let url = format!("{}{}", node_url, path);
+    node_request(async {
+        let response = reqwest::get(&url)
+            .await
+            .map_err(|error| network_failure(&url, error))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        check_node_response(response, &path)?
+            .json::<Option<String>>()
+            .await
+            .map_err(|_error| TzError::ParsingFailure)
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+struct PackedData {
+    packed: String,
+}
+
+/// Packs `data` of type `schema` with the node at `node_url`, the result is prefixed like
+/// `MichelsonV1Expression::pack`.
+pub async fn pack_data(
+    node_url: &str,
+    data: &MichelsonV1Expression,
+    schema: &MichelsonV1Expression,
+) -> Result<String, TzError> {
+    let path = format!(
+        "/chains/main/blocks/{}/helpers/scripts/pack_data",
+        HEAD_BLOCK
+    );
+    let url = format!("{}{}", node_url, path);
+    let body = serde_json::json!({ "data": data, "type": schema });
+    let result = node_request(async {
+        let response = reqwest::Client::new()
+            .post(&url)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|error| network_failure(&url, error))?;
+        check_node_response(response, &path)?
+            .json::<PackedData>()
+            .await
+            .map_err(|_error| TzError::ParsingFailure)
+    })
+    .await?;
+
+    Ok(result.packed)
+}
+
+pub async fn big_map_value(
+    node_url: &str,
+    big_map_id: i64,
+    key_hash: &str,
+) -> Result<Option<MichelsonV1Expression>, TzError> {
+    let url = format!(
+        "{}/chains/main/blocks/head/context/big_maps/{}/{}",
+        node_url, big_map_id, key_hash
+    );
+    node_request(fetch_big_map_value(&url)).await
+}
+
+async fn fetch_big_map_value(url: &str) -> Result<Option<MichelsonV1Expression>, TzError> {
+    let response = reqwest::get(url)
         .await
-        .map_err(|_error| TzError::NetworkFailure)?
-        .json::<String>()
+        .map_err(|error| network_failure(url, error))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    let result = response
+        .error_for_status()
+        .map_err(|error| network_failure(url, error))?
+        .json::<MichelsonV1Expression>()
         .await
         .map_err(|_error| TzError::ParsingFailure)?;
 
+    Ok(Some(result))
+}
+
+const CONFIRMATION_LOOKBACK_BLOCKS: i64 = 5;
+
+#[derive(Deserialize)]
+struct BlockHeader {
+    level: i64,
+}
+
+pub async fn head_level(node_url: &str) -> Result<i64, TzError> {
+    let url = format!("{}/chains/main/blocks/head/header", node_url);
+    let result = node_request(async {
+        reqwest::get(&url)
+            .await
+            .map_err(|error| network_failure(&url, error))?
+            .json::<BlockHeader>()
+            .await
+            .map_err(|_error| TzError::ParsingFailure)
+    })
+    .await?;
+
+    Ok(result.level)
+}
+
+#[derive(Deserialize)]
+struct HeadHeader {
+    level: i64,
+    protocol: String,
+}
+
+/// The result of a node health check. `head_level` and `protocol` are those of the head block,
+/// both `None` if the node is unreachable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeHealth {
+    pub reachable: bool,
+    pub head_level: Option<i64>,
+    pub protocol: Option<String>,
+}
+
+/// Asks the node at `node_url` for its head block header. A node that fails to answer with one
+/// within `timeout` is reported as unreachable.
+pub async fn node_health(node_url: &str, timeout: Duration) -> NodeHealth {
+    let url = format!("{}/chains/main/blocks/{}/header", node_url, HEAD_BLOCK);
+    let result = node_request(async {
+        reqwest::Client::new()
+            .get(&url)
+            .timeout(timeout)
+            .send()
+            .await
+            .map_err(|error| network_failure(&url, error))?
+            .error_for_status()
+            .map_err(|error| network_failure(&url, error))?
+            .json::<HeadHeader>()
+            .await
+            .map_err(|_error| TzError::ParsingFailure)
+    })
+    .await;
+
+    match result {
+        Ok(header) => NodeHealth {
+            reachable: true,
+            head_level: Some(header.level),
+            protocol: Some(header.protocol),
+        },
+        Err(_) => NodeHealth {
+            reachable: false,
+            head_level: None,
+            protocol: None,
+        },
+    }
+}
+
+pub async fn operation_hashes(node_url: &str, block: &str) -> Result<Vec<Vec<String>>, TzError> {
+    let url = format!("{}/chains/main/blocks/{}/operation_hashes", node_url, block);
+    let result = node_request(async {
+        reqwest::get(&url)
+            .await
+            .map_err(|error| network_failure(&url, error))?
+            .json::<Vec<Vec<String>>>()
+            .await
+            .map_err(|_error| TzError::ParsingFailure)
+    })
+    .await?;
+
     Ok(result)
 }
+
+/// Polls the node up to `max_attempts` times, waiting `interval` between attempts, and checks
+/// every block baked since the first attempt (plus a few blocks before it) for the operation.
+/// Returns `false` if the operation could not be found within that window.
+pub async fn wait_for_operation(
+    node_url: &str,
+    operation_hash: &str,
+    max_attempts: u32,
+    interval: Duration,
+) -> bool {
+    let mut next_level: Option<i64> = None;
+    for attempt in 0..max_attempts {
+        if attempt > 0 {
+            delay_for(interval).await;
+        }
+
+        let head_level = match head_level(node_url).await {
+            Ok(level) => level,
+            Err(_) => continue,
+        };
+        let from_level =
+            next_level.unwrap_or_else(|| (head_level - CONFIRMATION_LOOKBACK_BLOCKS).max(0));
+
+        for level in from_level..=head_level {
+            let hashes = match operation_hashes(node_url, &level.to_string()).await {
+                Ok(hashes) => hashes,
+                Err(_) => break,
+            };
+            if hashes
+                .iter()
+                .flatten()
+                .any(|hash| hash.as_str() == operation_hash)
+            {
+                return true;
+            }
+            next_level = Some(level + 1);
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use actix_web::{rt::time::delay_for, web, App, HttpResponse};
+    use futures::future::join_all;
+
+    use super::{
+        chain_id, chain_id_or_fetch, delegate, wait_for_operation, NodeRequestLimiter, TzError,
+    };
+
+    const OPERATION_HASH: &str = "ooYnKSPRXS3pP1MHhHmJ4dFBNtxo2ZvtkWAi7NeNEUUXbk7kWBa";
+
+    fn mock_node(included_at: Option<i64>) -> actix_web::test::TestServer {
+        actix_web::test::start(move || {
+            App::new()
+                .route(
+                    "/chains/main/blocks/head/header",
+                    web::get().to(|| HttpResponse::Ok().json(serde_json::json!({ "level": 100 }))),
+                )
+                .route(
+                    "/chains/main/blocks/{level}/operation_hashes",
+                    web::get().to(move |level: web::Path<i64>| {
+                        let hashes: Vec<Vec<&str>> = if Some(*level) == included_at {
+                            vec![vec![], vec![], vec![], vec![OPERATION_HASH]]
+                        } else {
+                            vec![vec![], vec![], vec![], vec![]]
+                        };
+                        HttpResponse::Ok().json(hashes)
+                    }),
+                )
+        })
+    }
+
+    #[actix_rt::test]
+    async fn test_wait_for_operation_not_found() {
+        let node = mock_node(None);
+        let node_url = node.url("").trim_end_matches('/').to_owned();
+
+        let included =
+            wait_for_operation(&node_url, OPERATION_HASH, 3, Duration::from_millis(10)).await;
+
+        assert!(!included);
+    }
+
+    #[actix_rt::test]
+    async fn test_wait_for_operation_found() {
+        let node = mock_node(Some(98));
+        let node_url = node.url("").trim_end_matches('/').to_owned();
+
+        let included =
+            wait_for_operation(&node_url, OPERATION_HASH, 3, Duration::from_millis(10)).await;
+
+        assert!(included);
+    }
+
+    #[actix_rt::test]
+    async fn test_node_requests_are_limited() {
+        const MAX_CONCURRENT_REQUESTS: usize = 3;
+
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let node = {
+            let in_flight = in_flight.clone();
+            let max_in_flight = max_in_flight.clone();
+            actix_web::test::start(move || {
+                let in_flight = in_flight.clone();
+                let max_in_flight = max_in_flight.clone();
+                App::new().route(
+                    "/chains/main/chain_id",
+                    web::get().to(move || {
+                        let in_flight = in_flight.clone();
+                        let max_in_flight = max_in_flight.clone();
+                        async move {
+                            let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                            max_in_flight.fetch_max(current, Ordering::SeqCst);
+                            delay_for(Duration::from_millis(20)).await;
+                            in_flight.fetch_sub(1, Ordering::SeqCst);
+                            HttpResponse::Ok().json("NetXdQprcVkpaWU")
+                        }
+                    }),
+                )
+            })
+        };
+        let node_url = node.url("").trim_end_matches('/').to_owned();
+
+        let limiter = NodeRequestLimiter::new(MAX_CONCURRENT_REQUESTS);
+        let results = join_all((0..20).map(|_| limiter.run(chain_id(&node_url)))).await;
+
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert!(max_in_flight.load(Ordering::SeqCst) <= MAX_CONCURRENT_REQUESTS);
+        assert!(max_in_flight.load(Ordering::SeqCst) > 1);
+    }
+
+    fn delegate_node() -> actix_web::test::TestServer {
+        actix_web::test::start(|| {
+            App::new().route(
+                "/chains/main/blocks/head/context/contracts/{address}/delegate",
+                web::get().to(|path: web::Path<String>| {
+                    if path.into_inner() == "KT1MeQd67djiEEkscx3USfQ7vFYg589DySpZ" {
+                        HttpResponse::Ok().json("tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT")
+                    } else {
+                        HttpResponse::NotFound().finish()
+                    }
+                }),
+            )
+        })
+    }
+
+    #[actix_rt::test]
+    async fn test_delegate() -> Result<(), TzError> {
+        let node = delegate_node();
+        let node_url = node.url("").trim_end_matches('/').to_owned();
+
+        let delegated = delegate(&node_url, "KT1MeQd67djiEEkscx3USfQ7vFYg589DySpZ").await?;
+        assert_eq!(
+            delegated.as_deref(),
+            Some("tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT")
+        );
+
+        let undelegated = delegate(&node_url, "KT1KiJ1N9wgEVGkgPDYhLBYRBMPy1RG3pN2J").await?;
+        assert_eq!(undelegated, None);
+
+        Ok(())
+    }
+
+    #[actix_rt::test]
+    async fn test_configured_chain_id() -> Result<(), TzError> {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let node = {
+            let fetches = fetches.clone();
+            actix_web::test::start(move || {
+                let fetches = fetches.clone();
+                App::new().route(
+                    "/chains/main/chain_id",
+                    web::get().to(move || {
+                        fetches.fetch_add(1, Ordering::SeqCst);
+                        HttpResponse::Ok().json("NetXdQprcVkpaWU")
+                    }),
+                )
+            })
+        };
+        let node_url = node.url("").trim_end_matches('/').to_owned();
+
+        let chain_id = chain_id_or_fetch(Some("NetXm8tYqnMWky1"), &node_url).await?;
+        assert_eq!(chain_id, "NetXm8tYqnMWky1");
+        assert_eq!(fetches.load(Ordering::SeqCst), 0);
+
+        let chain_id = chain_id_or_fetch(None, &node_url).await?;
+        assert_eq!(chain_id, "NetXdQprcVkpaWU");
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 
 use crate::{
-    api::models::operation_request::OperationRequestKind,
+    api::models::{contract::ContractKind, operation_request::OperationRequestKind},
     tezos::{
         self,
         micheline::{
@@ -17,14 +17,19 @@ use crate::{
 use async_trait::async_trait;
 use num_bigint::BigInt;
 use tezos::micheline::{extract_key, extract_string, instructions};
+use tezos::mutez::Mutez;
+use tezos::nonce::MultisigNonce;
 
 use super::{
-    validate, Multisig, OperationRequestParams, Parameters, SignableMessage, Signature, Storage,
+    pack, validate, Multisig, OperationRequestParams, Parameters, SignableMessage, Signature,
+    Storage,
 };
 
 pub struct GenericMultisig {
     address: String,
     node_url: String,
+    block: String,
+    kind: ContractKind,
 
     storage: Option<Storage>,
 }
@@ -39,10 +44,10 @@ impl Multisig for GenericMultisig {
         &self.address
     }
 
-    async fn nonce(&mut self) -> Result<i64, TzError> {
+    async fn nonce(&mut self) -> Result<MultisigNonce, TzError> {
         let storage = self.fetch_storage().await?;
 
-        Ok(storage.nonce + 1)
+        Ok(storage.nonce.next())
     }
 
     async fn min_signatures(&mut self) -> Result<i64, TzError> {
@@ -64,15 +69,16 @@ impl Multisig for GenericMultisig {
         proposed_keyholders_pk: Option<Vec<String>>,
     ) -> Result<SignableMessage, TzError> {
         validate(operation_request_params, &proposed_keyholders_pk)?;
+        GenericMultisig::validate_kind(operation_request_params)?;
 
         let message =
-            self.michelson_message(contract, operation_request_params, proposed_keyholders_pk);
+            self.michelson_message(contract, operation_request_params, proposed_keyholders_pk)?;
 
         let data = data::pair(
             string(operation_request_params.chain_id.clone()),
             data::pair(
                 string(self.address.clone()),
-                data::pair(int(operation_request_params.nonce), message),
+                data::pair(operation_request_params.nonce.into(), message),
             ),
         );
         let schema = types::pair(
@@ -87,7 +93,13 @@ impl Multisig for GenericMultisig {
         );
 
         Ok(SignableMessage {
-            packed_data: data.pack(Some(&schema))?,
+            packed_data: pack(
+                &self.node_url,
+                operation_request_params.kind.try_into()?,
+                &data,
+                &schema,
+            )
+            .await?,
             michelson_data: data,
             michelson_type: schema,
         })
@@ -101,6 +113,7 @@ impl Multisig for GenericMultisig {
         signatures: Vec<Signature<'_>>,
     ) -> Result<Parameters, TzError> {
         validate(operation_request_params, &proposed_keyholders_pk)?;
+        GenericMultisig::validate_kind(operation_request_params)?;
 
         let mut signature_map_items = signatures
             .into_iter()
@@ -124,31 +137,51 @@ impl Multisig for GenericMultisig {
             operation_request_params,
             proposed_keyholders_pk,
             signature_map,
-        );
+        )?;
 
         let operation_request_kind: OperationRequestKind =
             operation_request_params.kind.try_into().unwrap();
-        let entrypoint = GenericMultisig::entrypoint(operation_request_kind);
+        let entrypoint = GenericMultisig::entrypoint(operation_request_kind)?;
 
         Ok(Parameters { entrypoint, value })
     }
 }
 
 impl GenericMultisig {
-    pub fn new(address: String, node_url: String) -> Self {
+    pub fn new(address: String, node_url: String, kind: ContractKind, block: String) -> Self {
         GenericMultisig {
             address,
             node_url,
+            block,
+            kind,
             storage: None,
         }
     }
 
+    /// FA2 contracts have no FA1.2 `approve` entrypoint, a lambda calling it would only fail on
+    /// chain after the keyholders signed it.
+    fn validate_kind(operation_request_params: &OperationRequestParams) -> Result<(), TzError> {
+        let operation_request_kind: OperationRequestKind =
+            operation_request_params.kind.try_into()?;
+        if operation_request_kind == OperationRequestKind::Approve {
+            return Err(GenericMultisig::approve_unsupported());
+        }
+
+        Ok(())
+    }
+
+    fn approve_unsupported() -> TzError {
+        TzError::InvalidValue {
+            description: "approve operation requests are not supported by FA2 contracts".to_owned(),
+        }
+    }
+
     async fn fetch_storage(&mut self) -> Result<&Storage, TzError> {
         if let Some(_) = self.storage {
             return Ok(self.storage.as_ref().unwrap());
         }
 
-        let storage = Storage::fetch_from(self.address(), self.node_url()).await?;
+        let storage = Storage::fetch_from(self.address(), self.node_url(), &self.block).await?;
         self.storage = Some(storage);
 
         Ok(self.storage.as_ref().unwrap())
@@ -160,11 +193,12 @@ impl GenericMultisig {
         operation_request_params: &OperationRequestParams,
         proposed_keyholders_pk: Option<Vec<String>>,
         signature_map: MichelsonV1Expression,
-    ) -> MichelsonV1Expression {
+    ) -> Result<MichelsonV1Expression, TzError> {
         let operation_request_kind: OperationRequestKind =
             operation_request_params.kind.try_into().unwrap();
+        let xtz_amount = GenericMultisig::xtz_amount(operation_request_params);
 
-        match operation_request_kind {
+        let parameters = match operation_request_kind {
             OperationRequestKind::Mint => {
                 let lambda = self.mint_lambda(
                     operation_request_params
@@ -173,6 +207,7 @@ impl GenericMultisig {
                         .unwrap()
                         .into(),
                     contract.pkh.clone(),
+                    &contract.mint_entrypoint,
                     operation_request_params
                         .amount
                         .as_ref()
@@ -180,6 +215,18 @@ impl GenericMultisig {
                         .as_bigint_and_exponent()
                         .0,
                     contract.token_id.into(),
+                    xtz_amount,
+                );
+
+                data::pair(lambda, signature_map)
+            }
+            OperationRequestKind::BatchMint => {
+                let lambda = self.batch_mint_lambda(
+                    GenericMultisig::batch_mint_recipients(operation_request_params),
+                    contract.pkh.clone(),
+                    &contract.mint_entrypoint,
+                    contract.token_id.into(),
+                    xtz_amount,
                 );
 
                 data::pair(lambda, signature_map)
@@ -187,6 +234,7 @@ impl GenericMultisig {
             OperationRequestKind::Burn => {
                 let lambda = self.burn_lambda(
                     contract.pkh.clone(),
+                    &contract.burn_entrypoint,
                     operation_request_params
                         .amount
                         .as_ref()
@@ -194,6 +242,7 @@ impl GenericMultisig {
                         .as_bigint_and_exponent()
                         .0,
                     contract.token_id.into(),
+                    xtz_amount,
                 );
 
                 data::pair(lambda, signature_map)
@@ -211,6 +260,7 @@ impl GenericMultisig {
                         .unwrap()
                         .into(),
                     contract.pkh.clone(),
+                    xtz_amount,
                 );
 
                 data::pair(lambda, signature_map)
@@ -223,6 +273,7 @@ impl GenericMultisig {
                         .unwrap()
                         .into(),
                     contract.pkh.clone(),
+                    xtz_amount,
                 );
 
                 data::pair(lambda, signature_map)
@@ -235,6 +286,7 @@ impl GenericMultisig {
                         .unwrap()
                         .into(),
                     contract.pkh.clone(),
+                    xtz_amount,
                 );
 
                 data::pair(lambda, signature_map)
@@ -247,16 +299,20 @@ impl GenericMultisig {
                         .unwrap()
                         .into(),
                     contract.pkh.clone(),
+                    xtz_amount,
                 );
 
                 data::pair(lambda, signature_map)
             }
             OperationRequestKind::AcceptOwnership => {
-                let lambda = self.accept_ownership_lambda(contract.pkh.clone());
+                let lambda = self.accept_ownership_lambda(contract.pkh.clone(), xtz_amount);
 
                 data::pair(lambda, signature_map)
             }
-        }
+            OperationRequestKind::Approve => return Err(GenericMultisig::approve_unsupported()),
+        };
+
+        Ok(parameters)
     }
 
     fn michelson_message(
@@ -264,11 +320,12 @@ impl GenericMultisig {
         contract: &Contract,
         operation_request_params: &OperationRequestParams,
         proposed_keyholders_pk: Option<Vec<String>>,
-    ) -> MichelsonV1Expression {
+    ) -> Result<MichelsonV1Expression, TzError> {
         let operation_request_kind: OperationRequestKind =
             operation_request_params.kind.try_into().unwrap();
+        let xtz_amount = GenericMultisig::xtz_amount(operation_request_params);
 
-        match operation_request_kind {
+        let message = match operation_request_kind {
             OperationRequestKind::Mint => self.mint_lambda(
                 operation_request_params
                     .target_address
@@ -276,6 +333,7 @@ impl GenericMultisig {
                     .unwrap()
                     .into(),
                 contract.pkh.clone(),
+                &contract.mint_entrypoint,
                 operation_request_params
                     .amount
                     .as_ref()
@@ -283,9 +341,18 @@ impl GenericMultisig {
                     .as_bigint_and_exponent()
                     .0,
                 contract.token_id.into(),
+                xtz_amount,
+            ),
+            OperationRequestKind::BatchMint => self.batch_mint_lambda(
+                GenericMultisig::batch_mint_recipients(operation_request_params),
+                contract.pkh.clone(),
+                &contract.mint_entrypoint,
+                contract.token_id.into(),
+                xtz_amount,
             ),
             OperationRequestKind::Burn => self.burn_lambda(
                 contract.pkh.clone(),
+                &contract.burn_entrypoint,
                 operation_request_params
                     .amount
                     .as_ref()
@@ -293,6 +360,7 @@ impl GenericMultisig {
                     .as_bigint_and_exponent()
                     .0,
                 contract.token_id.into(),
+                xtz_amount,
             ),
             OperationRequestKind::UpdateKeyholders => self.update_keyholders_michelson_message(
                 operation_request_params.threshold.unwrap(),
@@ -305,6 +373,7 @@ impl GenericMultisig {
                     .unwrap()
                     .into(),
                 contract.pkh.clone(),
+                xtz_amount,
             ),
             OperationRequestKind::RemoveOperator => self.remove_operator_lambda(
                 operation_request_params
@@ -313,6 +382,7 @@ impl GenericMultisig {
                     .unwrap()
                     .into(),
                 contract.pkh.clone(),
+                xtz_amount,
             ),
             OperationRequestKind::SetRedeemAddress => self.set_redeem_address_lambda(
                 operation_request_params
@@ -321,6 +391,7 @@ impl GenericMultisig {
                     .unwrap()
                     .into(),
                 contract.pkh.clone(),
+                xtz_amount,
             ),
             OperationRequestKind::TransferOwnership => self.transfer_ownership_lambda(
                 operation_request_params
@@ -329,27 +400,122 @@ impl GenericMultisig {
                     .unwrap()
                     .into(),
                 contract.pkh.clone(),
+                xtz_amount,
             ),
             OperationRequestKind::AcceptOwnership => {
-                self.accept_ownership_lambda(contract.pkh.clone())
+                self.accept_ownership_lambda(contract.pkh.clone(), xtz_amount)
             }
-        }
+            OperationRequestKind::Approve => return Err(GenericMultisig::approve_unsupported()),
+        };
+
+        Ok(message)
     }
 
     fn mint_lambda(
         &self,
         address: String,
         contract_address: String,
+        entrypoint: &str,
         amount: BigInt,
         token_id: i64,
+        xtz_amount: Mutez,
     ) -> MichelsonV1Expression {
-        sequence(vec![
-            instructions::drop(),
-            instructions::nil(types::operation()),
-            instructions::push(
-                types::address(),
-                string(format!("{}%mint", contract_address)),
-            ),
+        self.batch_mint_lambda(
+            vec![(address, amount)],
+            contract_address,
+            entrypoint,
+            token_id,
+            xtz_amount,
+        )
+    }
+
+    /// A lambda calling the mint entrypoint once per recipient, a single mint being a batch of one.
+    /// `xtz_amount` is sent along with every call.
+    fn batch_mint_lambda(
+        &self,
+        recipients: Vec<(String, BigInt)>,
+        contract_address: String,
+        entrypoint: &str,
+        token_id: i64,
+        xtz_amount: Mutez,
+    ) -> MichelsonV1Expression {
+        let contract_entrypoint = format!("{}%{}", contract_address, entrypoint);
+        let mut code = vec![instructions::drop(), instructions::nil(types::operation())];
+        for (address, amount) in recipients {
+            code.extend(match self.kind {
+                ContractKind::FA2SingleAsset => self.single_asset_mint_call(
+                    address,
+                    contract_entrypoint.clone(),
+                    amount,
+                    xtz_amount,
+                ),
+                _ => self.multi_asset_mint_call(
+                    address,
+                    contract_entrypoint.clone(),
+                    amount,
+                    token_id,
+                    xtz_amount,
+                ),
+            });
+        }
+
+        sequence(code)
+    }
+
+    /// The amount sent along with the contract calls of the lambda, validated to be a mutez
+    /// amount by `validate`.
+    fn xtz_amount(operation_request_params: &OperationRequestParams) -> Mutez {
+        operation_request_params
+            .xtz_amount
+            .and_then(|xtz_amount| Mutez::try_from(xtz_amount).ok())
+            .unwrap_or(Mutez::ZERO)
+    }
+
+    fn batch_mint_recipients(
+        operation_request_params: &OperationRequestParams,
+    ) -> Vec<(String, BigInt)> {
+        operation_request_params
+            .batch_mints
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|mint| {
+                (
+                    mint.target_address.clone(),
+                    mint.amount.as_bigint_and_exponent().0,
+                )
+            })
+            .collect()
+    }
+
+    fn burn_lambda(
+        &self,
+        contract_address: String,
+        entrypoint: &str,
+        amount: BigInt,
+        token_id: i64,
+        xtz_amount: Mutez,
+    ) -> MichelsonV1Expression {
+        let contract_entrypoint = format!("{}%{}", contract_address, entrypoint);
+        match self.kind {
+            ContractKind::FA2SingleAsset => {
+                self.single_asset_burn_lambda(contract_entrypoint, amount, xtz_amount)
+            }
+            _ => self.multi_asset_burn_lambda(contract_entrypoint, amount, token_id, xtz_amount),
+        }
+    }
+
+    /// Pushes the operation minting `amount` for `address` onto the operation list.
+    fn multi_asset_mint_call(
+        &self,
+        address: String,
+        contract_entrypoint: String,
+        amount: BigInt,
+        token_id: i64,
+        xtz_amount: Mutez,
+    ) -> Vec<MichelsonV1Expression> {
+        vec![
+            instructions::push(types::address(), string(contract_entrypoint)),
             instructions::contract(types::list(types::pair(
                 types::address(),
                 types::pair(types::nat(), types::nat()),
@@ -358,7 +524,7 @@ impl GenericMultisig {
                 sequence(vec![instructions::unit(), instructions::fail_with()]),
                 sequence(vec![]),
             )]),
-            instructions::push(types::mutez(), int(0)),
+            instructions::push(types::mutez(), xtz_amount.into()),
             instructions::nil(types::pair(
                 types::address(),
                 types::pair(types::nat(), types::nat()),
@@ -371,32 +537,30 @@ impl GenericMultisig {
             instructions::cons(),
             instructions::transfer_tokens(),
             instructions::cons(),
-        ])
+        ]
         // data::left(data::right(data::right(sequence(vec![data::pair(
         //     string(address),
         //     data::pair(int(token_id), int(amount)),
         // )]))))
     }
 
-    fn burn_lambda(
+    fn multi_asset_burn_lambda(
         &self,
-        contract_address: String,
+        contract_entrypoint: String,
         amount: BigInt,
         token_id: i64,
+        xtz_amount: Mutez,
     ) -> MichelsonV1Expression {
         sequence(vec![
             instructions::drop(),
             instructions::nil(types::operation()),
-            instructions::push(
-                types::address(),
-                string(format!("{}%burn", contract_address)),
-            ),
+            instructions::push(types::address(), string(contract_entrypoint)),
             instructions::contract(types::list(types::pair(types::nat(), types::nat()))),
             sequence(vec![instructions::if_none(
                 sequence(vec![instructions::unit(), instructions::fail_with()]),
                 sequence(vec![]),
             )]),
-            instructions::push(types::mutez(), int(0)),
+            instructions::push(types::mutez(), xtz_amount.into()),
             instructions::nil(types::pair(types::nat(), types::nat())),
             instructions::push(types::nat(), int(amount)),
             instructions::push(types::nat(), int(token_id)),
@@ -411,10 +575,61 @@ impl GenericMultisig {
         // )]))))
     }
 
+    /// Pushes the operation minting `amount` for `address` onto the operation list.
+    fn single_asset_mint_call(
+        &self,
+        address: String,
+        contract_entrypoint: String,
+        amount: BigInt,
+        xtz_amount: Mutez,
+    ) -> Vec<MichelsonV1Expression> {
+        vec![
+            instructions::push(types::address(), string(contract_entrypoint)),
+            instructions::contract(types::list(types::pair(types::address(), types::nat()))),
+            sequence(vec![instructions::if_none(
+                sequence(vec![instructions::unit(), instructions::fail_with()]),
+                sequence(vec![]),
+            )]),
+            instructions::push(types::mutez(), xtz_amount.into()),
+            instructions::nil(types::pair(types::address(), types::nat())),
+            instructions::push(types::nat(), int(amount)),
+            instructions::push(types::address(), string(address)),
+            instructions::pair(),
+            instructions::cons(),
+            instructions::transfer_tokens(),
+            instructions::cons(),
+        ]
+    }
+
+    fn single_asset_burn_lambda(
+        &self,
+        contract_entrypoint: String,
+        amount: BigInt,
+        xtz_amount: Mutez,
+    ) -> MichelsonV1Expression {
+        sequence(vec![
+            instructions::drop(),
+            instructions::nil(types::operation()),
+            instructions::push(types::address(), string(contract_entrypoint)),
+            instructions::contract(types::list(types::nat())),
+            sequence(vec![instructions::if_none(
+                sequence(vec![instructions::unit(), instructions::fail_with()]),
+                sequence(vec![]),
+            )]),
+            instructions::push(types::mutez(), xtz_amount.into()),
+            instructions::nil(types::nat()),
+            instructions::push(types::nat(), int(amount)),
+            instructions::cons(),
+            instructions::transfer_tokens(),
+            instructions::cons(),
+        ])
+    }
+
     fn add_operator_lambda(
         &self,
         address: String,
         contract_address: String,
+        xtz_amount: Mutez,
     ) -> MichelsonV1Expression {
         sequence(vec![
             instructions::drop(),
@@ -428,7 +643,7 @@ impl GenericMultisig {
                 sequence(vec![instructions::unit(), instructions::fail_with()]),
                 sequence(vec![]),
             )]),
-            instructions::push(types::mutez(), int(0)),
+            instructions::push(types::mutez(), xtz_amount.into()),
             instructions::nil(types::address()),
             instructions::push(types::address(), string(address)),
             instructions::cons(),
@@ -441,6 +656,7 @@ impl GenericMultisig {
         &self,
         address: String,
         contract_address: String,
+        xtz_amount: Mutez,
     ) -> MichelsonV1Expression {
         sequence(vec![
             instructions::drop(),
@@ -454,7 +670,7 @@ impl GenericMultisig {
                 sequence(vec![instructions::unit(), instructions::fail_with()]),
                 sequence(vec![]),
             )]),
-            instructions::push(types::mutez(), int(0)),
+            instructions::push(types::mutez(), xtz_amount.into()),
             instructions::nil(types::address()),
             instructions::push(types::address(), string(address)),
             instructions::cons(),
@@ -467,6 +683,7 @@ impl GenericMultisig {
         &self,
         address: String,
         contract_address: String,
+        xtz_amount: Mutez,
     ) -> MichelsonV1Expression {
         sequence(vec![
             instructions::drop(),
@@ -480,7 +697,7 @@ impl GenericMultisig {
                 sequence(vec![instructions::unit(), instructions::fail_with()]),
                 sequence(vec![]),
             )]),
-            instructions::push(types::mutez(), int(0)),
+            instructions::push(types::mutez(), xtz_amount.into()),
             instructions::nil(types::address()),
             instructions::push(types::address(), string(address)),
             instructions::cons(),
@@ -493,6 +710,7 @@ impl GenericMultisig {
         &self,
         address: String,
         contract_address: String,
+        xtz_amount: Mutez,
     ) -> MichelsonV1Expression {
         sequence(vec![
             instructions::drop(),
@@ -506,7 +724,7 @@ impl GenericMultisig {
                 sequence(vec![instructions::unit(), instructions::fail_with()]),
                 sequence(vec![]),
             )]),
-            instructions::push(types::mutez(), int(0)),
+            instructions::push(types::mutez(), xtz_amount.into()),
             instructions::nil(types::address()),
             instructions::push(types::address(), string(address)),
             instructions::cons(),
@@ -515,7 +733,11 @@ impl GenericMultisig {
         ])
     }
 
-    fn accept_ownership_lambda(&self, contract_address: String) -> MichelsonV1Expression {
+    fn accept_ownership_lambda(
+        &self,
+        contract_address: String,
+        xtz_amount: Mutez,
+    ) -> MichelsonV1Expression {
         sequence(vec![
             instructions::drop(),
             instructions::nil(types::operation()),
@@ -528,7 +750,7 @@ impl GenericMultisig {
                 sequence(vec![instructions::unit(), instructions::fail_with()]),
                 sequence(vec![]),
             )]),
-            instructions::push(types::mutez(), int(0)),
+            instructions::push(types::mutez(), xtz_amount.into()),
             instructions::nil(types::unit()),
             instructions::push(types::address(), unit()),
             instructions::cons(),
@@ -573,16 +795,282 @@ impl GenericMultisig {
         )
     }
 
-    fn entrypoint(operation_request_kind: OperationRequestKind) -> String {
-        match operation_request_kind {
+    fn entrypoint(operation_request_kind: OperationRequestKind) -> Result<String, TzError> {
+        let entrypoint = match operation_request_kind {
             OperationRequestKind::Mint
             | OperationRequestKind::Burn
             | OperationRequestKind::AddOperator
             | OperationRequestKind::RemoveOperator
             | OperationRequestKind::SetRedeemAddress
             | OperationRequestKind::TransferOwnership
-            | OperationRequestKind::AcceptOwnership => String::from("execute"),
+            | OperationRequestKind::AcceptOwnership
+            | OperationRequestKind::BatchMint => String::from("execute"),
             OperationRequestKind::UpdateKeyholders => String::from("update_signatory"),
-        }
+            OperationRequestKind::Approve => return Err(GenericMultisig::approve_unsupported()),
+        };
+
+        Ok(entrypoint)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryFrom;
+
+    use bigdecimal::BigDecimal;
+    use num_bigint::BigInt;
+
+    use super::{GenericMultisig, Multisig, OperationRequestParams};
+    use crate::{
+        api::models::{contract::ContractKind, operation_request::OperationRequestKind},
+        fixtures,
+        tezos::{coding::encode_contract, micheline::types, mutez::Mutez, TzError},
+    };
+
+    const MULTISIG_ADDRESS: &str = "KT1MeQd67djiEEkscx3USfQ7vFYg589DySpZ";
+    const CONTRACT_ADDRESS: &str = "KT1S4QXjASmM3ei7a6raRLkr4TbnKu7RuC9X";
+    const TARGET_ADDRESS: &str = "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT";
+    const SECOND_TARGET_ADDRESS: &str = "tz1gZdWPZAaFoTVfHDJ5zo5BbdqM9F9dYDTt";
+
+    fn multisig(kind: ContractKind) -> GenericMultisig {
+        GenericMultisig::new(MULTISIG_ADDRESS.into(), "".into(), kind, "head".into())
+    }
+
+    fn lambda_type() -> super::MichelsonV1Expression {
+        types::lambda(types::unit(), types::list(types::operation()))
+    }
+
+    #[test]
+    fn test_multi_asset_mint_lambda() -> Result<(), TzError> {
+        let lambda = multisig(ContractKind::FA2).mint_lambda(
+            TARGET_ADDRESS.into(),
+            CONTRACT_ADDRESS.into(),
+            "mint",
+            BigInt::from(1000),
+            0,
+            Mutez::ZERO,
+        );
+
+        let packed = lambda.pack(Some(&lambda_type()))?;
+        assert_eq!(packed, "0502000000940320053d036d0743036e0a0000001a01bfb439dbd450df69f25257e901c568377527cb11006d696e740555055f0765036e0765036203620200000010072f0200000004034f032702000000000743036a0000053d0765036e0765036203620743036200a80f07430362000003420743036e0a00000016000016e64994c2ddbd293695b63e4cade029d3c8b5e30342031b034d031b");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_asset_batch_mint_lambda() -> Result<(), TzError> {
+        let lambda = multisig(ContractKind::FA2).batch_mint_lambda(
+            vec![
+                (TARGET_ADDRESS.into(), BigInt::from(1000)),
+                (SECOND_TARGET_ADDRESS.into(), BigInt::from(2000)),
+            ],
+            CONTRACT_ADDRESS.into(),
+            "mint",
+            0,
+            Mutez::ZERO,
+        );
+
+        // PUSH address "KT1...%mint"; CONTRACT (list (pair address (pair nat nat)));
+        // IF_NONE { UNIT; FAILWITH } {}; PUSH mutez 0; NIL (pair address (pair nat nat));
+        // PUSH nat <amount>; PUSH nat 0; PAIR; PUSH address <target>; PAIR; CONS;
+        // TRANSFER_TOKENS; CONS
+        let mint_call = |amount: &str, target: &str| {
+            format!("0743036e0a0000001a01bfb439dbd450df69f25257e901c568377527cb11006d696e740555055f0765036e0765036203620200000010072f0200000004034f032702000000000743036a0000053d0765036e07650362036207430362{}07430362000003420743036e0a00000016{}0342031b034d031b", amount, target)
+        };
+        // DROP; NIL operation; followed by one mint call per recipient
+        let code = format!(
+            "0320053d036d{}{}",
+            mint_call("00a80f", "000016e64994c2ddbd293695b63e4cade029d3c8b5e3"),
+            mint_call("00901f", "0000e5859fae787861c886dfedc31febad178601bee3")
+        );
+        let expected = format!("0502{:08x}{}", code.len() / 2, code);
+
+        let packed = lambda.pack(Some(&lambda_type()))?;
+        assert_eq!(packed, expected);
+        assert_eq!(
+            lambda.to_string().matches("TRANSFER_TOKENS").count(),
+            2,
+            "one mint call per recipient"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_asset_burn_lambda() -> Result<(), TzError> {
+        let lambda = multisig(ContractKind::FA2).burn_lambda(
+            CONTRACT_ADDRESS.into(),
+            "burn",
+            BigInt::from(1000),
+            0,
+            Mutez::ZERO,
+        );
+
+        let packed = lambda.pack(Some(&lambda_type()))?;
+        assert_eq!(packed, "05020000006b0320053d036d0743036e0a0000001a01bfb439dbd450df69f25257e901c568377527cb11006275726e0555055f0765036203620200000010072f0200000004034f032702000000000743036a0000053d0765036203620743036200a80f0743036200000342031b034d031b");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_asset_mint_lambda() -> Result<(), TzError> {
+        let lambda = multisig(ContractKind::FA2SingleAsset).mint_lambda(
+            TARGET_ADDRESS.into(),
+            CONTRACT_ADDRESS.into(),
+            "mint",
+            BigInt::from(1000),
+            0,
+            Mutez::ZERO,
+        );
+
+        let packed = lambda.pack(Some(&lambda_type()))?;
+        assert_eq!(packed, "0502000000840320053d036d0743036e0a0000001a01bfb439dbd450df69f25257e901c568377527cb11006d696e740555055f0765036e03620200000010072f0200000004034f032702000000000743036a0000053d0765036e03620743036200a80f0743036e0a00000016000016e64994c2ddbd293695b63e4cade029d3c8b5e30342031b034d031b");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_asset_burn_lambda() -> Result<(), TzError> {
+        let lambda = multisig(ContractKind::FA2SingleAsset).burn_lambda(
+            CONTRACT_ADDRESS.into(),
+            "burn",
+            BigInt::from(1000),
+            0,
+            Mutez::ZERO,
+        );
+
+        let packed = lambda.pack(Some(&lambda_type()))?;
+        assert_eq!(packed, "05020000005b0320053d036d0743036e0a0000001a01bfb439dbd450df69f25257e901c568377527cb11006275726e0555055f03620200000010072f0200000004034f032702000000000743036a0000053d03620743036200a80f031b034d031b");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_target_entrypoint_is_preserved() -> Result<(), TzError> {
+        let target = format!("{}%do", CONTRACT_ADDRESS);
+        let lambda = multisig(ContractKind::FA2).add_operator_lambda(
+            target.clone(),
+            CONTRACT_ADDRESS.into(),
+            Mutez::ZERO,
+        );
+
+        let packed = lambda.pack(Some(&lambda_type()))?;
+        let encoded_target = hex::encode(encode_contract(&target)?);
+        assert!(encoded_target.ends_with(&hex::encode("do")));
+        assert!(packed.contains(&format!(
+            "0a{:08x}{}",
+            encoded_target.len() / 2,
+            encoded_target
+        )));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lambda_xtz_amount() -> Result<(), TzError> {
+        let lambda = multisig(ContractKind::FA2SingleAsset).burn_lambda(
+            CONTRACT_ADDRESS.into(),
+            "burn",
+            BigInt::from(1000),
+            0,
+            Mutez::try_from(1_500_000)?,
+        );
+        let packed = lambda.pack(Some(&lambda_type()))?;
+        // PUSH mutez 1500000 instead of PUSH mutez 0
+        assert_eq!(packed, "05020000005e0320053d036d0743036e0a0000001a01bfb439dbd450df69f25257e901c568377527cb11006275726e0555055f03620200000010072f0200000004034f032702000000000743036a00a08db701053d03620743036200a80f031b034d031b");
+        assert!(lambda.to_string().contains("PUSH mutez 1500000;"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_custom_mint_entrypoint() -> Result<(), TzError> {
+        let lambda = multisig(ContractKind::FA2).mint_lambda(
+            TARGET_ADDRESS.into(),
+            CONTRACT_ADDRESS.into(),
+            "mint_tokens",
+            BigInt::from(1000),
+            0,
+            Mutez::ZERO,
+        );
+
+        let packed = lambda.pack(Some(&lambda_type()))?;
+        assert!(packed.contains(&hex::encode("mint_tokens")));
+        assert_eq!(packed, "05020000009b0320053d036d0743036e0a0000002101bfb439dbd450df69f25257e901c568377527cb11006d696e745f746f6b656e730555055f0765036e0765036203620200000010072f0200000004034f032702000000000743036a0000053d0765036e0765036203620743036200a80f07430362000003420743036e0a00000016000016e64994c2ddbd293695b63e4cade029d3c8b5e30342031b034d031b");
+
+        Ok(())
+    }
+
+    #[actix_rt::test]
+    async fn test_approve_rejected() {
+        let contract = fixtures::contract();
+        let operation_request_params = OperationRequestParams {
+            target_address: Some(TARGET_ADDRESS.into()),
+            amount: Some(BigDecimal::from(1000i64)),
+            threshold: None,
+            batch_mints: None,
+            xtz_amount: None,
+            kind: OperationRequestKind::Approve.into(),
+            chain_id: "NetXm8tYqnMWky1".into(),
+            nonce: 3.into(),
+        };
+
+        let result = multisig(ContractKind::FA2)
+            .signable_message(&contract, &operation_request_params, None)
+            .await;
+
+        assert!(matches!(result, Err(TzError::InvalidValue { .. })));
+        assert!(matches!(
+            GenericMultisig::entrypoint(OperationRequestKind::Approve),
+            Err(TzError::InvalidValue { .. })
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn test_mint_tezos_client_command() -> Result<(), TzError> {
+        let contract = fixtures::contract();
+        let operation_request_params = OperationRequestParams {
+            target_address: Some(TARGET_ADDRESS.into()),
+            amount: Some(BigDecimal::from(1000i64)),
+            threshold: None,
+            batch_mints: None,
+            xtz_amount: None,
+            kind: OperationRequestKind::Mint.into(),
+            chain_id: "NetXm8tYqnMWky1".into(),
+            nonce: 3.into(),
+        };
+
+        let signable_message = multisig(ContractKind::FA2)
+            .signable_message(&contract, &operation_request_params, None)
+            .await?;
+
+        assert_eq!(signable_message.packed_data, "0507070a00000004a836502107070a00000016018f49d123dc1feb431cf64ebb63a12a011f32d1a3000707000302000000940320053d036d0743036e0a0000001a01bfb439dbd450df69f25257e901c568377527cb11006d696e740555055f0765036e0765036203620200000010072f0200000004034f032702000000000743036a0000053d0765036e0765036203620743036200a80f07430362000003420743036e0a00000016000016e64994c2ddbd293695b63e4cade029d3c8b5e30342031b034d031b");
+        assert_eq!(
+            signable_message.ledger_blake2b_hash()?,
+            "62HZXjda9sbD2oLwJSvC5i53zF3U7AUXyrV1RjdAPxnR"
+        );
+
+        let data = signable_message.michelson_data.to_string();
+        let type_ = signable_message.michelson_type.to_string();
+        assert_eq!(
+            type_,
+            "(pair chain_id (pair address (pair nat (lambda unit (list operation)))))"
+        );
+        assert!(data.starts_with(&format!(
+            "(Pair \"NetXm8tYqnMWky1\" (Pair \"{}\" (Pair 3 {{ DROP; NIL operation; PUSH address \"{}%mint\";",
+            MULTISIG_ADDRESS, CONTRACT_ADDRESS
+        )));
+        assert_eq!(
+            signable_message.tezos_client_command(),
+            format!("tezos-client hash data '{}' of type '{}'", data, type_)
+        );
+        assert_eq!(
+            signable_message
+                .michelson_data
+                .pack(Some(&signable_message.michelson_type))?,
+            signable_message.packed_data
+        );
+
+        Ok(())
     }
 }
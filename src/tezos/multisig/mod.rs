@@ -8,37 +8,123 @@ use std::{
 use crate::{
     api::models::{contract::ContractKind, operation_request::OperationRequestKind},
     db::models::{
-        contract::Contract, operation_request::NewOperationRequest,
-        operation_request::OperationRequest,
+        contract::Contract,
+        operation_request::{BatchMint, NewOperationRequest, OperationRequest},
     },
     tezos::micheline::{extract_prim, primitive::Primitive},
 };
 use crate::{
     crypto,
     tezos::{
+        self, check_node_response,
         micheline::{extract_int, extract_sequence, primitive::Data, MichelsonV1Expression},
-        TzError,
+        node_request, TzError,
     },
+    CONFIG,
 };
 use bigdecimal::BigDecimal;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use super::{coding::decode_public_key, micheline::extract_bytes};
+use super::{
+    coding::decode_public_key, micheline::extract_bytes, mutez::Mutez, nonce::MultisigNonce,
+    HEAD_BLOCK,
+};
 
 mod generic_multisig;
 mod specific_multisig;
 
 pub fn get_multisig(address: &str, kind: ContractKind, node_url: &str) -> Box<dyn Multisig> {
+    get_multisig_at_block(address, kind, node_url, HEAD_BLOCK)
+}
+
+/// Same as `get_multisig`, but all on-chain reads are made against `block` instead of the head.
+pub fn get_multisig_at_block(
+    address: &str,
+    kind: ContractKind,
+    node_url: &str,
+    block: &str,
+) -> Box<dyn Multisig> {
     match kind {
         ContractKind::FA1 => Box::new(specific_multisig::SpecificMultisig::new(
             address.to_owned(),
             node_url.to_owned(),
+            block.to_owned(),
         )) as Box<dyn Multisig>,
-        ContractKind::FA2 => Box::new(generic_multisig::GenericMultisig::new(
-            address.to_owned(),
-            node_url.to_owned(),
-        )) as Box<dyn Multisig>,
+        ContractKind::FA2 | ContractKind::FA2SingleAsset => {
+            Box::new(generic_multisig::GenericMultisig::new(
+                address.to_owned(),
+                node_url.to_owned(),
+                kind,
+                block.to_owned(),
+            )) as Box<dyn Multisig>
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Entrypoints {
+    entrypoints: HashMap<String, serde_json::Value>,
+}
+
+/// Checks that the multisig at `address` exposes the entrypoints of the multisig used for
+/// contracts of `kind`: `mainParameter` for FA1, `execute` and `update_signatory` for FA2.
+pub async fn verify_kind(address: &str, kind: ContractKind, node_url: &str) -> Result<(), TzError> {
+    let url = format!(
+        "{}/chains/main/blocks/{}/context/contracts/{}/entrypoints",
+        node_url, HEAD_BLOCK, address
+    );
+    let response = node_request(async {
+        reqwest::get(&url)
+            .await
+            .map_err(|_error| TzError::NetworkFailure)?
+            .json::<Entrypoints>()
+            .await
+            .map_err(|_error| TzError::ParsingFailure)
+    })
+    .await?;
+
+    let required_entrypoints: &[&str] = match kind {
+        ContractKind::FA1 => &["mainParameter"],
+        ContractKind::FA2 | ContractKind::FA2SingleAsset => &["execute", "update_signatory"],
+    };
+    let missing_entrypoints = required_entrypoints
+        .iter()
+        .filter(|entrypoint| !response.entrypoints.contains_key(**entrypoint))
+        .copied()
+        .collect::<Vec<_>>();
+    if !missing_entrypoints.is_empty() {
+        return Err(TzError::InvalidValue {
+            description: format!(
+                "multisig {} does not match kind {:?}, missing entrypoints: {}",
+                address,
+                kind,
+                missing_entrypoints.join(", ")
+            ),
+        });
+    }
+
+    Ok(())
+}
+
+/// Checks that `multisig` has approvers and requires at least one signature, an operation request
+/// on a multisig which does not would either never be executable or be approved without any
+/// signatures.
+pub async fn verify_signers(multisig: &mut dyn Multisig) -> Result<(), TzError> {
+    if multisig.approvers().await?.is_empty() {
+        return Err(TzError::InvalidValue {
+            description: format!("multisig {} has no approvers", multisig.address()),
+        });
     }
+    if multisig.min_signatures().await? <= 0 {
+        return Err(TzError::InvalidValue {
+            description: format!(
+                "multisig {} does not require any signatures",
+                multisig.address()
+            ),
+        });
+    }
+
+    Ok(())
 }
 
 #[async_trait]
@@ -46,7 +132,8 @@ pub trait Multisig: Send + Sync {
     fn node_url(&self) -> &String;
     fn address(&self) -> &String;
 
-    async fn nonce(&mut self) -> Result<i64, TzError>;
+    /// The nonce the next call to the multisig has to be signed with.
+    async fn nonce(&mut self) -> Result<MultisigNonce, TzError>;
     async fn min_signatures(&mut self) -> Result<i64, TzError>;
     async fn approvers(&mut self) -> Result<&Vec<String>, TzError>;
 
@@ -66,6 +153,53 @@ pub trait Multisig: Send + Sync {
     ) -> Result<Parameters, TzError>;
 }
 
+/// Packs `data` of type `schema`, the message of a `kind` operation request, after checking both
+/// against the configured micheline limits. With `server.verify_packed_data` set, the result is also compared with the packing of the node
+/// at `node_url`.
+async fn pack(
+    node_url: &str,
+    kind: OperationRequestKind,
+    data: &MichelsonV1Expression,
+    schema: &MichelsonV1Expression,
+) -> Result<String, TzError> {
+    let limits = CONFIG.server.micheline_limits();
+    data.check_limits(&limits)?;
+    schema.check_limits(&limits)?;
+
+    let packed_data = data.pack(Some(schema)).map_err(|error| {
+        error.with_michelson_context(&format!(
+            "failed to pack the {:?} message as {}",
+            kind, schema
+        ))
+    })?;
+    if CONFIG.server.verify_packed_data.unwrap_or(false) {
+        verify_packed_data(node_url, data, schema, &packed_data).await?;
+    }
+
+    Ok(packed_data)
+}
+
+/// Fails if the node at `node_url` packs `data` of type `schema` differently than `packed_data`,
+/// which would mean the signatures collected for it can't be used on chain.
+async fn verify_packed_data(
+    node_url: &str,
+    data: &MichelsonV1Expression,
+    schema: &MichelsonV1Expression,
+    packed_data: &str,
+) -> Result<(), TzError> {
+    let node_packed_data = tezos::pack_data(node_url, data, schema).await?;
+    if node_packed_data != packed_data {
+        return Err(TzError::InvalidValue {
+            description: format!(
+                "packed data {} does not match the node's packing {} of {} as {}",
+                packed_data, node_packed_data, data, schema
+            ),
+        });
+    }
+
+    Ok(())
+}
+
 fn validate(
     operation_request_params: &OperationRequestParams,
     proposed_keyholders_pk: &Option<Vec<String>>,
@@ -73,18 +207,22 @@ fn validate(
     let operation_request_kind: OperationRequestKind = operation_request_params.kind.try_into()?;
     if operation_request_params.amount.is_none()
         && (operation_request_kind == OperationRequestKind::Mint
-            || operation_request_kind == OperationRequestKind::Burn)
+            || operation_request_kind == OperationRequestKind::Burn
+            || operation_request_kind == OperationRequestKind::Approve)
     {
         return Err(TzError::InvalidValue {
-            description: "amount is required for mint and burn operation requests".to_owned(),
+            description: "amount is required for mint, burn and approve operation requests"
+                .to_owned(),
         });
     }
 
     if operation_request_params.target_address.is_none()
-        && operation_request_kind == OperationRequestKind::Mint
+        && (operation_request_kind == OperationRequestKind::Mint
+            || operation_request_kind == OperationRequestKind::Approve)
     {
         return Err(TzError::InvalidValue {
-            description: "target_address is required for mint operation requests".to_owned(),
+            description: "target_address is required for mint and approve operation requests"
+                .to_owned(),
         });
     }
 
@@ -97,6 +235,29 @@ fn validate(
         });
     }
 
+    if operation_request_params
+        .batch_mints
+        .as_ref()
+        .map_or(true, |batch_mints| batch_mints.is_empty())
+        && operation_request_kind == OperationRequestKind::BatchMint
+    {
+        return Err(TzError::InvalidValue {
+            description: "batch_mints are required for batch mint operation requests".to_owned(),
+        });
+    }
+
+    if let Some(xtz_amount) = operation_request_params.xtz_amount {
+        if !operation_request_kind.accepts_xtz_amount() {
+            return Err(TzError::InvalidValue {
+                description: format!(
+                    "xtz_amount is not supported for {} operation requests",
+                    operation_request_kind
+                ),
+            });
+        }
+        Mutez::try_from(xtz_amount)?;
+    }
+
     if proposed_keyholders_pk.is_none()
         && operation_request_kind == OperationRequestKind::UpdateKeyholders
     {
@@ -132,20 +293,24 @@ pub struct OperationRequestParams {
     pub target_address: Option<String>,
     pub amount: Option<BigDecimal>,
     pub threshold: Option<i64>,
+    pub batch_mints: Option<Vec<BatchMint>>,
+    pub xtz_amount: Option<i64>,
     pub kind: i16,
     pub chain_id: String,
-    pub nonce: i64,
+    pub nonce: MultisigNonce,
 }
 
 impl From<OperationRequest> for OperationRequestParams {
     fn from(value: OperationRequest) -> Self {
         OperationRequestParams {
+            batch_mints: value.batch_mints(),
+            xtz_amount: value.xtz_amount,
             target_address: value.target_address,
             amount: value.amount,
             threshold: value.threshold,
             kind: value.kind,
             chain_id: value.chain_id,
-            nonce: value.nonce,
+            nonce: value.nonce.into(),
         }
     }
 }
@@ -153,20 +318,25 @@ impl From<OperationRequest> for OperationRequestParams {
 impl From<NewOperationRequest> for OperationRequestParams {
     fn from(value: NewOperationRequest) -> Self {
         OperationRequestParams {
+            batch_mints: value.batch_mints(),
+            xtz_amount: value.xtz_amount,
             target_address: value.target_address,
             amount: value.amount,
             threshold: value.threshold,
             kind: value.kind,
             chain_id: value.chain_id,
-            nonce: value.nonce,
+            nonce: value.nonce.into(),
         }
     }
 }
 
 impl SignableMessage {
+    pub fn bytes(&self) -> Result<Vec<u8>, TzError> {
+        hex::decode(&self.packed_data).map_err(|_error| TzError::HexDecodingFailure)
+    }
+
     pub fn blake2b_hash(&self) -> Result<Vec<u8>, TzError> {
-        let message_bytes =
-            hex::decode(&self.packed_data).map_err(|_error| TzError::HexDecodingFailure)?;
+        let message_bytes = self.bytes()?;
 
         Ok(crypto::generic_hash(&message_bytes, 32).map_err(|_error| TzError::HashFailure)?)
     }
@@ -174,12 +344,28 @@ impl SignableMessage {
     pub fn ledger_blake2b_hash(&self) -> Result<String, TzError> {
         Ok(bs58::encode(self.blake2b_hash()?).into_string())
     }
+
+    /// The `tezos-client` command that packs and hashes this message. Data and type are taken
+    /// from the message itself, so the command reproduces `packed_data` and the ledger hash
+    /// whatever the multisig variant.
+    pub fn tezos_client_command(&self) -> String {
+        format!(
+            "tezos-client hash data {} of type {}",
+            shell_quote(&self.michelson_data.to_string()),
+            shell_quote(&self.michelson_type.to_string())
+        )
+    }
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
 }
 
 #[derive(Debug)]
 struct Storage {
-    nonce: i64,
+    nonce: MultisigNonce,
     min_signatures: i64,
+    /// Kept in the order of the contract's key list, signatures are checked against it by index.
     approvers_public_keys: Vec<String>,
 }
 
@@ -213,7 +399,7 @@ impl TryFrom<&MichelsonV1Expression> for Storage {
             .collect::<Result<Vec<String>, TzError>>()?;
 
         Ok(Storage {
-            nonce: nonce.to_i64().unwrap(),
+            nonce: nonce.to_i64().unwrap().into(),
             min_signatures: min_signatures.to_i64().unwrap(),
             approvers_public_keys: public_keys.iter().map(|pk| pk.to_owned()).collect(),
         })
@@ -221,27 +407,321 @@ impl TryFrom<&MichelsonV1Expression> for Storage {
 }
 
 impl Storage {
-    async fn fetch_from(address: &String, node_url: &String) -> Result<Storage, TzError> {
-        let path = format!(
-            "/chains/main/blocks/head/context/contracts/{}/storage/normalized",
-            address
+    /// Reads the storage from `/storage/normalized`, falling back to the storage section of
+    /// `/script/normalized` if the node's response to the former can't be read.
+    async fn fetch_from(
+        address: &String,
+        node_url: &String,
+        block: &str,
+    ) -> Result<Storage, TzError> {
+        let storage_path = format!(
+            "/chains/main/blocks/{}/context/contracts/{}/storage/normalized",
+            block, address
         );
-        let url = format!("{}{}", node_url, path);
-        let client = reqwest::Client::new();
-        let mut json = HashMap::new();
-        json.insert("unparsing_mode", "Optimized_legacy");
+        let expression = match fetch_normalized(node_url, &storage_path)
+            .await
+            .and_then(storage_expression)
+        {
+            Err(TzError::ParsingFailure) => {
+                let script_path = format!(
+                    "/chains/main/blocks/{}/context/contracts/{}/script/normalized",
+                    block, address
+                );
+                storage_expression(fetch_normalized(node_url, &script_path).await?)?
+            }
+            result => result?,
+        };
+
+        Storage::try_from(&expression)
+    }
+}
+
+/// Posts to one of the `normalized` contract RPCs, which take the unparsing mode.
+async fn fetch_normalized(node_url: &str, path: &str) -> Result<serde_json::Value, TzError> {
+    let url = format!("{}{}", node_url, path);
+    let client = reqwest::Client::new();
+    let mut json = HashMap::new();
+    json.insert("unparsing_mode", "Optimized_legacy");
+    node_request(async {
         let response = client
             .post(&url)
             .json(&json)
             .send()
             .await
-            .map_err(|_error| TzError::NetworkFailure)?
-            .json::<MichelsonV1Expression>()
+            .map_err(|_error| TzError::NetworkFailure)?;
+        check_node_response(response, path)?
+            .json::<serde_json::Value>()
             .await
-            .map_err(|_error| TzError::ParsingFailure)?;
+            .map_err(|_error| TzError::ParsingFailure)
+    })
+    .await
+}
+
+/// The storage expression of a response, which is either the bare expression or an object with a
+/// `storage` field, like the script of a contract. Micheline objects have no such field.
+fn storage_expression(value: serde_json::Value) -> Result<MichelsonV1Expression, TzError> {
+    let value = match value {
+        serde_json::Value::Object(mut object) if object.contains_key("storage") => {
+            object.remove("storage").unwrap()
+        }
+        value => value,
+    };
+
+    serde_json::from_value(value).map_err(|_error| TzError::ParsingFailure)
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::{http::StatusCode, web, App, HttpResponse, ResponseError};
+
+    use super::{get_multisig, verify_kind, verify_packed_data, verify_signers, Storage};
+    use crate::{
+        api::models::{contract::ContractKind, error::APIError},
+        tezos::{
+            micheline::{data, int, string, types},
+            TzError,
+        },
+    };
+
+    const MULTISIG_ADDRESS: &str = "KT1MeQd67djiEEkscx3USfQ7vFYg589DySpZ";
+    const PUBLIC_KEY: &str = "00e2b5ecd9ce2c1ed0e5ee4d88f1bdc81b6a2a9a4b4a7a0bfa91c9c20e4d9d9b8a";
+
+    fn fa1_shaped_node() -> actix_web::test::TestServer {
+        actix_web::test::start(|| {
+            App::new().route(
+                "/chains/main/blocks/head/context/contracts/{address}/entrypoints",
+                web::get().to(|| {
+                    HttpResponse::Ok().json(serde_json::json!({
+                        "entrypoints": {
+                            "mainParameter": types::pair(
+                                types::nat(),
+                                types::list(types::option(types::signature())),
+                            ),
+                        }
+                    }))
+                }),
+            )
+        })
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_kind_detects_mismatch() -> Result<(), TzError> {
+        let node = fa1_shaped_node();
+        let node_url = node.url("").trim_end_matches('/').to_owned();
+
+        verify_kind(MULTISIG_ADDRESS, ContractKind::FA1, &node_url).await?;
+
+        let result = verify_kind(MULTISIG_ADDRESS, ContractKind::FA2, &node_url).await;
+        match result {
+            Err(TzError::InvalidValue { description }) => {
+                assert!(description.contains("execute, update_signatory"))
+            }
+            _ => panic!("expected a kind mismatch, got {:?}", result),
+        }
+
+        Ok(())
+    }
+
+    fn storage_json(min_signatures: i64, public_keys: &[&str]) -> serde_json::Value {
+        let public_keys = public_keys
+            .iter()
+            .map(|public_key| serde_json::json!({ "bytes": public_key }))
+            .collect::<Vec<_>>();
+
+        serde_json::json!({
+            "prim": "Pair",
+            "args": [
+                { "int": "4" },
+                {
+                    "prim": "Pair",
+                    "args": [{ "int": min_signatures.to_string() }, public_keys]
+                }
+            ]
+        })
+    }
+
+    fn storage_node(
+        min_signatures: i64,
+        public_keys: Vec<&'static str>,
+    ) -> actix_web::test::TestServer {
+        actix_web::test::start(move || {
+            let public_keys = public_keys.clone();
+            App::new().route(
+                "/chains/main/blocks/head/context/contracts/{address}/storage/normalized",
+                web::post().to(move || {
+                    HttpResponse::Ok().json(storage_json(min_signatures, &public_keys))
+                }),
+            )
+        })
+    }
 
-        let storage = Storage::try_from(&response)?;
+    /// A node answering `storage_response` for the storage and the script with `storage_json`.
+    fn storage_shape_node(storage_response: serde_json::Value) -> actix_web::test::TestServer {
+        actix_web::test::start(move || {
+            let storage_response = storage_response.clone();
+            App::new()
+                .route(
+                    "/chains/main/blocks/head/context/contracts/{address}/storage/normalized",
+                    web::post().to(move || HttpResponse::Ok().json(storage_response.clone())),
+                )
+                .route(
+                    "/chains/main/blocks/head/context/contracts/{address}/script/normalized",
+                    web::post().to(|| {
+                        HttpResponse::Ok().json(serde_json::json!({
+                            "code": [],
+                            "storage": storage_json(3, &[PUBLIC_KEY]),
+                        }))
+                    }),
+                )
+        })
+    }
+
+    #[actix_rt::test]
+    async fn test_storage_response_shapes() -> Result<(), TzError> {
+        let shapes = vec![
+            (storage_json(2, &[PUBLIC_KEY]), 2),
+            (
+                serde_json::json!({ "storage": storage_json(2, &[PUBLIC_KEY]) }),
+                2,
+            ),
+            (serde_json::json!({ "unexpected": "shape" }), 3),
+            (serde_json::json!(42), 3),
+        ];
+        for (storage_response, min_signatures) in shapes {
+            let node = storage_shape_node(storage_response.clone());
+            let node_url = node.url("").trim_end_matches('/').to_owned();
+
+            let storage = Storage::fetch_from(&MULTISIG_ADDRESS.into(), &node_url, "head").await?;
+
+            assert_eq!(storage.nonce.value(), 4, "{}", storage_response);
+            assert_eq!(
+                storage.min_signatures, min_signatures,
+                "{}",
+                storage_response
+            );
+            assert_eq!(storage.approvers_public_keys.len(), 1);
+        }
+
+        Ok(())
+    }
+
+    async fn verify_signers_on(node: &actix_web::test::TestServer) -> Result<(), TzError> {
+        let node_url = node.url("").trim_end_matches('/').to_owned();
+        let mut multisig = get_multisig(MULTISIG_ADDRESS, ContractKind::FA1, &node_url);
+
+        verify_signers(multisig.as_mut()).await
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_signers() -> Result<(), TzError> {
+        verify_signers_on(&storage_node(1, vec![PUBLIC_KEY])).await?;
+
+        let result = verify_signers_on(&storage_node(2, vec![])).await;
+        match result {
+            Err(TzError::InvalidValue { description }) => {
+                assert_eq!(
+                    description,
+                    format!("multisig {} has no approvers", MULTISIG_ADDRESS)
+                )
+            }
+            _ => panic!(
+                "expected the empty approvers to be rejected, got {:?}",
+                result
+            ),
+        }
+
+        let result = verify_signers_on(&storage_node(0, vec![PUBLIC_KEY])).await;
+        match result {
+            Err(TzError::InvalidValue { description }) => assert_eq!(
+                description,
+                format!(
+                    "multisig {} does not require any signatures",
+                    MULTISIG_ADDRESS
+                )
+            ),
+            _ => panic!(
+                "expected the zero threshold to be rejected, got {:?}",
+                result
+            ),
+        }
+
+        Ok(())
+    }
+
+    fn failing_node(status: StatusCode) -> actix_web::test::TestServer {
+        actix_web::test::start(move || {
+            App::new().route(
+                "/chains/main/blocks/head/context/contracts/{address}/storage/normalized",
+                web::post().to(move || HttpResponse::build(status).finish()),
+            )
+        })
+    }
+
+    #[actix_rt::test]
+    async fn test_storage_node_errors() {
+        let node = failing_node(StatusCode::NOT_FOUND);
+        let node_url = node.url("").trim_end_matches('/').to_owned();
+        let result = Storage::fetch_from(&MULTISIG_ADDRESS.into(), &node_url, "head").await;
+        match result {
+            Err(TzError::NotFound { path }) => {
+                assert_eq!(
+                    path,
+                    format!(
+                        "/chains/main/blocks/head/context/contracts/{}/storage/normalized",
+                        MULTISIG_ADDRESS
+                    )
+                );
+                let error = APIError::from(TzError::NotFound { path });
+                assert_eq!(error.status_code(), StatusCode::NOT_FOUND);
+            }
+            _ => panic!("expected a not found error, got {:?}", result),
+        }
+
+        let node = failing_node(StatusCode::SERVICE_UNAVAILABLE);
+        let node_url = node.url("").trim_end_matches('/').to_owned();
+        let result = Storage::fetch_from(&MULTISIG_ADDRESS.into(), &node_url, "head").await;
+        match result {
+            Err(TzError::NetworkFailure) => {
+                let error = APIError::from(TzError::NetworkFailure);
+                assert_eq!(error.status_code(), StatusCode::BAD_GATEWAY);
+            }
+            _ => panic!("expected a network failure, got {:?}", result),
+        }
+    }
+
+    fn packing_node(packed: String) -> actix_web::test::TestServer {
+        actix_web::test::start(move || {
+            let packed = packed.clone();
+            App::new().route(
+                "/chains/main/blocks/head/helpers/scripts/pack_data",
+                web::post().to(move || {
+                    HttpResponse::Ok().json(serde_json::json!({ "packed": packed, "gas": "1000" }))
+                }),
+            )
+        })
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_packed_data() -> Result<(), TzError> {
+        let data = data::pair(string(MULTISIG_ADDRESS.into()), int(10));
+        let schema = types::pair(types::address(), types::nat());
+        let packed_data = data.pack(Some(&schema))?;
+
+        let node = packing_node(packed_data.clone());
+        let node_url = node.url("").trim_end_matches('/').to_owned();
+        verify_packed_data(&node_url, &data, &schema, &packed_data).await?;
+
+        let node = packing_node("0500".into());
+        let node_url = node.url("").trim_end_matches('/').to_owned();
+        let result = verify_packed_data(&node_url, &data, &schema, &packed_data).await;
+        match result {
+            Err(TzError::InvalidValue { description }) => {
+                assert!(description.contains(&packed_data));
+                assert!(description.contains("0500"));
+            }
+            _ => panic!("expected a packing mismatch, got {:?}", result),
+        }
 
-        Ok(storage)
+        Ok(())
     }
 }
@@ -14,19 +14,23 @@ use crate::{
 use crate::{
     db::models::contract::Contract,
     tezos::{
-        coding,
+        check_node_response, coding,
         micheline::{primitive::Primitive, primitive::Type, MichelsonV1Expression},
+        node_request,
+        nonce::MultisigNonce,
         TzError,
     },
 };
 
 use super::{
-    validate, Multisig, OperationRequestParams, Parameters, SignableMessage, Signature, Storage,
+    pack, validate, Multisig, OperationRequestParams, Parameters, SignableMessage, Signature,
+    Storage,
 };
 
 pub struct SpecificMultisig {
     address: String,
     node_url: String,
+    block: String,
 
     storage: Option<Storage>,
 }
@@ -41,7 +45,7 @@ impl Multisig for SpecificMultisig {
         &self.address
     }
 
-    async fn nonce(&mut self) -> Result<i64, TzError> {
+    async fn nonce(&mut self) -> Result<MultisigNonce, TzError> {
         let storage = self.fetch_storage().await?;
 
         Ok(storage.nonce)
@@ -66,6 +70,7 @@ impl Multisig for SpecificMultisig {
         proposed_keyholders_pk: Option<Vec<String>>,
     ) -> Result<SignableMessage, TzError> {
         validate(operation_request_params, &proposed_keyholders_pk)?;
+        SpecificMultisig::validate_kind(operation_request_params)?;
         let call = self.michelson_transaction_parameters(
             contract,
             operation_request_params,
@@ -74,25 +79,22 @@ impl Multisig for SpecificMultisig {
 
         let micheline = data::pair(
             string(self.address.to_owned()),
-            data::pair(int(operation_request_params.nonce), call),
+            data::pair(operation_request_params.nonce.into(), call),
         );
 
         let main_parameter_schema = self.fetch_main_parameter_schema().await?;
-        let signable_schema = match &main_parameter_schema {
-            MichelsonV1Expression::Prim(value) => {
-                if value.prim != Primitive::Type(Type::Pair) || value.args_count() != 2 {
-                    return Err(TzError::InvalidType);
-                }
+        let signable_schema = signable_schema(main_parameter_schema)?;
 
-                Ok(value.args.as_ref().unwrap().first().unwrap())
-            }
-            _ => Err(TzError::InvalidType),
-        }?;
-
-        let schema = types::pair(types::address(), signable_schema.to_owned());
+        let schema = types::pair(types::address(), signable_schema);
 
         Ok(SignableMessage {
-            packed_data: micheline.pack(Some(&schema))?,
+            packed_data: pack(
+                &self.node_url,
+                operation_request_params.kind.try_into()?,
+                &micheline,
+                &schema,
+            )
+            .await?,
             michelson_data: micheline,
             michelson_type: schema,
         })
@@ -106,33 +108,16 @@ impl Multisig for SpecificMultisig {
         signatures: Vec<Signature<'_>>,
     ) -> Result<Parameters, TzError> {
         validate(operation_request_params, &proposed_keyholders_pk)?;
+        SpecificMultisig::validate_kind(operation_request_params)?;
         let call = self.michelson_transaction_parameters(
             contract,
             operation_request_params,
             proposed_keyholders_pk,
         );
 
-        let ordered_signature_list = self
-            .approvers()
-            .await?
-            .into_iter()
-            .map(|public_key| {
-                signatures
-                    .iter()
-                    .find(|signature| signature.public_key == public_key)
-                    .map(|sig| coding::encode_signature(sig.value))
-                    .map_or(Ok(None), |r| r.map(Some))
-                    .map(|sig_bytes| {
-                        if let Some(sig_bytes) = sig_bytes {
-                            data::some(bytes(sig_bytes))
-                        } else {
-                            data::none()
-                        }
-                    })
-            })
-            .collect::<Result<Vec<MichelsonV1Expression>, TzError>>()?;
+        let ordered_signature_list = ordered_signatures(self.approvers().await?, &signatures)?;
         let value = data::pair(
-            data::pair(int(operation_request_params.nonce), call),
+            data::pair(operation_request_params.nonce.into(), call),
             sequence(ordered_signature_list),
         );
 
@@ -144,20 +129,41 @@ impl Multisig for SpecificMultisig {
 }
 
 impl SpecificMultisig {
-    pub fn new(address: String, node_url: String) -> Self {
+    pub fn new(address: String, node_url: String, block: String) -> Self {
         SpecificMultisig {
             address,
             node_url,
+            block,
             storage: None,
         }
     }
 
+    /// The FA1 multisig only supports a single call per operation, so there are no batch mints,
+    /// and calls its entrypoints without a lambda, so no tez can be sent along.
+    fn validate_kind(operation_request_params: &OperationRequestParams) -> Result<(), TzError> {
+        let operation_request_kind: OperationRequestKind =
+            operation_request_params.kind.try_into()?;
+        if operation_request_kind == OperationRequestKind::BatchMint {
+            return Err(TzError::InvalidValue {
+                description: "batch mint operation requests are not supported by FA1 contracts"
+                    .to_owned(),
+            });
+        }
+        if operation_request_params.xtz_amount.is_some() {
+            return Err(TzError::InvalidValue {
+                description: "xtz_amount is not supported by FA1 contracts".to_owned(),
+            });
+        }
+
+        Ok(())
+    }
+
     async fn fetch_storage(&mut self) -> Result<&Storage, TzError> {
         if let Some(_) = self.storage {
             return Ok(self.storage.as_ref().unwrap());
         }
 
-        let storage = Storage::fetch_from(self.address(), self.node_url()).await?;
+        let storage = Storage::fetch_from(self.address(), self.node_url(), &self.block).await?;
         self.storage = Some(storage);
 
         Ok(self.storage.as_ref().unwrap())
@@ -165,16 +171,20 @@ impl SpecificMultisig {
 
     async fn fetch_main_parameter_schema(&self) -> Result<MichelsonV1Expression, TzError> {
         let path = format!(
-            "/chains/main/blocks/head/context/contracts/{}/entrypoints/mainParameter",
-            self.address
+            "/chains/main/blocks/{}/context/contracts/{}/entrypoints/mainParameter",
+            self.block, self.address
         );
         let url = format!("{}{}", self.node_url, path);
-        let response = reqwest::get(&url)
-            .await
-            .map_err(|_error| TzError::NetworkFailure)?
-            .json::<MichelsonV1Expression>()
-            .await
-            .map_err(|_error| TzError::ParsingFailure)?;
+        let response = node_request(async {
+            let response = reqwest::get(&url)
+                .await
+                .map_err(|_error| TzError::NetworkFailure)?;
+            check_node_response(response, &path)?
+                .json::<MichelsonV1Expression>()
+                .await
+                .map_err(|_error| TzError::ParsingFailure)
+        })
+        .await?;
 
         Ok(response)
     }
@@ -204,6 +214,9 @@ impl SpecificMultisig {
                     .0,
                 contract.token_id.into(),
             ),
+            OperationRequestKind::BatchMint => {
+                unreachable!("batch mints are rejected by validate_kind")
+            }
             OperationRequestKind::Burn => self.burn_michelson_parameters(
                 contract.pkh.clone(),
                 operation_request_params
@@ -254,6 +267,20 @@ impl SpecificMultisig {
             OperationRequestKind::AcceptOwnership => {
                 self.accept_ownership_michelson_parameters(contract.pkh.clone())
             }
+            OperationRequestKind::Approve => self.approve_michelson_parameters(
+                operation_request_params
+                    .target_address
+                    .as_ref()
+                    .unwrap()
+                    .into(),
+                operation_request_params
+                    .amount
+                    .as_ref()
+                    .unwrap()
+                    .as_bigint_and_exponent()
+                    .0,
+                contract.pkh.clone(),
+            ),
         }
     }
 
@@ -333,6 +360,20 @@ impl SpecificMultisig {
         data::left(data::pair(call, string(contract_address)))
     }
 
+    fn approve_michelson_parameters(
+        &self,
+        spender: String,
+        value: BigInt,
+        contract_address: String,
+    ) -> MichelsonV1Expression {
+        let call = data::left(data::right(data::right(data::right(data::pair(
+            string(spender),
+            int(value),
+        )))));
+
+        data::left(data::pair(call, string(contract_address)))
+    }
+
     fn update_keyholders_michelson_parameters(
         &self,
         threshold: i64,
@@ -349,3 +390,246 @@ impl SpecificMultisig {
         ))
     }
 }
+
+/// Lines `signatures` up with `approvers`, which must be the keys in the order the contract stores
+/// them: the contract checks the i-th signature against its i-th key, so the result has exactly one
+/// entry per approver, `Some` if that approver signed and `None` otherwise. Signatures from keys
+/// which are not approvers are left out.
+fn ordered_signatures(
+    approvers: &[String],
+    signatures: &[Signature<'_>],
+) -> Result<Vec<MichelsonV1Expression>, TzError> {
+    approvers
+        .iter()
+        .map(|public_key| {
+            let signature = signatures
+                .iter()
+                .find(|signature| signature.public_key == public_key);
+
+            match signature {
+                Some(signature) => Ok(data::some(bytes(coding::encode_signature(
+                    signature.value,
+                )?))),
+                None => Ok(data::none()),
+            }
+        })
+        .collect()
+}
+
+/// Extracts the signed part of the main parameter, which is the first element of its (possibly comb) pair.
+fn signable_schema(
+    main_parameter_schema: MichelsonV1Expression,
+) -> Result<MichelsonV1Expression, TzError> {
+    match main_parameter_schema.normalized() {
+        MichelsonV1Expression::Prim(value) => {
+            if value.prim != Primitive::Type(Type::Pair) || value.args_count() != 2 {
+                return Err(TzError::InvalidType);
+            }
+
+            Ok(value.args.unwrap().remove(0))
+        }
+        _ => Err(TzError::InvalidType),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use actix_web::{web, App, HttpResponse};
+    use num_bigint::BigInt;
+
+    use super::{ordered_signatures, signable_schema, SpecificMultisig};
+    use crate::tezos::{
+        micheline::{bytes, data, primitive::Type, types, MichelsonV1Expression},
+        multisig::{Multisig, Signature},
+        TzError,
+    };
+
+    const MULTISIG_ADDRESS: &str = "KT1MeQd67djiEEkscx3USfQ7vFYg589DySpZ";
+    const PINNED_BLOCK: &str = "BLockGenesisGenesisGenesisGenesisGenesisb83baZgbyZe";
+
+    #[actix_rt::test]
+    async fn test_fetches_use_pinned_block() -> Result<(), TzError> {
+        let requested_blocks = Arc::new(Mutex::new(Vec::<String>::new()));
+        let recorded_blocks = requested_blocks.clone();
+        let node = actix_web::test::start(move || {
+            let storage_blocks = recorded_blocks.clone();
+            let schema_blocks = recorded_blocks.clone();
+            App::new()
+                .route(
+                    "/chains/main/blocks/{block}/context/contracts/{address}/storage/normalized",
+                    web::post().to(move |path: web::Path<(String, String)>| {
+                        storage_blocks.lock().unwrap().push(path.0.clone());
+                        HttpResponse::Ok().json(serde_json::json!({
+                            "prim": "Pair",
+                            "args": [
+                                { "int": "4" },
+                                { "prim": "Pair", "args": [{ "int": "2" }, []] }
+                            ]
+                        }))
+                    }),
+                )
+                .route(
+                    "/chains/main/blocks/{block}/context/contracts/{address}/entrypoints/mainParameter",
+                    web::get().to(move |path: web::Path<(String, String)>| {
+                        schema_blocks.lock().unwrap().push(path.0.clone());
+                        HttpResponse::Ok().json(serde_json::json!({
+                            "prim": "pair",
+                            "args": [{ "prim": "nat" }, { "prim": "list", "args": [{ "prim": "option", "args": [{ "prim": "signature" }] }] }]
+                        }))
+                    }),
+                )
+        });
+        let node_url = node.url("").trim_end_matches('/').to_owned();
+
+        let mut multisig =
+            SpecificMultisig::new(MULTISIG_ADDRESS.into(), node_url, PINNED_BLOCK.into());
+        let nonce = multisig.nonce().await?;
+        let _schema = multisig.fetch_main_parameter_schema().await?;
+
+        assert_eq!(nonce.value(), 4);
+        assert_eq!(
+            *requested_blocks.lock().unwrap(),
+            vec![PINNED_BLOCK.to_owned(), PINNED_BLOCK.to_owned()]
+        );
+
+        Ok(())
+    }
+
+    /// The `safeEntrypoints` parameter type of tzBTC, the FA1.2 contract the multisig is made for:
+    /// `(or (or (or (or (pair %run string bytes) (pair %upgrade ...)) (or (pair %epwBeginUpgrade
+    /// nat nat) (lambda %epwApplyMigration ...))) (or (or (lambda %epwSetCode ...) (unit
+    /// %epwFinishUpgrade)) (or (pair %transfer address (pair address nat)) (pair %approve address
+    /// nat)))) (or (or (or (pair %mint address nat) (nat %burn)) (or (address %addOperator)
+    /// (address %removeOperator))) (or (or (address %setRedeemAddress) (unit %pause)) (or (unit
+    /// %unpause) (or (address %transferOwnership) (unit %acceptOwnership))))))`
+    fn tzbtc_safe_parameter() -> MichelsonV1Expression {
+        let bytes_type = || types::prim(Type::Bytes, None);
+        let store = || types::prim(Type::BigMap, Some(vec![bytes_type(), bytes_type()]));
+        let migration = || types::lambda(store(), store());
+        let code = |parameter: MichelsonV1Expression| {
+            types::lambda(
+                types::pair(parameter, store()),
+                types::pair(types::list(types::operation()), store()),
+            )
+        };
+        let uparam = || types::pair(types::string(), bytes_type());
+        let upgrade = types::pair(
+            types::pair(types::nat(), types::nat()),
+            types::pair(
+                migration(),
+                types::pair(
+                    types::option(code(uparam())),
+                    types::option(code(types::unit())),
+                ),
+            ),
+        );
+
+        types::or(
+            types::or(
+                types::or(
+                    types::or(uparam(), upgrade),
+                    types::or(types::pair(types::nat(), types::nat()), migration()),
+                ),
+                types::or(
+                    types::or(code(uparam()), types::unit()),
+                    types::or(
+                        types::pair(
+                            types::address(),
+                            types::pair(types::address(), types::nat()),
+                        ),
+                        types::pair(types::address(), types::nat()),
+                    ),
+                ),
+            ),
+            types::or(
+                types::or(
+                    types::or(types::pair(types::address(), types::nat()), types::nat()),
+                    types::or(types::address(), types::address()),
+                ),
+                types::or(
+                    types::or(types::address(), types::unit()),
+                    types::or(types::unit(), types::or(types::address(), types::unit())),
+                ),
+            ),
+        )
+    }
+
+    #[test]
+    fn test_approve_michelson_parameters() -> Result<(), TzError> {
+        let multisig = SpecificMultisig::new(MULTISIG_ADDRESS.into(), "".into(), "head".into());
+        let parameters = multisig.approve_michelson_parameters(
+            "tz1Mj7RzPmMAqDUNFBn5t5VbXmWW4cSUAdtT".into(),
+            BigInt::from(1000),
+            "KT1S4QXjASmM3ei7a6raRLkr4TbnKu7RuC9X".into(),
+        );
+
+        let schema = types::or(
+            types::pair(tzbtc_safe_parameter(), types::address()),
+            types::pair(types::nat(), types::list(types::key())),
+        );
+
+        let packed = parameters.pack(Some(&schema))?;
+        assert_eq!(packed, "0505050707050505080508050807070a00000016000016e64994c2ddbd293695b63e4cade029d3c8b5e300a80f0a0000001601bfb439dbd450df69f25257e901c568377527cb1100");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_signable_schema_comb_pair() -> Result<(), TzError> {
+        let payload = types::pair(types::nat(), types::unit());
+        let signatures = types::list(types::option(types::signature()));
+
+        let main_parameter = types::pair(payload.clone(), signatures.clone());
+        assert_eq!(signable_schema(main_parameter)?, payload);
+
+        let comb_main_parameter = types::prim(
+            Type::Pair,
+            Some(vec![payload.clone(), signatures, types::unit()]),
+        );
+        assert_eq!(signable_schema(comb_main_parameter)?, payload);
+
+        assert!(signable_schema(types::nat()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ordered_signatures_follow_approvers() -> Result<(), TzError> {
+        let approvers = vec![
+            "edpkuAJhbFLfJ4zWbQQWTZNGDg7hrcG1m1CBSWVB3iDHChjuzeaZB6".to_owned(),
+            "edpkuHG9N83cBavucaLSeeKX3AVjn9wDyFeFmrhaSLqvmBycP5N7Zs".to_owned(),
+            "edpktfkToequZjyn3jz3GJobiYApkc5q4xnJiksStYbZkznUdsxDUw".to_owned(),
+        ];
+        let signatures = vec![
+            Signature {
+                value: "edsigtYCcgFDhPQH6CpsS6GtRtBcURGrtBuD9CWVK6xdyHabxnpgfrdyyFx3nkvRAzj1tCJZbaNsQiwUVNuPNFX69v1ihq9ZZSo",
+                public_key: &approvers[2],
+            },
+            Signature {
+                value: "edsigtY4zqwP9zRKwq6A1Ycm6uytHr563ZDHatdFZYrHpzvVczqHCpRQCe3Bq7iBR3rsbfuqG17AGXSnswxmASUz2xgVXvMzLmj",
+                public_key: "edpkvGfYw3LyB1UcCahKQk4rF2tvbMUk8GFiTuMjL75uGXrpvKXhjn",
+            },
+            Signature {
+                value: "edsigtXwP1dYcbSNoTMSazxdmwnA7GsKCvXN2ak1ozjwgiGPHCqsjnCpS28KsUVwf6zjK9X6vRqT8Kx7GX28xdSsv1MGMxVvJ1X",
+                public_key: &approvers[0],
+            },
+        ];
+
+        assert_eq!(
+            ordered_signatures(&approvers, &signatures)?,
+            vec![
+                data::some(bytes(vec![1; 64])),
+                data::none(),
+                data::some(bytes(vec![3; 64])),
+            ]
+        );
+        assert_eq!(
+            ordered_signatures(&approvers, &[])?,
+            vec![data::none(), data::none(), data::none()]
+        );
+
+        Ok(())
+    }
+}
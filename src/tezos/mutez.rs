@@ -0,0 +1,95 @@
+use std::{
+    convert::TryFrom,
+    fmt::{self, Display},
+};
+
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
+use super::{micheline::int, micheline::MichelsonV1Expression, TzError};
+
+/// An amount in mutez, guaranteed to be within the protocol's range (0 <= value < 2^63).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Mutez(i64);
+
+impl Mutez {
+    pub const ZERO: Mutez = Mutez(0);
+
+    pub fn value(&self) -> i64 {
+        self.0
+    }
+}
+
+impl TryFrom<i64> for Mutez {
+    type Error = TzError;
+
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        if value < 0 {
+            return Err(TzError::InvalidValue {
+                description: format!("mutez amount cannot be negative, found {}", value),
+            });
+        }
+
+        Ok(Mutez(value))
+    }
+}
+
+impl TryFrom<&BigInt> for Mutez {
+    type Error = TzError;
+
+    fn try_from(value: &BigInt) -> Result<Self, Self::Error> {
+        let value = value.to_i64().ok_or_else(|| TzError::InvalidValue {
+            description: format!("mutez amount {} is out of range", value),
+        })?;
+
+        Mutez::try_from(value)
+    }
+}
+
+impl From<Mutez> for MichelsonV1Expression {
+    fn from(value: Mutez) -> Self {
+        int(value.0)
+    }
+}
+
+impl Display for Mutez {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryFrom;
+
+    use num_bigint::BigInt;
+
+    use super::Mutez;
+    use crate::tezos::{micheline::types, TzError};
+
+    #[test]
+    fn test_mutez_upper_bound() -> Result<(), TzError> {
+        let max = BigInt::from(i64::MAX);
+        let mutez = Mutez::try_from(&max)?;
+
+        assert_eq!(mutez.value(), i64::MAX);
+
+        let packed = super::MichelsonV1Expression::from(mutez).pack(Some(&types::mutez()))?;
+        assert_eq!(packed, "0500bfffffffffffffffff01");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mutez_overflow() {
+        let overflowing = BigInt::from(i64::MAX) + 1;
+
+        assert!(Mutez::try_from(&overflowing).is_err());
+    }
+
+    #[test]
+    fn test_mutez_negative() {
+        assert!(Mutez::try_from(-1).is_err());
+        assert!(Mutez::try_from(&BigInt::from(-1)).is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use std::fmt::{self, Display};
+
+use super::micheline::{int, MichelsonV1Expression};
+
+/// The replay protection counter in the storage of a multisig contract. It is signed as part of
+/// every multisig message and only advances when the contract executes a call, so it is the
+/// number operation requests are ordered by.
+///
+/// Not to be confused with an [`AccountCounter`], which belongs to the implicit account
+/// submitting the transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MultisigNonce(i64);
+
+impl MultisigNonce {
+    pub fn value(&self) -> i64 {
+        self.0
+    }
+
+    pub fn next(&self) -> MultisigNonce {
+        MultisigNonce(self.0 + 1)
+    }
+}
+
+impl From<i64> for MultisigNonce {
+    fn from(value: i64) -> Self {
+        MultisigNonce(value)
+    }
+}
+
+impl From<MultisigNonce> for MichelsonV1Expression {
+    fn from(value: MultisigNonce) -> Self {
+        int(value.0)
+    }
+}
+
+impl Display for MultisigNonce {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The counter of an implicit account, which the protocol increments with every manager
+/// operation the account sends, whatever contract it calls. It says nothing about the state of a
+/// multisig and must never be used as a [`MultisigNonce`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AccountCounter(u64);
+
+impl AccountCounter {
+    pub fn value(&self) -> u64 {
+        self.0
+    }
+
+    pub fn next(&self) -> AccountCounter {
+        AccountCounter(self.0 + 1)
+    }
+}
+
+impl From<u64> for AccountCounter {
+    fn from(value: u64) -> Self {
+        AccountCounter(value)
+    }
+}
+
+impl Display for AccountCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AccountCounter, MultisigNonce};
+    use crate::tezos::micheline::{int, MichelsonV1Expression};
+
+    /// Fails to compile if `$source` converts into, or compares with, `$target`: with such an
+    /// impl the second blanket impl below applies as well and the trait's parameter can no longer
+    /// be inferred.
+    macro_rules! assert_not_interchangeable {
+        ($source:ty, $target:ty) => {{
+            trait AmbiguousIfConvertible<A> {
+                fn check() {}
+            }
+            impl<T: ?Sized> AmbiguousIfConvertible<()> for T {}
+
+            struct FromSource;
+            impl<T: ?Sized + From<$source>> AmbiguousIfConvertible<FromSource> for T {}
+
+            struct EqSource;
+            impl<T: ?Sized + PartialEq<$source>> AmbiguousIfConvertible<EqSource> for T {}
+
+            <$target as AmbiguousIfConvertible<_>>::check();
+        }};
+    }
+
+    #[test]
+    fn test_nonce_and_counter_not_interchangeable() {
+        assert_not_interchangeable!(AccountCounter, MultisigNonce);
+        assert_not_interchangeable!(MultisigNonce, AccountCounter);
+        assert_not_interchangeable!(u64, MultisigNonce);
+        assert_not_interchangeable!(i64, AccountCounter);
+        assert_not_interchangeable!(AccountCounter, MichelsonV1Expression);
+    }
+
+    #[test]
+    fn test_multisig_nonce() {
+        let nonce = MultisigNonce::from(4);
+
+        assert_eq!(nonce.next().value(), 5);
+        assert!(nonce < nonce.next());
+        assert_eq!(MichelsonV1Expression::from(nonce), int(4));
+    }
+}
@@ -16,19 +16,33 @@ where
     format!("{:0>width$}", &value_to_pad.to_string(), width = pad_length)
 }
 
+/// The longest string, bytes or sequence accepted when decoding, in bytes.
+pub const DEFAULT_MAX_VALUE_LENGTH: usize = 1024 * 1024;
+
 pub struct ConsumableHexStr<'a> {
     str: &'a str,
     position: usize,
+    max_value_length: usize,
 }
 
 impl<'a> ConsumableHexStr<'a> {
     pub fn new(str: &'a str) -> Self {
+        ConsumableHexStr::with_max_value_length(str, DEFAULT_MAX_VALUE_LENGTH)
+    }
+
+    pub fn with_max_value_length(str: &'a str, max_value_length: usize) -> Self {
         ConsumableHexStr {
             str: str,
             position: 0,
+            max_value_length,
         }
     }
 
+    /// A reader over `value`, typically consumed from this one, with the same limits.
+    pub fn nested(&self, value: &'a str) -> Self {
+        ConsumableHexStr::with_max_value_length(value, self.max_value_length)
+    }
+
     pub fn consume_bytes(&mut self, size: usize) -> Result<&'a str, TzError> {
         let end_index = self.position + (size * 2);
         let result = self.read_to(end_index)?;
@@ -37,9 +51,29 @@ impl<'a> ConsumableHexStr<'a> {
         Ok(result)
     }
 
+    /// Consumes a length prefix and the value it announces. The length is checked against the
+    /// remaining input and `max_value_length` before anything is read.
     pub fn consume_lengh_and_value(&mut self, bytes: Option<usize>) -> Result<&'a str, TzError> {
-        let length = self.consume_int(bytes)?;
-        self.consume_bytes(length as usize)
+        let length = self.consume_int(bytes)? as usize;
+        if length > self.max_value_length {
+            return Err(TzError::InvalidValue {
+                description: format!(
+                    "declared length {} exceeds the maximum of {} bytes",
+                    length, self.max_value_length
+                ),
+            });
+        }
+        let remaining = self.remaining_bytes();
+        if length > remaining {
+            return Err(TzError::InvalidValue {
+                description: format!(
+                    "declared length {} exceeds the {} remaining bytes",
+                    length, remaining
+                ),
+            });
+        }
+
+        self.consume_bytes(length)
     }
 
     pub fn consume_int(&mut self, bytes: Option<usize>) -> Result<i64, TzError> {
@@ -58,6 +92,10 @@ impl<'a> ConsumableHexStr<'a> {
         self.position < self.str.len()
     }
 
+    fn remaining_bytes(&self) -> usize {
+        self.str.len().saturating_sub(self.position) / 2
+    }
+
     fn read_to(&self, index: usize) -> Result<&'a str, TzError> {
         if index > self.str.len() {
             return Err(TzError::InvalidIndex);
@@ -74,7 +112,8 @@ pub fn biguint_to_u8(a: &BigUint) -> u8 {
 
 #[cfg(test)]
 mod test {
-    use super::num_to_padded_str;
+    use super::{num_to_padded_str, ConsumableHexStr};
+    use crate::tezos::TzError;
 
     #[test]
     fn test_padding() -> () {
@@ -82,4 +121,31 @@ mod test {
 
         assert_eq!("000000ff", padded);
     }
+
+    #[test]
+    fn test_consume_length_and_value() -> Result<(), TzError> {
+        let mut encoded = ConsumableHexStr::new("0000000454657374");
+        assert_eq!(encoded.consume_lengh_and_value(None)?, "54657374");
+        assert!(!encoded.has_more());
+
+        let mut encoded = ConsumableHexStr::new("ffffffff54657374");
+        assert!(matches!(
+            encoded.consume_lengh_and_value(None),
+            Err(TzError::InvalidValue { .. })
+        ));
+
+        let mut encoded = ConsumableHexStr::new("0000000554657374");
+        assert!(matches!(
+            encoded.consume_lengh_and_value(None),
+            Err(TzError::InvalidValue { .. })
+        ));
+
+        let mut encoded = ConsumableHexStr::with_max_value_length("0000000454657374", 3);
+        assert!(matches!(
+            encoded.consume_lengh_and_value(None),
+            Err(TzError::InvalidValue { .. })
+        ));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,84 @@
+use openssl::{
+    error::ErrorStack,
+    ssl::{SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod},
+};
+
+use crate::{api::models::error::APIError, settings};
+
+/// Builds the TLS acceptor for the HTTP server if a certificate and key are configured. The
+/// files are loaded here, so a missing or mismatched certificate or key fails at startup.
+pub fn acceptor(server: &settings::Server) -> Result<Option<SslAcceptorBuilder>, APIError> {
+    server.validate()?;
+
+    let (cert, key) = match (&server.tls_cert, &server.tls_key) {
+        (Some(cert), Some(key)) => (cert, key),
+        _ => return Ok(None),
+    };
+
+    let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())
+        .map_err(|error| tls_error("failed to create TLS acceptor", error))?;
+    builder
+        .set_private_key_file(key, SslFiletype::PEM)
+        .map_err(|error| tls_error(&format!("failed to load TLS key {}", key), error))?;
+    builder
+        .set_certificate_chain_file(cert)
+        .map_err(|error| tls_error(&format!("failed to load TLS certificate {}", cert), error))?;
+    builder
+        .check_private_key()
+        .map_err(|error| tls_error("TLS key does not match the certificate", error))?;
+
+    Ok(Some(builder))
+}
+
+fn tls_error(description: &str, error: ErrorStack) -> APIError {
+    APIError::InvalidValue {
+        description: format!("{}: {}", description, error),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use config::{Config, File, FileFormat};
+
+    use super::acceptor;
+    use crate::settings::Server;
+
+    fn server(tls: &str) -> Server {
+        let toml = format!(
+            r#"
+            address = "0.0.0.0:443"
+            domain_name = "localhost"
+            inactivity_timeout_seconds = 1800
+            {}
+            "#,
+            tls
+        );
+        let mut config = Config::new();
+        config
+            .merge(File::from_str(&toml, FileFormat::Toml))
+            .unwrap();
+
+        config.try_into().unwrap()
+    }
+
+    #[test]
+    fn test_acceptor() {
+        assert!(acceptor(&server("")).unwrap().is_none());
+
+        let cert_only = server(r#"tls_cert = "/etc/tz-wrapped/cert.pem""#);
+        assert!(cert_only.validate().is_err());
+        assert!(acceptor(&cert_only).is_err());
+
+        let key_only = server(r#"tls_key = "/etc/tz-wrapped/key.pem""#);
+        assert!(key_only.validate().is_err());
+
+        let missing_files = server(
+            r#"
+            tls_cert = "/nonexistent/cert.pem"
+            tls_key = "/nonexistent/key.pem"
+            "#,
+        );
+        assert!(missing_files.validate().is_ok());
+        assert!(acceptor(&missing_files).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+use std::convert::TryFrom;
+
+use crate::api::models::error::APIError;
+
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_DIRECTORY_HEADER_SIGNATURE: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+const VERSION: u16 = 20;
+const UTF8_NAMES_FLAG: u16 = 1 << 11;
+const STORED: u16 = 0;
+// 1980-01-01 00:00, the earliest date representable in a zip archive
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+struct CentralDirectoryEntry {
+    name: String,
+    crc32: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Writes a zip archive of uncompressed entries piece by piece, so that an archive can be
+/// streamed while its entries are being produced.
+pub struct ZipWriter {
+    entries: Vec<CentralDirectoryEntry>,
+    offset: u32,
+}
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        ZipWriter {
+            entries: vec![],
+            offset: 0,
+        }
+    }
+
+    /// Returns the bytes of an entry named `name` with content `data`.
+    pub fn entry(&mut self, name: &str, data: &[u8]) -> Result<Vec<u8>, APIError> {
+        let entry = CentralDirectoryEntry {
+            name: name.into(),
+            crc32: crc32(data),
+            size: to_u32(data.len())?,
+            offset: self.offset,
+        };
+
+        let mut bytes = Vec::with_capacity(30 + name.len() + data.len());
+        bytes.extend(&LOCAL_FILE_HEADER_SIGNATURE.to_le_bytes());
+        bytes.extend(&VERSION.to_le_bytes());
+        bytes.extend(&UTF8_NAMES_FLAG.to_le_bytes());
+        bytes.extend(&STORED.to_le_bytes());
+        bytes.extend(&DOS_TIME.to_le_bytes());
+        bytes.extend(&DOS_DATE.to_le_bytes());
+        bytes.extend(&entry.crc32.to_le_bytes());
+        bytes.extend(&entry.size.to_le_bytes());
+        bytes.extend(&entry.size.to_le_bytes());
+        bytes.extend(&to_u16(name.len())?.to_le_bytes());
+        bytes.extend(&0u16.to_le_bytes());
+        bytes.extend(name.as_bytes());
+        bytes.extend(data);
+
+        self.offset = self
+            .offset
+            .checked_add(to_u32(bytes.len())?)
+            .ok_or_else(too_large)?;
+        self.entries.push(entry);
+
+        Ok(bytes)
+    }
+
+    /// Returns the central directory, which ends the archive.
+    pub fn finish(self) -> Result<Vec<u8>, APIError> {
+        let mut bytes = vec![];
+        for entry in self.entries.iter() {
+            bytes.extend(&CENTRAL_DIRECTORY_HEADER_SIGNATURE.to_le_bytes());
+            bytes.extend(&VERSION.to_le_bytes());
+            bytes.extend(&VERSION.to_le_bytes());
+            bytes.extend(&UTF8_NAMES_FLAG.to_le_bytes());
+            bytes.extend(&STORED.to_le_bytes());
+            bytes.extend(&DOS_TIME.to_le_bytes());
+            bytes.extend(&DOS_DATE.to_le_bytes());
+            bytes.extend(&entry.crc32.to_le_bytes());
+            bytes.extend(&entry.size.to_le_bytes());
+            bytes.extend(&entry.size.to_le_bytes());
+            bytes.extend(&to_u16(entry.name.len())?.to_le_bytes());
+            // extra field length, comment length, disk number, internal and external attributes
+            bytes.extend(&[0u8; 12]);
+            bytes.extend(&entry.offset.to_le_bytes());
+            bytes.extend(entry.name.as_bytes());
+        }
+
+        let entries_count = to_u16(self.entries.len())?;
+        let central_directory_size = to_u32(bytes.len())?;
+        bytes.extend(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        bytes.extend(&0u16.to_le_bytes());
+        bytes.extend(&0u16.to_le_bytes());
+        bytes.extend(&entries_count.to_le_bytes());
+        bytes.extend(&entries_count.to_le_bytes());
+        bytes.extend(&central_directory_size.to_le_bytes());
+        bytes.extend(&self.offset.to_le_bytes());
+        bytes.extend(&0u16.to_le_bytes());
+
+        Ok(bytes)
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    !crc
+}
+
+fn to_u16(value: usize) -> Result<u16, APIError> {
+    u16::try_from(value).map_err(|_| too_large())
+}
+
+fn to_u32(value: usize) -> Result<u32, APIError> {
+    u32::try_from(value).map_err(|_| too_large())
+}
+
+fn too_large() -> APIError {
+    APIError::Internal {
+        description: "zip archive too large".into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{crc32, ZipWriter};
+    use crate::api::models::error::APIError;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+
+    #[test]
+    fn test_zip_archive() -> Result<(), APIError> {
+        let mut writer = ZipWriter::new();
+        let mut archive = writer.entry("a.json", b"{}")?;
+        archive.extend(writer.entry("b.json", b"[]")?);
+        archive.extend(writer.finish()?);
+
+        assert_eq!(
+            hex::encode(&archive),
+            "504b03041400000800000000210043bfa6a3020000000200000006000000612e6a736f6e7b7d504b\
+             03041400000800000000210029bb4c0d020000000200000006000000622e6a736f6e5b5d504b0102\
+             14001400000800000000210043bfa6a3020000000200000006000000000000000000000000000000\
+             0000612e6a736f6e504b010214001400000800000000210029bb4c0d020000000200000006000000\
+             0000000000000000000026000000622e6a736f6e504b05060000000002000200680000004c000000\
+             0000"
+        );
+
+        Ok(())
+    }
+}